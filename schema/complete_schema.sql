@@ -101,6 +101,8 @@ CREATE TABLE addresses (
     door_code TEXT,                             -- Código de puerta del edificio
     has_mailbox_access BOOLEAN DEFAULT FALSE,   -- Acceso a buzón
     driver_notes TEXT,                          -- "Apto 12: buzón bloqueado"
+    floor VARCHAR(20),                          -- "3", "RDC"
+    concierge_notes TEXT,                       -- "Gardienne de 8h à 12h, loge au fond"
     
     -- Metadata
     last_updated_by VARCHAR(100),               -- Matricule del chofer que actualizó
@@ -111,4 +113,125 @@ CREATE TABLE addresses (
 -- Índices para búsqueda rápida en tabla addresses
 CREATE INDEX idx_addresses_street_postcode ON addresses(street_name, postcode);
 CREATE INDEX idx_addresses_coordinates ON addresses USING GIST(coordinates);
-CREATE INDEX idx_addresses_postcode ON addresses(postcode);
\ No newline at end of file
+CREATE INDEX idx_addresses_postcode ON addresses(postcode);
+-- =====================================================
+-- 7. PACKAGE_LABELS (etiquetas libres de los dispatchers)
+-- =====================================================
+-- Los paquetes vienen de Colis Privé: se identifican por su référence colis
+CREATE TABLE package_labels (
+    reference_colis VARCHAR(50) NOT NULL,       -- "CP123456789"
+    label VARCHAR(40) NOT NULL,                 -- "fragile", "reattempt"
+    created_by VARCHAR(100),                    -- Matricule que añadió la etiqueta
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (reference_colis, label)
+);
+
+CREATE INDEX idx_package_labels_label ON package_labels(label);
+-- =====================================================
+-- 8. PACKAGE_STATUSES (último estado conocido de cada paquete)
+-- =====================================================
+-- Se registran al cargar una tournée y se actualizan por webhook
+CREATE TABLE package_statuses (
+    reference_colis VARCHAR(50) PRIMARY KEY,    -- "CP123456789"
+    status VARCHAR(50),                         -- "LIVRE", "ECHEC"...
+    status_at TIMESTAMP WITH TIME ZONE,         -- Momento del estado según el emisor
+    source VARCHAR(20) NOT NULL DEFAULT 'tournee', -- 'tournee' | 'webhook'
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+-- =====================================================
+-- 9. COMPANY_SETTINGS (preferencias por société de Colis Privé)
+-- =====================================================
+CREATE TABLE company_settings (
+    societe VARCHAR(50) PRIMARY KEY,            -- "PCP0010699"
+    optimization_providers VARCHAR(100),        -- "local,colisprive" (NULL = orden global)
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+-- =====================================================
+-- 10. ADDRESS_VALIDATIONS (resultado de validar cada dirección de tournée)
+-- =====================================================
+-- Clave: dirección normalizada (minúsculas, sin puntuación). Las que quedan
+-- en 'requires_manual' las fija un dispatcher ('manual_confirmed') y se
+-- reutilizan en las tournées siguientes
+CREATE TABLE address_validations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    normalized_address TEXT NOT NULL UNIQUE,    -- "12 rue de la paix 75002 paris"
+    original_address TEXT NOT NULL,             -- "12 RUE DE LA PAIX, 75002, PARIS"
+    latitude DOUBLE PRECISION,
+    longitude DOUBLE PRECISION,
+    validation_method VARCHAR(30) NOT NULL,     -- "completed_auto", "requires_manual", "manual_confirmed"
+    confidence DOUBLE PRECISION,
+    confirmed_by VARCHAR(100),                  -- Dispatcher que fijó las coordenadas
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_address_validations_method ON address_validations(validation_method);
+-- =====================================================
+-- 11. ADDRESS_VALIDATION_OUTCOMES (métodos de validación por tournée)
+-- =====================================================
+-- Una fila por tournée y método; se sustituyen si la tournée se vuelve a
+-- pedir. GET /analysis/validation-trends las suma por día
+CREATE TABLE address_validation_outcomes (
+    tournee_date DATE NOT NULL,
+    societe VARCHAR(50) NOT NULL,               -- "PCP0010699"
+    matricule VARCHAR(50) NOT NULL,             -- "A187518"
+    validation_method VARCHAR(30) NOT NULL,     -- "auto_validated", "requires_manual"...
+    outcomes INTEGER NOT NULL,                  -- Paquetes validados con el método
+    confidence_sum DOUBLE PRECISION NOT NULL DEFAULT 0,
+    scored INTEGER NOT NULL DEFAULT 0,          -- Paquetes con puntuación de confianza
+    recorded_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (tournee_date, societe, matricule, validation_method)
+);
+-- =====================================================
+-- 12. DEPOTS (almacenes de salida y vuelta de las rutas)
+-- =====================================================
+-- La optimización sale del almacén por defecto de la société; sin almacén
+-- se usa WAREHOUSE_LOCATION
+CREATE TABLE depots (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,                 -- "Entrepôt Lyon Sud"
+    societe VARCHAR(50),                        -- "PCP0010699"
+    latitude DOUBLE PRECISION NOT NULL,
+    longitude DOUBLE PRECISION NOT NULL,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_depots_societe ON depots(societe);
+-- Un solo almacén por defecto por empresa
+CREATE UNIQUE INDEX idx_depots_company_default ON depots(company_id) WHERE is_default;
+
+-- =====================================================
+-- 13. TOURNEE_SNAPSHOTS (fotos del estado de una tournée durante el día)
+-- =====================================================
+-- Para disputas: qué estado tenía cada paquete en cada momento
+CREATE TABLE tournee_snapshots (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    societe VARCHAR(50) NOT NULL,               -- "PCP0010699"
+    matricule VARCHAR(50) NOT NULL,             -- "A187518"
+    date_tournee DATE NOT NULL,
+    taken_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    packages JSONB NOT NULL                     -- [{reference_colis, status, outcome, attempts}]
+);
+
+CREATE INDEX idx_tournee_snapshots_tournee ON tournee_snapshots(societe, matricule, date_tournee, taken_at);
+
+-- =====================================================
+-- 14. PACKAGES (paquetes importados por CSV, fuera de Colis Privé)
+-- =====================================================
+-- Alta masiva al dar de alta una empresa (POST /packages/import)
+CREATE TABLE packages (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    tracking_number VARCHAR(50) NOT NULL,       -- "TRK001"
+    recipient TEXT NOT NULL,
+    address TEXT NOT NULL,                      -- "12 rue de la Paix"
+    postal_code VARCHAR(5) NOT NULL,            -- "75002"
+    city TEXT,
+    phone TEXT,
+    instructions TEXT,
+    latitude DOUBLE PRECISION,                  -- NULL = pendiente de geocodificar
+    longitude DOUBLE PRECISION,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (company_id, tracking_number)
+);
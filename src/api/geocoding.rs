@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::services::geocoding_service::GeocodingService;
 use crate::services::address_cache_service::AddressCacheService;
+use crate::utils::app_json::AppJson;
 use crate::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -48,7 +49,7 @@ pub fn create_geocoding_router() -> Router<AppState> {
 /// Endpoint para geocodificar una sola dirección
 pub async fn geocode_address(
     State(state): State<AppState>,
-    Json(request): Json<GeocodingApiRequest>,
+    AppJson(request): AppJson<GeocodingApiRequest>,
 ) -> Result<Json<GeocodingApiResponse>, StatusCode> {
     log::info!("🗺️ Geocoding request received: {}", request.address);
 
@@ -82,7 +83,9 @@ pub async fn geocode_address(
     };
 
     // Crear el servicio de geocoding
-    let geocoding_service = GeocodingService::new(mapbox_token);
+    let geocoding_service = GeocodingService::new(mapbox_token)
+        .with_locale(state.config.geocoding_locale.clone())
+        .with_cache(state.geocode_cache.clone());
 
     // Realizar la geocodificación
     match geocoding_service.geocode_address(&request.address).await {
@@ -114,7 +117,7 @@ pub async fn geocode_address(
 /// Endpoint para geocodificar múltiples direcciones en lote
 pub async fn batch_geocode_addresses(
     State(state): State<AppState>,
-    Json(request): Json<BatchGeocodingApiRequest>,
+    AppJson(request): AppJson<BatchGeocodingApiRequest>,
 ) -> Result<Json<BatchGeocodingApiResponse>, StatusCode> {
     log::info!("🗺️ Batch geocoding request received: {} addresses", request.addresses.len());
 
@@ -155,7 +158,9 @@ pub async fn batch_geocode_addresses(
     };
 
     // Crear el servicio de geocoding
-    let geocoding_service = GeocodingService::new(mapbox_token);
+    let geocoding_service = GeocodingService::new(mapbox_token)
+        .with_locale(state.config.geocoding_locale.clone())
+        .with_cache(state.geocode_cache.clone());
 
     // Realizar la geocodificación en lote
     match geocoding_service.batch_geocode(request.addresses).await {
@@ -0,0 +1,366 @@
+//! Cache en memoria de segundo nivel
+//!
+//! Si Redis no responde, las lecturas y escrituras caen a un LRU acotado
+//! dentro del proceso, de modo que las direcciones repetidas no vuelven a
+//! llamar a Mapbox durante la caída. Las entradas escritas mientras Redis
+//! estaba caído se promocionan a Redis en cuanto vuelve a responder.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{debug, info, warn};
+
+/// Capacidad por defecto del LRU en memoria
+pub const DEFAULT_MEMORY_CAPACITY: usize = 1000;
+
+/// Cache remoto (Redis) que, a diferencia de `RedisClient::get`, reporta los
+/// errores de conexión para poder caer al nivel en memoria
+#[async_trait]
+pub trait RemoteCache: Send + Sync {
+    async fn fetch_raw(&self, key: &str) -> Result<Option<String>>;
+    async fn store_raw(&self, key: &str, value: &str, ttl: u64) -> Result<()>;
+    /// Eliminar claves; devuelve cuántas existían
+    async fn remove_raw(&self, keys: &[String]) -> Result<usize>;
+    /// Claves que coinciden con un patrón glob de Redis
+    async fn keys_matching(&self, pattern: &str) -> Result<Vec<String>>;
+}
+
+struct MemoryEntry {
+    value: String,
+    ttl: u64,
+    expires_at: Instant,
+    tick: u64,
+}
+
+/// LRU acotado: al superar la capacidad se descarta la entrada menos usada
+struct MemoryLru {
+    capacity: usize,
+    entries: HashMap<String, MemoryEntry>,
+    /// Orden de uso: tick → clave (el menor es el menos usado)
+    usage: BTreeMap<u64, String>,
+    next_tick: u64,
+}
+
+impl MemoryLru {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            usage: BTreeMap::new(),
+            next_tick: 0,
+        }
+    }
+
+    fn touch(&mut self, key: &str) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.usage.remove(&entry.tick);
+            entry.tick = tick;
+            self.usage.insert(tick, key.to_string());
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<(String, u64)> {
+        let expired = self.entries.get(key)?.expires_at <= Instant::now();
+        if expired {
+            self.remove(key);
+            return None;
+        }
+
+        self.touch(key);
+        self.entries.get(key).map(|entry| (entry.value.clone(), entry.ttl))
+    }
+
+    fn put(&mut self, key: &str, value: String, ttl: u64) {
+        self.remove(key);
+
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.usage.pop_first() else { break };
+            self.entries.remove(&oldest);
+        }
+
+        self.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                value,
+                ttl,
+                expires_at: Instant::now() + Duration::from_secs(ttl),
+                tick: 0,
+            },
+        );
+        self.touch(key);
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.usage.remove(&entry.tick);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.entries.keys().filter(|key| key.starts_with(prefix)).cloned().collect()
+    }
+}
+
+/// Cache de dos niveles: Redis primero, LRU en memoria como respaldo
+#[derive(Clone)]
+pub struct FallbackCache<R> {
+    remote: R,
+    memory: Arc<Mutex<MemoryLru>>,
+    /// Claves escritas solo en memoria (Redis caído) pendientes de promoción
+    pending: Arc<Mutex<HashSet<String>>>,
+}
+
+impl<R: RemoteCache> FallbackCache<R> {
+    pub fn new(remote: R, capacity: usize) -> Self {
+        Self {
+            remote,
+            memory: Arc::new(Mutex::new(MemoryLru::new(capacity))),
+            pending: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Leer de Redis; si no responde, del LRU en memoria
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let raw = match self.remote.fetch_raw(key).await {
+            Ok(Some(value)) => {
+                self.promote_pending().await;
+                Some(value)
+            }
+            Ok(None) => {
+                self.promote_pending().await;
+                self.memory.lock().unwrap().get(key).map(|(value, _)| value)
+            }
+            Err(e) => {
+                warn!("⚠️ Redis no disponible, usando cache en memoria para {}: {}", key, e);
+                self.memory.lock().unwrap().get(key).map(|(value, _)| value)
+            }
+        };
+
+        match raw {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Guardar en memoria y en Redis; si Redis falla queda pendiente de promoción
+    pub async fn set<T: Serialize + Sync>(&self, key: &str, value: &T, ttl: u64) -> Result<()> {
+        let serialized = serde_json::to_string(value)?;
+        self.memory.lock().unwrap().put(key, serialized.clone(), ttl);
+
+        match self.remote.store_raw(key, &serialized, ttl).await {
+            Ok(()) => {
+                self.promote_pending().await;
+            }
+            Err(e) => {
+                warn!("⚠️ Redis no disponible, {} guardado solo en memoria: {}", key, e);
+                self.pending.lock().unwrap().insert(key.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Devolver el valor cacheado o calcularlo (p.ej. llamando a Mapbox) y guardarlo
+    pub async fn get_or_insert_with<T, F, Fut>(&self, key: &str, ttl: u64, fetch: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned + Sync,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        if let Some(cached) = self.get(key).await? {
+            return Ok(cached);
+        }
+
+        let value = fetch().await?;
+        self.set(key, &value, ttl).await?;
+        Ok(value)
+    }
+
+    /// Eliminar una clave de ambos niveles; devuelve si existía en alguno
+    pub async fn remove(&self, key: &str) -> Result<bool> {
+        let in_memory = !self.forget(&[key.to_string()]).is_empty();
+        let in_remote = self.remote.remove_raw(&[key.to_string()]).await? > 0;
+        Ok(in_memory || in_remote)
+    }
+
+    /// Eliminar de ambos niveles las claves que empiezan por `prefix` (sin
+    /// caracteres glob); devuelve cuántas claves distintas se eliminaron
+    pub async fn remove_prefix(&self, prefix: &str) -> Result<usize> {
+        let memory_keys = self.memory.lock().unwrap().keys_with_prefix(prefix);
+        let forgotten = self.forget(&memory_keys);
+
+        let remote_keys = self.remote.keys_matching(&format!("{}*", prefix)).await?;
+        let remote_removed = if remote_keys.is_empty() { 0 } else { self.remote.remove_raw(&remote_keys).await? };
+
+        // Las claves que estaban en los dos niveles cuentan una vez
+        let only_in_memory = forgotten.iter().filter(|key| !remote_keys.contains(key)).count();
+        Ok(remote_removed + only_in_memory)
+    }
+
+    /// Quitar claves del LRU y de las pendientes de promoción; devuelve las que estaban en memoria
+    fn forget(&self, keys: &[String]) -> Vec<String> {
+        let mut memory = self.memory.lock().unwrap();
+        let mut pending = self.pending.lock().unwrap();
+        keys.iter()
+            .filter(|key| {
+                pending.remove(key.as_str());
+                let present = memory.get(key).is_some();
+                memory.remove(key);
+                present
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Número de claves esperando a que Redis vuelva
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Número de entradas en el LRU en memoria
+    pub fn memory_len(&self) -> usize {
+        self.memory.lock().unwrap().len()
+    }
+
+    /// Copiar a Redis las entradas escritas durante la caída
+    async fn promote_pending(&self) {
+        let keys: Vec<String> = {
+            let mut pending = self.pending.lock().unwrap();
+            if pending.is_empty() {
+                return;
+            }
+            pending.drain().collect()
+        };
+
+        let mut promoted = 0;
+        for (index, key) in keys.iter().enumerate() {
+            // La entrada pudo expirar o ser desalojada mientras tanto
+            let Some((value, ttl)) = self.memory.lock().unwrap().get(key) else { continue };
+
+            if let Err(e) = self.remote.store_raw(key, &value, ttl).await {
+                debug!("⚠️ Promoción a Redis interrumpida: {}", e);
+                self.pending.lock().unwrap().extend(keys[index..].iter().cloned());
+                return;
+            }
+            promoted += 1;
+        }
+
+        info!("✅ {} entradas promocionadas de memoria a Redis", promoted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Redis simulado que se puede "apagar"
+    #[derive(Default)]
+    struct FakeRedis {
+        down: AtomicBool,
+        data: Mutex<HashMap<String, String>>,
+    }
+
+    #[async_trait]
+    impl RemoteCache for Arc<FakeRedis> {
+        async fn fetch_raw(&self, key: &str) -> Result<Option<String>> {
+            if self.down.load(Ordering::SeqCst) {
+                anyhow::bail!("connection refused");
+            }
+            Ok(self.data.lock().unwrap().get(key).cloned())
+        }
+
+        async fn store_raw(&self, key: &str, value: &str, _ttl: u64) -> Result<()> {
+            if self.down.load(Ordering::SeqCst) {
+                anyhow::bail!("connection refused");
+            }
+            self.data.lock().unwrap().insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+
+        async fn remove_raw(&self, keys: &[String]) -> Result<usize> {
+            if self.down.load(Ordering::SeqCst) {
+                anyhow::bail!("connection refused");
+            }
+            let mut data = self.data.lock().unwrap();
+            Ok(keys.iter().filter(|key| data.remove(key.as_str()).is_some()).count())
+        }
+
+        async fn keys_matching(&self, pattern: &str) -> Result<Vec<String>> {
+            let prefix = pattern.trim_end_matches('*');
+            Ok(self.data.lock().unwrap().keys().filter(|key| key.starts_with(prefix)).cloned().collect())
+        }
+    }
+
+    fn redis_down() -> Arc<FakeRedis> {
+        let redis = Arc::new(FakeRedis::default());
+        redis.down.store(true, Ordering::SeqCst);
+        redis
+    }
+
+    #[tokio::test]
+    async fn test_repeated_geocode_hits_memory_when_redis_is_down() {
+        let cache = FallbackCache::new(redis_down(), 10);
+        let mapbox_calls = AtomicUsize::new(0);
+
+        for _ in 0..3 {
+            let coords: (f64, f64) = cache
+                .get_or_insert_with("geocode:12 rue de la paix 75002 paris", 3600, || async {
+                    mapbox_calls.fetch_add(1, Ordering::SeqCst);
+                    Ok((48.8686, 2.3314))
+                })
+                .await
+                .unwrap();
+            assert_eq!(coords, (48.8686, 2.3314));
+        }
+
+        assert_eq!(mapbox_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.pending_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pending_entries_are_promoted_when_redis_recovers() {
+        let redis = redis_down();
+        let cache = FallbackCache::new(redis.clone(), 10);
+
+        cache.set("geocode:a", &"A", 3600).await.unwrap();
+        cache.set("geocode:b", &"B", 3600).await.unwrap();
+        assert!(redis.data.lock().unwrap().is_empty());
+
+        redis.down.store(false, Ordering::SeqCst);
+        let _: Option<String> = cache.get("geocode:otra").await.unwrap();
+
+        assert_eq!(cache.pending_count(), 0);
+        let data = redis.data.lock().unwrap();
+        assert_eq!(data.get("geocode:a").map(String::as_str), Some("\"A\""));
+        assert_eq!(data.get("geocode:b").map(String::as_str), Some("\"B\""));
+    }
+
+    #[tokio::test]
+    async fn test_memory_tier_is_bounded_and_evicts_least_recently_used() {
+        let cache = FallbackCache::new(redis_down(), 2);
+
+        cache.set("a", &1, 3600).await.unwrap();
+        cache.set("b", &2, 3600).await.unwrap();
+        // Usar "a" para que "b" sea la menos reciente
+        assert_eq!(cache.get::<i32>("a").await.unwrap(), Some(1));
+        cache.set("c", &3, 3600).await.unwrap();
+
+        assert_eq!(cache.memory_len(), 2);
+        assert_eq!(cache.get::<i32>("a").await.unwrap(), Some(1));
+        assert_eq!(cache.get::<i32>("b").await.unwrap(), None);
+        assert_eq!(cache.get::<i32>("c").await.unwrap(), Some(3));
+    }
+}
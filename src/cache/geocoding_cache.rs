@@ -0,0 +1,248 @@
+//! Cache de resultados de geocoding
+//!
+//! La misma dirección aparece en muchas tournées; cada geocoding es una
+//! llamada a Mapbox que consume cuota. Los resultados correctos se guardan
+//! bajo el hash de la dirección normalizada (Redis con LRU en memoria de
+//! respaldo) y se cuentan aciertos y fallos para medir el ahorro.
+//!
+//! La clave lleva también el código postal de la dirección, de modo que se
+//! puede invalidar una dirección concreta o toda una zona (p.ej. tras
+//! renumerar una calle) sin vaciar el cache entero.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::fallback_cache::{FallbackCache, RemoteCache};
+use super::redis_client::RedisClient;
+use crate::services::geocoding_service::GeocodingResponse;
+
+/// TTL por defecto de un resultado de geocoding: 30 días
+pub const DEFAULT_GEOCODING_TTL_SECS: u64 = 30 * 24 * 3600;
+
+/// Aciertos y fallos desde el arranque
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct GeocodingCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Proporción de aciertos (0 sin consultas)
+    pub hit_ratio: f64,
+}
+
+#[derive(Clone)]
+pub struct GeocodingCache<R = RedisClient> {
+    cache: FallbackCache<R>,
+    ttl_secs: u64,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+/// Dirección normalizada: minúsculas, sin puntuación y con espacios simples
+pub fn normalize_address(address: &str) -> String {
+    address
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Prefijo de las claves del cache por código postal
+const POSTAL_KEY_PREFIX: &str = "delivery_optimizer:geocode:cp:";
+
+/// Código postal (cinco dígitos) de la dirección, si lo tiene
+pub fn postal_code(address: &str) -> Option<String> {
+    normalize_address(address)
+        .split(' ')
+        .find(|token| token.len() == 5 && token.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+}
+
+fn cache_key(address: &str) -> String {
+    let digest = Sha256::digest(normalize_address(address).as_bytes());
+    let hash: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let postal_code = postal_code(address).unwrap_or_else(|| "none".to_string());
+    format!("{}{}:{}", POSTAL_KEY_PREFIX, postal_code, hash)
+}
+
+impl<R: RemoteCache> GeocodingCache<R> {
+    pub fn new(cache: FallbackCache<R>, ttl_secs: u64) -> Self {
+        Self {
+            cache,
+            ttl_secs,
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Resultado cacheado para la dirección (cuenta acierto o fallo)
+    pub async fn get(&self, address: &str) -> Option<GeocodingResponse> {
+        let cached = self
+            .cache
+            .get::<GeocodingResponse>(&cache_key(address))
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("⚠️ Cache de geocoding ilegible para '{}': {}", address, e);
+                None
+            });
+
+        match cached {
+            Some(response) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                log::info!("🎯 Geocoding desde cache: {}", address);
+                Some(response)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Guardar un resultado; los fallidos no se cachean para reintentarlos
+    pub async fn put(&self, address: &str, response: &GeocodingResponse) {
+        if !response.success {
+            return;
+        }
+        if let Err(e) = self.cache.set(&cache_key(address), response, self.ttl_secs).await {
+            log::warn!("⚠️ No se pudo cachear el geocoding de '{}': {}", address, e);
+        }
+    }
+
+    /// Olvidar el resultado de una dirección; devuelve si estaba cacheado
+    pub async fn evict(&self, address: &str) -> anyhow::Result<bool> {
+        let removed = self.cache.remove(&cache_key(address)).await?;
+        log::info!("🧹 Geocoding de '{}' eliminado del cache: {}", address, removed);
+        Ok(removed)
+    }
+
+    /// Olvidar los resultados de todos los códigos postales que empiezan por
+    /// `prefix` (1 a 5 dígitos); devuelve cuántos se eliminaron
+    pub async fn evict_postal_prefix(&self, prefix: &str) -> anyhow::Result<usize> {
+        let removed = self.cache.remove_prefix(&format!("{}{}", POSTAL_KEY_PREFIX, prefix)).await?;
+        log::info!("🧹 {} resultados de geocoding eliminados del cache (CP {}*)", removed, prefix);
+        Ok(removed)
+    }
+
+    pub fn stats(&self) -> GeocodingCacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let total = hits + misses;
+        GeocodingCacheStats {
+            hits,
+            misses,
+            hit_ratio: if total == 0 { 0.0 } else { hits as f64 / total as f64 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeRedis {
+        data: Mutex<HashMap<String, String>>,
+    }
+
+    #[async_trait]
+    impl RemoteCache for Arc<FakeRedis> {
+        async fn fetch_raw(&self, key: &str) -> Result<Option<String>> {
+            Ok(self.data.lock().unwrap().get(key).cloned())
+        }
+
+        async fn store_raw(&self, key: &str, value: &str, _ttl: u64) -> Result<()> {
+            self.data.lock().unwrap().insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+
+        async fn remove_raw(&self, keys: &[String]) -> Result<usize> {
+            let mut data = self.data.lock().unwrap();
+            Ok(keys.iter().filter(|key| data.remove(key.as_str()).is_some()).count())
+        }
+
+        async fn keys_matching(&self, pattern: &str) -> Result<Vec<String>> {
+            let prefix = pattern.trim_end_matches('*');
+            Ok(self.data.lock().unwrap().keys().filter(|key| key.starts_with(prefix)).cloned().collect())
+        }
+    }
+
+    fn geocoded(success: bool) -> GeocodingResponse {
+        GeocodingResponse {
+            success,
+            latitude: success.then_some(48.8686),
+            longitude: success.then_some(2.3314),
+            formatted_address: None,
+            message: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_equivalent_addresses_share_a_key() {
+        assert_eq!(normalize_address("  12, Rue de la PAIX  75002 Paris "), "12 rue de la paix 75002 paris");
+        assert_eq!(cache_key("12 rue de la Paix, 75002 PARIS"), cache_key("12  RUE DE LA PAIX 75002 paris"));
+        assert_ne!(cache_key("12 rue de la paix"), cache_key("14 rue de la paix"));
+        assert_eq!(postal_code("12, Rue de la Paix 75002 Paris").as_deref(), Some("75002"));
+        assert_eq!(postal_code("12 rue de la paix"), None);
+    }
+
+    fn cache() -> GeocodingCache<Arc<FakeRedis>> {
+        GeocodingCache::new(FallbackCache::new(Arc::new(FakeRedis::default()), 10), DEFAULT_GEOCODING_TTL_SECS)
+    }
+
+    #[tokio::test]
+    async fn test_single_address_is_evicted() {
+        let cache = cache();
+        cache.put("12 rue de la paix, 75002 paris", &geocoded(true)).await;
+        cache.put("14 rue de la paix, 75002 paris", &geocoded(true)).await;
+
+        assert!(cache.evict("12 RUE DE LA PAIX 75002 PARIS").await.unwrap());
+        assert!(!cache.evict("12 rue de la paix, 75002 paris").await.unwrap());
+
+        assert!(cache.get("12 rue de la paix, 75002 paris").await.is_none());
+        assert!(cache.get("14 rue de la paix, 75002 paris").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_postal_code_prefix_evicts_the_region() {
+        let cache = cache();
+        for address in [
+            "12 rue de la paix, 75002 paris",
+            "3 rue oberkampf, 75011 paris",
+            "1 place bellecour, 69002 lyon",
+        ] {
+            cache.put(address, &geocoded(true)).await;
+        }
+
+        assert_eq!(cache.evict_postal_prefix("750").await.unwrap(), 2);
+
+        assert!(cache.get("12 rue de la paix, 75002 paris").await.is_none());
+        assert!(cache.get("3 rue oberkampf, 75011 paris").await.is_none());
+        assert!(cache.get("1 place bellecour, 69002 lyon").await.is_some());
+        assert_eq!(cache.evict_postal_prefix("750").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cache_counts_hits_and_skips_failed_results() {
+        let cache = GeocodingCache::new(FallbackCache::new(Arc::new(FakeRedis::default()), 10), DEFAULT_GEOCODING_TTL_SECS);
+
+        assert!(cache.get("12 rue de la paix, 75002 paris").await.is_none());
+        cache.put("12 rue de la paix, 75002 paris", &geocoded(true)).await;
+        cache.put("adresse inconnue", &geocoded(false)).await;
+
+        let hit = cache.get("12 RUE DE LA PAIX 75002 PARIS").await.unwrap();
+        assert_eq!(hit.latitude, Some(48.8686));
+        assert!(cache.get("adresse inconnue").await.is_none());
+
+        assert_eq!(cache.stats(), GeocodingCacheStats { hits: 1, misses: 2, hit_ratio: 1.0 / 3.0 });
+    }
+}
@@ -5,5 +5,7 @@
 pub mod redis_client;
 // pub mod detail_cache; // Comentado - legacy, necesita refactoring
 pub mod cache_config;
+pub mod fallback_cache;
+pub mod geocoding_cache;
 
 pub use cache_config::CacheConfig;
\ No newline at end of file
@@ -4,6 +4,7 @@ use serde::{de::DeserializeOwned, Serialize};
 use tracing::{debug, error, info, warn};
 
 use super::cache_config::CacheConfig;
+use super::fallback_cache::RemoteCache;
 
 /// Cliente Redis con connection pooling y operaciones async
 #[derive(Clone)]
@@ -30,23 +31,58 @@ impl RedisClient {
     }
     
     /// Generar clave de cache con prefijo
-    fn make_key(&self, prefix: &str, identifier: &str) -> String {
+    fn make_key(prefix: &str, identifier: &str) -> String {
         format!("delivery_optimizer:{}:{}", prefix, identifier)
     }
     
     /// Generar clave de auth cache
-    pub fn auth_key(&self, username: &str, societe: &str) -> String {
-        self.make_key("auth", &format!("{}:{}", username, societe))
+    pub fn auth_key(username: &str, societe: &str) -> String {
+        Self::make_key("auth", &format!("{}:{}", username, societe))
+    }
+    
+    /// Patrón de las claves de token de todos los usuarios de una société
+    pub fn auth_pattern(societe: &str) -> String {
+        Self::make_key("auth", &format!("*:{}", societe))
     }
     
     /// Generar clave de tournée cache
     pub fn tournee_key(&self, societe: &str, matricule: &str, date: &str) -> String {
-        self.make_key("tournee", &format!("{}:{}:{}", societe, matricule, date))
+        Self::make_key("tournee", &format!("{}:{}:{}", societe, matricule, date))
     }
     
+    /// Generar clave de matriz de duraciones (por conjunto de paradas)
+    pub fn matrix_key(&self, stop_set: &str) -> String {
+        Self::make_key("matrix", stop_set)
+    }
+
+    /// Generar clave de la última optimización de una tournée
+    pub fn optimization_key(&self, societe: &str, matricule: &str, date: &str) -> String {
+        Self::make_key("optimization", &format!("{}:{}:{}", societe, matricule, date))
+    }
+
+    /// Generar clave de la optimización anterior de una tournée
+    pub fn previous_optimization_key(&self, societe: &str, matricule: &str, date: &str) -> String {
+        Self::make_key("optimization_previous", &format!("{}:{}:{}", societe, matricule, date))
+    }
+
+    /// Generar clave del progreso de entrega de una tournée
+    pub fn progress_key(&self, societe: &str, matricule: &str, date: &str) -> String {
+        Self::make_key("progress", &format!("{}:{}:{}", societe, matricule, date))
+    }
+
+    /// Patrón de las claves de progreso de todos los repartidores de una société en una fecha
+    pub fn progress_pattern(&self, societe: &str, date: &str) -> String {
+        Self::make_key("progress", &format!("{}:*:{}", societe, date))
+    }
+
+    /// Generar clave de una parada ya registrada con `Idempotency-Key`
+    pub fn stop_idempotency_key(&self, societe: &str, matricule: &str, reference: &str, key: &str) -> String {
+        Self::make_key("stop_idempotency", &format!("{}:{}:{}:{}", societe, matricule, reference, key))
+    }
+
     /// Generar clave de rate limiting
     pub fn rate_limit_key(&self, identifier: &str) -> String {
-        self.make_key("rate_limit", identifier)
+        Self::make_key("rate_limit", identifier)
     }
 }
 
@@ -90,7 +126,35 @@ impl RedisClient {
         }
     }
     
-    pub async fn delete(&self, key: &str) -> Result<()> {
+    /// Guardar solo si la clave no existe (SET NX EX); devuelve si se guardó
+    pub async fn set_nx<T: Serialize + Send + Sync>(&self, key: &str, value: &T, ttl: u64) -> Result<bool> {
+        let mut conn = self.manager.clone();
+
+        let serialized = serde_json::to_string(value)?;
+
+        let result: RedisResult<Option<String>> = redis::cmd("SET")
+            .arg(key)
+            .arg(serialized)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl)
+            .query_async(&mut conn)
+            .await;
+
+        match result {
+            Ok(stored) => {
+                debug!("💾 Cache SET NX para clave: {} (guardada: {})", key, stored.is_some());
+                Ok(stored.is_some())
+            }
+            Err(e) => {
+                error!("❌ Error guardando en cache para clave {}: {}", key, e);
+                Err(anyhow::anyhow!("Error de Redis: {}", e))
+            }
+        }
+    }
+
+    /// Eliminar una clave; devuelve si existía
+    pub async fn delete(&self, key: &str) -> Result<bool> {
         let mut conn = self.manager.clone();
         
         let result: RedisResult<i64> = conn.del(key).await;
@@ -98,11 +162,11 @@ impl RedisClient {
         match result {
             Ok(count) => {
                 debug!("🗑️ Cache DELETE para clave: {} (eliminados: {})", key, count);
-                Ok(())
+                Ok(count > 0)
             }
             Err(e) => {
                 warn!("⚠️ Error eliminando cache para clave {}: {}", key, e);
-                Ok(()) // No fallar si no se puede eliminar
+                Ok(false) // No fallar si no se puede eliminar
             }
         }
     }
@@ -183,6 +247,17 @@ impl RedisClient {
         Ok(())
     }
     
+    /// Claves que coinciden con un patrón (SCAN, sin bloquear Redis como KEYS)
+    pub async fn scan_keys(&self, pattern: &str) -> Result<Vec<String>> {
+        let mut conn = self.manager.clone();
+        let mut keys = Vec::new();
+        let mut iter: redis::AsyncIter<String> = conn.scan_match(pattern).await?;
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
     /// Verificar si Redis está conectado
     pub async fn is_connected(&self) -> bool {
         let mut conn = self.manager.clone();
@@ -193,6 +268,30 @@ impl RedisClient {
     }
 }
 
+#[async_trait::async_trait]
+impl RemoteCache for RedisClient {
+    async fn fetch_raw(&self, key: &str) -> Result<Option<String>> {
+        let mut conn = self.manager.clone();
+        Ok(conn.get::<_, Option<String>>(key).await?)
+    }
+
+    async fn store_raw(&self, key: &str, value: &str, ttl: u64) -> Result<()> {
+        let mut conn = self.manager.clone();
+        let _: () = conn.set_ex(key, value, ttl).await?;
+        Ok(())
+    }
+
+    async fn remove_raw(&self, keys: &[String]) -> Result<usize> {
+        let mut conn = self.manager.clone();
+        let removed: usize = conn.del(keys).await?;
+        Ok(removed)
+    }
+
+    async fn keys_matching(&self, pattern: &str) -> Result<Vec<String>> {
+        self.scan_keys(pattern).await
+    }
+}
+
 /// Estadísticas del cache
 #[derive(Debug, Default)]
 pub struct CacheStats {
@@ -3,6 +3,17 @@
 //! Este módulo maneja la configuración del entorno y variables de configuración.
 
 use std::env;
+use std::time::Duration;
+
+use crate::cache::geocoding_cache::DEFAULT_GEOCODING_TTL_SECS;
+use crate::dto::colis_prive_dto::OptimizationEngine;
+use crate::services::csv_import_service::MAX_IMPORT_ROWS;
+use crate::services::eta_service::WorkingHours;
+use crate::services::geocoding_service::GeocodingLocale;
+use crate::services::local_optimizer_service::StoppingCriteria;
+use crate::services::optimization_provider_service::{parse_provider_order, DEFAULT_PROVIDER_ORDER};
+use crate::utils::geo::CoordinateBounds;
+use crate::utils::retry::RetryPolicy;
 
 /// Configuración del entorno
 #[derive(Debug, Clone)]
@@ -16,12 +27,57 @@ pub struct EnvironmentConfig {
     pub rate_limit_requests: u32,
     pub rate_limit_window: u64,
     pub mapbox_token: Option<String>,
+    /// Idioma y país de los resultados de geocoding (GEOCODING_LANGUAGE / GEOCODING_COUNTRY)
+    pub geocoding_locale: GeocodingLocale,
+    /// Segundos que se guarda un resultado de geocoding (GEOCODING_CACHE_TTL_SECS, 30 días)
+    pub geocoding_cache_ttl_secs: u64,
+    /// Segundos durante los que se reutiliza una optimización reciente
+    pub optimization_reuse_window_secs: i64,
+    /// Peso de las paradas prioritarias (RCS) en el optimizador local (0 = solo distancia)
+    pub local_optimizer_priority_weight: f64,
+    /// Criterios de parada del 2-opt local (tiempo, movimientos sin mejora, mejora objetivo)
+    pub local_optimizer_stopping: StoppingCriteria,
+    /// Paradas por vehículo a partir de las que el optimizador local divide la
+    /// tournée en sub-rutas (LOCAL_OPTIMIZER_MAX_STOPS, 0 = sin límite)
+    pub local_optimizer_max_stops: usize,
+    /// Metros a partir de los que una entrega lejos del paquete se marca (DELIVERY_GEOFENCE_RADIUS_M)
+    pub delivery_geofence_radius_m: f64,
+    /// Orden global de proveedores de optimización (si la société no configura el suyo)
+    pub optimization_provider_order: Vec<OptimizationEngine>,
+    /// Horario de trabajo que limita las ETAs (WORKING_HOURS_START / _END / _UTC_OFFSET_MINUTES)
+    pub working_hours: WorkingHours,
+    /// Regiones donde unas coordenadas se consideran válidas (COORDINATE_REGIONS, por defecto metropole)
+    pub coordinate_bounds: CoordinateBounds,
+    /// Almacén `(lat, lon)` por defecto para las sociétés sin almacén registrado (WAREHOUSE_LOCATION)
+    pub warehouse_location: Option<(f64, f64)>,
+    /// Segundos que se espera a Postgres y Redis al arrancar (STARTUP_WAIT_TIMEOUT_SECS)
+    pub startup_wait_timeout_secs: u64,
     // URLs de Colis Privé
     pub colis_prive_auth_url: String,
     pub colis_prive_tournee_url: String,
     pub colis_prive_detail_url: String,
     pub colis_prive_gestion_url: String,
     pub colis_prive_referentiel_url: String,
+    /// Sociétés aceptadas en la autenticación (vacío = todas)
+    pub colis_prive_allowed_societes: Vec<String>,
+    /// Hosts de Colis Privé sin verificación de certificado (vacío = verificar siempre)
+    pub colis_prive_ssl_bypass_hosts: Vec<String>,
+    /// Reintentos ante 5xx o errores de conexión de Colis Privé
+    /// (COLIS_PRIVE_RETRY_MAX / COLIS_PRIVE_RETRY_BASE_DELAY_MS)
+    pub colis_prive_retry: RetryPolicy,
+    /// Secreto HMAC del webhook de estados (sin secreto el webhook está desactivado)
+    pub colis_prive_webhook_secret: Option<String>,
+    /// Secreto de la cabecera `X-Admin-Key` de las rutas de administración
+    /// (sin secreto esas rutas están desactivadas)
+    pub admin_api_key: Option<String>,
+    /// Autenticaciones por minuto y cuenta de Colis Privé (0 = sin límite)
+    pub colis_prive_auth_rate_per_min: u32,
+    /// Descargas de tournée por minuto y cuenta de Colis Privé (0 = sin límite)
+    pub colis_prive_tournee_rate_per_min: u32,
+    /// Filas de datos aceptadas por POST /packages/import (CSV_IMPORT_MAX_ROWS)
+    pub csv_import_max_rows: usize,
+    /// Dispatchers conectados a la vez a la misma tournée en directo (TOURNEE_LIVE_MAX_SUBSCRIBERS)
+    pub tournee_live_max_subscribers: usize,
 }
 
 impl Default for EnvironmentConfig {
@@ -52,6 +108,57 @@ impl Default for EnvironmentConfig {
                 .parse()
                 .expect("RATE_LIMIT_WINDOW must be a valid number"),
             mapbox_token: env::var("MAPBOX_TOKEN").ok(),
+            geocoding_locale: GeocodingLocale::from_env(),
+            geocoding_cache_ttl_secs: env::var("GEOCODING_CACHE_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|ttl: &u64| *ttl > 0)
+                .unwrap_or(DEFAULT_GEOCODING_TTL_SECS),
+            optimization_reuse_window_secs: env::var("OPTIMIZATION_REUSE_WINDOW_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(600),
+            local_optimizer_priority_weight: env::var("LOCAL_OPTIMIZER_PRIORITY_WEIGHT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.5),
+            local_optimizer_stopping: StoppingCriteria {
+                time_budget: Duration::from_millis(
+                    env::var("LOCAL_OPTIMIZER_TIME_BUDGET_MS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(2000),
+                ),
+                max_no_improvement: env::var("LOCAL_OPTIMIZER_MAX_NO_IMPROVEMENT")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(10_000),
+                target_improvement: env::var("LOCAL_OPTIMIZER_TARGET_IMPROVEMENT")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|target: &f64| *target > 0.0),
+            },
+            local_optimizer_max_stops: env::var("LOCAL_OPTIMIZER_MAX_STOPS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(150),
+            delivery_geofence_radius_m: env::var("DELIVERY_GEOFENCE_RADIUS_M")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|radius: &f64| *radius > 0.0)
+                .unwrap_or(200.0),
+            optimization_provider_order: env::var("OPTIMIZATION_PROVIDER_ORDER")
+                .ok()
+                .map(|v| parse_provider_order(&v))
+                .filter(|order| !order.is_empty())
+                .unwrap_or_else(|| DEFAULT_PROVIDER_ORDER.to_vec()),
+            working_hours: working_hours_from_env(),
+            coordinate_bounds: coordinate_bounds_from_env(),
+            warehouse_location: warehouse_location_from_env(),
+            startup_wait_timeout_secs: env::var("STARTUP_WAIT_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
             // URLs de Colis Privé
             colis_prive_auth_url: env::var("COLIS_PRIVE_AUTH_URL")
                 .expect("COLIS_PRIVE_AUTH_URL must be set"),
@@ -63,6 +170,52 @@ impl Default for EnvironmentConfig {
                 .expect("COLIS_PRIVE_GESTION_URL must be set"),
             colis_prive_referentiel_url: env::var("COLIS_PRIVE_REFERENTIEL_URL")
                 .expect("COLIS_PRIVE_REFERENTIEL_URL must be set"),
+            colis_prive_allowed_societes: env::var("COLIS_PRIVE_ALLOWED_SOCIETES")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            colis_prive_ssl_bypass_hosts: env::var("COLIS_PRIVE_SSL_BYPASS_HOSTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            colis_prive_retry: RetryPolicy {
+                max_retries: env::var("COLIS_PRIVE_RETRY_MAX")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(RetryPolicy::default().max_retries),
+                base_delay: env::var("COLIS_PRIVE_RETRY_BASE_DELAY_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .map(Duration::from_millis)
+                    .unwrap_or(RetryPolicy::default().base_delay),
+            },
+            colis_prive_webhook_secret: env::var("COLIS_PRIVE_WEBHOOK_SECRET")
+                .ok()
+                .filter(|s| !s.trim().is_empty()),
+            admin_api_key: env::var("ADMIN_API_KEY")
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+            colis_prive_auth_rate_per_min: env::var("COLIS_PRIVE_AUTH_RATE_PER_MIN")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+            colis_prive_tournee_rate_per_min: env::var("COLIS_PRIVE_TOURNEE_RATE_PER_MIN")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            csv_import_max_rows: env::var("CSV_IMPORT_MAX_ROWS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(MAX_IMPORT_ROWS),
+            tournee_live_max_subscribers: env::var("TOURNEE_LIVE_MAX_SUBSCRIBERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(20),
         }
     }
 }
@@ -84,6 +237,55 @@ impl EnvironmentConfig {
     }
 }
 
+/// Horario de trabajo desde el entorno ("HH:MM"); valores inválidos o un fin
+/// anterior al inicio dejan el horario por defecto (08:00–18:00, UTC+1)
+fn working_hours_from_env() -> WorkingHours {
+    let default = WorkingHours::default();
+    let time = |name: &str| {
+        env::var(name)
+            .ok()
+            .and_then(|v| chrono::NaiveTime::parse_from_str(v.trim(), "%H:%M").ok())
+    };
+
+    let start = time("WORKING_HOURS_START").unwrap_or(default.start);
+    let end = time("WORKING_HOURS_END").unwrap_or(default.end);
+    let utc_offset_minutes = env::var("WORKING_HOURS_UTC_OFFSET_MINUTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default.utc_offset_minutes);
+
+    if end <= start {
+        log::warn!("⚠️ WORKING_HOURS_END ({}) no es posterior a WORKING_HOURS_START ({}), usando 08:00–18:00", end, start);
+        return WorkingHours { utc_offset_minutes, ..default };
+    }
+    WorkingHours { start, end, utc_offset_minutes }
+}
+
+/// Regiones válidas desde el entorno; una lista inválida deja Francia metropolitana
+fn coordinate_bounds_from_env() -> CoordinateBounds {
+    match env::var("COORDINATE_REGIONS") {
+        Ok(spec) if !spec.trim().is_empty() => CoordinateBounds::parse(&spec).unwrap_or_else(|e| {
+            log::warn!("⚠️ COORDINATE_REGIONS inválido ({}), usando metropole", e);
+            CoordinateBounds::default()
+        }),
+        _ => CoordinateBounds::default(),
+    }
+}
+
+/// Almacén desde el entorno ("lat,lon"); un valor inválido se ignora
+fn warehouse_location_from_env() -> Option<(f64, f64)> {
+    let spec = env::var("WAREHOUSE_LOCATION").ok().filter(|v| !v.trim().is_empty())?;
+    let point = spec
+        .split_once(',')
+        .and_then(|(lat, lon)| Some((lat.trim().parse::<f64>().ok()?, lon.trim().parse::<f64>().ok()?)))
+        .filter(|(lat, lon)| (-90.0..=90.0).contains(lat) && (-180.0..=180.0).contains(lon));
+
+    if point.is_none() {
+        log::warn!("⚠️ WAREHOUSE_LOCATION inválido ({}), se usa la primera parada como salida", spec);
+    }
+    point
+}
+
 // Las credenciales de Colis Privé ahora se reciben dinámicamente via HTTP requests
 // No hay credenciales hardcodeadas en el código
 
@@ -1,19 +1,41 @@
-use crate::dto::address_dto::{SaveAddressRequest, AddressResponse, SearchAddressRequest};
+use crate::cache::geocoding_cache::GeocodingCache;
+use crate::dto::address_dto::{SaveAddressRequest, AddressResponse, SearchAddressRequest, GeocodeCandidatesRequest, CleanPreviewRequest, PinCoordinatesRequest, GeocacheEvictionQuery, GeocacheEvictionResponse, NearbyAddressesQuery, AddressesInBoundsQuery};
 use crate::dto::company_dto::ApiResponse;
+use crate::models::address_validation::AddressValidation;
+use crate::models::address::Address;
 use crate::repositories::address_repository::AddressRepository;
-use crate::services::geocoding_service::GeocodingService;
+use crate::repositories::address_validation_repository::AddressValidationRepository;
+use crate::repositories::spatial_query::{PageRequest, SpatialArea, SpatialPage};
+use crate::services::address_validation_service::validate_pinned_coordinates;
+use crate::services::address_cleaning_service::{clean_address_report, CleaningReport};
+use crate::services::geocoding_service::{GeocodeCandidate, GeocodingLocale, GeocodingService};
 use crate::utils::errors::AppError;
+use crate::utils::geo::CoordinateBounds;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+/// Candidatos devueltos si el cliente no indica `limit`
+const DEFAULT_CANDIDATES: usize = 3;
+
+/// Direcciones pendientes devueltas si el cliente no indica `limit`
+const DEFAULT_PENDING_VALIDATIONS: i64 = 50;
+
+/// Radio de /address/near si el cliente no indica `radius_m` (metros)
+const DEFAULT_NEAR_RADIUS_M: f64 = 200.0;
+
+/// Radio máximo de /address/near (metros)
+const MAX_NEAR_RADIUS_M: f64 = 5000.0;
+
 pub struct AddressController {
     repository: AddressRepository,
+    validations: AddressValidationRepository,
 }
 
 impl AddressController {
     pub fn new(pool: PgPool) -> Self {
         Self {
-            repository: AddressRepository::new(pool),
+            repository: AddressRepository::new(pool.clone()),
+            validations: AddressValidationRepository::new(pool),
         }
     }
 
@@ -58,24 +80,12 @@ impl AddressController {
         ))
     }
 
-    pub async fn get_by_id(&self, id: Uuid) -> Result<AddressResponse, AppError> {
-        let address = self.repository
-            .find_by_id(id)
+    /// Dirección con los datos guardados para el chofer (código, BAL, planta, gardien)
+    pub async fn get_by_id(&self, id: Uuid) -> Result<Address, AppError> {
+        self.repository
+            .find_book_entry(id)
             .await?
-            .ok_or_else(|| AppError::NotFound("Dirección no encontrada".to_string()))?;
-
-        Ok(AddressResponse {
-            id: address.id,
-            route_id: address.route_id,
-            address: address.address,
-            postal_code: address.postal_code,
-            door_codes: address.door_codes,
-            mailbox_access: address.mailbox_access.unwrap_or(false),
-            access_instructions: address.access_instructions,
-            latitude: None,
-            longitude: None,
-            created_at: address.created_at,
-        })
+            .ok_or_else(|| AppError::NotFound("Dirección no encontrada".to_string()))
     }
 
     pub async fn list_by_route(&self, route_id: Uuid) -> Result<Vec<AddressResponse>, AppError> {
@@ -164,7 +174,8 @@ impl AddressController {
         // Crear servicio de geocodificación (necesita token de Mapbox)
         let mapbox_token = std::env::var("MAPBOX_TOKEN")
             .map_err(|_| AppError::Internal("MAPBOX_TOKEN no configurado".to_string()))?;
-        let geocoding_service = GeocodingService::new(mapbox_token);
+        let geocoding_service = GeocodingService::new(mapbox_token)
+            .with_locale(GeocodingLocale::from_env());
         
         // Geocodificar
         match geocoding_service.geocode_address(&address).await {
@@ -197,4 +208,182 @@ impl AddressController {
             }
         }
     }
+
+    /// Candidatos de geocodificación para que el chófer elija el correcto
+    pub async fn geocode_candidates(
+        &self,
+        request: GeocodeCandidatesRequest,
+    ) -> Result<ApiResponse<Vec<GeocodeCandidate>>, AppError> {
+        if request.address.trim().is_empty() {
+            return Err(AppError::ValidationError("La dirección es requerida".to_string()));
+        }
+
+        let mapbox_token = std::env::var("MAPBOX_TOKEN")
+            .map_err(|_| AppError::Internal("MAPBOX_TOKEN no configurado".to_string()))?;
+        let geocoding_service = GeocodingService::new(mapbox_token)
+            .with_locale(GeocodingLocale::from_env());
+
+        let limit = request.limit.unwrap_or(DEFAULT_CANDIDATES);
+        let candidates = geocoding_service
+            .geocode_candidates(&request.address, limit)
+            .await
+            .map_err(|e| {
+                log::error!("❌ Error obteniendo candidatos: {}", e);
+                AppError::ExternalApi(format!("Error en geocodificación: {}", e))
+            })?;
+
+        let message = format!("{} candidatos encontrados", candidates.len());
+        Ok(ApiResponse::success_with_message(candidates, message))
+    }
+
+    /// Mostrar qué hace la limpieza automática con una dirección (sin guardar nada)
+    pub fn clean_preview(&self, request: CleanPreviewRequest) -> Result<ApiResponse<CleaningReport>, AppError> {
+        if request.address.trim().is_empty() {
+            return Err(AppError::ValidationError("La dirección es requerida".to_string()));
+        }
+
+        let report = clean_address_report(&request.address);
+        let message = format!("{} transformaciones aplicadas", report.transformations.len());
+        Ok(ApiResponse::success_with_message(report, message))
+    }
+
+    /// Direcciones que el geocoding no resolvió, para fijarlas a mano
+    pub async fn pending_validations(&self, limit: Option<i64>) -> Result<Vec<AddressValidation>, AppError> {
+        self.validations
+            .pending_manual(limit.unwrap_or(DEFAULT_PENDING_VALIDATIONS).clamp(1, 500))
+            .await
+    }
+
+    /// Direcciones de la libreta alrededor de un punto, de la más cercana a la
+    /// más lejana, con su distancia
+    pub async fn near(&self, query: NearbyAddressesQuery) -> Result<SpatialPage<Address>, AppError> {
+        let area = nearby_area(query.lat, query.lon, query.radius_m)?;
+        self.repository
+            .find_book_entries_in(area, PageRequest::new(query.page, query.per_page))
+            .await
+    }
+
+    /// Direcciones de la libreta dentro de un rectángulo, de la más cercana a
+    /// la más lejana de su centro
+    pub async fn in_bounds(&self, query: AddressesInBoundsQuery) -> Result<SpatialPage<Address>, AppError> {
+        let area = bounds_area(query.min_lat, query.min_lon, query.max_lat, query.max_lon)?;
+        self.repository
+            .find_book_entries_in(area, PageRequest::new(query.page, query.per_page))
+            .await
+    }
+
+    /// Olvidar del cache de geocoding una dirección o una zona (prefijo de
+    /// código postal) para que la próxima consulta vuelva a geocodificar
+    pub async fn evict_geocache(
+        &self,
+        query: GeocacheEvictionQuery,
+        cache: &GeocodingCache,
+    ) -> Result<ApiResponse<GeocacheEvictionResponse>, AppError> {
+        let address = query.address.as_deref().map(str::trim).filter(|a| !a.is_empty());
+        let prefix = query.postal_code_prefix.as_deref().map(str::trim).filter(|p| !p.is_empty());
+
+        let removed = match (address, prefix) {
+            (Some(address), None) => usize::from(cache.evict(address).await.map_err(cache_unavailable)?),
+            (None, Some(prefix)) => {
+                if prefix.len() > 5 || !prefix.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(AppError::ValidationError(format!(
+                        "Prefijo de código postal inválido '{}' (1 a 5 dígitos)",
+                        prefix
+                    )));
+                }
+                cache.evict_postal_prefix(prefix).await.map_err(cache_unavailable)?
+            }
+            _ => {
+                return Err(AppError::ValidationError(
+                    "Indique address o postal_code_prefix (solo uno)".to_string(),
+                ))
+            }
+        };
+
+        let message = format!("{} entradas eliminadas del cache de geocoding", removed);
+        Ok(ApiResponse::success_with_message(GeocacheEvictionResponse { removed }, message))
+    }
+
+    /// Fijar a mano las coordenadas de una dirección; las tournées siguientes las reutilizan
+    pub async fn pin_coordinates(
+        &self,
+        id: Uuid,
+        request: PinCoordinatesRequest,
+        bounds: &CoordinateBounds,
+    ) -> Result<ApiResponse<AddressValidation>, AppError> {
+        validate_pinned_coordinates(request.latitude, request.longitude, bounds)?;
+
+        let confirmed_by = request.confirmed_by.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        let validation = self
+            .validations
+            .confirm(id, request.latitude, request.longitude, confirmed_by)
+            .await?;
+
+        log::info!("📌 Coordenadas confirmadas para '{}' ({}, {})", validation.original_address, request.latitude, request.longitude);
+        Ok(ApiResponse::success_with_message(validation, "Coordenadas confirmadas".to_string()))
+    }
+}
+
+fn cache_unavailable(e: anyhow::Error) -> AppError {
+    log::error!("❌ No se pudo vaciar el cache de geocoding: {}", e);
+    AppError::ServiceUnavailable(format!("Cache de geocoding no disponible: {}", e))
+}
+
+fn check_point(latitude: f64, longitude: f64) -> Result<(), AppError> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(AppError::ValidationError(format!("Coordenadas inválidas ({}, {})", latitude, longitude)));
+    }
+    Ok(())
+}
+
+/// Zona de /address/near; 400 si el punto o el radio no son válidos
+fn nearby_area(latitude: f64, longitude: f64, radius_m: Option<f64>) -> Result<SpatialArea, AppError> {
+    check_point(latitude, longitude)?;
+
+    let radius_m = radius_m.unwrap_or(DEFAULT_NEAR_RADIUS_M);
+    if !(radius_m > 0.0 && radius_m <= MAX_NEAR_RADIUS_M) {
+        return Err(AppError::ValidationError(format!(
+            "radius_m debe estar entre 0 y {} metros",
+            MAX_NEAR_RADIUS_M
+        )));
+    }
+
+    Ok(SpatialArea::Radius { latitude, longitude, radius_m })
+}
+
+/// Zona de /address/in-bounds; 400 si las esquinas no son válidas o están invertidas
+fn bounds_area(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Result<SpatialArea, AppError> {
+    check_point(min_lat, min_lon)?;
+    check_point(max_lat, max_lon)?;
+    if min_lat >= max_lat || min_lon >= max_lon {
+        return Err(AppError::ValidationError(
+            "min_lat/min_lon deben ser menores que max_lat/max_lon".to_string(),
+        ));
+    }
+
+    Ok(SpatialArea::Bounds { min_lat, min_lon, max_lat, max_lon })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearby_area_defaults_radius_and_rejects_invalid_input() {
+        assert_eq!(
+            nearby_area(48.8566, 2.3522, None).unwrap(),
+            SpatialArea::Radius { latitude: 48.8566, longitude: 2.3522, radius_m: DEFAULT_NEAR_RADIUS_M }
+        );
+        assert!(nearby_area(91.0, 2.3522, None).is_err());
+        assert!(nearby_area(48.8566, 2.3522, Some(0.0)).is_err());
+        assert!(nearby_area(48.8566, 2.3522, Some(MAX_NEAR_RADIUS_M + 1.0)).is_err());
+        assert!(nearby_area(48.8566, 2.3522, Some(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn test_bounds_area_rejects_inverted_corners() {
+        assert!(bounds_area(48.8, 2.2, 48.9, 2.4).is_ok());
+        assert!(bounds_area(48.9, 2.2, 48.8, 2.4).is_err());
+        assert!(bounds_area(48.8, 2.4, 48.9, 2.2).is_err());
+    }
 }
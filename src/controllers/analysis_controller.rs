@@ -0,0 +1,126 @@
+use chrono::{Duration, NaiveDate};
+
+use crate::dto::analysis_dto::{ReattemptQuery, ReattemptWorklistResponse, ValidationTrendsQuery, ValidationTrendsResponse};
+use crate::repositories::address_validation_repository::AddressValidationRepository;
+use crate::repositories::delivery_progress_repository::DeliveryProgressRepository;
+use crate::repositories::optimization_repository::OptimizationRepository;
+use crate::services::reattempt_service::build_worklist;
+use crate::services::validation_trends_service::build_trends;
+use crate::state::AppState;
+use crate::utils::errors::AppError;
+
+/// Días de tendencias devueltos si el cliente no indica `from`
+const DEFAULT_TREND_DAYS: i64 = 30;
+
+/// Rango máximo de las tendencias
+const MAX_TREND_DAYS: i64 = 366;
+
+pub struct AnalysisController;
+
+impl AnalysisController {
+    /// Paquetes fallidos de todos los repartidores de la société en la fecha
+    pub async fn reattempt_worklist(
+        query: &ReattemptQuery,
+        state: &AppState,
+    ) -> Result<ReattemptWorklistResponse, AppError> {
+        let date = query
+            .date
+            .clone()
+            .unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%d").to_string());
+
+        let progresses = DeliveryProgressRepository::new(state.redis.clone())
+            .list_for_date(&query.societe, &date)
+            .await?;
+
+        // Direcciones desde la ruta optimizada guardada de cada repartidor
+        let history = OptimizationRepository::new(state.redis.clone());
+        let mut tournees = Vec::with_capacity(progresses.len());
+        for progress in progresses {
+            let packages = history
+                .latest(&query.societe, &progress.matricule, &date)
+                .await
+                .map(|stored| stored.packages)
+                .unwrap_or_default();
+            tournees.push((progress, packages));
+        }
+
+        let groups = build_worklist(&tournees, query.group_by);
+        let total_packages = groups.iter().map(|group| group.packages.len()).sum();
+        log::info!(
+            "🔁 Reintentos {} del {}: {} paquetes de {} tournées",
+            query.societe, date, total_packages, tournees.len()
+        );
+
+        Ok(ReattemptWorklistResponse {
+            success: true,
+            societe: query.societe.clone(),
+            date,
+            group_by: query.group_by,
+            total_packages,
+            groups,
+        })
+    }
+
+    /// Resultados de validación de direcciones por día
+    pub async fn validation_trends(
+        query: &ValidationTrendsQuery,
+        state: &AppState,
+    ) -> Result<ValidationTrendsResponse, AppError> {
+        let (from, to) = trend_range(query.from.as_deref(), query.to.as_deref(), chrono::Utc::now().date_naive())?;
+
+        let rows = AddressValidationRepository::new(state.pool.clone())
+            .daily_outcomes(from, to)
+            .await?;
+        let days = build_trends(&rows, from, to);
+        log::info!("📈 Tendencias de validación del {} al {}: {} paquetes", from, to, days.iter().map(|d| d.total).sum::<i64>());
+
+        Ok(ValidationTrendsResponse {
+            success: true,
+            from: from.to_string(),
+            to: to.to_string(),
+            days,
+        })
+    }
+}
+
+fn parse_day(value: &str, name: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| AppError::ValidationError(format!("Fecha '{}' inválida en {} (formato YYYY-MM-DD)", value, name)))
+}
+
+/// Rango de días pedido: por defecto los últimos `DEFAULT_TREND_DAYS` hasta hoy
+fn trend_range(from: Option<&str>, to: Option<&str>, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let to = to.map(|to| parse_day(to, "to")).transpose()?.unwrap_or(today);
+    let from = from
+        .map(|from| parse_day(from, "from"))
+        .transpose()?
+        .unwrap_or(to - Duration::days(DEFAULT_TREND_DAYS - 1));
+
+    if from > to {
+        return Err(AppError::ValidationError(format!("'from' ({}) es posterior a 'to' ({})", from, to)));
+    }
+    if (to - from).num_days() >= MAX_TREND_DAYS {
+        return Err(AppError::ValidationError(format!("El rango no puede superar {} días", MAX_TREND_DAYS)));
+    }
+    Ok((from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trend_range_defaults_and_limits() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+
+        let (from, to) = trend_range(None, None, today).unwrap();
+        assert_eq!((from.to_string(), to), ("2025-01-02".to_string(), today));
+
+        let (from, _) = trend_range(Some("2025-01-10"), Some("2025-01-20"), today).unwrap();
+        assert_eq!(from.to_string(), "2025-01-10");
+
+        assert!(matches!(trend_range(Some("2025-01-20"), Some("2025-01-10"), today), Err(AppError::ValidationError(_))));
+        assert!(matches!(trend_range(Some("2023-01-01"), None, today), Err(AppError::ValidationError(_))));
+        assert!(matches!(trend_range(Some("10/01/2025"), None, today), Err(AppError::ValidationError(_))));
+    }
+}
@@ -1,32 +1,98 @@
 use crate::dto::colis_prive_dto::*;
+use crate::dto::mapbox_optimization_dto::OptimizationPackage;
+use crate::models::delivery_progress::{DeliveryProgress, StopOutcome};
+use crate::models::optimization::StoredOptimization;
+use crate::models::tournee_snapshot::TourneeSnapshot;
+use crate::repositories::address_validation_repository::AddressValidationRepository;
 use crate::repositories::colis_prive_repository::ColisPriveRepository;
-use crate::services::colis_prive_service::ColisPriveService;
+use crate::repositories::company_settings_repository::CompanySettingsRepository;
+use crate::repositories::delivery_progress_repository::{DeliveryProgressRepository, StopIdempotency};
+use crate::repositories::optimization_repository::OptimizationRepository;
+use crate::repositories::package_label_repository::PackageLabelRepository;
+use crate::repositories::package_status_repository::PackageStatusRepository;
+use crate::repositories::tournee_snapshot_repository::TourneeSnapshotRepository;
+use crate::services::colis_prive_service::{full_matricule, require_coordinates, sanitize_coordinates, AddressValidationSummary, AuthenticationResult, ColisPriveService};
+use crate::services::address_validation_service::{apply_stored_validation, geocoding_outcome_method};
 use crate::services::colis_prive_companies_service;
+use crate::services::delivery_geofence_service::{outside_geofence, reported_location};
+use crate::services::depot_service::societe_depot;
+use crate::services::validation_trends_service::tournee_outcomes;
+use crate::services::eta_service::{estimate_completion, planned_duration};
+use crate::services::export_service;
+use crate::services::full_tournee_service;
+use crate::services::packages_batch_service;
+use crate::services::geocoding_quality_service::quality_report;
+use crate::services::gpx_export_service::{build_route_gpx, GpxRoute};
 use crate::services::geocoding_service::GeocodingService;
-use crate::utils::errors::AppError;
+use crate::services::local_optimizer_service::{LocalOptimizerService, LocalOptimizerStats, RouteStop};
+use crate::services::mapbox_matrix_service::MapboxMatrixService;
+use crate::services::mapbox_optimization_service::{apply_solution, tournee_stop, validate_time_windows, MapboxOptimizationService};
+use crate::services::navigation_service;
+use crate::services::route_depot_service::depot_stops;
+use crate::services::route_split_service::split_stops;
+use crate::services::package_label_service::PRIORITY_LABELS;
+use crate::services::optimization_history_service::{check_tournee_unchanged, compute_order_diff, reusable_optimization, stored_or_not_found};
+use crate::services::optimization_provider_service::resolve_provider_order;
+use crate::services::status_webhook_service;
+use crate::services::tournee_fetch_service::{fetch_shared, tournee_flight_key, TourneeFetches};
+use crate::services::tournee_live_service::{StopEvent, TourneeLiveHub};
+use crate::services::tournee_snapshot_service::{diff_snapshots, package_states};
+use crate::services::tournee_merge_service::{merge_tournees, MAX_MERGED_TOURNEES};
+use crate::utils::errors::{AppError, OptimizationError};
+use crate::utils::single_flight::SingleFlight;
 use crate::state::AppState;
+use chrono::NaiveDate;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::broadcast;
+
+/// Resultado de un proveedor de optimización: paquetes en orden y, si es el
+/// optimizador local, sus métricas
+struct EngineOutput {
+    matricule_chauffeur: String,
+    date_tournee: String,
+    packages: Vec<PackageData>,
+    optimizer_stats: Option<LocalOptimizerStats>,
+    suggested_split: Vec<SuggestedSubRoute>,
+}
 
 pub struct ColisPriveController {
     repository: ColisPriveRepository,
     service: ColisPriveService,
+    logins: SingleFlight<AuthenticationResult>,
+    tournee_fetches: TourneeFetches,
 }
 
 impl ColisPriveController {
     pub fn new(state: &AppState) -> Self {
         Self {
-            repository: ColisPriveRepository::new(state.auth_tokens.clone()),
-            service: ColisPriveService::new(state.http_client.clone(), state.config.clone()),
+            repository: ColisPriveRepository::new(state.auth_tokens.clone(), state.redis.clone()),
+            service: ColisPriveService::new(state.colis_prive_clients.clone(), state.config.clone()),
+            logins: state.colis_prive_logins.clone(),
+            tournee_fetches: state.tournee_fetches.clone(),
         }
     }
 
+    #[tracing::instrument(
+        name = "colis_prive_auth",
+        skip_all,
+        fields(societe = %request.societe, username = %request.username)
+    )]
     pub async fn authenticate(
         &self,
         request: ColisPriveAuthRequest,
     ) -> Result<ColisPriveAuthResponse, AppError> {
         log::info!("🔐 Autenticando usuario: {}", request.username);
 
-        // Llamar al servicio para autenticar
-        match self.service.authenticate(&request.username, &request.password, &request.societe).await {
+        // Llamar al servicio para autenticar; logins idénticos simultáneos
+        // comparten la misma llamada a Colis Privé
+        let login = async {
+            self.service
+                .authenticate(&request.username, &request.password, &request.societe)
+                .await
+                .map_err(|e| e.to_string())
+        };
+        match self.logins.run(&login_flight_key(&request), login).await {
             Ok(auth_data) => {
                 // Extraer solo la parte del matricule (después del _)
                 let matricule_only = if let Some(pos) = auth_data.matricule_chauffeur.rfind('_') {
@@ -70,42 +136,162 @@ impl ColisPriveController {
                     success: false,
                     message: None,
                     authentication: None,
-                    error: Some(e.to_string()),
+                    error: Some(e),
                 })
             }
         }
     }
 
+    /// Invalidar el token guardado de un usuario (soporte: credenciales
+    /// cambiadas o token obsoleto que provoca 401 repetidos)
+    pub async fn logout(&self, request: ColisPriveLogoutRequest) -> ColisPriveLogoutResponse {
+        let (username, societe) = (&request.username, &request.societe);
+
+        let removed = self.repository.remove_token(societe, username).await;
+        if removed {
+            log::info!("🚪 Token de {}:{} invalidado", societe, username);
+        } else {
+            log::info!("🚪 Sin token guardado para {}:{}", societe, username);
+        }
+
+        ColisPriveLogoutResponse {
+            success: true,
+            removed,
+            message: if removed {
+                "Token invalidado".to_string()
+            } else {
+                "No había token guardado".to_string()
+            },
+        }
+    }
+
+    /// Invalidar los tokens de todos los usuarios de una société (rotación de
+    /// las credenciales compartidas): cada usuario vuelve a autenticarse
+    pub async fn revoke_societe_tokens(&self, societe: &str) -> Result<SocieteTokensRevokedResponse, AppError> {
+        let societe = societe.trim();
+        if societe.is_empty() {
+            return Err(AppError::ValidationError("La société es requerida".to_string()));
+        }
+
+        let removed = self.repository.remove_societe_tokens(societe).await;
+        log::warn!("🔐 Rotación de credenciales de {}: {} tokens invalidados", societe, removed);
+
+        Ok(SocieteTokensRevokedResponse {
+            success: true,
+            societe: societe.to_string(),
+            removed,
+            message: format!("{} tokens invalidados; los usuarios deben autenticarse de nuevo", removed),
+        })
+    }
+
+    #[tracing::instrument(
+        name = "colis_prive_packages",
+        skip_all,
+        fields(societe = %request.societe, username = %request.matricule)
+    )]
     pub async fn get_packages(
         &self,
         request: GetPackagesRequest,
         state: &AppState,
     ) -> Result<PackagesResponse, AppError> {
-        log::info!("📦 Obteniendo paquetes para: {}:{}", request.societe, request.matricule);
+        let packages = self
+            .validated_packages(&request.societe, &request.matricule, request.date.as_deref(), state)
+            .await?;
+        let total = packages.len();
+
+        let address_validation = AddressValidationSummary::from_packages(&packages);
+        log::info!("📊 Direcciones: {} con coordenadas, {} sin coordenadas",
+            address_validation.with_coordinates, address_validation.without_coordinates);
+
+        Ok(PackagesResponse {
+            success: true,
+            packages,
+            total,
+            address_validation: Some(address_validation),
+        })
+    }
+
+    /// Paquetes de varias fechas; cada fecha lleva su propio éxito o error
+    pub async fn get_packages_batch(
+        &self,
+        request: PackagesBatchRequest,
+        state: &AppState,
+    ) -> Result<PackagesBatchResponse, AppError> {
+        let dates = packages_batch_service::unique_dates(&request.dates)?;
+        log::info!("📦 Lote de {} fechas para {}:{}", dates.len(), request.societe, request.matricule);
+
+        let results = packages_batch_service::fetch_dates(dates, |date| {
+            let request = &request;
+            async move {
+                let date = parse_tournee_date(Some(&date))?.format("%Y-%m-%d").to_string();
+                self.get_packages(
+                    GetPackagesRequest {
+                        matricule: request.matricule.clone(),
+                        societe: request.societe.clone(),
+                        date: Some(date),
+                    },
+                    state,
+                )
+                .await
+            }
+        })
+        .await;
+
+        Ok(PackagesBatchResponse {
+            success: results.values().any(|result| result.success),
+            matricule: request.matricule,
+            results,
+        })
+    }
+
+    /// Paquetes de la tournée con coordenadas: los de Colis Privé se usan tal
+    /// cual y el resto se geocodifica con Mapbox
+    async fn validated_packages(
+        &self,
+        societe: &str,
+        matricule: &str,
+        date: Option<&str>,
+        state: &AppState,
+    ) -> Result<Vec<PackageData>, AppError> {
+        log::info!("📦 Obteniendo paquetes para: {}:{}", societe, matricule);
 
         // Obtener token del cache
         let token = self.repository
-            .get_token(&request.societe, &request.matricule)
+            .get_token(societe, matricule)
             .await
             .ok_or_else(|| AppError::Unauthorized("Token no encontrado. Por favor, autentíquese primero.".to_string()))?;
 
         // Verificar si el token expiró
         if token.is_expired() {
             log::warn!("⚠️ Token expirado, removiendo del cache");
-            self.repository.remove_token(&request.societe, &request.matricule).await;
-            return Err(AppError::Unauthorized("Token expirado. Por favor, autentíquese nuevamente.".to_string()));
+            self.repository.remove_token(societe, matricule).await;
+            return Err(AppError::AuthExpired("Token expirado. Por favor, autentíquese nuevamente.".to_string()));
         }
 
-        // Llamar al servicio para obtener paquetes
-        let mut packages = self.service.get_tournee(
-            &token.token,
-            &request.matricule,
-            &request.societe,
-            request.date.as_deref(),
-        ).await?;
+        // Llamar al servicio para obtener paquetes; descargas idénticas
+        // simultáneas (reintentos de la app) comparten la misma llamada
+        let flight_key = tournee_flight_key(societe, matricule, date.unwrap_or(&today()));
+        let fetch = self.service.get_tournee(&token.token, matricule, societe, date);
+        let mut packages = match fetch_shared(&self.tournee_fetches, &flight_key, fetch).await {
+            Ok(packages) => packages,
+            Err(AppError::AuthExpired(msg)) => {
+                log::warn!("⚠️ Colis Privé rechazó el token, removiendo del cache");
+                self.repository.remove_token(societe, matricule).await;
+                return Err(AppError::AuthExpired(msg));
+            }
+            Err(e) => return Err(e),
+        };
 
-        let total = packages.len();
-        log::info!("✅ Paquetes obtenidos: {}", total);
+        log::info!("✅ Paquetes obtenidos: {}", packages.len());
+
+        // Registrar los paquetes para que el webhook de estados pueda actualizarlos
+        let known: Vec<(String, Option<String>)> = packages
+            .iter()
+            .map(|p| (p.reference_colis.clone(), p.code_statut_article.clone()))
+            .collect();
+        if let Err(e) = PackageStatusRepository::new(state.pool.clone()).register_known(&known).await {
+            log::warn!("⚠️ No se pudieron registrar los paquetes de la tournée: {}", e);
+        }
 
         // 🗺️ Geocoding automático de paquetes
         log::info!("🗺️ Iniciando geocoding automático de {} paquetes...", packages.len());
@@ -114,12 +300,18 @@ impl ColisPriveController {
         let mapbox_token = state.config.mapbox_token.clone()
             .ok_or_else(|| AppError::ExternalApi("Mapbox token no configurado".to_string()))?;
         
-        let geocoding_service = GeocodingService::new(mapbox_token);
+        let geocoding_service = GeocodingService::new(mapbox_token)
+            .with_locale(state.config.geocoding_locale.clone())
+            .with_cache(state.geocode_cache.clone());
 
+        let validations = AddressValidationRepository::new(state.pool.clone());
         let mut geocoded_count = 0;
         let mut already_geocoded = 0;
+        let mut reused_validations = 0;
 
         for package in &mut packages {
+            sanitize_coordinates(package, &state.config.coordinate_bounds);
+
             // Si ya tiene coordenadas de Colis Privé, usarlas
             if package.coord_x_destinataire.is_some() && package.coord_y_destinataire.is_some() {
                 package.latitude = package.coord_y_destinataire;
@@ -153,8 +345,18 @@ impl ColisPriveController {
                 continue;
             }
 
+            // Validación guardada (p.ej. coordenadas confirmadas por un dispatcher)
+            match validations.find(&full_address).await {
+                Ok(Some(stored)) if apply_stored_validation(package, &stored) => {
+                    reused_validations += 1;
+                    continue;
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("⚠️ No se pudo leer la validación de {}: {}", full_address, e),
+            }
+
             // Hacer geocoding
-            match geocoding_service.geocode_address(&full_address).await {
+            let found = match geocoding_service.geocode_address(&full_address).await {
                 Ok(geo_result) if geo_result.success => {
                     package.latitude = geo_result.latitude;
                     package.longitude = geo_result.longitude;
@@ -162,32 +364,114 @@ impl ColisPriveController {
                     package.validation_method = Some("geocoded".to_string());
                     package.validation_confidence = Some(0.9); // Alta confianza para Mapbox
                     geocoded_count += 1;
+                    true
                 }
                 Ok(_) => {
                     log::warn!("⚠️ No se pudo geocodificar: {}", full_address);
+                    false
                 }
                 Err(e) => {
                     log::error!("❌ Error geocodificando {}: {}", full_address, e);
+                    false
                 }
+            };
+
+            let coordinates = package.latitude.zip(package.longitude).filter(|_| found);
+            if let Err(e) = validations
+                .record(&full_address, coordinates, geocoding_outcome_method(found), package.validation_confidence.filter(|_| found))
+                .await
+            {
+                log::warn!("⚠️ No se pudo guardar la validación de {}: {}", full_address, e);
             }
         }
 
-        log::info!("✅ Geocoding completado: {} nuevos, {} ya existentes, {} total", 
-            geocoded_count, already_geocoded, packages.len());
+        log::info!("✅ Geocoding completado: {} nuevos, {} ya existentes, {} validaciones reutilizadas, {} total", 
+            geocoded_count, already_geocoded, reused_validations, packages.len());
 
-        Ok(PackagesResponse {
+        // Resultados de validación para las tendencias diarias
+        let outcomes = tournee_outcomes(&packages);
+        log::info!(
+            "📊 Validación {}:{}: {}",
+            societe,
+            matricule,
+            outcomes.iter().map(|o| format!("{}={}", o.method.as_str(), o.outcomes)).collect::<Vec<_>>().join(", ")
+        );
+        if let Ok(day) = parse_tournee_date(date) {
+            if let Err(e) = validations.record_tournee_outcomes(day, societe, matricule, &outcomes).await {
+                log::warn!("⚠️ No se pudieron guardar los resultados de validación: {}", e);
+            }
+        }
+
+        Ok(packages)
+    }
+
+    /// Tournée completa en una llamada: paquetes validados y, con `details`,
+    /// el detalle de los prioritarios
+    pub async fn get_full_tournee(
+        &self,
+        matricule: &str,
+        query: FullTourneeQuery,
+        state: &AppState,
+    ) -> Result<FullTourneeResponse, AppError> {
+        let date = parse_tournee_date(query.date.as_deref())?.format("%Y-%m-%d").to_string();
+        let packages = self
+            .validated_packages(&query.societe, matricule, Some(&date), state)
+            .await?;
+        let address_validation = AddressValidationSummary::from_packages(&packages);
+
+        // Detalle solo si se pide: una consulta de etiquetas por etiqueta prioritaria
+        let priority_labels = if query.details {
+            let labels = PackageLabelRepository::new(state.pool.clone());
+            let mut by_reference: HashMap<String, Vec<String>> = HashMap::new();
+            for label in PRIORITY_LABELS {
+                for reference in labels.references_with_label(label).await? {
+                    by_reference.entry(reference).or_default().push(label.to_string());
+                }
+            }
+            Some(by_reference)
+        } else {
+            None
+        };
+
+        let total = packages.len();
+        let packages = full_tournee_service::consolidate(packages, priority_labels.as_ref());
+        log::info!(
+            "📦 Tournée completa {}:{} ({} paquetes, {} con detalle)",
+            query.societe, matricule, total, packages.iter().filter(|p| p.detail.is_some()).count()
+        );
+
+        Ok(FullTourneeResponse {
             success: true,
-            packages,
+            matricule: matricule.to_string(),
+            date_tournee: date,
             total,
+            packages,
+            address_validation,
         })
     }
 
     pub async fn optimize_route(
         &self,
         request: OptimizeRouteRequest,
+        query: OptimizeQuery,
         state: &AppState,
     ) -> Result<OptimizeRouteResponse, AppError> {
-        log::info!("🔄 Optimizando ruta para: {}:{}", request.societe, request.matricule);
+        let tournee_date = parse_tournee_date(request.date.as_deref())?;
+        // Un matricule mal formado no se arregla cambiando de proveedor
+        full_matricule(&request.societe, &request.matricule)?;
+        let company_order = CompanySettingsRepository::new(state.pool.clone())
+            .optimization_providers(&request.societe)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("⚠️ No se pudo leer la configuración de {}: {}", request.societe, e);
+                None
+            });
+        let providers = resolve_provider_order(
+            query.engine,
+            company_order.as_deref(),
+            &state.config.optimization_provider_order,
+        );
+        log::info!("🔄 Optimizando ruta para: {}:{} (proveedores: {:?})", request.societe, request.matricule, providers);
 
         // Obtener token del cache
         let token = self.repository
@@ -199,26 +483,891 @@ impl ColisPriveController {
         if token.is_expired() {
             log::warn!("⚠️ Token expirado");
             self.repository.remove_token(&request.societe, &request.matricule).await;
-            return Err(AppError::Unauthorized("Token expirado. Por favor, autentíquese nuevamente.".to_string()));
+            return Err(AppError::AuthExpired("Token expirado. Por favor, autentíquese nuevamente.".to_string()));
         }
 
-        // Llamar al servicio para optimizar
-        let optimized_data = self.service.optimize_tournee(
-            &token.token,
-            &request.matricule,
+        let history = OptimizationRepository::new(state.redis.clone());
+        let date_key = tournee_date.format("%Y-%m-%d").to_string();
+        let depot = societe_depot(&state.pool, &request.societe, state.config.warehouse_location).await;
+
+        // Reutilizar una optimización reciente salvo que se fuerce el recálculo
+        let cached = history.latest(&request.societe, &request.matricule, &date_key).await;
+        if let Some(stored) = reusable_optimization(
+            cached,
+            providers[0],
+            query.force,
+            chrono::Duration::seconds(state.config.optimization_reuse_window_secs),
+            chrono::Utc::now(),
+        ) {
+            log::info!("♻️ Reutilizando optimización de {} (usar ?force=true para recalcular)", stored.created_at);
+            return Ok(OptimizeRouteResponse {
+                success: true,
+                message: Some("Optimización reciente reutilizada".to_string()),
+                data: Some(with_depot(stored, depot, state)),
+            });
+        }
+
+        // Probar los proveedores en orden hasta que uno responda
+        let mut attempt = None;
+        for (index, &engine) in providers.iter().enumerate() {
+            match self.run_engine(engine, &token.token, &request, tournee_date, state).await {
+                Ok(result) => {
+                    attempt = Some((engine, result));
+                    break;
+                }
+                // Sin coordenadas ningún otro proveedor puede optimizar
+                Err(e @ AppError::Optimization(OptimizationError::NoCoordinates { .. })) => return Err(e),
+                Err(e) if index + 1 < providers.len() => {
+                    log::warn!("⚠️ Proveedor {:?} falló, probando el siguiente: {}", engine, e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let (engine, EngineOutput { matricule_chauffeur, date_tournee, packages: optimized_packages, optimizer_stats, suggested_split }) = attempt
+            .ok_or_else(|| AppError::Internal("Ningún proveedor de optimización configurado".to_string()))?;
+
+        // Guardar el resultado (sobrescribe el anterior) y comparar con la optimización previa
+        let stored = StoredOptimization::new(
             &request.societe,
-        ).await?;
+            &request.matricule,
+            &matricule_chauffeur,
+            &date_key,
+            engine,
+            optimized_packages.clone(),
+        );
+        let order_changes = history
+            .latest(&request.societe, &request.matricule, &stored.date_tournee)
+            .await
+            .map(|previous| compute_order_diff(&previous.package_order, &stored.package_order));
+        if let Some(changes) = &order_changes {
+            log::info!("🔀 {} paquetes cambiaron de posición respecto a la optimización anterior", changes.moved.len());
+        }
+        if let Err(e) = history.save(&stored).await {
+            log::warn!("⚠️ No se pudo guardar la optimización: {}", e);
+        }
+
+        let depot_stops = route_depot_stops(&optimized_packages, tournee_date, depot, state);
+        let duration = planned_duration(&optimized_packages, depot);
+        let data = OptimizationData {
+            tournee_hash: stored.tournee_hash(),
+            matricule_chauffeur,
+            date_tournee,
+            optimized_packages: optimized_packages.into_iter().map(Into::into).collect(),
+            order_changes,
+            optimizer_stats,
+            depot_stops,
+            suggested_split,
+            duration,
+        };
 
         log::info!("✅ Ruta optimizada");
 
         Ok(OptimizeRouteResponse {
             success: true,
             message: Some("Ruta optimizada exitosamente".to_string()),
-            data: Some(OptimizationData {
-                matricule_chauffeur: optimized_data.matricule_chauffeur,
-                date_tournee: optimized_data.date_tournee,
-                optimized_packages: optimized_data.packages,
-            }),
+            data: Some(data),
+        })
+    }
+
+    /// Optimizar con un proveedor concreto
+    async fn run_engine(
+        &self,
+        engine: OptimizationEngine,
+        token: &str,
+        request: &OptimizeRouteRequest,
+        tournee_date: NaiveDate,
+        state: &AppState,
+    ) -> Result<EngineOutput, AppError> {
+        match engine {
+            OptimizationEngine::ColisPrive => {
+                // Llamar al servicio para optimizar
+                let optimized_data = self.service.optimize_tournee(
+                    token,
+                    &request.matricule,
+                    &request.societe,
+                    tournee_date,
+                ).await?;
+
+                Ok(EngineOutput {
+                    matricule_chauffeur: optimized_data.matricule_chauffeur,
+                    date_tournee: optimized_data.date_tournee,
+                    packages: optimized_data.packages,
+                    optimizer_stats: None,
+                    suggested_split: Vec::new(),
+                })
+            }
+            OptimizationEngine::Local => self.optimize_locally(token, request, tournee_date, state).await,
+            OptimizationEngine::Mapbox => self.optimize_tournee_mapbox(token, request, tournee_date, state).await,
+        }
+    }
+
+    /// Optimizar la tournée con el optimizador local (vecino más cercano + 2-opt)
+    async fn optimize_locally(
+        &self,
+        sso_token: &str,
+        request: &OptimizeRouteRequest,
+        tournee_date: NaiveDate,
+        state: &AppState,
+    ) -> Result<EngineOutput, AppError> {
+        let date = tournee_date.format("%Y-%m-%d").to_string();
+        let packages = self.service.get_tournee(
+            sso_token,
+            &request.matricule,
+            &request.societe,
+            Some(&date),
+        ).await?;
+
+        // Separar paquetes con y sin coordenadas
+        let (located, unlocated): (Vec<PackageData>, Vec<PackageData>) = packages
+            .into_iter()
+            .partition(|p| package_coordinates(p).is_some());
+
+        if located.is_empty() {
+            require_coordinates(&unlocated)?;
+        }
+
+        // Paquetes prioritarios (RCS...) según las etiquetas de los dispatchers
+        let labels = PackageLabelRepository::new(state.pool.clone());
+        let mut priority_references = HashSet::new();
+        for label in PRIORITY_LABELS {
+            match labels.references_with_label(label).await {
+                Ok(references) => priority_references.extend(references),
+                Err(e) => log::warn!("⚠️ No se pudieron leer las etiquetas '{}': {}", label, e),
+            }
+        }
+
+        let stops: Vec<RouteStop> = located
+            .iter()
+            .filter_map(|p| {
+                package_coordinates(p).map(|(latitude, longitude)| RouteStop {
+                    latitude,
+                    longitude,
+                    high_priority: priority_references.contains(&p.reference_colis),
+                    delivery_slot: p.delivery_slot,
+                })
+            })
+            .collect();
+
+        let optimizer = match (&state.config.mapbox_token, request.use_traffic) {
+            (Some(mapbox_token), true) => LocalOptimizerService::with_traffic(
+                MapboxMatrixService::new(mapbox_token.clone()),
+                Some(state.redis.clone()),
+            ),
+            (None, true) => {
+                log::warn!("⚠️ Mapbox token no configurado, optimizando sin tráfico");
+                LocalOptimizerService::new()
+            }
+            _ => LocalOptimizerService::new(),
+        }
+        .with_priority_weight(state.config.local_optimizer_priority_weight)
+        .with_stopping_criteria(state.config.local_optimizer_stopping);
+
+        // Por encima del máximo de paradas se optimiza cada grupo k-means por separado
+        let clusters = split_stops(&stops, state.config.local_optimizer_max_stops);
+        if clusters.len() > 1 {
+            log::info!(
+                "✂️ {} paradas superan el máximo de {}, dividiendo en {} sub-rutas",
+                stops.len(),
+                state.config.local_optimizer_max_stops,
+                clusters.len()
+            );
+        }
+
+        let mut order = Vec::with_capacity(stops.len());
+        let mut sub_routes = Vec::with_capacity(clusters.len());
+        for cluster in &clusters {
+            let cluster_stops: Vec<RouteStop> = cluster.iter().map(|&index| stops[index].clone()).collect();
+            let result = optimizer.optimize(&cluster_stops).await;
+            let cluster_order: Vec<usize> = result.order.iter().map(|&position| cluster[position]).collect();
+            order.extend_from_slice(&cluster_order);
+            sub_routes.push(SuggestedSubRoute {
+                references: cluster_order.iter().map(|&index| located[index].reference_colis.clone()).collect(),
+                optimizer_stats: result.stats,
+            });
+        }
+
+        let mut optimized_packages: Vec<PackageData> = order
+            .iter()
+            .enumerate()
+            .map(|(position, &index)| {
+                let mut package = located[index].clone();
+                package.numero_ordre = Some(position as i32 + 1);
+                package.num_ordre_passage_prevu = Some(position as i32 + 1);
+                package
+            })
+            .collect();
+
+        if !unlocated.is_empty() {
+            log::warn!("⚠️ {} paquetes sin coordenadas quedan al final de la ruta", unlocated.len());
+        }
+        optimized_packages.extend(unlocated.into_iter().map(|mut package| {
+            package.numero_ordre = None;
+            package.num_ordre_passage_prevu = None;
+            package
+        }));
+
+        // Sin división las métricas van en la respuesta; con varias sub-rutas, en cada una
+        let optimizer_stats = if sub_routes.len() == 1 {
+            sub_routes.pop().map(|route| route.optimizer_stats)
+        } else {
+            None
+        };
+
+        Ok(EngineOutput {
+            matricule_chauffeur: format!("{}_{}", request.societe, request.matricule),
+            date_tournee: date,
+            packages: optimized_packages,
+            optimizer_stats,
+            suggested_split: sub_routes,
+        })
+    }
+
+    /// Optimizar la tournée con Mapbox Optimization saliendo del almacén configurado
+    async fn optimize_tournee_mapbox(
+        &self,
+        sso_token: &str,
+        request: &OptimizeRouteRequest,
+        tournee_date: NaiveDate,
+        state: &AppState,
+    ) -> Result<EngineOutput, AppError> {
+        let mapbox_token = state
+            .config
+            .mapbox_token
+            .clone()
+            .ok_or_else(|| AppError::ServiceUnavailable("Mapbox token no configurado".to_string()))?;
+
+        let date = tournee_date.format("%Y-%m-%d").to_string();
+        let packages = self.service.get_tournee(
+            sso_token,
+            &request.matricule,
+            &request.societe,
+            Some(&date),
+        ).await?;
+
+        let (located, unlocated): (Vec<PackageData>, Vec<PackageData>) = packages
+            .into_iter()
+            .partition(|p| package_coordinates(p).is_some());
+
+        if located.is_empty() {
+            require_coordinates(&unlocated)?;
+        }
+
+        let stops: Vec<OptimizationPackage> = located
+            .iter()
+            .filter_map(|p| package_coordinates(p).map(|coordinates| tournee_stop(p, coordinates)))
+            .collect();
+        // Ventanas horarias que no son RFC 3339 o terminan antes de empezar: 400 sin llamar a Mapbox
+        validate_time_windows(&stops).map_err(AppError::ValidationError)?;
+        // Mapbox espera (lon, lat)
+        let warehouse = societe_depot(&state.pool, &request.societe, state.config.warehouse_location)
+            .await
+            .map(|(latitude, longitude)| (longitude, latitude));
+
+        let response = MapboxOptimizationService::new(mapbox_token)
+            .optimize_route(stops, warehouse)
+            .await?;
+        let solution = response.data.map(|data| data.optimized_packages).unwrap_or_default();
+
+        let mut optimized_packages = apply_solution(located, &solution);
+        let unrouted = optimized_packages.iter().filter(|p| p.numero_ordre.is_none()).count();
+        if unrouted > 0 || !unlocated.is_empty() {
+            log::warn!(
+                "⚠️ {} paquetes descartados por Mapbox y {} sin coordenadas quedan al final de la ruta",
+                unrouted, unlocated.len()
+            );
+        }
+        optimized_packages.extend(unlocated.into_iter().map(|mut package| {
+            package.numero_ordre = None;
+            package.num_ordre_passage_prevu = None;
+            package
+        }));
+
+        Ok(EngineOutput {
+            matricule_chauffeur: format!("{}_{}", request.societe, request.matricule),
+            date_tournee: date,
+            packages: optimized_packages,
+            optimizer_stats: None,
+            suggested_split: Vec::new(),
+        })
+    }
+
+    /// Última optimización guardada (sin volver a llamar al optimizador)
+    pub async fn get_latest_optimization(
+        &self,
+        matricule: &str,
+        query: OptimizationHistoryQuery,
+        state: &AppState,
+    ) -> Result<OptimizeRouteResponse, AppError> {
+        let date = query.date.unwrap_or_else(today);
+        let history = OptimizationRepository::new(state.redis.clone());
+
+        let latest = stored_or_not_found(history.latest(&query.societe, matricule, &date).await, &query.societe, matricule, &date)?;
+        log::info!("♻️ Devolviendo optimización guardada de {}:{} ({})", query.societe, matricule, latest.created_at);
+        let depot = societe_depot(&state.pool, &query.societe, state.config.warehouse_location).await;
+
+        Ok(OptimizeRouteResponse {
+            success: true,
+            message: Some("Última optimización guardada".to_string()),
+            data: Some(with_depot(latest, depot, state)),
+        })
+    }
+
+    /// Cambios de orden entre las dos últimas optimizaciones de una tournée
+    pub async fn get_order_changes(
+        &self,
+        matricule: &str,
+        query: OptimizationHistoryQuery,
+        state: &AppState,
+    ) -> Result<OrderChangesResponse, AppError> {
+        let date = query.date.unwrap_or_else(today);
+        let history = OptimizationRepository::new(state.redis.clone());
+
+        let latest = stored_or_not_found(history.latest(&query.societe, matricule, &date).await, &query.societe, matricule, &date)?;
+        let previous = history.previous(&query.societe, matricule, &date).await;
+
+        let changes = match &previous {
+            Some(previous) => compute_order_diff(&previous.package_order, &latest.package_order),
+            None => compute_order_diff(&latest.package_order, &latest.package_order),
+        };
+
+        Ok(OrderChangesResponse {
+            success: true,
+            matricule: matricule.to_string(),
+            date_tournee: date,
+            previous_optimized_at: previous.map(|p| p.created_at),
+            latest_optimized_at: latest.created_at,
+            changes,
+        })
+    }
+
+    /// Aplicar la última optimización si la tournée no cambió desde entonces (409 si cambió)
+    pub async fn apply_optimization(
+        &self,
+        matricule: &str,
+        request: ApplyOptimizationRequest,
+        state: &AppState,
+    ) -> Result<ApplyOptimizationResponse, AppError> {
+        let date = request.date.clone().unwrap_or_else(today);
+        log::info!("📌 Aplicando optimización de {}:{} del {}", request.societe, matricule, date);
+
+        let history = OptimizationRepository::new(state.redis.clone());
+        let stored = stored_or_not_found(history.latest(&request.societe, matricule, &date).await, &request.societe, matricule, &date)?;
+
+        let optimized_hash = stored.tournee_hash();
+        if request.tournee_hash != optimized_hash {
+            return Err(AppError::Conflict(
+                "Existe una optimización más reciente para esta tournée. Vuelva a cargarla.".to_string(),
+            ));
+        }
+
+        let token = self.repository
+            .get_token(&request.societe, matricule)
+            .await
+            .ok_or_else(|| AppError::Unauthorized("Token no encontrado. Por favor, autentíquese primero.".to_string()))?;
+
+        if token.is_expired() {
+            self.repository.remove_token(&request.societe, matricule).await;
+            return Err(AppError::AuthExpired("Token expirado. Por favor, autentíquese nuevamente.".to_string()));
+        }
+
+        let current = self.service.get_tournee(&token.token, matricule, &request.societe, request.date.as_deref()).await?;
+        let current_references: Vec<String> = current.into_iter().map(|p| p.reference_colis).collect();
+        if let Err(e) = check_tournee_unchanged(&optimized_hash, &current_references) {
+            log::warn!("⚠️ Tournée {}:{} cambió desde la optimización ({} paquetes ahora)", request.societe, matricule, current_references.len());
+            return Err(e);
+        }
+
+        log::info!("✅ Optimización aplicada: {} paquetes", stored.packages.len());
+
+        Ok(ApplyOptimizationResponse {
+            success: true,
+            matricule: matricule.to_string(),
+            date_tournee: date,
+            tournee_hash: optimized_hash,
+            optimized_packages: stored.packages.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    /// Fusionar las tournées de varias sociétés (cada una con el token de su société)
+    pub async fn merge_tournees(&self, request: MergeTourneesRequest) -> Result<MergeTourneesResponse, AppError> {
+        if request.tournees.is_empty() || request.tournees.len() > MAX_MERGED_TOURNEES {
+            return Err(AppError::ValidationError(format!(
+                "Se requieren entre 1 y {} tournées para fusionar",
+                MAX_MERGED_TOURNEES
+            )));
+        }
+
+        log::info!("🔗 Fusionando {} tournées", request.tournees.len());
+
+        let mut tournees = Vec::with_capacity(request.tournees.len());
+        for source in request.tournees {
+            let token = self.repository
+                .get_token(&source.societe, &source.matricule)
+                .await
+                .ok_or_else(|| AppError::Unauthorized(format!(
+                    "Token no encontrado para {}:{}. Por favor, autentíquese primero.",
+                    source.societe, source.matricule
+                )))?;
+
+            if token.is_expired() {
+                self.repository.remove_token(&source.societe, &source.matricule).await;
+                return Err(AppError::AuthExpired(format!(
+                    "Token expirado para {}:{}. Por favor, autentíquese nuevamente.",
+                    source.societe, source.matricule
+                )));
+            }
+
+            let packages = self.service
+                .get_tournee(&token.token, &source.matricule, &source.societe, source.date.as_deref())
+                .await?;
+            log::info!("📦 {}:{} → {} paquetes", source.societe, source.matricule, packages.len());
+
+            tournees.push((source, packages));
+        }
+
+        let (sources, packages) = merge_tournees(tournees);
+        log::info!("✅ Tournées fusionadas: {} paquetes", packages.len());
+
+        Ok(MergeTourneesResponse {
+            success: true,
+            total_packages: packages.len(),
+            sources,
+            packages,
+        })
+    }
+
+    /// Exportar una tournée: la última optimización guardada o, si no hay, la tournée de Colis Privé.
+    /// Devuelve `(contenido, content-type, nombre de fichero)`.
+    pub async fn export_tournee(
+        &self,
+        matricule: &str,
+        query: ExportQuery,
+        state: &AppState,
+    ) -> Result<(Vec<u8>, &'static str, String), AppError> {
+        let date = query.date.clone().unwrap_or_else(today);
+        log::info!("📤 Exportando tournée {}:{} del {} ({:?})", query.societe, matricule, date, query.format);
+
+        let packages = self.tournee_packages(&query.societe, matricule, query.date.as_deref(), state).await?;
+
+        let rows = export_service::build_rows(&packages);
+        let filename = format!("tournee_{}_{}_{}", query.societe, matricule, date);
+
+        let export = match query.format {
+            ExportFormat::Csv => (
+                export_service::to_csv(&rows).into_bytes(),
+                export_service::CSV_CONTENT_TYPE,
+                format!("{}.csv", filename),
+            ),
+            ExportFormat::Xlsx => (
+                export_service::to_xlsx(&rows)?,
+                export_service::XLSX_CONTENT_TYPE,
+                format!("{}.xlsx", filename),
+            ),
+        };
+
+        log::info!("✅ Tournée exportada: {} paquetes", rows.len());
+        Ok(export)
+    }
+
+    /// Ruta optimizada en GPX 1.1 para GPS independientes (Garmin).
+    /// Devuelve la ruta y el nombre de fichero; 404 si no hay optimización guardada.
+    pub async fn export_route_gpx(
+        &self,
+        matricule: &str,
+        query: OptimizationHistoryQuery,
+        state: &AppState,
+    ) -> Result<(GpxRoute, String), AppError> {
+        let date = query.date.unwrap_or_else(today);
+        let history = OptimizationRepository::new(state.redis.clone());
+        let latest = stored_or_not_found(history.latest(&query.societe, matricule, &date).await, &query.societe, matricule, &date)?;
+
+        let route = build_route_gpx(&format!("{} {}", matricule, date), &latest.packages);
+        log::info!(
+            "🧭 GPX de {}:{} del {}: {} paradas, {} sin coordenadas",
+            query.societe, matricule, date, latest.packages.len() - route.skipped, route.skipped
+        );
+
+        Ok((route, format!("ruta_{}_{}_{}.gpx", query.societe, matricule, date)))
+    }
+
+    /// Chequeo previo a la salida: distribución de la calidad del geocoding
+    pub async fn get_geocoding_quality(
+        &self,
+        matricule: &str,
+        query: QualityQuery,
+        state: &AppState,
+    ) -> Result<GeocodingQualityResponse, AppError> {
+        let date = query.date.clone().unwrap_or_else(today);
+        let packages = self.tournee_packages(&query.societe, matricule, query.date.as_deref(), state).await?;
+
+        let report = quality_report(&packages);
+        log::info!(
+            "🔎 Calidad de geocoding {}:{} del {}: {} paquetes, {} a revisar",
+            query.societe, matricule, date, report.total_packages, report.low_confidence.len()
+        );
+
+        Ok(GeocodingQualityResponse {
+            success: true,
+            matricule: matricule.to_string(),
+            date_tournee: date,
+            report,
+        })
+    }
+
+    /// Paquetes de la tournée: la última optimización guardada o, si no hay,
+    /// la tournée de Colis Privé (requiere token)
+    async fn tournee_packages(
+        &self,
+        societe: &str,
+        matricule: &str,
+        date: Option<&str>,
+        state: &AppState,
+    ) -> Result<Vec<PackageData>, AppError> {
+        let date_key = date.map(str::to_string).unwrap_or_else(today);
+        let history = OptimizationRepository::new(state.redis.clone());
+        if let Some(stored) = history.latest(societe, matricule, &date_key).await {
+            return Ok(stored.packages);
+        }
+
+        let token = self.repository
+            .get_token(societe, matricule)
+            .await
+            .ok_or_else(|| AppError::Unauthorized("Token no encontrado. Por favor, autentíquese primero.".to_string()))?;
+
+        if token.is_expired() {
+            self.repository.remove_token(societe, matricule).await;
+            return Err(AppError::AuthExpired("Token expirado. Por favor, autentíquese nuevamente.".to_string()));
+        }
+
+        self.service.get_tournee(&token.token, matricule, societe, date).await
+    }
+
+    /// Registrar una parada visitada (entregada o fallida). Con `idempotency_key`,
+    /// un reintento con la misma clave devuelve la respuesta original sin
+    /// registrar la parada otra vez
+    pub async fn record_stop(
+        &self,
+        reference_colis: &str,
+        outcome: StopOutcome,
+        request: StopProgressRequest,
+        idempotency_key: Option<&str>,
+        state: &AppState,
+    ) -> Result<StopProgressResponse, AppError> {
+        let Some(key) = idempotency_key else {
+            return self.apply_stop(reference_colis, outcome, request, state).await;
+        };
+
+        let (societe, matricule) = (request.societe.clone(), request.matricule.clone());
+        let repository = DeliveryProgressRepository::new(state.redis.clone());
+
+        // La clave se reserva antes de registrar: de dos reintentos simultáneos
+        // solo uno llega a registrar la parada
+        match repository.reserve_stop(&societe, &matricule, reference_colis, key).await? {
+            StopIdempotency::Reserved => {}
+            StopIdempotency::InProgress => {
+                return Err(AppError::Conflict(format!(
+                    "La parada {} ya se está registrando con la misma Idempotency-Key",
+                    reference_colis
+                )));
+            }
+            StopIdempotency::Processed(previous) => {
+                log::info!("🔁 Parada {} ya registrada con Idempotency-Key {}", reference_colis, key);
+                return Ok(previous);
+            }
+        }
+
+        let result = self.apply_stop(reference_colis, outcome, request, state).await;
+        match &result {
+            // La parada ya está registrada: si no se puede guardar la clave solo se avisa
+            Ok(response) => {
+                if let Err(e) = repository
+                    .save_processed_stop(&societe, &matricule, reference_colis, key, response)
+                    .await
+                {
+                    log::warn!("⚠️ {}", e);
+                }
+            }
+            Err(_) => repository.release_stop(&societe, &matricule, reference_colis, key).await,
+        }
+
+        result
+    }
+
+    /// Registrar la parada en el progreso de la tournée y publicarla en directo
+    async fn apply_stop(
+        &self,
+        reference_colis: &str,
+        outcome: StopOutcome,
+        request: StopProgressRequest,
+        state: &AppState,
+    ) -> Result<StopProgressResponse, AppError> {
+        let date = request.date.unwrap_or_else(today);
+        let reported = reported_location(request.delivery_lat, request.delivery_lng)?;
+        let repository = DeliveryProgressRepository::new(state.redis.clone());
+
+        let mut progress = repository
+            .get(&request.societe, &request.matricule, &date)
+            .await
+            .unwrap_or_else(|| DeliveryProgress::new(&request.societe, &request.matricule, &date));
+
+        if let Err((current, requested)) = progress.check_transition(reference_colis, outcome) {
+            log::warn!("⛔ Transición inválida para {}: {:?} → {:?}", reference_colis, current, requested);
+            return Err(AppError::Conflict(format!(
+                "El paquete {} está {:?} y no puede pasar a {:?}",
+                reference_colis, current, requested
+            )));
+        }
+
+        let distance_outside = match (outcome, reported) {
+            (StopOutcome::Delivered, Some(reported)) => {
+                self.delivery_outside_geofence(reference_colis, reported, &request.societe, &request.matricule, &date, state)
+                    .await
+            }
+            _ => None,
+        };
+
+        let reported_address = match (distance_outside, reported) {
+            (Some(_), Some(reported)) => reported_address(reported, state).await,
+            _ => None,
+        };
+
+        let completed_at = chrono::Utc::now();
+        let reason = request.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+        progress.record(reference_colis, outcome, reason, completed_at);
+        if distance_outside.is_some() {
+            progress.flag_delivery_location(reference_colis);
+        }
+        repository.save(&progress).await?;
+
+        log::info!("📍 Parada {} marcada como {:?} ({}:{})", reference_colis, outcome, request.societe, request.matricule);
+
+        let warning = distance_outside.map(|distance| {
+            log::warn!("🚩 Paquete {} entregado a {:.0} m de su dirección", reference_colis, distance);
+            let mut warning = format!(
+                "Entrega registrada a {:.0} m de la dirección del paquete (máximo {:.0} m)",
+                distance, state.config.delivery_geofence_radius_m
+            );
+            if let Some(address) = &reported_address {
+                warning.push_str(&format!(", cerca de {}", address));
+            }
+            warning
+        });
+
+        let response = StopProgressResponse {
+            success: true,
+            reference_colis: reference_colis.to_string(),
+            outcome,
+            completed_at,
+            completed_stops: progress.completed.len(),
+            delivery_location_flagged: distance_outside.is_some(),
+            reported_address,
+            warning,
+        };
+
+        state.tournee_live.publish(
+            &TourneeLiveHub::tournee_key(&request.societe, &request.matricule, &date),
+            StopEvent {
+                matricule: request.matricule.clone(),
+                reference_colis: reference_colis.to_string(),
+                outcome,
+                completed_at,
+                completed_stops: response.completed_stops,
+            },
+        );
+
+        Ok(response)
+    }
+
+    /// Distancia (m) de la entrega a las coordenadas del paquete si queda fuera
+    /// de la geocerca; si no se conocen las coordenadas no se comprueba
+    async fn delivery_outside_geofence(
+        &self,
+        reference_colis: &str,
+        reported: (f64, f64),
+        societe: &str,
+        matricule: &str,
+        date: &str,
+        state: &AppState,
+    ) -> Option<f64> {
+        let packages = match self.tournee_packages(societe, matricule, Some(date), state).await {
+            Ok(packages) => packages,
+            Err(e) => {
+                log::warn!("⚠️ No se pudo comprobar la geocerca de {}: {}", reference_colis, e);
+                return None;
+            }
+        };
+
+        let coordinates = packages
+            .iter()
+            .find(|package| package.reference_colis == reference_colis)
+            .and_then(package_coordinates)?;
+        outside_geofence(reported, coordinates, state.config.delivery_geofence_radius_m)
+    }
+
+    /// Tomar una foto del estado actual de los paquetes de una tournée
+    pub async fn take_snapshot(
+        &self,
+        matricule: &str,
+        request: TourneeSnapshotRequest,
+        state: &AppState,
+    ) -> Result<TourneeSnapshotResponse, AppError> {
+        let tournee_date = parse_tournee_date(request.date.as_deref())?;
+        let date = tournee_date.format("%Y-%m-%d").to_string();
+
+        let packages = self.tournee_packages(&request.societe, matricule, Some(&date), state).await?;
+        let references: Vec<String> = packages.iter().map(|p| p.reference_colis.clone()).collect();
+        let statuses = PackageStatusRepository::new(state.pool.clone()).statuses(&references).await?;
+        let progress = DeliveryProgressRepository::new(state.redis.clone())
+            .get(&request.societe, matricule, &date)
+            .await;
+
+        let states = package_states(&packages, &statuses, progress.as_ref());
+        let snapshot = TourneeSnapshotRepository::new(state.pool.clone())
+            .create(&request.societe, matricule, tournee_date, states)
+            .await?;
+
+        log::info!("📸 Foto de la tournée {}:{} del {} ({} paquetes)", request.societe, matricule, date, packages.len());
+
+        Ok(TourneeSnapshotResponse { success: true, snapshot })
+    }
+
+    /// Fotos de una tournée en orden cronológico
+    pub async fn list_snapshots(
+        &self,
+        matricule: &str,
+        query: OptimizationHistoryQuery,
+        state: &AppState,
+    ) -> Result<TourneeSnapshotsResponse, AppError> {
+        let tournee_date = parse_tournee_date(query.date.as_deref())?;
+        let snapshots = TourneeSnapshotRepository::new(state.pool.clone())
+            .list(&query.societe, matricule, tournee_date)
+            .await?;
+
+        Ok(TourneeSnapshotsResponse {
+            success: true,
+            matricule: matricule.to_string(),
+            date_tournee: tournee_date.format("%Y-%m-%d").to_string(),
+            snapshots,
+        })
+    }
+
+    /// Cambios de estado entre dos fotos de la misma tournée
+    pub async fn diff_snapshots(
+        &self,
+        matricule: &str,
+        query: SnapshotDiffQuery,
+        state: &AppState,
+    ) -> Result<SnapshotDiffResponse, AppError> {
+        let tournee_date = parse_tournee_date(query.date.as_deref())?;
+        let repository = TourneeSnapshotRepository::new(state.pool.clone());
+        let snapshot = |found, id| tournee_snapshot(found, id, &query.societe, matricule, tournee_date);
+        let from = snapshot(repository.find_by_id(query.from).await?, query.from)?;
+        let to = snapshot(repository.find_by_id(query.to).await?, query.to)?;
+
+        Ok(SnapshotDiffResponse {
+            success: true,
+            matricule: matricule.to_string(),
+            from_taken_at: from.taken_at,
+            to_taken_at: to.taken_at,
+            diff: diff_snapshots(&from.packages, &to.packages),
+        })
+    }
+
+    /// Suscribirse a las paradas que se registren en una tournée (WebSocket en directo)
+    pub fn subscribe_live(
+        matricule: &str,
+        query: OptimizationHistoryQuery,
+        state: &AppState,
+    ) -> Result<(String, broadcast::Receiver<StopEvent>), AppError> {
+        let date = query.date.unwrap_or_else(today);
+        let tournee = TourneeLiveHub::tournee_key(&query.societe, matricule, &date);
+        let receiver = state.tournee_live.subscribe(&tournee)?;
+        log::info!("📡 Dispatcher conectado a la tournée {}", tournee);
+        Ok((tournee, receiver))
+    }
+
+    /// Estimar la hora de fin de la tournée según el progreso y la ruta optimizada
+    pub async fn estimate_eta(
+        &self,
+        matricule: &str,
+        query: EtaQuery,
+        state: &AppState,
+    ) -> Result<EtaResponse, AppError> {
+        let date = query.date.unwrap_or_else(today);
+
+        let route = OptimizationRepository::new(state.redis.clone())
+            .latest(&query.societe, matricule, &date)
+            .await
+            .ok_or_else(|| AppError::NotFound(format!("No hay ruta optimizada para {}:{} el {}", query.societe, matricule, date)))?;
+        let progress = DeliveryProgressRepository::new(state.redis.clone())
+            .get(&query.societe, matricule, &date)
+            .await;
+
+        let eta = estimate_completion(&route.packages, progress.as_ref(), chrono::Utc::now(), &state.config.working_hours);
+        if !eta.unschedulable.is_empty() {
+            log::warn!(
+                "⚠️ {} paquetes de {}:{} no caben en la jornada (fin {})",
+                eta.unschedulable.len(), query.societe, matricule, eta.working_hours_end
+            );
+        }
+
+        log::info!(
+            "⏱️ ETA {}:{}: {} ({:?}, {} paradas restantes)",
+            query.societe, matricule, eta.estimated_finish, eta.method, eta.remaining_stops
+        );
+
+        Ok(EtaResponse {
+            success: true,
+            matricule: matricule.to_string(),
+            date_tournee: date,
+            method: eta.method,
+            estimated_finish: eta.estimated_finish,
+            completed_stops: eta.completed_stops,
+            remaining_stops: eta.remaining_stops,
+            remaining_distance_km: eta.remaining_distance_km,
+            average_stop_seconds: eta.average_stop_seconds,
+            working_hours_end: eta.working_hours_end,
+            unschedulable_count: eta.unschedulable.len(),
+            unschedulable: eta.unschedulable,
+        })
+    }
+
+    /// Deep links de navegación para la última ruta optimizada
+    pub async fn navigation_links(
+        &self,
+        matricule: &str,
+        query: NavLinkQuery,
+        state: &AppState,
+    ) -> Result<NavLinkResponse, AppError> {
+        let date = query.date.unwrap_or_else(today);
+
+        let route = OptimizationRepository::new(state.redis.clone())
+            .latest(&query.societe, matricule, &date)
+            .await
+            .ok_or_else(|| AppError::NotFound(format!("No hay ruta optimizada para {}:{} el {}", query.societe, matricule, date)))?;
+
+        let stops: Vec<Option<(f64, f64)>> = route.packages.iter().map(package_coordinates).collect();
+        let skipped: Vec<String> = route
+            .packages
+            .iter()
+            .zip(&stops)
+            .filter(|(_, stop)| stop.is_none())
+            .map(|(package, _)| package.reference_colis.clone())
+            .collect();
+        let links = navigation_service::build_links(query.app, &stops);
+
+        log::info!(
+            "🧭 {} enlaces {:?} para {}:{} ({} paquetes sin coordenadas)",
+            links.len(), query.app, query.societe, matricule, skipped.len()
+        );
+
+        Ok(NavLinkResponse {
+            success: true,
+            matricule: matricule.to_string(),
+            date_tournee: date,
+            app: query.app,
+            links,
+            skipped,
         })
     }
 
@@ -243,4 +1392,187 @@ impl ColisPriveController {
             companies: company_list,
         })
     }
+
+    /// Refrescar las sociétés aceptadas con los códigos del referencial de empresas
+    pub async fn refresh_allowed_societes(state: &AppState) -> Result<AllowedSocietesResponse, AppError> {
+        log::info!("🔄 Refrescando sociétés soportadas");
+
+        let companies = colis_prive_companies_service::fetch_all_companies().await?;
+        let total = state
+            .societe_allowlist
+            .refresh(companies.into_iter().map(|c| c.code));
+
+        log::info!("✅ Sociétés soportadas: {}", total);
+
+        Ok(AllowedSocietesResponse {
+            success: true,
+            enabled: state.societe_allowlist.is_enabled(),
+            societes: state.societe_allowlist.allowed(),
+        })
+    }
+
+    /// Procesar un lote firmado de estados enviado por webhook
+    pub async fn receive_status_webhook(
+        state: &AppState,
+        signature: Option<&str>,
+        body: &[u8],
+    ) -> Result<StatusWebhookResponse, AppError> {
+        let secret = state
+            .config
+            .colis_prive_webhook_secret
+            .as_deref()
+            .ok_or_else(|| AppError::ServiceUnavailable("Webhook de estados no configurado".to_string()))?;
+
+        status_webhook_service::verify_signature(secret, body, signature)?;
+
+        let request: StatusWebhookRequest = serde_json::from_slice(body)
+            .map_err(|e| AppError::BadRequest(format!("Lote de estados inválido: {}", e)))?;
+
+        log::info!("📨 Webhook de estados: {} actualizaciones", request.updates.len());
+
+        let outcome = PackageStatusRepository::new(state.pool.clone())
+            .apply_batch(request.updates)
+            .await?;
+
+        log::info!(
+            "✅ Estados actualizados: {} (desconocidos: {}, antiguos: {})",
+            outcome.updated,
+            outcome.ignored_unknown,
+            outcome.ignored_stale
+        );
+
+        Ok(StatusWebhookResponse {
+            success: true,
+            outcome,
+        })
+    }
+}
+
+/// Coordenadas `(lat, lon)` de un paquete: primero las de Colis Privé, luego las geocodificadas
+fn package_coordinates(package: &PackageData) -> Option<(f64, f64)> {
+    let latitude = package.coord_y_destinataire.or(package.latitude)?;
+    let longitude = package.coord_x_destinataire.or(package.longitude)?;
+    Some((latitude, longitude))
+}
+
+/// Dirección más cercana a la posición de una entrega fuera de la geocerca,
+/// para que el dispatcher vea dónde se entregó (sin Mapbox o si falla, `None`)
+async fn reported_address((latitude, longitude): (f64, f64), state: &AppState) -> Option<String> {
+    let mapbox_token = state.config.mapbox_token.clone()?;
+    let response = GeocodingService::new(mapbox_token)
+        .with_locale(state.config.geocoding_locale.clone())
+        .reverse_geocode(latitude, longitude)
+        .await;
+
+    match response {
+        Ok(response) => response.formatted_address,
+        Err(e) => {
+            log::warn!("⚠️ Reverse geocoding de ({}, {}) fallido: {}", latitude, longitude, e);
+            None
+        }
+    }
+}
+
+/// Salida y vuelta al almacén `(lat, lon)`, saliendo al inicio de la jornada de la tournée
+fn route_depot_stops(
+    packages: &[PackageData],
+    tournee_date: NaiveDate,
+    depot: Option<(f64, f64)>,
+    state: &AppState,
+) -> Vec<DepotStop> {
+    let departure = state.config.working_hours.day_start(tournee_date);
+    depot_stops(packages, depot, departure)
+}
+
+/// Respuesta de una optimización guardada con el almacén de salida y vuelta
+fn with_depot(stored: StoredOptimization, depot: Option<(f64, f64)>, state: &AppState) -> OptimizationData {
+    let depot_stops = NaiveDate::parse_from_str(&stored.date_tournee, "%Y-%m-%d")
+        .map(|date| route_depot_stops(&stored.packages, date, depot, state))
+        .unwrap_or_default();
+    let duration = planned_duration(&stored.packages, depot);
+    OptimizationData { depot_stops, duration, ..stored.into() }
+}
+
+/// Clave de login en curso: `{societe}_{username}` más una huella de la
+/// contraseña, para que un login con otra contraseña no reciba el token ajeno
+fn login_flight_key(request: &ColisPriveAuthRequest) -> String {
+    let digest = Sha256::digest(request.password.as_bytes());
+    let fingerprint: String = digest.iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}_{}:{}", request.societe, request.username, fingerprint)
+}
+
+/// Fecha de la tournée pedida (YYYY-MM-DD) o, si no se indica, hoy
+fn parse_tournee_date(date: Option<&str>) -> Result<NaiveDate, AppError> {
+    match date {
+        Some(date) => NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map_err(|_| AppError::ValidationError(format!("Fecha de tournée inválida '{}' (formato YYYY-MM-DD)", date))),
+        None => Ok(chrono::Utc::now().date_naive()),
+    }
+}
+
+/// Foto `id` si pertenece a la tournée `(societe, matricule, date)`; 404 si
+/// no existe o es de otra société (mismo matricule) o de otro día
+fn tournee_snapshot(
+    found: Option<TourneeSnapshot>,
+    id: uuid::Uuid,
+    societe: &str,
+    matricule: &str,
+    date: NaiveDate,
+) -> Result<TourneeSnapshot, AppError> {
+    found
+        .filter(|snapshot| snapshot.societe == societe && snapshot.matricule == matricule && snapshot.date_tournee == date)
+        .ok_or_else(|| {
+            AppError::NotFound(format!("No hay foto {} de la tournée {}:{} del {}", id, societe, matricule, date))
+        })
+}
+
+/// Fecha de hoy (YYYY-MM-DD), usada como fecha de tournée por defecto
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(societe: &str, matricule: &str, date: &str) -> TourneeSnapshot {
+        TourneeSnapshot {
+            id: uuid::Uuid::new_v4(),
+            societe: societe.to_string(),
+            matricule: matricule.to_string(),
+            date_tournee: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            taken_at: chrono::Utc::now(),
+            packages: sqlx::types::Json(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_of_the_requested_tournee_is_found() {
+        let found = snapshot("PCP0010699", "A187518", "2024-03-12");
+        let date = found.date_tournee;
+
+        let result = tournee_snapshot(Some(found.clone()), found.id, "PCP0010699", "A187518", date);
+
+        assert_eq!(result.unwrap().id, found.id);
+    }
+
+    #[test]
+    fn test_same_matricule_in_another_societe_is_not_found() {
+        let other = snapshot("PCP0020000", "A187518", "2024-03-12");
+        let date = other.date_tournee;
+
+        let result = tournee_snapshot(Some(other.clone()), other.id, "PCP0010699", "A187518", date);
+
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_snapshot_from_another_day_is_not_found() {
+        let yesterday = snapshot("PCP0010699", "A187518", "2024-03-11");
+        let today = NaiveDate::from_ymd_opt(2024, 3, 12).unwrap();
+
+        let result = tournee_snapshot(Some(yesterday.clone()), yesterday.id, "PCP0010699", "A187518", today);
+
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
 }
@@ -0,0 +1,114 @@
+use crate::dto::depot_dto::{CreateDepotRequest, DepotResponse, UpdateDepotRequest};
+use crate::dto::company_dto::ApiResponse;
+use crate::repositories::depot_repository::DepotRepository;
+use crate::services::depot_service::{normalize_societe, validate_depot};
+use crate::utils::errors::AppError;
+use crate::utils::geo::CoordinateBounds;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct DepotController {
+    repository: DepotRepository,
+    bounds: CoordinateBounds,
+}
+
+impl DepotController {
+    pub fn new(pool: PgPool, bounds: CoordinateBounds) -> Self {
+        Self {
+            repository: DepotRepository::new(pool),
+            bounds,
+        }
+    }
+
+    pub async fn create(
+        &self,
+        company_id: Uuid,
+        request: CreateDepotRequest,
+    ) -> Result<ApiResponse<DepotResponse>, AppError> {
+        validate_depot(&request.name, request.latitude, request.longitude, &self.bounds)?;
+
+        let depot = self.repository.create(
+            company_id,
+            request.name.trim().to_string(),
+            normalize_societe(request.societe),
+            request.latitude,
+            request.longitude,
+            request.is_default.unwrap_or(false),
+        ).await?;
+
+        log::info!("🏭 Almacén '{}' creado ({}, {})", depot.name, depot.latitude, depot.longitude);
+
+        Ok(ApiResponse::success_with_message(
+            depot.into(),
+            "Almacén creado exitosamente".to_string()
+        ))
+    }
+
+    pub async fn get_by_id(
+        &self,
+        id: Uuid,
+        company_id: Uuid,
+    ) -> Result<DepotResponse, AppError> {
+        let depot = self.repository
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Almacén no encontrado".to_string()))?;
+
+        // Verificar que pertenece a la empresa
+        if depot.company_id != company_id {
+            return Err(AppError::Forbidden("No tienes permiso para acceder a este almacén".to_string()));
+        }
+
+        Ok(depot.into())
+    }
+
+    pub async fn list_by_company(
+        &self,
+        company_id: Uuid,
+    ) -> Result<Vec<DepotResponse>, AppError> {
+        let depots = self.repository.find_by_company(company_id).await?;
+        Ok(depots.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn update(
+        &self,
+        id: Uuid,
+        company_id: Uuid,
+        request: UpdateDepotRequest,
+    ) -> Result<ApiResponse<DepotResponse>, AppError> {
+        // Validar solo lo que cambia, contra los valores actuales
+        if request.name.is_some() || request.latitude.is_some() || request.longitude.is_some() {
+            let current = self.get_by_id(id, company_id).await?;
+            validate_depot(
+                request.name.as_deref().unwrap_or(&current.name),
+                request.latitude.unwrap_or(current.latitude),
+                request.longitude.unwrap_or(current.longitude),
+                &self.bounds,
+            )?;
+        }
+
+        let depot = self.repository.update(
+            id,
+            company_id,
+            request.name.map(|name| name.trim().to_string()),
+            normalize_societe(request.societe),
+            request.latitude,
+            request.longitude,
+            request.is_default,
+        ).await?;
+
+        Ok(ApiResponse::success_with_message(
+            depot.into(),
+            "Almacén actualizado exitosamente".to_string()
+        ))
+    }
+
+    pub async fn delete(
+        &self,
+        id: Uuid,
+        company_id: Uuid,
+    ) -> Result<(), AppError> {
+        self.repository.delete(id, company_id).await?;
+        Ok(())
+    }
+}
@@ -10,7 +10,8 @@ use axum::{
 use serde_json::json;
 
 use crate::dto::mapbox_optimization_dto::*;
-use crate::services::mapbox_optimization_service::MapboxOptimizationService;
+use crate::services::depot_service::societe_depot;
+use crate::services::mapbox_optimization_service::{validate_time_windows, MapboxOptimizationService};
 use crate::state::AppState;
 use crate::utils::errors::AppError;
 
@@ -21,6 +22,8 @@ pub async fn optimize_route(
 ) -> Result<Json<OptimizationResponse>, AppError> {
     log::info!("🎯 Recibida solicitud de optimización Mapbox para {} paquetes", request.packages.len());
 
+    validate_time_windows(&request.packages).map_err(AppError::ValidationError)?;
+
     // Verificar que tenemos el token de Mapbox
     let mapbox_token = match &state.config.mapbox_token {
         Some(token) => token.clone(),
@@ -37,9 +40,11 @@ pub async fn optimize_route(
     // Crear servicio de optimización
     let optimization_service = MapboxOptimizationService::new(mapbox_token);
 
-    // Para este endpoint, usamos la ubicación por defecto de París como warehouse
-    // En una implementación real, esto vendría de la configuración de la empresa
-    let warehouse_location = Some((2.3522, 48.8566)); // París centro
+    // Almacén de la société (o WAREHOUSE_LOCATION); sin almacén la ruta sale
+    // de la primera parada. Mapbox espera (lon, lat)
+    let warehouse_location = societe_depot(&state.pool, &request.societe, state.config.warehouse_location)
+        .await
+        .map(|(latitude, longitude)| (longitude, latitude));
 
     // Ejecutar optimización
     match optimization_service.optimize_route(request.packages, warehouse_location).await {
@@ -48,8 +53,8 @@ pub async fn optimize_route(
             Ok(Json(response))
         }
         Err(e) => {
-            log::error!("❌ Error en optimización Mapbox: {}", e);
-            Err(AppError::ExternalApi(format!("Error en optimización Mapbox: {}", e)))
+            log::error!("❌ Error en optimización Mapbox ({}): {}", e.code(), e);
+            Err(e.into())
         }
     }
 }
@@ -1,6 +1,9 @@
 pub mod company_controller;
 pub mod vehicle_controller;
+pub mod depot_controller;
 pub mod address_controller;
 pub mod colis_prive_controller;
+pub mod package_label_controller;
+pub mod analysis_controller;
 // pub mod mapbox_optimization_controller; // Deshabilitado hasta tener acceso a Mapbox v2 Beta
 
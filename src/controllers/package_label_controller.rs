@@ -0,0 +1,56 @@
+use crate::dto::colis_prive_dto::{AddPackageLabelRequest, PackageLabelsResponse};
+use crate::repositories::package_label_repository::PackageLabelRepository;
+use crate::services::package_label_service::normalize_label;
+use crate::utils::errors::AppError;
+use sqlx::PgPool;
+
+pub struct PackageLabelController {
+    repository: PackageLabelRepository,
+}
+
+impl PackageLabelController {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            repository: PackageLabelRepository::new(pool),
+        }
+    }
+
+    pub async fn list(&self, reference_colis: &str) -> Result<PackageLabelsResponse, AppError> {
+        let labels = self.repository.find_by_reference(reference_colis).await?;
+
+        Ok(PackageLabelsResponse {
+            success: true,
+            reference_colis: reference_colis.to_string(),
+            labels: labels.into_iter().map(|l| l.label).collect(),
+        })
+    }
+
+    pub async fn add(
+        &self,
+        reference_colis: &str,
+        request: AddPackageLabelRequest,
+    ) -> Result<PackageLabelsResponse, AppError> {
+        let label = normalize_label(&request.label)?;
+
+        self.repository
+            .add(reference_colis, &label, request.created_by.as_deref())
+            .await?;
+        log::info!("🏷️ Etiqueta '{}' añadida a {}", label, reference_colis);
+
+        self.list(reference_colis).await
+    }
+
+    pub async fn remove(&self, reference_colis: &str, label: &str) -> Result<PackageLabelsResponse, AppError> {
+        let label = normalize_label(label)?;
+
+        if !self.repository.remove(reference_colis, &label).await? {
+            return Err(AppError::NotFound(format!(
+                "El paquete {} no tiene la etiqueta '{}'",
+                reference_colis, label
+            )));
+        }
+        log::info!("🏷️ Etiqueta '{}' quitada de {}", label, reference_colis);
+
+        self.list(reference_colis).await
+    }
+}
@@ -36,3 +36,69 @@ pub struct SearchAddressRequest {
     pub address: Option<String>,
     pub postal_code: Option<String>,
 }
+
+// Request para obtener varios candidatos de geocodificación
+#[derive(Debug, Deserialize)]
+pub struct GeocodeCandidatesRequest {
+    pub address: String,
+    /// Número de candidatos (por defecto 3, máximo 10)
+    pub limit: Option<usize>,
+}
+
+// Request para previsualizar la limpieza de una dirección
+#[derive(Debug, Deserialize)]
+pub struct CleanPreviewRequest {
+    pub address: String,
+}
+
+// Request para fijar a mano las coordenadas de una dirección pendiente
+#[derive(Debug, Deserialize)]
+pub struct PinCoordinatesRequest {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Dispatcher que confirma las coordenadas
+    pub confirmed_by: Option<String>,
+}
+
+// Query params del listado de direcciones pendientes (?limit=...)
+#[derive(Debug, Deserialize)]
+pub struct PendingValidationsQuery {
+    pub limit: Option<i64>,
+}
+
+// Query params de las direcciones cercanas (?lat=...&lon=...&radius_m=...&page=...&per_page=...)
+#[derive(Debug, Deserialize)]
+pub struct NearbyAddressesQuery {
+    pub lat: f64,
+    pub lon: f64,
+    /// Radio en metros (por defecto 200)
+    pub radius_m: Option<f64>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+// Query params de las direcciones dentro de un rectángulo (?min_lat=...&min_lon=...&max_lat=...&max_lon=...)
+#[derive(Debug, Deserialize)]
+pub struct AddressesInBoundsQuery {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+// Query params para vaciar el cache de geocoding (?address=... o ?postal_code_prefix=...)
+#[derive(Debug, Deserialize)]
+pub struct GeocacheEvictionQuery {
+    /// Dirección concreta a olvidar
+    pub address: Option<String>,
+    /// Prefijo de código postal (1 a 5 dígitos) de la zona a olvidar
+    pub postal_code_prefix: Option<String>,
+}
+
+// Response tras vaciar entradas del cache de geocoding
+#[derive(Debug, Serialize)]
+pub struct GeocacheEvictionResponse {
+    pub removed: usize,
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use crate::services::reattempt_service::{ReattemptGroup, ReattemptGrouping};
+use crate::services::validation_trends_service::DailyValidationTrend;
+
+// Formato de la lista de reintentos (?format=json|csv)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorklistFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+// Query params de la lista de reintentos (?societe=...&date=...&group_by=driver|postal_code)
+#[derive(Debug, Deserialize)]
+pub struct ReattemptQuery {
+    pub societe: String,
+    pub date: Option<String>,
+    #[serde(default)]
+    pub group_by: ReattemptGrouping,
+    #[serde(default)]
+    pub format: WorklistFormat,
+}
+
+// Response de la lista de reintentos
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ReattemptWorklistResponse {
+    pub success: bool,
+    pub societe: String,
+    pub date: String,
+    pub group_by: ReattemptGrouping,
+    pub total_packages: usize,
+    pub groups: Vec<ReattemptGroup>,
+}
+
+// Query params de las tendencias de validación (?from=YYYY-MM-DD&to=YYYY-MM-DD)
+#[derive(Debug, Deserialize)]
+pub struct ValidationTrendsQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+// Response de las tendencias de validación
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ValidationTrendsResponse {
+    pub success: bool,
+    pub from: String,
+    pub to: String,
+    pub days: Vec<DailyValidationTrend>,
+}
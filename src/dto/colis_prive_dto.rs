@@ -1,19 +1,100 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::dto::package_dto::{FullPackageDto, TourneePackageDto};
+use crate::models::delivery_progress::StopOutcome;
+use crate::models::package_status::StatusUpdate;
+use crate::models::tournee_snapshot::{PackageState, TourneeSnapshot};
+use crate::services::colis_prive_service::AddressValidationSummary;
+use crate::services::eta_service::{EtaMethod, PlannedDuration};
+use crate::services::geocoding_quality_service::GeocodingQualityReport;
+use crate::services::local_optimizer_service::LocalOptimizerStats;
+use crate::services::navigation_service::{NavigationApp, NavigationLink};
+use crate::services::status_webhook_service::WebhookOutcome;
+use crate::utils::errors::AppError;
+
+// Convención de nombres: las respuestas públicas van en snake_case
+// (`#[serde(rename_all = "snake_case")]` a nivel de struct). Los nombres en
+// PascalCase de Colis Privé (`MatriculeChauffeur`, ...) solo aparecen en los
+// structs de deserialización de su API, con `rename` explícito.
 
 // Re-export para compatibilidad
 pub use crate::dto::colis_prive_dto::PackageData as PublicPackageData;
 
+lazy_static! {
+    /// Código de société de Colis Privé (p.ej. "PCP0010699")
+    static ref SOCIETE_REGEX: Regex = Regex::new(r"^[A-Za-z0-9_-]{2,32}$").unwrap();
+}
+
 // Request para autenticación Colis Privé
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct ColisPriveAuthRequest {
+    #[validate(length(min = 2, max = 64, message = "El usuario debe tener entre 2 y 64 caracteres"))]
     pub username: String,
+    #[validate(length(min = 4, max = 128, message = "La contraseña debe tener entre 4 y 128 caracteres"))]
     pub password: String,
+    #[validate(regex(path = "SOCIETE_REGEX", message = "La société solo admite letras, números, '_' y '-' (2 a 32 caracteres)"))]
+    pub societe: String,
+}
+
+impl ColisPriveAuthRequest {
+    /// Quitar espacios de usuario y société (la contraseña se envía tal cual)
+    pub fn trimmed(self) -> Self {
+        Self {
+            username: self.username.trim().to_string(),
+            password: self.password,
+            societe: self.societe.trim().to_string(),
+        }
+    }
+}
+
+// Request para invalidar el token guardado de un usuario (logout forzado)
+#[derive(Debug, Deserialize, Validate)]
+pub struct ColisPriveLogoutRequest {
+    #[validate(length(min = 2, max = 64, message = "El usuario debe tener entre 2 y 64 caracteres"))]
+    pub username: String,
+    #[validate(regex(path = "SOCIETE_REGEX", message = "La société solo admite letras, números, '_' y '-' (2 a 32 caracteres)"))]
+    pub societe: String,
+}
+
+impl ColisPriveLogoutRequest {
+    pub fn trimmed(self) -> Self {
+        Self {
+            username: self.username.trim().to_string(),
+            societe: self.societe.trim().to_string(),
+        }
+    }
+}
+
+// Response del logout forzado
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ColisPriveLogoutResponse {
+    pub success: bool,
+    /// Si había un token guardado para el usuario
+    pub removed: bool,
+    pub message: String,
+}
+
+// Response de la invalidación de tokens de una société (rotación de credenciales)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SocieteTokensRevokedResponse {
+    pub success: bool,
     pub societe: String,
+    /// Tokens guardados que se invalidaron
+    pub removed: usize,
+    pub message: String,
 }
 
 // Response de autenticación Colis Privé
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct ColisPriveAuthResponse {
     pub success: bool,
     pub message: Option<String>,
@@ -22,6 +103,7 @@ pub struct ColisPriveAuthResponse {
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct ColisPriveAuthData {
     pub sso_token: String,
     pub matricule_chauffeur: String,
@@ -38,15 +120,105 @@ pub struct GetPackagesRequest {
     pub date: Option<String>,
 }
 
+// Filtros de la lista de paquetes (?label=fragile)
+#[derive(Debug, Default, Deserialize)]
+pub struct PackagesFilterQuery {
+    pub label: Option<String>,
+}
+
+// Request para etiquetar un paquete
+#[derive(Debug, Deserialize)]
+pub struct AddPackageLabelRequest {
+    pub label: String,
+    /// Matricule de quien añade la etiqueta
+    pub created_by: Option<String>,
+}
+
+// Etiquetas de un paquete
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PackageLabelsResponse {
+    pub success: bool,
+    pub reference_colis: String,
+    pub labels: Vec<String>,
+}
+
 // Response de paquetes
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct PackagesResponse {
     pub success: bool,
     pub packages: Vec<PackageData>,
     pub total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address_validation: Option<AddressValidationSummary>,
+}
+
+// Request de paquetes para varias fechas (precarga de los próximos días)
+#[derive(Debug, Deserialize)]
+pub struct PackagesBatchRequest {
+    pub matricule: String,
+    pub societe: String,
+    pub dates: Vec<String>,
+}
+
+// Resultado de una fecha del lote: cada fecha tiene su propio éxito o error
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DatePackagesResult {
+    pub success: bool,
+    pub packages: Vec<PackageData>,
+    pub total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address_validation: Option<AddressValidationSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<Result<PackagesResponse, AppError>> for DatePackagesResult {
+    fn from(result: Result<PackagesResponse, AppError>) -> Self {
+        match result {
+            Ok(response) => Self {
+                success: response.success,
+                packages: response.packages,
+                total: response.total,
+                address_validation: response.address_validation,
+                error: None,
+            },
+            Err(e) => Self {
+                success: false,
+                packages: Vec::new(),
+                total: 0,
+                address_validation: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+// Response del lote: fecha → resultado
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PackagesBatchResponse {
+    pub success: bool,
+    pub matricule: String,
+    pub results: BTreeMap<String, DatePackagesResult>,
+}
+
+// Franja de entrega pedida por el destinatario
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliverySlot {
+    /// Solo por la mañana
+    Morning,
+    /// Solo por la tarde
+    Afternoon,
+    #[default]
+    AnyTime,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
 pub struct PackageData {
     // Campos principales de Colis Privé
     pub reference_colis: String,
@@ -112,6 +284,8 @@ pub struct PackageData {
     pub validation_warnings: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub num_ordre_passage_prevu: Option<i32>,
+    #[serde(default)]
+    pub delivery_slot: DeliverySlot,
 }
 
 // Request para optimización
@@ -119,10 +293,87 @@ pub struct PackageData {
 pub struct OptimizeRouteRequest {
     pub matricule: String,
     pub societe: String,
+    /// Fecha de la tournée a optimizar (YYYY-MM-DD); por defecto hoy
+    #[serde(default)]
+    pub date: Option<String>,
+    /// Solo optimizador local: usar duraciones con tráfico (Mapbox Matrix)
+    #[serde(default)]
+    pub use_traffic: bool,
+}
+
+// Motor de optimización a utilizar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptimizationEngine {
+    /// Optimizador de Colis Privé (por defecto)
+    #[default]
+    ColisPrive,
+    /// Optimizador local (vecino más cercano + 2-opt)
+    Local,
+    /// Mapbox Optimization API (mejor en tournées urbanas densas)
+    Mapbox,
+}
+
+impl OptimizationEngine {
+    /// Leer el nombre usado en la configuración ("colisprive", "local", "mapbox")
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "colisprive" | "colis_prive" => Some(Self::ColisPrive),
+            "local" => Some(Self::Local),
+            "mapbox" => Some(Self::Mapbox),
+            _ => None,
+        }
+    }
+}
+
+// Query params para consultar el historial de optimización
+#[derive(Debug, Deserialize)]
+pub struct OptimizationHistoryQuery {
+    pub societe: String,
+    pub date: Option<String>,
+}
+
+// Request para aplicar el orden optimizado
+#[derive(Debug, Deserialize)]
+pub struct ApplyOptimizationRequest {
+    pub societe: String,
+    pub date: Option<String>,
+    /// `tournee_hash` devuelto por la optimización que se quiere aplicar
+    pub tournee_hash: String,
+}
+
+// Query params de optimización (?engine=colisprive|local|mapbox&force=true)
+#[derive(Debug, Default, Deserialize)]
+pub struct OptimizeQuery {
+    /// Sin motor explícito se usa el orden de proveedores de la société
+    #[serde(default)]
+    pub engine: Option<OptimizationEngine>,
+    /// Ignorar el resultado reciente guardado y recalcular
+    #[serde(default)]
+    pub force: bool,
+}
+
+// Formato de exportación de una tournée
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+}
+
+// Query params de exportación (?societe=...&date=...&format=csv|xlsx)
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub societe: String,
+    pub date: Option<String>,
+    #[serde(default)]
+    pub format: ExportFormat,
 }
 
 // Response de optimización
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct OptimizeRouteResponse {
     pub success: bool,
     pub message: Option<String>,
@@ -130,23 +381,479 @@ pub struct OptimizeRouteResponse {
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct OptimizationData {
     pub matricule_chauffeur: String,
     pub date_tournee: String,
-    pub optimized_packages: Vec<PackageData>,
+    pub optimized_packages: Vec<TourneePackageDto>,
+    /// Hash del conjunto de paquetes; se envía al aplicar el orden
+    pub tournee_hash: String,
+    /// Cambios respecto a la optimización anterior de la misma tournée
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_changes: Option<OrderDiff>,
+    /// Métricas del optimizador local (mejora sobre vecino más cercano, motivo de parada)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub optimizer_stats: Option<LocalOptimizerStats>,
+    /// Salida del almacén (primera) y vuelta (última); vacío sin almacén
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub depot_stops: Vec<DepotStop>,
+    /// Sub-rutas sugeridas cuando la tournée supera el máximo de paradas del
+    /// optimizador local; vacío si no se dividió
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggested_split: Vec<SuggestedSubRoute>,
+    /// Duración planificada: total, conducción y paradas por separado
+    #[serde(flatten)]
+    pub duration: PlannedDuration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepotStopKind {
+    Start,
+    Return,
+}
+
+/// Almacén como parada explícita de la ruta, con su hora planificada
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DepotStop {
+    pub kind: DepotStopKind,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub eta: DateTime<Utc>,
+}
+
+/// Grupo de paradas cercanas optimizado por separado al dividir una tournée grande
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SuggestedSubRoute {
+    /// Referencias en el orden optimizado de la sub-ruta
+    pub references: Vec<String>,
+    pub optimizer_stats: LocalOptimizerStats,
 }
 
 // Company list response
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct CompaniesListResponse {
     pub success: bool,
     pub companies: Vec<CompanyInfo>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
 pub struct CompanyInfo {
     pub code: String,
     pub name: String,
     pub description: Option<String>,
 }
 
+// Request para marcar una parada como entregada o fallida
+#[derive(Debug, Deserialize)]
+pub struct StopProgressRequest {
+    pub matricule: String,
+    pub societe: String,
+    pub date: Option<String>,
+    /// Motivo del fallo (ausente, dirección errónea...); alimenta la lista de reintentos
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Posición del chofer al entregar; se compara con las coordenadas del paquete
+    #[serde(default)]
+    pub delivery_lat: Option<f64>,
+    #[serde(default)]
+    pub delivery_lng: Option<f64>,
+}
+
+// Response tras registrar una parada
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct StopProgressResponse {
+    pub success: bool,
+    pub reference_colis: String,
+    pub outcome: StopOutcome,
+    pub completed_at: DateTime<Utc>,
+    pub completed_stops: usize,
+    /// Entrega registrada lejos de la dirección del paquete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub delivery_location_flagged: bool,
+    /// Dirección más cercana a la posición reportada, si la entrega quedó fuera de la geocerca
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reported_address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+// Query params de la tournée completa (?societe=...&date=...&details=true)
+#[derive(Debug, Deserialize)]
+pub struct FullTourneeQuery {
+    pub societe: String,
+    pub date: Option<String>,
+    /// Añadir el detalle de los paquetes prioritarios (más lento)
+    #[serde(default)]
+    pub details: bool,
+}
+
+// Response de la tournée completa: paquetes + validación (+ detalle) en una llamada
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct FullTourneeResponse {
+    pub success: bool,
+    pub matricule: String,
+    pub date_tournee: String,
+    pub total: usize,
+    pub packages: Vec<FullPackageDto>,
+    pub address_validation: AddressValidationSummary,
+}
+
+// Query params del chequeo de calidad de geocoding (?societe=...&date=...)
+#[derive(Debug, Deserialize)]
+pub struct QualityQuery {
+    pub societe: String,
+    pub date: Option<String>,
+}
+
+// Response del chequeo de calidad de geocoding
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct GeocodingQualityResponse {
+    pub success: bool,
+    pub matricule: String,
+    pub date_tournee: String,
+    #[serde(flatten)]
+    pub report: GeocodingQualityReport,
+}
+
+// Query params de la estimación de fin de tournée
+#[derive(Debug, Deserialize)]
+pub struct EtaQuery {
+    pub societe: String,
+    pub date: Option<String>,
+}
+
+// Response de la estimación de fin de tournée
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct EtaResponse {
+    pub success: bool,
+    pub matricule: String,
+    pub date_tournee: String,
+    pub method: EtaMethod,
+    pub estimated_finish: DateTime<Utc>,
+    pub completed_stops: usize,
+    pub remaining_stops: usize,
+    pub remaining_distance_km: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_stop_seconds: Option<f64>,
+    /// Fin de la jornada; `estimated_finish` no lo supera
+    pub working_hours_end: DateTime<Utc>,
+    /// Paquetes que no caben hoy: el dispatcher debe partir la ruta
+    pub unschedulable_count: usize,
+    pub unschedulable: Vec<String>,
+}
+
+// Query params de los enlaces de navegación (?societe=...&date=...&app=google|waze|apple)
+#[derive(Debug, Deserialize)]
+pub struct NavLinkQuery {
+    pub societe: String,
+    pub date: Option<String>,
+    #[serde(default)]
+    pub app: NavigationApp,
+}
+
+// Response de los enlaces de navegación de la ruta optimizada
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct NavLinkResponse {
+    pub success: bool,
+    pub matricule: String,
+    pub date_tournee: String,
+    pub app: NavigationApp,
+    /// Enlaces en orden, con las posiciones de la ruta que cubre cada uno
+    pub links: Vec<NavigationLink>,
+    /// Paquetes sin coordenadas, que no aparecen en ningún enlace
+    pub skipped: Vec<String>,
+}
+
+// Tournée a fusionar (cada una con su propia autenticación)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TourneeSource {
+    pub societe: String,
+    pub matricule: String,
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeTourneesRequest {
+    pub tournees: Vec<TourneeSource>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct MergedSourceSummary {
+    pub societe: String,
+    pub matricule: String,
+    pub date: Option<String>,
+    pub packages: usize,
+}
+
+// Paquete de una tournée fusionada, etiquetado con su origen
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct MergedPackageDto {
+    pub source_societe: String,
+    pub source_matricule: String,
+    #[serde(flatten)]
+    pub package: TourneePackageDto,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct MergeTourneesResponse {
+    pub success: bool,
+    pub total_packages: usize,
+    pub sources: Vec<MergedSourceSummary>,
+    pub packages: Vec<MergedPackageDto>,
+}
+
+// Lote de estados recibido por webhook
+#[derive(Debug, Deserialize)]
+pub struct StatusWebhookRequest {
+    pub updates: Vec<StatusUpdate>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct StatusWebhookResponse {
+    pub success: bool,
+    #[serde(flatten)]
+    pub outcome: WebhookOutcome,
+}
+
+// Sociétés aceptadas en la autenticación
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct AllowedSocietesResponse {
+    pub success: bool,
+    /// `false` si no hay lista configurada (se acepta cualquier société)
+    pub enabled: bool,
+    pub societes: Vec<String>,
+}
+
+// Cambio de posición de un paquete tras re-optimizar
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PackageOrderChange {
+    pub reference_colis: String,
+    pub previous_position: usize,
+    pub new_position: usize,
+    /// Posiciones ganadas (positivo = sube, se entrega antes)
+    pub shift: i64,
+    pub direction: OrderChangeDirection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderChangeDirection {
+    Up,
+    Down,
+}
+
+// Diferencia entre dos órdenes de la misma tournée
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct OrderDiff {
+    pub moved: Vec<PackageOrderChange>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+// Request para tomar una foto del estado de una tournée
+#[derive(Debug, Deserialize)]
+pub struct TourneeSnapshotRequest {
+    pub societe: String,
+    pub date: Option<String>,
+}
+
+// Query params para comparar dos fotos de una tournée
+#[derive(Debug, Deserialize)]
+pub struct SnapshotDiffQuery {
+    pub societe: String,
+    pub date: Option<String>,
+    pub from: Uuid,
+    pub to: Uuid,
+}
+
+// Response con la foto recién tomada
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TourneeSnapshotResponse {
+    pub success: bool,
+    pub snapshot: TourneeSnapshot,
+}
+
+// Response con las fotos de una tournée en orden cronológico
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TourneeSnapshotsResponse {
+    pub success: bool,
+    pub matricule: String,
+    pub date_tournee: String,
+    pub snapshots: Vec<TourneeSnapshot>,
+}
+
+// Paquete cuyo estado cambió entre dos fotos
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PackageStateChange {
+    pub reference_colis: String,
+    pub before: PackageState,
+    pub after: PackageState,
+}
+
+// Diferencia entre dos fotos de la misma tournée
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SnapshotDiff {
+    pub changed: Vec<PackageStateChange>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+// Response con la diferencia entre dos fotos
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SnapshotDiffResponse {
+    pub success: bool,
+    pub matricule: String,
+    pub from_taken_at: DateTime<Utc>,
+    pub to_taken_at: DateTime<Utc>,
+    pub diff: SnapshotDiff,
+}
+
+// Response al aplicar el orden optimizado
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ApplyOptimizationResponse {
+    pub success: bool,
+    pub matricule: String,
+    pub date_tournee: String,
+    pub tournee_hash: String,
+    pub optimized_packages: Vec<TourneePackageDto>,
+}
+
+// Response de cambios de orden tras re-optimización
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct OrderChangesResponse {
+    pub success: bool,
+    pub matricule: String,
+    pub date_tournee: String,
+    pub previous_optimized_at: Option<DateTime<Utc>>,
+    pub latest_optimized_at: DateTime<Utc>,
+    pub changes: OrderDiff,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth_request(username: &str, password: &str, societe: &str) -> ColisPriveAuthRequest {
+        ColisPriveAuthRequest {
+            username: username.to_string(),
+            password: password.to_string(),
+            societe: societe.to_string(),
+        }
+        .trimmed()
+    }
+
+    fn invalid_fields(request: &ColisPriveAuthRequest) -> Vec<&'static str> {
+        let errors = request.validate().unwrap_err();
+        let mut fields: Vec<&'static str> = errors.field_errors().keys().copied().collect();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn test_valid_auth_request_passes() {
+        let request = auth_request("  A187518 ", "secret", " PCP0010699 ");
+
+        assert!(request.validate().is_ok());
+        assert_eq!(request.username, "A187518");
+        assert_eq!(request.societe, "PCP0010699");
+    }
+
+    #[test]
+    fn test_blank_username_is_rejected() {
+        assert_eq!(invalid_fields(&auth_request("   ", "secret", "PCP0010699")), vec!["username"]);
+    }
+
+    #[test]
+    fn test_short_password_is_rejected() {
+        assert_eq!(invalid_fields(&auth_request("A187518", "abc", "PCP0010699")), vec!["password"]);
+    }
+
+    #[test]
+    fn test_societe_with_invalid_characters_is_rejected() {
+        assert_eq!(invalid_fields(&auth_request("A187518", "secret", "PCP 0010699")), vec!["societe"]);
+        assert_eq!(invalid_fields(&auth_request("A187518", "secret", "")), vec!["societe"]);
+    }
+
+    fn assert_snake_case_keys(value: &serde_json::Value, path: &str) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, child) in map {
+                    assert!(
+                        !key.is_empty() && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'),
+                        "clave fuera de snake_case: {}.{}",
+                        path,
+                        key
+                    );
+                    assert_snake_case_keys(child, &format!("{}.{}", path, key));
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    assert_snake_case_keys(item, path);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_optimize_response_uses_snake_case_keys() {
+        let package = PackageData {
+            reference_colis: "COLIS-1".to_string(),
+            destinataire_nom: "Client".to_string(),
+            destinataire_adresse1: Some("12 RUE DE LA PAIX".to_string()),
+            coord_x_destinataire: Some(2.3314),
+            coord_y_destinataire: Some(48.8686),
+            ..Default::default()
+        };
+        let response = OptimizeRouteResponse {
+            success: true,
+            message: None,
+            data: Some(OptimizationData {
+                matricule_chauffeur: "A187518".to_string(),
+                date_tournee: "2026-10-16".to_string(),
+                optimized_packages: vec![TourneePackageDto::from(package.clone())],
+                tournee_hash: "abc".to_string(),
+                order_changes: None,
+                optimizer_stats: None,
+                depot_stops: Vec::new(),
+                suggested_split: Vec::new(),
+                duration: PlannedDuration::default(),
+            }),
+        };
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_snake_case_keys(&json, "response");
+        assert_eq!(json["data"]["matricule_chauffeur"], "A187518");
+        assert!(json["data"].get("MatriculeChauffeur").is_none());
+
+        assert_snake_case_keys(&serde_json::to_value(&package).unwrap(), "package");
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::models::depot::Depot;
+
+// Request para crear un almacén
+#[derive(Debug, Deserialize)]
+pub struct CreateDepotRequest {
+    pub name: String,
+    /// Société de Colis Privé cuyas tournées salen del almacén (p.ej. "PCP0010699")
+    pub societe: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Almacén por defecto de la empresa (solo puede haber uno)
+    pub is_default: Option<bool>,
+}
+
+// Request para actualizar un almacén
+#[derive(Debug, Deserialize)]
+pub struct UpdateDepotRequest {
+    pub name: Option<String>,
+    pub societe: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub is_default: Option<bool>,
+}
+
+// Response de almacén
+#[derive(Debug, Serialize)]
+pub struct DepotResponse {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub name: String,
+    pub societe: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Depot> for DepotResponse {
+    fn from(depot: Depot) -> Self {
+        Self {
+            id: depot.id,
+            company_id: depot.company_id,
+            name: depot.name,
+            societe: depot.societe,
+            latitude: depot.latitude,
+            longitude: depot.longitude,
+            is_default: depot.is_default,
+            created_at: depot.created_at,
+        }
+    }
+}
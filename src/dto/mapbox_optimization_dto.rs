@@ -41,6 +41,18 @@ pub struct MapboxService {
     pub duration: u32, // duración en segundos
     #[serde(skip_serializing_if = "Option::is_none")]
     pub size: Option<Vec<i32>>,
+    /// Ventanas horarias en las que se puede realizar el servicio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_times: Option<Vec<MapboxTimeWindow>>,
+}
+
+/// Ventana horaria de un servicio (RFC 3339)
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct MapboxTimeWindow {
+    pub earliest: String,
+    pub latest: String,
+    #[serde(rename = "type")]
+    pub window_type: String,
 }
 
 /// Opciones de optimización
@@ -151,6 +163,12 @@ pub struct OptimizationPackage {
     pub coord_x_destinataire: Option<f64>,
     pub coord_y_destinataire: Option<f64>,
     pub statut: Option<String>,
+    /// Tiempo de parada en segundos (por defecto 120; más largo para entregas RCS)
+    #[serde(default)]
+    pub service_duration_secs: Option<u32>,
+    /// Ventana de entrega `(inicio, fin)` en RFC 3339, p.ej. 08:00–12:00 para particulares
+    #[serde(default)]
+    pub time_window: Option<(String, String)>,
 }
 
 /// Response de nuestro endpoint interno (compatible con frontend)
@@ -167,6 +185,27 @@ pub struct OptimizationData {
     pub matricule_chauffeur: Option<String>,
     pub date_tournee: Option<String>,
     pub optimized_packages: Vec<OptimizedPackage>,
+    /// Paquetes que Mapbox dejó fuera de la ruta y por qué
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dropped_services: Vec<DroppedService>,
+}
+
+/// Motivo por el que un paquete quedó fuera de la ruta
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    /// Su ventana horaria no es alcanzable con el resto de la ruta
+    TimeWindowInfeasible,
+    /// Mapbox no lo incluyó en la solución
+    NotRouted,
+}
+
+/// Paquete descartado por Mapbox
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct DroppedService {
+    pub id: String,
+    pub reference_colis: String,
+    pub reason: DropReason,
 }
 
 /// Paquete optimizado (compatible con frontend)
@@ -192,6 +231,7 @@ pub struct OptimizedPackage {
     pub formatted_address: Option<String>,
     pub num_ordre_passage_prevu: Option<i32>,
     pub eta: Option<String>, // Nueva: tiempo estimado de llegada
+    pub dropped: bool, // Mapbox no pudo incluirlo en la ruta
 }
 
 impl From<OptimizationPackage> for OptimizedPackage {
@@ -224,6 +264,7 @@ impl From<OptimizationPackage> for OptimizedPackage {
             formatted_address: address,
             num_ordre_passage_prevu: None, // Se asignará después de la optimización
             eta: None, // Se asignará después de la optimización
+            dropped: false,
         }
     }
 }
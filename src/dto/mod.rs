@@ -1,7 +1,9 @@
 pub mod company_dto;
 pub mod vehicle_dto;
+pub mod depot_dto;
 pub mod address_dto;
 pub mod auth_dto;
 pub mod colis_prive_dto;
+pub mod package_dto;
 pub mod mapbox_optimization_dto;
-
+pub mod analysis_dto;
@@ -0,0 +1,342 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::dto::colis_prive_dto::{DeliverySlot, PackageData};
+use crate::services::csv_import_service::CsvRowResult;
+use crate::models::package::{
+    CustomerGroup, DeliveryDetails, DeliveryGroup, GroupedPackages, PackageInfo, SinglePackage, SubStop,
+};
+
+// Paquete de tournée expuesto al cliente (sin campos legacy ni datos crudos de Colis Privé)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TourneePackageDto {
+    pub reference_colis: String,
+    pub destinataire_nom: String,
+    pub destinataire_adresse1: Option<String>,
+    pub destinataire_adresse2: Option<String>,
+    pub destinataire_cp: Option<String>,
+    pub destinataire_ville: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub code_statut_article: Option<String>,
+    pub numero_ordre: Option<i32>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub formatted_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_confidence: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_warnings: Option<Vec<String>>,
+    pub delivery_slot: DeliverySlot,
+}
+
+impl From<PackageData> for TourneePackageDto {
+    fn from(package: PackageData) -> Self {
+        Self {
+            latitude: package.coord_y_destinataire.or(package.latitude),
+            longitude: package.coord_x_destinataire.or(package.longitude),
+            code_statut_article: package.code_statut_article.or(package.statut),
+            numero_ordre: package.numero_ordre.or(package.num_ordre_passage_prevu),
+            phone: package.phone.or(package.phone_fixed),
+            reference_colis: package.reference_colis,
+            destinataire_nom: package.destinataire_nom,
+            destinataire_adresse1: package.destinataire_adresse1,
+            destinataire_adresse2: package.destinataire_adresse2,
+            destinataire_cp: package.destinataire_cp,
+            destinataire_ville: package.destinataire_ville,
+            email: package.email,
+            formatted_address: package.formatted_address,
+            validation_method: package.validation_method,
+            validation_confidence: package.validation_confidence,
+            validation_warnings: package.validation_warnings,
+            delivery_slot: package.delivery_slot,
+        }
+    }
+}
+
+// Detalle de un paquete prioritario (opt-in en /colis-prive/full)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PackageDetailDto {
+    pub labels: Vec<String>,
+    pub delivery: DeliveryDetails,
+}
+
+// Paquete de la tournée completa: datos, validación y detalle si se pidió
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct FullPackageDto {
+    #[serde(flatten)]
+    pub package: TourneePackageDto,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<PackageDetailDto>,
+}
+
+// Response del alta masiva: resultado de cada fila, en el orden del fichero
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PackageImportResponse {
+    pub success: bool,
+    pub imported: usize,
+    pub failed: usize,
+    pub rows: Vec<CsvRowResult>,
+}
+
+// Query params de la exportación CSV (mismos filtros que la lista de paquetes)
+#[derive(Debug, Deserialize)]
+pub struct PackagesExportQuery {
+    pub societe: String,
+    pub matricule: String,
+    pub date: Option<String>,
+    pub label: Option<String>,
+}
+
+// Response de paquetes agrupados por dirección
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct GroupedPackagesResponse {
+    pub singles: Vec<SinglePackageDto>,
+    pub groups: Vec<DeliveryGroupDto>,
+    pub total_packages: usize,
+    pub total_addresses: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SinglePackageDto {
+    pub id: Uuid,
+    pub tracking: String,
+    pub customer_name: String,
+    pub phone_number: Option<String>,
+    pub customer_indication: Option<String>,
+    pub official_label: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub mailbox_access: bool,
+    pub driver_notes: String,
+    pub address_id: Option<Uuid>,
+    pub code_statut_article: Option<String>,
+    pub is_problematic: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DeliveryGroupDto {
+    pub id: Uuid,
+    pub official_label: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub mailbox_access: bool,
+    pub driver_notes: String,
+    pub customers: Vec<CustomerGroupDto>,
+    /// Unidades del edificio (planta, puerta, apartamento) ordenadas por planta
+    pub sub_stops: Vec<SubStopDto>,
+    pub total_packages: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SubStopDto {
+    pub package_id: Uuid,
+    pub tracking: String,
+    pub customer_name: String,
+    pub details: DeliveryDetails,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CustomerGroupDto {
+    pub packages: Vec<PackageInfoDto>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PackageInfoDto {
+    pub id: Uuid,
+    pub tracking: String,
+    pub customer_name: String,
+    pub phone_number: Option<String>,
+    pub customer_indication: Option<String>,
+    pub code_statut_article: Option<String>,
+    pub is_problematic: bool,
+}
+
+impl From<GroupedPackages> for GroupedPackagesResponse {
+    fn from(grouped: GroupedPackages) -> Self {
+        Self {
+            singles: grouped.singles.into_iter().map(Into::into).collect(),
+            groups: grouped.groups.into_iter().map(Into::into).collect(),
+            total_packages: grouped.total_packages,
+            total_addresses: grouped.total_addresses,
+        }
+    }
+}
+
+impl From<SinglePackage> for SinglePackageDto {
+    fn from(package: SinglePackage) -> Self {
+        Self {
+            id: package.id,
+            tracking: package.tracking,
+            customer_name: package.customer_name,
+            phone_number: package.phone_number,
+            customer_indication: package.customer_indication,
+            official_label: package.official_label,
+            latitude: package.latitude,
+            longitude: package.longitude,
+            mailbox_access: package.mailbox_access,
+            driver_notes: package.driver_notes,
+            address_id: package.address_id,
+            code_statut_article: package.code_statut_article,
+            is_problematic: package.is_problematic,
+        }
+    }
+}
+
+impl From<DeliveryGroup> for DeliveryGroupDto {
+    fn from(group: DeliveryGroup) -> Self {
+        Self {
+            id: group.id,
+            official_label: group.official_label,
+            latitude: group.latitude,
+            longitude: group.longitude,
+            mailbox_access: group.mailbox_access,
+            driver_notes: group.driver_notes,
+            customers: group.customers.into_iter().map(Into::into).collect(),
+            sub_stops: group.sub_stops.into_iter().map(Into::into).collect(),
+            total_packages: group.total_packages,
+        }
+    }
+}
+
+impl From<SubStop> for SubStopDto {
+    fn from(sub_stop: SubStop) -> Self {
+        Self {
+            package_id: sub_stop.package_id,
+            tracking: sub_stop.tracking,
+            customer_name: sub_stop.customer_name,
+            details: sub_stop.details,
+        }
+    }
+}
+
+impl From<CustomerGroup> for CustomerGroupDto {
+    fn from(customer: CustomerGroup) -> Self {
+        Self {
+            packages: customer.packages.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<PackageInfo> for PackageInfoDto {
+    fn from(package: PackageInfo) -> Self {
+        Self {
+            id: package.id,
+            tracking: package.tracking,
+            customer_name: package.customer_name,
+            phone_number: package.phone_number,
+            customer_indication: package.customer_indication,
+            code_statut_article: package.code_statut_article,
+            is_problematic: package.is_problematic,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_package() -> PackageData {
+        PackageData {
+            reference_colis: "CP123".to_string(),
+            destinataire_nom: "DUPONT".to_string(),
+            destinataire_adresse1: Some("4 RUE GASTON TISSANDIER".to_string()),
+            destinataire_cp: Some("75018".to_string()),
+            destinataire_ville: Some("PARIS".to_string()),
+            coord_x_destinataire: Some(2.36),
+            coord_y_destinataire: Some(48.89),
+            statut: Some("LIV".to_string()),
+            code_statut_article: Some("LIV".to_string()),
+            numero_ordre: Some(3),
+            num_voie_geocode_destinataire: Some("4".to_string()),
+            qualite_geocodage_destinataire: Some("Bon".to_string()),
+            id: Some("987654".to_string()),
+            tracking_number: Some("CP123".to_string()),
+            recipient_name: Some("DUPONT".to_string()),
+            address: Some("4 RUE GASTON TISSANDIER, 75018 PARIS".to_string()),
+            status: Some("LIV".to_string()),
+            phone_fixed: Some("0140000000".to_string()),
+            num_ordre_passage_prevu: Some(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tournee_dto_omits_internal_fields() {
+        let json = serde_json::to_value(TourneePackageDto::from(sample_package())).unwrap();
+        let object = json.as_object().unwrap();
+
+        for internal in [
+            "id",
+            "tracking_number",
+            "recipient_name",
+            "address",
+            "status",
+            "statut",
+            "coord_x_destinataire",
+            "coord_y_destinataire",
+            "num_voie_geocode_destinataire",
+            "qualite_geocodage_destinataire",
+            "phone_fixed",
+            "num_ordre_passage_prevu",
+        ] {
+            assert!(!object.contains_key(internal), "campo interno expuesto: {}", internal);
+        }
+    }
+
+    #[test]
+    fn test_tournee_dto_includes_public_fields() {
+        let json = serde_json::to_value(TourneePackageDto::from(sample_package())).unwrap();
+
+        assert_eq!(json["reference_colis"], "CP123");
+        assert_eq!(json["destinataire_nom"], "DUPONT");
+        assert_eq!(json["destinataire_cp"], "75018");
+        assert_eq!(json["latitude"], 48.89);
+        assert_eq!(json["longitude"], 2.36);
+        assert_eq!(json["code_statut_article"], "LIV");
+        assert_eq!(json["numero_ordre"], 3);
+        assert_eq!(json["phone"], "0140000000");
+    }
+
+    #[test]
+    fn test_grouped_response_keeps_totals() {
+        let mut grouped = GroupedPackages::new();
+        grouped.add_single(SinglePackage {
+            id: Uuid::new_v4(),
+            tracking: "CP1".to_string(),
+            customer_name: "DUPONT".to_string(),
+            phone_number: None,
+            customer_indication: None,
+            official_label: "4 Rue Gaston Tissandier 75018".to_string(),
+            latitude: 48.89,
+            longitude: 2.36,
+            mailbox_access: true,
+            driver_notes: String::new(),
+            door_code: None,
+            floor: None,
+            concierge_notes: None,
+            address_id: None,
+            code_statut_article: None,
+            is_problematic: false,
+        });
+
+        let json = serde_json::to_value(GroupedPackagesResponse::from(grouped)).unwrap();
+
+        assert_eq!(json["total_packages"], 1);
+        assert_eq!(json["total_addresses"], 1);
+        assert_eq!(json["singles"][0]["tracking"], "CP1");
+        assert_eq!(json["singles"][0]["mailbox_access"], true);
+    }
+}
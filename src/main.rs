@@ -19,8 +19,10 @@ use axum::{
     response::Json,
 };
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::signal;
 use tracing::{info, error};
+use tracing_subscriber::fmt::format::FmtSpan;
 use dotenvy::dotenv;
 use serde_json::json;
 
@@ -28,6 +30,8 @@ use config::environment::EnvironmentConfig;
 use state::*;
 use database::DatabaseConnection;
 use middleware::cors::cors_middleware;
+use middleware::request_id::request_id_middleware;
+use utils::startup::{wait_for_dependency, STARTUP_RETRY_INTERVAL};
 
 use cache::redis_client::RedisClient;
 
@@ -36,16 +40,23 @@ async fn main() -> Result<()> {
     // Cargar variables de entorno
     dotenv().ok();
 
-    // Configurar logging
+    // Configurar logging: cada línea lleva los campos de sus spans (request_id,
+    // société, usuario) y al cerrar un span se registra su duración
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::DEBUG)
+        .with_span_events(FmtSpan::CLOSE)
         .init();
 
     info!("🚚 Delivery Route Optimizer - API Web Colis Privé");
     info!("================================================");
 
+    let config = EnvironmentConfig::default();
+
+    // Esperar a Postgres y Redis (pueden arrancar a la vez que la API)
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(config.startup_wait_timeout_secs);
+
     // Inicializar base de datos
-    let db_connection = match DatabaseConnection::new_default().await {
+    let db_connection = match wait_for_dependency("PostgreSQL", deadline, STARTUP_RETRY_INTERVAL, DatabaseConnection::new_default).await {
         Ok(conn) => conn,
         Err(e) => {
             error!("❌ Error conectando a la base de datos: {}", e);
@@ -65,7 +76,7 @@ async fn main() -> Result<()> {
         max_connections: 10,
     };
     
-    let redis_client = match RedisClient::new(redis_config).await {
+    let redis_client = match wait_for_dependency("Redis", deadline, STARTUP_RETRY_INTERVAL, || RedisClient::new(redis_config.clone())).await {
         Ok(client) => {
             info!("✅ Redis conectado exitosamente");
             client
@@ -77,19 +88,26 @@ async fn main() -> Result<()> {
     };
 
     // Crear router de la API
-    let app_state = AppState::new(pool, EnvironmentConfig::default(), redis_client);
+    let app_state = AppState::new(pool, config, redis_client);
     
     let app = Router::new()
+        .merge(routes::root_routes::create_root_router())
         .route("/test", get(test_endpoint))
         // Nuevas rutas MVC
         .nest("/company", routes::company_routes::create_company_router())
         .nest("/vehicle", routes::vehicle_routes::create_vehicle_router())
+        .nest("/depot", routes::depot_routes::create_depot_router())
         .nest("/address", routes::address_routes::create_address_router())
         .nest("/colis-prive", routes::colis_prive_routes::create_colis_prive_routes())
+        .nest("/analysis", routes::analysis_routes::create_analysis_router())
+        .nest("/tournee", routes::tournee_routes::create_tournee_router())
         .nest("/", routes::package_routes::package_routes())
         // .nest("/api/mapbox-optimization", routes::mapbox_optimization_routes::create_mapbox_optimization_routes()) // Deshabilitado hasta tener acceso a v2 Beta
         // Endpoints legacy (geocoding, hybrid)
         .merge(api::create_legacy_api_router())
+        // 404 en JSON para cualquier ruta desconocida
+        .fallback(routes::root_routes::not_found)
+        .layer(axum::middleware::from_fn(request_id_middleware))
         .layer(cors_middleware())
         .with_state(app_state);
 
@@ -99,6 +117,7 @@ async fn main() -> Result<()> {
 
     info!("🌐 Servidor iniciando en http://{}", addr);
     info!("🔍 Endpoints disponibles:");
+    info!("   GET  / - Descripción de la API");
     info!("   GET  /test - Endpoint de prueba");
     info!("🏢 Endpoints MVC - Company:");
     info!("   POST /company/register - Registrar empresa");
@@ -110,23 +129,65 @@ async fn main() -> Result<()> {
     info!("   GET  /vehicle/:id - Obtener vehículo");
     info!("   PUT  /vehicle/:id - Actualizar vehículo");
     info!("   DELETE /vehicle/:id - Eliminar vehículo");
+    info!("🏭 Endpoints MVC - Depot:");
+    info!("   POST /depot - Crear almacén");
+    info!("   GET  /depot - Listar almacenes");
+    info!("   GET  /depot/:id - Obtener almacén");
+    info!("   PUT  /depot/:id - Actualizar almacén");
+    info!("   DELETE /depot/:id - Eliminar almacén");
     info!("📍 Endpoints MVC - Address:");
     info!("   POST /address - Guardar dirección");
     info!("   GET  /address/search - Buscar direcciones");
-    info!("   GET  /address/:id - Obtener dirección");
+    info!("   GET  /address/near - Direcciones de la libreta cerca de un punto, por distancia");
+    info!("   GET  /address/in-bounds - Direcciones de la libreta dentro de un rectángulo");
+    info!("   POST /address/candidates - Candidatos de geocodificación");
+    info!("   POST /address/clean-preview - Previsualizar limpieza de dirección");
+    info!("   GET  /address/validations/pending - Direcciones pendientes de validar");
+    info!("   DELETE /address/geocache - Vaciar el cache de geocoding de una dirección o zona");
+    info!("   GET  /address/:id - Obtener dirección con sus códigos/BAL guardados");
     info!("   PUT  /address/:id - Actualizar código/BAL");
     info!("   DELETE /address/:id - Eliminar dirección");
+    info!("   PUT  /address/:id/coordinates - Fijar coordenadas de una validación");
     info!("   GET  /address/route/:route_id - Direcciones por ruta");
     info!("📦 Endpoints MVC - Colis Privé:");
     info!("   POST /colis-prive/auth - Autenticación");
-    info!("   POST /colis-prive/packages - Obtener paquetes");
+    info!("   DELETE /colis-prive/token - Invalidar el token guardado de un usuario (requiere X-Admin-Key)");
+    info!("   POST /colis-prive/packages - Obtener paquetes (?label= para filtrar)");
+    info!("   POST /colis-prive/packages/batch - Paquetes de varias fechas");
     info!("   POST /colis-prive/optimize - Optimizar ruta (Colis Privé)");
+    info!("   POST /colis-prive/merge-tournees - Fusionar tournées de varias sociétés");
+    info!("   GET  /colis-prive/optimization/:matricule/latest - Última optimización guardada");
+    info!("   GET  /colis-prive/optimization/:matricule/changes - Cambios de orden");
+    info!("   POST /colis-prive/optimization/:matricule/apply - Aplicar orden optimizado");
+    info!("   GET|POST /colis-prive/packages/:reference/labels - Etiquetas del paquete");
+    info!("   DELETE /colis-prive/packages/:reference/labels/:label - Quitar etiqueta");
+    info!("   POST /colis-prive/packages/:reference/delivered|failed - Registrar parada");
+    info!("   GET  /colis-prive/eta/:matricule - Estimación de fin de tournée");
+    info!("   GET  /colis-prive/route/:matricule/navlink - Enlaces de navegación (Google/Waze/Apple)");
+    info!("   GET  /colis-prive/quality/:matricule - Calidad del geocoding de la tournée");
+    info!("   GET|POST /colis-prive/snapshots/:matricule - Fotos del estado de la tournée (?societe=&date=)");
+    info!("   GET  /colis-prive/snapshots/:matricule/diff - Cambios entre dos fotos (?societe=&date=&from=&to=)");
+    info!("   GET  /colis-prive/full/:matricule - Paquetes + validación (+ detalle con ?details=true)");
+    info!("   GET  /colis-prive/export/:matricule - Exportar tournée (CSV/Excel)");
+    info!("   GET  /colis-prive/export/:matricule/gpx - Exportar ruta optimizada (GPX para GPS)");
     info!("   GET  /colis-prive/companies - Listar empresas");
+    info!("   GET  /colis-prive/societes - Sociétés soportadas");
+    info!("   POST /colis-prive/societes/refresh - Refrescar sociétés soportadas");
+    info!("   DELETE /colis-prive/societes/:societe/tokens - Invalidar tokens de una société (rotación de credenciales, requiere X-Admin-Key)");
+    info!("   POST /colis-prive/webhook/status - Webhook firmado de estados de paquetes");
     info!("   GET  /colis-prive/health - Health check");
+    info!("📡 Endpoints MVC - Tournée en directo:");
+    info!("   GET  /tournee/:matricule/live - WebSocket con las paradas entregadas/fallidas (?societe=&date=)");
     info!("📦 Endpoints MVC - Packages:");
     info!("   GET  /packages/grouped - Obtener paquetes agrupados");
     info!("   GET  /packages/stats - Estadísticas de procesamiento");
+    info!("   POST /packages/import - Alta masiva de paquetes de la empresa desde un CSV (multipart, campo file)");
+    info!("   POST /packages/import/csv - Alias de /packages/import");
+    info!("   GET  /packages/export.csv - Exportar paquetes a CSV (hojas de cálculo)");
     info!("   PUT  /addresses/:id/driver-data - Actualizar datos del chofer");
+    info!("📊 Endpoints MVC - Analysis:");
+    info!("   GET  /analysis/reattempts - Paquetes fallidos a reintentar (JSON/CSV)");
+    info!("   GET  /analysis/validation-trends - Calidad de direcciones por día");
     info!("🗺️ Endpoints MVC - Mapbox Optimization:");
     info!("   POST /mapbox-optimization/optimize - Optimizar ruta (Mapbox)");
     info!("   GET  /mapbox-optimization/health - Health check");
@@ -3,4 +3,6 @@
 //! Este módulo contiene el middleware de la aplicación.
 
 // pub mod auth; // Comentado temporalmente - migrar a MVC
-pub mod cors;
\ No newline at end of file
+pub mod cors;
+pub mod rate_limit;
+pub mod request_id;
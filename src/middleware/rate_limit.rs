@@ -0,0 +1,182 @@
+//! Rate limiting por cuenta de Colis Privé
+//!
+//! Un cliente que repite logins o descargas de tournée sin control puede hacer
+//! que Colis Privé bloquee nuestra IP. Cada cuenta (`{societe}_{username}`)
+//! tiene un token bucket para la autenticación y otro para las descargas de
+//! tournée; al agotarlo se responde 429 con `Retry-After`.
+//!
+//! La cuenta viene en el cuerpo JSON de la mayoría de rutas, así que la
+//! comprobación se hace en los handlers de `/colis-prive` tras extraerlo.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::utils::errors::AppError;
+
+/// Cuentas vigiladas a partir de las cuales se olvidan los buckets llenos
+const MAX_TRACKED_ACCOUNTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket por clave: `capacity` peticiones seguidas y recarga continua
+/// de `capacity` por minuto. Con capacidad 0 no limita.
+#[derive(Clone)]
+pub struct TokenBucketLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl TokenBucketLimiter {
+    pub fn per_minute(limit: u32) -> Self {
+        Self {
+            capacity: limit as f64,
+            refill_per_sec: limit as f64 / 60.0,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Consumir `cost` tokens de `key`; si no hay suficientes, devuelve cuánto esperar
+    pub fn try_acquire(&self, key: &str, cost: u32, now: Instant) -> Result<(), Duration> {
+        if self.capacity <= 0.0 {
+            return Ok(());
+        }
+        // Un coste mayor que el bucket nunca cabría: cuenta como el bucket entero
+        let cost = (cost as f64).min(self.capacity);
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_TRACKED_ACCOUNTS && !buckets.contains_key(key) {
+            let (capacity, rate) = (self.capacity, self.refill_per_sec);
+            buckets.retain(|_, bucket| refilled(bucket, capacity, rate, now) < capacity);
+        }
+
+        let bucket = buckets
+            .entry(key.to_string())
+            .or_insert(Bucket { tokens: self.capacity, updated: now });
+        bucket.tokens = refilled(bucket, self.capacity, self.refill_per_sec, now);
+        bucket.updated = now;
+
+        if bucket.tokens >= cost {
+            bucket.tokens -= cost;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((cost - bucket.tokens) / self.refill_per_sec))
+        }
+    }
+}
+
+fn refilled(bucket: &Bucket, capacity: f64, refill_per_sec: f64, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    (bucket.tokens + elapsed * refill_per_sec).min(capacity)
+}
+
+/// Límites de las llamadas a Colis Privé por cuenta
+#[derive(Clone)]
+pub struct ColisPriveRateLimiter {
+    auth: TokenBucketLimiter,
+    tournee: TokenBucketLimiter,
+}
+
+impl ColisPriveRateLimiter {
+    pub fn new(auth_per_minute: u32, tournee_per_minute: u32) -> Self {
+        Self {
+            auth: TokenBucketLimiter::per_minute(auth_per_minute),
+            tournee: TokenBucketLimiter::per_minute(tournee_per_minute),
+        }
+    }
+
+    /// Intento de autenticación de la cuenta
+    pub fn check_auth(&self, societe: &str, username: &str) -> Result<(), AppError> {
+        check(&self.auth, "autenticación", societe, username, 1)
+    }
+
+    /// `fetches` descargas de tournée de la cuenta (una por fecha pedida)
+    pub fn check_tournee(&self, societe: &str, matricule: &str, fetches: usize) -> Result<(), AppError> {
+        check(&self.tournee, "tournée", societe, matricule, fetches.max(1) as u32)
+    }
+}
+
+fn check(limiter: &TokenBucketLimiter, call: &str, societe: &str, username: &str, cost: u32) -> Result<(), AppError> {
+    let key = account_key(societe, username);
+    limiter.try_acquire(&key, cost, Instant::now()).map_err(|wait| {
+        let retry_after_secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+        log::warn!("🚦 Límite de {} alcanzado para {} (reintentar en {} s)", call, key, retry_after_secs);
+        AppError::RateLimitExceeded { retry_after_secs }
+    })
+}
+
+/// Cuenta de Colis Privé: `{societe}_{username}`
+fn account_key(societe: &str, username: &str) -> String {
+    format!("{}_{}", societe.trim(), username.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    #[test]
+    fn test_bucket_allows_burst_then_refills() {
+        let limiter = TokenBucketLimiter::per_minute(5);
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            assert!(limiter.try_acquire("PCP0010699_A187518", 1, start).is_ok());
+        }
+        let wait = limiter.try_acquire("PCP0010699_A187518", 1, start).unwrap_err();
+        // 5 por minuto: un token cada 12 s
+        assert!((wait.as_secs_f64() - 12.0).abs() < 0.01);
+
+        assert!(limiter.try_acquire("PCP0010699_A187518", 1, start + Duration::from_secs(12)).is_ok());
+        assert!(limiter.try_acquire("PCP0010699_A187518", 1, start + Duration::from_secs(12)).is_err());
+    }
+
+    #[test]
+    fn test_accounts_have_separate_buckets() {
+        let limiter = TokenBucketLimiter::per_minute(1);
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire("PCP0010699_A187518", 1, now).is_ok());
+        assert!(limiter.try_acquire("PCP0010699_A187518", 1, now).is_err());
+        assert!(limiter.try_acquire("PCP0010699_B000001", 1, now).is_ok());
+        assert!(limiter.try_acquire("PCP0010700_A187518", 1, now).is_ok());
+    }
+
+    #[test]
+    fn test_zero_limit_disables_limiting() {
+        let limiter = TokenBucketLimiter::per_minute(0);
+        let now = Instant::now();
+        assert!((0..100).all(|_| limiter.try_acquire("PCP0010699_A187518", 1, now).is_ok()));
+    }
+
+    #[test]
+    fn test_batch_cost_is_capped_to_the_bucket() {
+        let limiter = TokenBucketLimiter::per_minute(30);
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire("PCP0010699_A187518", 10, now).is_ok());
+        assert!(limiter.try_acquire("PCP0010699_A187518", 20, now).is_ok());
+        assert!(limiter.try_acquire("PCP0010699_A187518", 1, now).is_err());
+        // Un lote mayor que el bucket cabe cuando el bucket está lleno
+        assert!(limiter.try_acquire("PCP0010699_B000001", 50, now).is_ok());
+    }
+
+    #[test]
+    fn test_exceeded_limit_is_429_with_retry_after() {
+        let limiter = ColisPriveRateLimiter::new(1, 30);
+        assert!(limiter.check_auth("PCP0010699", "A187518").is_ok());
+
+        let error = limiter.check_auth(" PCP0010699", "A187518 ").unwrap_err();
+        let response = error.into_response();
+
+        assert_eq!(response.status(), axum::http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[axum::http::header::RETRY_AFTER], "60");
+        // Las descargas de tournée tienen su propio bucket
+        assert!(limiter.check_tournee("PCP0010699", "A187518", 1).is_ok());
+    }
+}
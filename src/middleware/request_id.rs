@@ -0,0 +1,62 @@
+//! Identificador de petición
+//!
+//! Cada petición abre un span `request` con un `request_id` generado; todas las
+//! líneas de log de esa petición (también las de los spans de Colis Privé que
+//! cuelgan de él) lo llevan. El mismo id vuelve en la cabecera `x-request-id`
+//! para que soporte pueda cruzar la captura de un chofer con los logs.
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Middleware (`axum::middleware::from_fn`) que abre el span de la petición
+pub async fn request_id_middleware(request: Request, next: Next) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn request_id_of(app: Router) -> String {
+        let response = app
+            .oneshot(Request::get("/colis-prive/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_each_response_carries_its_own_request_id() {
+        let app = Router::new()
+            .route("/colis-prive/health", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(request_id_middleware));
+
+        let first = request_id_of(app.clone()).await;
+        let second = request_id_of(app).await;
+
+        assert!(Uuid::parse_str(&first).is_ok());
+        assert_ne!(first, second);
+    }
+}
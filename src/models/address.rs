@@ -20,6 +20,8 @@ pub struct Address {
     pub door_code: Option<String>,
     pub has_mailbox_access: bool,
     pub driver_notes: Option<String>,
+    pub floor: Option<String>,
+    pub concierge_notes: Option<String>,
     
     // Metadata
     pub last_updated_by: Option<String>,
@@ -27,6 +29,15 @@ pub struct Address {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Datos de acceso guardados en la libreta de direcciones (código, BAL, planta, gardien)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AddressAccess {
+    pub door_code: Option<String>,
+    pub has_mailbox_access: bool,
+    pub floor: Option<String>,
+    pub concierge_notes: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddressSearch {
     pub street_name: String,
@@ -0,0 +1,41 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::services::colis_prive_service::ValidationMethod;
+
+/// Resultado guardado de la validación de una dirección de tournée
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AddressValidation {
+    pub id: Uuid,
+    pub normalized_address: String,
+    pub original_address: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub validation_method: String,
+    pub confidence: Option<f64>,
+    pub confirmed_by: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AddressValidation {
+    pub fn method(&self) -> Option<ValidationMethod> {
+        ValidationMethod::parse(&self.validation_method)
+    }
+
+    /// Coordenadas `(lat, lon)` si la validación las encontró
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        Some((self.latitude?, self.longitude?))
+    }
+}
+
+/// Resultados de validación de un día y método, sumados sobre todas las tournées
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DailyValidationOutcome {
+    pub day: NaiveDate,
+    pub validation_method: String,
+    pub outcomes: i64,
+    /// Suma de las confianzas de los paquetes con puntuación
+    pub confidence_sum: f64,
+    pub scored: i64,
+}
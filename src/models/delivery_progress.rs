@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Resultado de una parada ya visitada
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StopOutcome {
+    Delivered,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedStop {
+    pub reference_colis: String,
+    pub outcome: StopOutcome,
+    pub completed_at: DateTime<Utc>,
+    /// Motivo indicado por el repartidor (ausente, dirección errónea...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Intentos fallidos en la jornada (volver a pasar suma uno)
+    #[serde(default = "first_attempt")]
+    pub attempts: u32,
+    /// Entregado a más del radio de geocerca de las coordenadas del paquete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub delivery_location_flagged: bool,
+}
+
+fn first_attempt() -> u32 {
+    1
+}
+
+/// Progreso de entrega de una tournée (paradas visitadas en orden cronológico)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryProgress {
+    pub societe: String,
+    pub matricule: String,
+    pub date_tournee: String,
+    pub completed: Vec<CompletedStop>,
+}
+
+impl DeliveryProgress {
+    pub fn new(societe: &str, matricule: &str, date_tournee: &str) -> Self {
+        Self {
+            societe: societe.to_string(),
+            matricule: matricule.to_string(),
+            date_tournee: date_tournee.to_string(),
+            completed: Vec::new(),
+        }
+    }
+
+    /// Registrar una parada; si ya estaba registrada se actualiza su resultado
+    /// (un nuevo fallo sobre una parada ya fallida cuenta como otro intento)
+    pub fn record(
+        &mut self,
+        reference_colis: &str,
+        outcome: StopOutcome,
+        reason: Option<String>,
+        completed_at: DateTime<Utc>,
+    ) {
+        let previous_failures = self
+            .completed
+            .iter()
+            .find(|stop| stop.reference_colis == reference_colis && stop.outcome == StopOutcome::Failed)
+            .map(|stop| stop.attempts)
+            .unwrap_or(0);
+
+        self.completed.retain(|stop| stop.reference_colis != reference_colis);
+        self.completed.push(CompletedStop {
+            reference_colis: reference_colis.to_string(),
+            outcome,
+            completed_at,
+            reason,
+            attempts: match outcome {
+                StopOutcome::Failed => previous_failures + 1,
+                StopOutcome::Delivered => previous_failures.max(1),
+            },
+            delivery_location_flagged: false,
+        });
+        self.completed.sort_by_key(|stop| stop.completed_at);
+    }
+
+    /// Marcar la entrega de un paquete ya registrado como hecha fuera de la geocerca
+    pub fn flag_delivery_location(&mut self, reference_colis: &str) {
+        if let Some(stop) = self.completed.iter_mut().find(|stop| stop.reference_colis == reference_colis) {
+            stop.delivery_location_flagged = true;
+        }
+    }
+
+    pub fn is_completed(&self, reference_colis: &str) -> bool {
+        self.completed.iter().any(|stop| stop.reference_colis == reference_colis)
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use super::delivery_progress::{DeliveryProgress, StopOutcome};
+
+/// Estado de entrega de un paquete
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    InTransit,
+    OutForDelivery,
+    Delivered,
+    Failed,
+    Rescheduled,
+}
+
+impl DeliveryStatus {
+    /// Transiciones permitidas:
+    /// pending → in_transit → out_for_delivery → delivered | failed,
+    /// failed → rescheduled → pending, y failed → out_for_delivery cuando el
+    /// repartidor vuelve a pasar en la misma jornada. `delivered` es final.
+    pub fn can_transition(from: DeliveryStatus, to: DeliveryStatus) -> bool {
+        use DeliveryStatus::*;
+
+        matches!(
+            (from, to),
+            (Pending, InTransit)
+                | (InTransit, OutForDelivery)
+                | (OutForDelivery, Delivered)
+                | (OutForDelivery, Failed)
+                | (Failed, Rescheduled)
+                | (Failed, OutForDelivery)
+                | (Rescheduled, Pending)
+        )
+    }
+}
+
+impl From<StopOutcome> for DeliveryStatus {
+    fn from(outcome: StopOutcome) -> Self {
+        match outcome {
+            StopOutcome::Delivered => DeliveryStatus::Delivered,
+            StopOutcome::Failed => DeliveryStatus::Failed,
+        }
+    }
+}
+
+impl DeliveryProgress {
+    /// Estado de un paquete de la tournée: en reparto hasta que se registra su parada
+    pub fn status_of(&self, reference_colis: &str) -> DeliveryStatus {
+        self.completed
+            .iter()
+            .find(|stop| stop.reference_colis == reference_colis)
+            .map(|stop| stop.outcome.into())
+            .unwrap_or(DeliveryStatus::OutForDelivery)
+    }
+
+    /// Comprobar que se puede registrar `outcome` para el paquete; un paquete
+    /// fallido vuelve a estar en reparto al pasar de nuevo. Devuelve el par
+    /// `(actual, pedido)` si la transición no es válida.
+    pub fn check_transition(
+        &self,
+        reference_colis: &str,
+        outcome: StopOutcome,
+    ) -> Result<(), (DeliveryStatus, DeliveryStatus)> {
+        let current = self.status_of(reference_colis);
+        let target = DeliveryStatus::from(outcome);
+
+        let from = match current {
+            DeliveryStatus::Failed if DeliveryStatus::can_transition(current, DeliveryStatus::OutForDelivery) => {
+                DeliveryStatus::OutForDelivery
+            }
+            other => other,
+        };
+
+        if DeliveryStatus::can_transition(from, target) {
+            Ok(())
+        } else {
+            Err((current, target))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_valid_transitions_follow_the_state_machine() {
+        use DeliveryStatus::*;
+
+        assert!(DeliveryStatus::can_transition(OutForDelivery, Delivered));
+        assert!(DeliveryStatus::can_transition(Failed, Rescheduled));
+        assert!(DeliveryStatus::can_transition(Rescheduled, Pending));
+        assert!(!DeliveryStatus::can_transition(Pending, Delivered));
+    }
+
+    #[test]
+    fn test_delivered_is_final() {
+        use DeliveryStatus::*;
+
+        for to in [Pending, InTransit, OutForDelivery, Delivered, Failed, Rescheduled] {
+            assert!(!DeliveryStatus::can_transition(Delivered, to), "delivered → {:?}", to);
+        }
+
+        let mut progress = DeliveryProgress::new("PCP0010699", "A187518", "2025-01-15");
+        assert!(progress.check_transition("CP1", StopOutcome::Failed).is_ok());
+        progress.record("CP1", StopOutcome::Failed, None, Utc::now());
+
+        // Segundo paso tras un fallo: se puede entregar o volver a fallar
+        assert!(progress.check_transition("CP1", StopOutcome::Failed).is_ok());
+        assert!(progress.check_transition("CP1", StopOutcome::Delivered).is_ok());
+        progress.record("CP1", StopOutcome::Delivered, None, Utc::now());
+
+        assert_eq!(
+            progress.check_transition("CP1", StopOutcome::Failed),
+            Err((Delivered, Failed))
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! Modelo de Depot
+//!
+//! Almacén de una empresa: punto de salida y vuelta de las rutas optimizadas.
+//! `societe` enlaza el almacén con la société de Colis Privé cuyas tournées
+//! salen de él; la optimización busca el almacén por defecto de la société.
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Depot {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub name: String,
+    pub societe: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Depot {
+    /// Coordenadas `(lat, lon)` del almacén
+    pub fn location(&self) -> (f64, f64) {
+        (self.latitude, self.longitude)
+    }
+}
@@ -4,7 +4,15 @@
 
 pub mod company;
 pub mod vehicle;
+pub mod depot;
 pub mod route;
 pub mod colis_prive_company;
 pub mod address;
-pub mod package;
\ No newline at end of file
+pub mod address_validation;
+pub mod package;
+pub mod optimization;
+pub mod delivery_progress;
+pub mod delivery_status;
+pub mod package_label;
+pub mod package_status;
+pub mod tournee_snapshot;
@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::dto::colis_prive_dto::{OptimizationData, OptimizationEngine, PackageData};
+use crate::services::eta_service::planned_duration;
+
+/// Resultado de optimización guardado para comparar re-optimizaciones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredOptimization {
+    pub societe: String,
+    pub matricule: String,
+    pub matricule_chauffeur: String,
+    pub date_tournee: String,
+    pub engine: OptimizationEngine,
+    /// Referencias de los paquetes en el orden optimizado
+    pub package_order: Vec<String>,
+    pub packages: Vec<PackageData>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl StoredOptimization {
+    pub fn new(
+        societe: &str,
+        matricule: &str,
+        matricule_chauffeur: &str,
+        date_tournee: &str,
+        engine: OptimizationEngine,
+        packages: Vec<PackageData>,
+    ) -> Self {
+        Self {
+            societe: societe.to_string(),
+            matricule: matricule.to_string(),
+            matricule_chauffeur: matricule_chauffeur.to_string(),
+            date_tournee: date_tournee.to_string(),
+            engine,
+            package_order: packages.iter().map(|p| p.reference_colis.clone()).collect(),
+            packages,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Hash del conjunto de paquetes optimizado (ver [`tournee_hash`])
+    pub fn tournee_hash(&self) -> String {
+        tournee_hash(&self.package_order)
+    }
+
+    /// Antigüedad del resultado respecto a `now`
+    pub fn age(&self, now: DateTime<Utc>) -> chrono::Duration {
+        now - self.created_at
+    }
+}
+
+impl From<StoredOptimization> for OptimizationData {
+    fn from(stored: StoredOptimization) -> Self {
+        Self {
+            tournee_hash: stored.tournee_hash(),
+            duration: planned_duration(&stored.packages, None),
+            matricule_chauffeur: stored.matricule_chauffeur,
+            date_tournee: stored.date_tournee,
+            optimized_packages: stored.packages.into_iter().map(Into::into).collect(),
+            order_changes: None,
+            optimizer_stats: None,
+            depot_stops: Vec::new(),
+            suggested_split: Vec::new(),
+        }
+    }
+}
+
+/// Hash SHA-256 del conjunto de referencias de una tournée.
+///
+/// No depende del orden: dos tournées con los mismos paquetes tienen el mismo hash.
+pub fn tournee_hash(references: &[String]) -> String {
+    let mut sorted: Vec<&str> = references.iter().map(|r| r.as_str()).collect();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut hasher = Sha256::new();
+    for reference in sorted {
+        hasher.update(reference.as_bytes());
+        hasher.update(b"\n");
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+
+use crate::models::address::Address;
 use chrono::{DateTime, Utc};
 
 /// Paquete individual de Colis Privé
@@ -46,6 +48,9 @@ pub struct ProcessedPackage {
     pub longitude: f64,
     pub mailbox_access: bool,
     pub driver_notes: String,
+    pub door_code: Option<String>,
+    pub floor: Option<String>,
+    pub concierge_notes: Option<String>,
     pub address_id: Option<Uuid>,
     pub code_statut_article: Option<String>,
     pub is_problematic: bool, // Marcado si qualiteGeocodage != "Bon"
@@ -69,6 +74,32 @@ pub struct CustomerGroup {
     pub packages: Vec<PackageInfo>,
 }
 
+/// Datos de entrega dentro del edificio extraídos de las indicaciones del destinatario
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryDetails {
+    pub batiment: Option<String>,
+    pub escalier: Option<String>,
+    /// Planta (0 = rez-de-chaussée)
+    pub etage: Option<i32>,
+    pub porte: Option<String>,
+    pub appartement: Option<String>,
+}
+
+impl DeliveryDetails {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Parada dentro de un grupo (una unidad del edificio)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubStop {
+    pub package_id: Uuid,
+    pub tracking: String,
+    pub customer_name: String,
+    pub details: DeliveryDetails,
+}
+
 /// Grupo de entrega (múltiples paquetes en misma dirección)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeliveryGroup {
@@ -78,7 +109,12 @@ pub struct DeliveryGroup {
     pub longitude: f64,
     pub mailbox_access: bool,
     pub driver_notes: String,
+    pub door_code: Option<String>,
+    pub floor: Option<String>,
+    pub concierge_notes: Option<String>,
     pub customers: Vec<CustomerGroup>,
+    /// Unidades del edificio ordenadas por planta
+    pub sub_stops: Vec<SubStop>,
     pub total_packages: usize,
 }
 
@@ -95,6 +131,9 @@ pub struct SinglePackage {
     pub longitude: f64,
     pub mailbox_access: bool,
     pub driver_notes: String,
+    pub door_code: Option<String>,
+    pub floor: Option<String>,
+    pub concierge_notes: Option<String>,
     pub address_id: Option<Uuid>,
     pub code_statut_article: Option<String>,
     pub is_problematic: bool, // Marcado si qualiteGeocodage != "Bon"
@@ -153,6 +192,21 @@ impl GroupedPackages {
     }
 }
 
+impl ProcessedPackage {
+    /// Usar los datos de la libreta de direcciones (coordenadas oficiales y códigos guardados)
+    pub fn attach_address(&mut self, address: &Address) {
+        self.official_label = address.official_label.clone();
+        self.latitude = address.latitude;
+        self.longitude = address.longitude;
+        self.mailbox_access = address.has_mailbox_access;
+        self.driver_notes = address.driver_notes.clone().unwrap_or_default();
+        self.door_code = address.door_code.clone();
+        self.floor = address.floor.clone();
+        self.concierge_notes = address.concierge_notes.clone();
+        self.address_id = Some(address.id);
+    }
+}
+
 impl From<ColisPrivePackage> for ProcessedPackage {
     fn from(colis: ColisPrivePackage) -> Self {
         // Determinar si es problemático basado en qualiteGeocodage
@@ -190,6 +244,9 @@ impl From<ColisPrivePackage> for ProcessedPackage {
             longitude: colis.longitude,
             mailbox_access: false,
             driver_notes: String::new(),
+            door_code: None,
+            floor: None,
+            concierge_notes: None,
             address_id: None,
             code_statut_article: colis.code_statut_article,
             is_problematic,
@@ -210,6 +267,9 @@ impl From<ProcessedPackage> for SinglePackage {
             longitude: processed.longitude,
             mailbox_access: processed.mailbox_access,
             driver_notes: processed.driver_notes,
+            door_code: processed.door_code,
+            floor: processed.floor,
+            concierge_notes: processed.concierge_notes,
             address_id: processed.address_id,
             code_statut_article: processed.code_statut_article,
             is_problematic: processed.is_problematic,
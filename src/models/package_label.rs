@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Etiqueta libre asociada a un paquete de Colis Privé
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PackageLabel {
+    pub reference_colis: String,
+    pub label: String,
+    pub created_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
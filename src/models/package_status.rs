@@ -0,0 +1,10 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Actualización de estado recibida por webhook
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusUpdate {
+    pub tracking_number: String,
+    pub status: String,
+    pub timestamp: DateTime<Utc>,
+}
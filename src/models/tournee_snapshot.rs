@@ -0,0 +1,35 @@
+//! Modelo de TourneeSnapshot
+//!
+//! Foto del estado de los paquetes de una tournée en un momento del día. Las
+//! fotos sucesivas reconstruyen la progresión de la jornada para resolver
+//! disputas (qué estado tenía un paquete a qué hora).
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::delivery_progress::StopOutcome;
+
+/// Estado de un paquete en la foto
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageState {
+    pub reference_colis: String,
+    /// Estado de Colis Privé ("LIVRE", "ECHEC"...), el del webhook si llegó alguno
+    pub status: Option<String>,
+    /// Resultado registrado por el chofer, si ya pasó por la parada
+    pub outcome: Option<StopOutcome>,
+    /// Intentos registrados por el chofer (0 si aún no pasó)
+    pub attempts: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TourneeSnapshot {
+    pub id: Uuid,
+    pub societe: String,
+    pub matricule: String,
+    pub date_tournee: NaiveDate,
+    pub taken_at: DateTime<Utc>,
+    pub packages: Json<Vec<PackageState>>,
+}
@@ -1,3 +1,5 @@
+use crate::models::address::{Address as AddressBookEntry, AddressAccess};
+use crate::repositories::spatial_query::{PageRequest, SpatialArea, SpatialPage, SpatialQuery};
 use crate::utils::errors::AppError;
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -16,6 +18,13 @@ pub struct Address {
     pub created_at: chrono::DateTime<Utc>,
 }
 
+const BOOK_ENTRY_COLUMNS: &str = r#"
+    id, company_id, official_label, street_name, street_number, postcode, city,
+        ST_Y(coordinates) AS latitude, ST_X(coordinates) AS longitude,
+        door_code, has_mailbox_access, driver_notes, floor, concierge_notes,
+        last_updated_by, created_at, updated_at
+"#;
+
 pub struct AddressRepository {
     pool: PgPool,
 }
@@ -135,6 +144,69 @@ impl AddressRepository {
         Ok(addr)
     }
 
+    /// Entrada de la libreta de direcciones (códigos, BAL, planta, gardien)
+    pub async fn find_book_entry(&self, id: Uuid) -> Result<Option<AddressBookEntry>, AppError> {
+        let entry = sqlx::query_as::<_, AddressBookEntry>(&format!("SELECT {} FROM addresses WHERE id = $1", BOOK_ENTRY_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Error finding address book entry: {}", e)))?;
+
+        Ok(entry)
+    }
+
+    /// Entradas de la libreta en una zona, de la más cercana a la más lejana
+    pub async fn find_book_entries_in(
+        &self,
+        area: SpatialArea,
+        page: PageRequest,
+    ) -> Result<SpatialPage<AddressBookEntry>, AppError> {
+        SpatialQuery {
+            table: "addresses",
+            columns: BOOK_ENTRY_COLUMNS,
+            geometry_column: "coordinates",
+            area,
+            page,
+        }
+        .fetch(&self.pool)
+        .await
+    }
+
+    /// Guardar los datos de acceso; se adjuntan a los paquetes de futuras tournées
+    pub async fn update_access(
+        &self,
+        id: Uuid,
+        access: &AddressAccess,
+        driver_notes: Option<String>,
+        updated_by: Option<String>,
+    ) -> Result<AddressBookEntry, AppError> {
+        let entry = sqlx::query_as::<_, AddressBookEntry>(
+            r#"
+            UPDATE addresses
+            SET door_code = $2, has_mailbox_access = $3, floor = $4, concierge_notes = $5,
+                driver_notes = $6, last_updated_by = $7, updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, company_id, official_label, street_name, street_number, postcode, city,
+                ST_Y(coordinates) AS latitude, ST_X(coordinates) AS longitude,
+                door_code, has_mailbox_access, driver_notes, floor, concierge_notes,
+                last_updated_by, created_at, updated_at
+            "#
+        )
+        .bind(id)
+        .bind(&access.door_code)
+        .bind(access.has_mailbox_access)
+        .bind(&access.floor)
+        .bind(&access.concierge_notes)
+        .bind(driver_notes)
+        .bind(updated_by)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error updating address access: {}", e)))?
+        .ok_or_else(|| AppError::NotFound("Address not found".to_string()))?;
+
+        Ok(entry)
+    }
+
     pub async fn delete(&self, id: Uuid) -> Result<(), AppError> {
         sqlx::query("DELETE FROM addresses WHERE id = $1")
             .bind(id)
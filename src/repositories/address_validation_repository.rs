@@ -0,0 +1,173 @@
+use chrono::NaiveDate;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::cache::geocoding_cache::normalize_address;
+use crate::models::address_validation::{AddressValidation, DailyValidationOutcome};
+use crate::services::colis_prive_service::ValidationMethod;
+use crate::services::validation_trends_service::MethodOutcome;
+use crate::utils::errors::AppError;
+
+const VALIDATION_COLUMNS: &str = "id, normalized_address, original_address, latitude, longitude, validation_method, confidence, confirmed_by, updated_at";
+
+/// Validaciones de direcciones de tournée, por dirección normalizada
+pub struct AddressValidationRepository {
+    pool: PgPool,
+}
+
+impl AddressValidationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find(&self, address: &str) -> Result<Option<AddressValidation>, AppError> {
+        sqlx::query_as::<_, AddressValidation>(&format!(
+            "SELECT {} FROM address_validations WHERE normalized_address = $1",
+            VALIDATION_COLUMNS
+        ))
+        .bind(normalize_address(address))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error finding address validation: {}", e)))
+    }
+
+    /// Guardar el resultado de una validación automática; nunca sustituye
+    /// unas coordenadas confirmadas a mano
+    pub async fn record(
+        &self,
+        address: &str,
+        coordinates: Option<(f64, f64)>,
+        method: ValidationMethod,
+        confidence: Option<f64>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO address_validations (normalized_address, original_address, latitude, longitude, validation_method, confidence)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (normalized_address) DO UPDATE
+            SET original_address = EXCLUDED.original_address, latitude = EXCLUDED.latitude,
+                longitude = EXCLUDED.longitude, validation_method = EXCLUDED.validation_method,
+                confidence = EXCLUDED.confidence, updated_at = NOW()
+            WHERE address_validations.validation_method <> $7
+            "#
+        )
+        .bind(normalize_address(address))
+        .bind(address)
+        .bind(coordinates.map(|(latitude, _)| latitude))
+        .bind(coordinates.map(|(_, longitude)| longitude))
+        .bind(method.as_str())
+        .bind(confidence)
+        .bind(ValidationMethod::ManualConfirmed.as_str())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error recording address validation: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Fijar a mano las coordenadas de una dirección
+    pub async fn confirm(
+        &self,
+        id: Uuid,
+        latitude: f64,
+        longitude: f64,
+        confirmed_by: Option<&str>,
+    ) -> Result<AddressValidation, AppError> {
+        sqlx::query_as::<_, AddressValidation>(&format!(
+            r#"
+            UPDATE address_validations
+            SET latitude = $2, longitude = $3, validation_method = $4, confidence = 1.0,
+                confirmed_by = $5, updated_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            VALIDATION_COLUMNS
+        ))
+        .bind(id)
+        .bind(latitude)
+        .bind(longitude)
+        .bind(ValidationMethod::ManualConfirmed.as_str())
+        .bind(confirmed_by)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error confirming address coordinates: {}", e)))?
+        .ok_or_else(|| AppError::NotFound(format!("Validación de dirección {} no encontrada", id)))
+    }
+
+    /// Direcciones pendientes de validación manual, las más recientes primero
+    pub async fn pending_manual(&self, limit: i64) -> Result<Vec<AddressValidation>, AppError> {
+        sqlx::query_as::<_, AddressValidation>(&format!(
+            "SELECT {} FROM address_validations WHERE validation_method = $1 ORDER BY updated_at DESC LIMIT $2",
+            VALIDATION_COLUMNS
+        ))
+        .bind(ValidationMethod::RequiresManual.as_str())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error listing pending address validations: {}", e)))
+    }
+
+    /// Guardar los resultados de validación de una tournée; sustituye los de
+    /// una petición anterior de la misma tournée
+    pub async fn record_tournee_outcomes(
+        &self,
+        date: NaiveDate,
+        societe: &str,
+        matricule: &str,
+        outcomes: &[MethodOutcome],
+    ) -> Result<(), AppError> {
+        let db_error = |e: sqlx::Error| AppError::DatabaseError(format!("Error recording validation outcomes: {}", e));
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+
+        sqlx::query("DELETE FROM address_validation_outcomes WHERE tournee_date = $1 AND societe = $2 AND matricule = $3")
+            .bind(date)
+            .bind(societe)
+            .bind(matricule)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+
+        for outcome in outcomes {
+            sqlx::query(
+                r#"
+                INSERT INTO address_validation_outcomes
+                    (tournee_date, societe, matricule, validation_method, outcomes, confidence_sum, scored)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#
+            )
+            .bind(date)
+            .bind(societe)
+            .bind(matricule)
+            .bind(outcome.method.as_str())
+            .bind(outcome.outcomes)
+            .bind(outcome.confidence_sum)
+            .bind(outcome.scored)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        }
+
+        tx.commit().await.map_err(db_error)
+    }
+
+    /// Resultados por día y método entre `from` y `to` (ambos incluidos)
+    pub async fn daily_outcomes(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyValidationOutcome>, AppError> {
+        sqlx::query_as::<_, DailyValidationOutcome>(
+            r#"
+            SELECT tournee_date AS day, validation_method,
+                   SUM(outcomes)::BIGINT AS outcomes,
+                   COALESCE(SUM(confidence_sum), 0)::DOUBLE PRECISION AS confidence_sum,
+                   SUM(scored)::BIGINT AS scored
+            FROM address_validation_outcomes
+            WHERE tournee_date BETWEEN $1 AND $2
+            GROUP BY tournee_date, validation_method
+            ORDER BY tournee_date
+            "#
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error loading validation trends: {}", e)))
+    }
+}
@@ -1,41 +1,195 @@
-use crate::state::AuthToken;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use uuid::Uuid;
-
-// Repository para manejar el cache de tokens SSO de Colis Privé
-pub struct ColisPriveRepository {
-    auth_tokens: Arc<RwLock<HashMap<String, AuthToken>>>,
+use crate::cache::fallback_cache::RemoteCache;
+use crate::cache::redis_client::RedisClient;
+use crate::state::{AuthToken, AuthTokenStore};
+
+// Repository para manejar el cache de tokens SSO de Colis Privé.
+// Redis es la fuente compartida entre réplicas (sobrevive a reinicios); la
+// copia en memoria solo se consulta si Redis no responde, para que un token
+// invalidado en otra réplica no se siga sirviendo desde aquí.
+pub struct ColisPriveRepository<R = RedisClient> {
+    auth_tokens: AuthTokenStore,
+    redis: R,
 }
 
-impl ColisPriveRepository {
-    pub fn new(auth_tokens: Arc<RwLock<HashMap<String, AuthToken>>>) -> Self {
-        Self { auth_tokens }
+impl<R: RemoteCache> ColisPriveRepository<R> {
+    pub fn new(auth_tokens: AuthTokenStore, redis: R) -> Self {
+        Self { auth_tokens, redis }
     }
 
     pub async fn get_token(&self, societe: &str, matricule: &str) -> Option<AuthToken> {
-        let tokens = self.auth_tokens.read().await;
-        let key = format!("{}:{}", societe, matricule);
-        tokens.get(&key).cloned()
+        let redis_key = RedisClient::auth_key(matricule, societe);
+        match self.redis.fetch_raw(&redis_key).await {
+            Ok(Some(raw)) => match serde_json::from_str(&raw) {
+                Ok(token) => Some(token),
+                Err(e) => {
+                    log::warn!("⚠️ Token de {}:{} ilegible en Redis: {}", societe, matricule, e);
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(e) => {
+                log::warn!("⚠️ Redis no disponible, token de {}:{} leído de memoria: {}", societe, matricule, e);
+                self.auth_tokens.get(societe, matricule).await
+            }
+        }
     }
 
     pub async fn save_token(&self, societe: &str, matricule: &str, token: AuthToken) {
-        let mut tokens = self.auth_tokens.write().await;
-        let key = format!("{}:{}", societe, matricule);
-        tokens.insert(key, token);
+        let redis_key = RedisClient::auth_key(matricule, societe);
+        let ttl = token.remaining_secs(chrono::Utc::now());
+        let stored = match serde_json::to_string(&token) {
+            Ok(raw) => self.redis.store_raw(&redis_key, &raw, ttl).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = stored {
+            log::warn!("⚠️ Token de {}:{} guardado solo en memoria: {}", societe, matricule, e);
+        }
+
+        self.auth_tokens.insert(societe, matricule, token).await;
+    }
+
+    /// Invalidar el token en Redis y en memoria; devuelve si había alguno
+    pub async fn remove_token(&self, societe: &str, matricule: &str) -> bool {
+        let in_redis = self
+            .redis
+            .remove_raw(&[RedisClient::auth_key(matricule, societe)])
+            .await
+            .map(|removed| removed > 0)
+            .unwrap_or(false);
+        let in_memory = self.auth_tokens.remove(societe, matricule).await;
+
+        in_redis || in_memory
     }
 
-    pub async fn remove_token(&self, societe: &str, matricule: &str) {
-        let mut tokens = self.auth_tokens.write().await;
-        let key = format!("{}:{}", societe, matricule);
-        tokens.remove(&key);
+    /// Invalidar los tokens de todos los usuarios de una société; devuelve cuántos había.
+    ///
+    /// Las claves se borran con un único DEL, así ninguna réplica ve la
+    /// rotación a medias; las copias en memoria de otras réplicas dejan de
+    /// servirse porque `get_token` solo las consulta si Redis falla.
+    pub async fn remove_societe_tokens(&self, societe: &str) -> usize {
+        let in_redis = match self.redis.keys_matching(&RedisClient::auth_pattern(societe)).await {
+            Ok(keys) if keys.is_empty() => 0,
+            Ok(keys) => self.redis.remove_raw(&keys).await.unwrap_or_else(|e| {
+                log::warn!("⚠️ No se pudieron borrar los tokens de {} en Redis: {}", societe, e);
+                0
+            }),
+            Err(e) => {
+                log::warn!("⚠️ No se pudieron listar los tokens de {} en Redis: {}", societe, e);
+                0
+            }
+        };
+        let in_memory = self.auth_tokens.remove_societe(societe).await;
+
+        in_redis.max(in_memory)
     }
 
     pub async fn token_exists(&self, societe: &str, matricule: &str) -> bool {
-        let tokens = self.auth_tokens.read().await;
-        let key = format!("{}:{}", societe, matricule);
-        tokens.contains_key(&key)
+        self.auth_tokens.contains(societe, matricule).await
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// Redis simulado compartido entre "réplicas", que se puede apagar
+    #[derive(Default)]
+    struct FakeRedis {
+        down: AtomicBool,
+        data: Mutex<HashMap<String, String>>,
+    }
+
+    impl FakeRedis {
+        fn check(&self) -> Result<()> {
+            if self.down.load(Ordering::SeqCst) {
+                anyhow::bail!("connection refused");
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl RemoteCache for Arc<FakeRedis> {
+        async fn fetch_raw(&self, key: &str) -> Result<Option<String>> {
+            self.check()?;
+            Ok(self.data.lock().unwrap().get(key).cloned())
+        }
+
+        async fn store_raw(&self, key: &str, value: &str, _ttl: u64) -> Result<()> {
+            self.check()?;
+            self.data.lock().unwrap().insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+
+        async fn remove_raw(&self, keys: &[String]) -> Result<usize> {
+            self.check()?;
+            let mut data = self.data.lock().unwrap();
+            Ok(keys.iter().filter(|key| data.remove(key.as_str()).is_some()).count())
+        }
+
+        async fn keys_matching(&self, pattern: &str) -> Result<Vec<String>> {
+            self.check()?;
+            let (prefix, suffix) = pattern.split_once('*').unwrap_or((pattern, ""));
+            Ok(self
+                .data
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|key| key.starts_with(prefix) && key.ends_with(suffix))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn token(matricule: &str, societe: &str) -> AuthToken {
+        AuthToken::new("sso-token".to_string(), matricule.to_string(), societe.to_string(), 1)
+    }
+
+    #[tokio::test]
+    async fn test_token_removed_from_redis_is_not_served_from_memory() {
+        let redis = Arc::new(FakeRedis::default());
+        let replica = ColisPriveRepository::new(AuthTokenStore::default(), redis.clone());
+        replica.save_token("PCP0010699", "A187518", token("A187518", "PCP0010699")).await;
+
+        // Logout atendido por otra réplica: solo borra Redis y su propia memoria
+        redis.data.lock().unwrap().clear();
+
+        assert!(replica.token_exists("PCP0010699", "A187518").await);
+        assert!(replica.get_token("PCP0010699", "A187518").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_purged_societe_tokens_are_not_served_by_other_replicas() {
+        let redis = Arc::new(FakeRedis::default());
+        let replica_a = ColisPriveRepository::new(AuthTokenStore::default(), redis.clone());
+        let replica_b = ColisPriveRepository::new(AuthTokenStore::default(), redis.clone());
+        replica_a.save_token("PCP0010699", "A187518", token("A187518", "PCP0010699")).await;
+        replica_a.save_token("PCP0010699", "B200001", token("B200001", "PCP0010699")).await;
+        replica_a.save_token("PCP0020000", "C300001", token("C300001", "PCP0020000")).await;
+
+        assert_eq!(replica_b.remove_societe_tokens("PCP0010699").await, 2);
+
+        // La réplica A conserva su copia en memoria, pero ya no la sirve
+        assert!(replica_a.token_exists("PCP0010699", "A187518").await);
+        assert!(replica_a.get_token("PCP0010699", "A187518").await.is_none());
+        assert!(replica_a.get_token("PCP0010699", "B200001").await.is_none());
+        assert!(replica_a.get_token("PCP0020000", "C300001").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_token_falls_back_to_memory_when_redis_is_down() {
+        let redis = Arc::new(FakeRedis::default());
+        let replica = ColisPriveRepository::new(AuthTokenStore::default(), redis.clone());
+        replica.save_token("PCP0010699", "A187518", token("A187518", "PCP0010699")).await;
+
+        redis.down.store(true, Ordering::SeqCst);
+
+        let cached = replica.get_token("PCP0010699", "A187518").await;
+        assert_eq!(cached.map(|t| t.token), Some("sso-token".to_string()));
+    }
+}
@@ -0,0 +1,31 @@
+use sqlx::PgPool;
+
+use crate::dto::colis_prive_dto::OptimizationEngine;
+use crate::services::optimization_provider_service::parse_provider_order;
+use crate::utils::errors::AppError;
+
+pub struct CompanySettingsRepository {
+    pool: PgPool,
+}
+
+impl CompanySettingsRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Orden de proveedores de optimización de la société (None si no está configurado)
+    pub async fn optimization_providers(&self, societe: &str) -> Result<Option<Vec<OptimizationEngine>>, AppError> {
+        let providers: Option<Option<String>> = sqlx::query_scalar(
+            "SELECT optimization_providers FROM company_settings WHERE societe = $1"
+        )
+        .bind(societe.trim().to_uppercase())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error reading company settings: {}", e)))?;
+
+        Ok(providers
+            .flatten()
+            .map(|raw| parse_provider_order(&raw))
+            .filter(|order| !order.is_empty()))
+    }
+}
@@ -0,0 +1,110 @@
+use crate::cache::redis_client::RedisClient;
+use crate::dto::colis_prive_dto::StopProgressResponse;
+use crate::models::delivery_progress::DeliveryProgress;
+use crate::utils::errors::AppError;
+use crate::utils::idempotency::{IDEMPOTENCY_RESERVATION_TTL, IDEMPOTENCY_TTL};
+
+/// El progreso solo es útil durante la jornada de la tournée
+const PROGRESS_TTL: u64 = 48 * 3600;
+
+// Repository para guardar en Redis el progreso de entrega de una tournée
+pub struct DeliveryProgressRepository {
+    redis: RedisClient,
+}
+
+impl DeliveryProgressRepository {
+    pub fn new(redis: RedisClient) -> Self {
+        Self { redis }
+    }
+
+    pub async fn get(&self, societe: &str, matricule: &str, date: &str) -> Option<DeliveryProgress> {
+        let key = self.redis.progress_key(societe, matricule, date);
+        self.redis.get(&key).await.ok().flatten()
+    }
+
+    /// Progreso de todas las tournées de una société en una fecha
+    pub async fn list_for_date(&self, societe: &str, date: &str) -> Result<Vec<DeliveryProgress>, AppError> {
+        let keys = self
+            .redis
+            .scan_keys(&self.redis.progress_pattern(societe, date))
+            .await
+            .map_err(|e| AppError::Internal(format!("Error listando progreso de entrega: {}", e)))?;
+
+        let mut progresses = Vec::with_capacity(keys.len());
+        for key in keys {
+            if let Ok(Some(progress)) = self.redis.get::<DeliveryProgress>(&key).await {
+                progresses.push(progress);
+            }
+        }
+        Ok(progresses)
+    }
+
+    pub async fn save(&self, progress: &DeliveryProgress) -> Result<(), AppError> {
+        let key = self.redis.progress_key(&progress.societe, &progress.matricule, &progress.date_tournee);
+        self.redis
+            .set(&key, progress, PROGRESS_TTL)
+            .await
+            .map_err(|e| AppError::Internal(format!("Error guardando progreso de entrega: {}", e)))
+    }
+
+    /// Reservar la `Idempotency-Key` de una parada antes de registrarla (SET NX).
+    ///
+    /// La clave guarda `null` mientras la petición está en curso y la
+    /// respuesta cuando termina, así dos reintentos simultáneos no registran
+    /// la parada dos veces.
+    pub async fn reserve_stop(
+        &self,
+        societe: &str,
+        matricule: &str,
+        reference: &str,
+        idempotency_key: &str,
+    ) -> Result<StopIdempotency, AppError> {
+        let key = self.redis.stop_idempotency_key(societe, matricule, reference, idempotency_key);
+        let reserved = self
+            .redis
+            .set_nx(&key, &None::<StopProgressResponse>, IDEMPOTENCY_RESERVATION_TTL)
+            .await
+            .map_err(|e| AppError::Internal(format!("Error reservando Idempotency-Key: {}", e)))?;
+        if reserved {
+            return Ok(StopIdempotency::Reserved);
+        }
+
+        match self.redis.get::<Option<StopProgressResponse>>(&key).await.ok().flatten() {
+            Some(Some(response)) => Ok(StopIdempotency::Processed(response)),
+            // En curso, o la reserva expiró entre SET NX y GET: el cliente reintenta
+            _ => Ok(StopIdempotency::InProgress),
+        }
+    }
+
+    /// Guardar la respuesta de la parada bajo su `Idempotency-Key` (sustituye la reserva)
+    pub async fn save_processed_stop(
+        &self,
+        societe: &str,
+        matricule: &str,
+        reference: &str,
+        idempotency_key: &str,
+        response: &StopProgressResponse,
+    ) -> Result<(), AppError> {
+        let key = self.redis.stop_idempotency_key(societe, matricule, reference, idempotency_key);
+        self.redis
+            .set(&key, &Some(response), IDEMPOTENCY_TTL)
+            .await
+            .map_err(|e| AppError::Internal(format!("Error guardando Idempotency-Key: {}", e)))
+    }
+
+    /// Liberar la reserva si la parada no se pudo registrar, para que un reintento la procese
+    pub async fn release_stop(&self, societe: &str, matricule: &str, reference: &str, idempotency_key: &str) {
+        let key = self.redis.stop_idempotency_key(societe, matricule, reference, idempotency_key);
+        let _ = self.redis.delete(&key).await;
+    }
+}
+
+/// Estado de la `Idempotency-Key` de una parada
+pub enum StopIdempotency {
+    /// Clave nueva, reservada para esta petición
+    Reserved,
+    /// Otra petición con la misma clave se está procesando
+    InProgress,
+    /// Ya procesada: respuesta original
+    Processed(StopProgressResponse),
+}
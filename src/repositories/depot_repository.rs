@@ -0,0 +1,170 @@
+use crate::models::depot::Depot;
+use crate::utils::errors::AppError;
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::Utc;
+
+pub struct DepotRepository {
+    pool: PgPool,
+}
+
+impl DepotRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Crear un almacén; si es el de por defecto, los demás de la empresa dejan de serlo
+    pub async fn create(
+        &self,
+        company_id: Uuid,
+        name: String,
+        societe: Option<String>,
+        latitude: f64,
+        longitude: f64,
+        is_default: bool,
+    ) -> Result<Depot, AppError> {
+        let mut tx = self.pool.begin().await
+            .map_err(|e| AppError::DatabaseError(format!("Error creating depot: {}", e)))?;
+
+        if is_default {
+            Self::clear_default(&mut tx, company_id).await?;
+        }
+
+        let depot = sqlx::query_as::<_, Depot>(
+            r#"
+            INSERT INTO depots (id, company_id, name, societe, latitude, longitude, is_default, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(company_id)
+        .bind(name)
+        .bind(societe)
+        .bind(latitude)
+        .bind(longitude)
+        .bind(is_default)
+        .bind(Utc::now())
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error creating depot: {}", e)))?;
+
+        tx.commit().await
+            .map_err(|e| AppError::DatabaseError(format!("Error creating depot: {}", e)))?;
+
+        Ok(depot)
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Depot>, AppError> {
+        let depot = sqlx::query_as::<_, Depot>("SELECT * FROM depots WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Error finding depot: {}", e)))?;
+
+        Ok(depot)
+    }
+
+    pub async fn find_by_company(&self, company_id: Uuid) -> Result<Vec<Depot>, AppError> {
+        let depots = sqlx::query_as::<_, Depot>(
+            "SELECT * FROM depots WHERE company_id = $1 ORDER BY is_default DESC, created_at"
+        )
+        .bind(company_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error listing depots: {}", e)))?;
+
+        Ok(depots)
+    }
+
+    /// Almacén de salida de las tournées de una société: el de por defecto o, si
+    /// ninguno lo es, el más antiguo
+    pub async fn default_for_societe(&self, societe: &str) -> Result<Option<Depot>, AppError> {
+        let depot = sqlx::query_as::<_, Depot>(
+            "SELECT * FROM depots WHERE societe = $1 ORDER BY is_default DESC, created_at LIMIT 1"
+        )
+        .bind(societe.trim().to_uppercase())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error finding default depot: {}", e)))?;
+
+        Ok(depot)
+    }
+
+    pub async fn update(
+        &self,
+        id: Uuid,
+        company_id: Uuid,
+        name: Option<String>,
+        societe: Option<String>,
+        latitude: Option<f64>,
+        longitude: Option<f64>,
+        is_default: Option<bool>,
+    ) -> Result<Depot, AppError> {
+        let current = self.owned(id, company_id).await?;
+
+        let mut tx = self.pool.begin().await
+            .map_err(|e| AppError::DatabaseError(format!("Error updating depot: {}", e)))?;
+
+        if is_default == Some(true) && !current.is_default {
+            Self::clear_default(&mut tx, company_id).await?;
+        }
+
+        let depot = sqlx::query_as::<_, Depot>(
+            r#"
+            UPDATE depots
+            SET name = $2, societe = $3, latitude = $4, longitude = $5, is_default = $6
+            WHERE id = $1
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(name.unwrap_or(current.name))
+        .bind(societe.or(current.societe))
+        .bind(latitude.unwrap_or(current.latitude))
+        .bind(longitude.unwrap_or(current.longitude))
+        .bind(is_default.unwrap_or(current.is_default))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error updating depot: {}", e)))?;
+
+        tx.commit().await
+            .map_err(|e| AppError::DatabaseError(format!("Error updating depot: {}", e)))?;
+
+        Ok(depot)
+    }
+
+    pub async fn delete(&self, id: Uuid, company_id: Uuid) -> Result<(), AppError> {
+        self.owned(id, company_id).await?;
+
+        sqlx::query("DELETE FROM depots WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Error deleting depot: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Almacén existente y de la empresa
+    async fn owned(&self, id: Uuid, company_id: Uuid) -> Result<Depot, AppError> {
+        let depot = self.find_by_id(id).await?
+            .ok_or_else(|| AppError::NotFound("Depot not found".to_string()))?;
+
+        if depot.company_id != company_id {
+            return Err(AppError::Forbidden("Depot does not belong to this company".to_string()));
+        }
+
+        Ok(depot)
+    }
+
+    async fn clear_default(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, company_id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE depots SET is_default = FALSE WHERE company_id = $1 AND is_default")
+            .bind(company_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Error clearing default depot: {}", e)))?;
+
+        Ok(())
+    }
+}
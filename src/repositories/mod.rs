@@ -1,5 +1,14 @@
 pub mod company_repository;
 pub mod vehicle_repository;
+pub mod depot_repository;
 pub mod address_repository;
+pub mod address_validation_repository;
 pub mod colis_prive_repository;
-
+pub mod optimization_repository;
+pub mod delivery_progress_repository;
+pub mod package_label_repository;
+pub mod package_repository;
+pub mod package_status_repository;
+pub mod company_settings_repository;
+pub mod tournee_snapshot_repository;
+pub mod spatial_query;
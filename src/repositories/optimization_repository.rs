@@ -0,0 +1,53 @@
+use crate::cache::redis_client::RedisClient;
+use crate::models::optimization::StoredOptimization;
+use crate::utils::errors::AppError;
+
+/// Los resultados se conservan dos días (tournée del día + re-optimizaciones)
+const OPTIMIZATION_TTL: u64 = 48 * 3600;
+
+// Repository para guardar en Redis la última optimización y la anterior
+pub struct OptimizationRepository {
+    redis: RedisClient,
+}
+
+impl OptimizationRepository {
+    pub fn new(redis: RedisClient) -> Self {
+        Self { redis }
+    }
+
+    /// Guardar una optimización; la última guardada pasa a ser la anterior
+    pub async fn save(&self, optimization: &StoredOptimization) -> Result<(), AppError> {
+        let latest_key = self.redis.optimization_key(
+            &optimization.societe,
+            &optimization.matricule,
+            &optimization.date_tournee,
+        );
+        let previous_key = self.redis.previous_optimization_key(
+            &optimization.societe,
+            &optimization.matricule,
+            &optimization.date_tournee,
+        );
+
+        if let Ok(Some(current)) = self.redis.get::<StoredOptimization>(&latest_key).await {
+            self.redis
+                .set(&previous_key, &current, OPTIMIZATION_TTL)
+                .await
+                .map_err(|e| AppError::Internal(format!("Error guardando optimización previa: {}", e)))?;
+        }
+
+        self.redis
+            .set(&latest_key, optimization, OPTIMIZATION_TTL)
+            .await
+            .map_err(|e| AppError::Internal(format!("Error guardando optimización: {}", e)))
+    }
+
+    pub async fn latest(&self, societe: &str, matricule: &str, date: &str) -> Option<StoredOptimization> {
+        let key = self.redis.optimization_key(societe, matricule, date);
+        self.redis.get(&key).await.ok().flatten()
+    }
+
+    pub async fn previous(&self, societe: &str, matricule: &str, date: &str) -> Option<StoredOptimization> {
+        let key = self.redis.previous_optimization_key(societe, matricule, date);
+        self.redis.get(&key).await.ok().flatten()
+    }
+}
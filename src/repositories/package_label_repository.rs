@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+
+use sqlx::PgPool;
+
+use crate::models::package_label::PackageLabel;
+use crate::utils::errors::AppError;
+
+pub struct PackageLabelRepository {
+    pool: PgPool,
+}
+
+impl PackageLabelRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Añadir una etiqueta (idempotente)
+    pub async fn add(&self, reference_colis: &str, label: &str, created_by: Option<&str>) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO package_labels (reference_colis, label, created_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (reference_colis, label) DO NOTHING
+            "#
+        )
+        .bind(reference_colis)
+        .bind(label)
+        .bind(created_by)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error adding package label: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Quitar una etiqueta; devuelve `false` si el paquete no la tenía
+    pub async fn remove(&self, reference_colis: &str, label: &str) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM package_labels WHERE reference_colis = $1 AND label = $2")
+            .bind(reference_colis)
+            .bind(label)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Error removing package label: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn find_by_reference(&self, reference_colis: &str) -> Result<Vec<PackageLabel>, AppError> {
+        sqlx::query_as::<_, PackageLabel>(
+            "SELECT reference_colis, label, created_by, created_at FROM package_labels WHERE reference_colis = $1 ORDER BY label"
+        )
+        .bind(reference_colis)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error listing package labels: {}", e)))
+    }
+
+    /// Referencias de los paquetes que tienen la etiqueta
+    pub async fn references_with_label(&self, label: &str) -> Result<HashSet<String>, AppError> {
+        let references: Vec<(String,)> = sqlx::query_as("SELECT reference_colis FROM package_labels WHERE label = $1")
+            .bind(label)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Error filtering by package label: {}", e)))?;
+
+        Ok(references.into_iter().map(|(reference,)| reference).collect())
+    }
+}
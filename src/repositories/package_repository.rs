@@ -0,0 +1,55 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::csv_import_service::CsvPackageRow;
+use crate::utils::errors::AppError;
+
+pub struct PackageRepository {
+    pool: PgPool,
+}
+
+impl PackageRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Insertar las filas importadas en una sola transacción; devuelve, en el
+    /// mismo orden, si cada fila se insertó (`false` si la empresa ya tenía
+    /// ese tracking_number)
+    pub async fn insert_imported(&self, company_id: Uuid, rows: &[CsvPackageRow]) -> Result<Vec<bool>, AppError> {
+        let db_error = |e: sqlx::Error| AppError::DatabaseError(format!("Error importing packages: {}", e));
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        let mut inserted = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let (latitude, longitude) = row.coordinates.unzip();
+            let id: Option<(Uuid,)> = sqlx::query_as(
+                r#"
+                INSERT INTO packages
+                    (company_id, tracking_number, recipient, address, postal_code, city, phone, instructions, latitude, longitude)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (company_id, tracking_number) DO NOTHING
+                RETURNING id
+                "#
+            )
+            .bind(company_id)
+            .bind(&row.tracking_number)
+            .bind(&row.recipient)
+            .bind(&row.address)
+            .bind(&row.postal_code)
+            .bind(&row.city)
+            .bind(&row.phone)
+            .bind(&row.instructions)
+            .bind(latitude)
+            .bind(longitude)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db_error)?;
+
+            inserted.push(id.is_some());
+        }
+
+        tx.commit().await.map_err(db_error)?;
+        Ok(inserted)
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::models::package_status::StatusUpdate;
+use crate::services::status_webhook_service::{plan_updates, WebhookOutcome};
+use crate::utils::errors::AppError;
+
+pub struct PackageStatusRepository {
+    pool: PgPool,
+}
+
+impl PackageStatusRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Registrar los paquetes de una tournée (los ya conocidos no se tocan)
+    pub async fn register_known(&self, packages: &[(String, Option<String>)]) -> Result<(), AppError> {
+        if packages.is_empty() {
+            return Ok(());
+        }
+
+        let (references, statuses): (Vec<String>, Vec<Option<String>>) = packages.iter().cloned().unzip();
+
+        sqlx::query(
+            r#"
+            INSERT INTO package_statuses (reference_colis, status)
+            SELECT * FROM UNNEST($1::varchar[], $2::varchar[])
+            ON CONFLICT (reference_colis) DO NOTHING
+            "#
+        )
+        .bind(&references)
+        .bind(&statuses)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error registering package statuses: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Último estado conocido de los paquetes indicados (los desconocidos no aparecen)
+    pub async fn statuses(&self, references: &[String]) -> Result<HashMap<String, Option<String>>, AppError> {
+        let statuses = sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT reference_colis, status FROM package_statuses WHERE reference_colis = ANY($1)"
+        )
+        .bind(references)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error reading package statuses: {}", e)))?
+        .into_iter()
+        .collect();
+
+        Ok(statuses)
+    }
+
+    /// Aplicar un lote del webhook en una transacción (paquetes desconocidos ignorados)
+    pub async fn apply_batch(&self, updates: Vec<StatusUpdate>) -> Result<WebhookOutcome, AppError> {
+        let db_error = |e: sqlx::Error| AppError::DatabaseError(format!("Error applying status updates: {}", e));
+
+        let references: Vec<String> = updates.iter().map(|u| u.tracking_number.clone()).collect();
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+
+        let known: HashMap<String, Option<DateTime<Utc>>> = sqlx::query_as::<_, (String, Option<DateTime<Utc>>)>(
+            "SELECT reference_colis, status_at FROM package_statuses WHERE reference_colis = ANY($1) FOR UPDATE"
+        )
+        .bind(&references)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .collect();
+
+        let (to_apply, outcome) = plan_updates(updates, &known);
+
+        for update in &to_apply {
+            sqlx::query(
+                r#"
+                UPDATE package_statuses
+                SET status = $2, status_at = $3, source = 'webhook', updated_at = NOW()
+                WHERE reference_colis = $1
+                "#
+            )
+            .bind(&update.tracking_number)
+            .bind(&update.status)
+            .bind(update.timestamp)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        }
+
+        tx.commit().await.map_err(db_error)?;
+
+        Ok(outcome)
+    }
+}
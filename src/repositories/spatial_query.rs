@@ -0,0 +1,285 @@
+//! Consultas espaciales paginadas (PostGIS)
+//!
+//! Punto común para los endpoints espaciales (cerca de un punto, dentro de
+//! un rectángulo...): filtra por zona, anota cada fila con su distancia en
+//! metros al centro y pagina ordenando por esa distancia.
+//!
+//! El filtro se hace en dos pasos: `&&` contra un rectángulo en grados usa el
+//! índice GIST de la columna geométrica, y `ST_DWithin` sobre geography
+//! descarta las esquinas con la distancia exacta.
+
+use serde::Serialize;
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+
+use crate::utils::errors::AppError;
+
+/// Tamaño máximo de página
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// Metros por grado de latitud (aproximación esférica)
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Zona de búsqueda
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpatialArea {
+    /// Círculo alrededor de un punto
+    Radius { latitude: f64, longitude: f64, radius_m: f64 },
+    /// Rectángulo lat/lon; las distancias se miden desde su centro
+    Bounds { min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64 },
+}
+
+impl SpatialArea {
+    /// Punto `(lat, lon)` desde el que se miden las distancias
+    pub fn center(&self) -> (f64, f64) {
+        match *self {
+            SpatialArea::Radius { latitude, longitude, .. } => (latitude, longitude),
+            SpatialArea::Bounds { min_lat, min_lon, max_lat, max_lon } => {
+                ((min_lat + max_lat) / 2.0, (min_lon + max_lon) / 2.0)
+            }
+        }
+    }
+
+    /// Rectángulo `(min_lat, min_lon, max_lat, max_lon)` que contiene la zona
+    pub fn envelope(&self) -> (f64, f64, f64, f64) {
+        match *self {
+            SpatialArea::Radius { latitude, longitude, radius_m } => {
+                let lat_delta = radius_m / METERS_PER_DEGREE;
+                let lon_delta = radius_m / (METERS_PER_DEGREE * latitude.to_radians().cos().max(0.01));
+                (latitude - lat_delta, longitude - lon_delta, latitude + lat_delta, longitude + lon_delta)
+            }
+            SpatialArea::Bounds { min_lat, min_lon, max_lat, max_lon } => (min_lat, min_lon, max_lat, max_lon),
+        }
+    }
+}
+
+/// Página pedida (empieza en 1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self { page: 1, per_page: 20 }
+    }
+}
+
+impl PageRequest {
+    /// Página normalizada: como mínimo la 1, entre 1 y `MAX_PAGE_SIZE` filas
+    pub fn new(page: Option<u32>, per_page: Option<u32>) -> Self {
+        let default = Self::default();
+        Self {
+            page: page.unwrap_or(default.page).max(1),
+            per_page: per_page.unwrap_or(default.per_page).clamp(1, MAX_PAGE_SIZE),
+        }
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page as i64 - 1) * self.per_page as i64
+    }
+}
+
+/// Fila con su distancia al centro de la zona
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WithDistance<T> {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub item: T,
+    pub distance_m: f64,
+}
+
+/// Página de resultados ordenados por distancia
+#[derive(Debug, Clone, Serialize)]
+pub struct SpatialPage<T> {
+    pub items: Vec<WithDistance<T>>,
+    pub page: u32,
+    pub per_page: u32,
+    pub has_more: bool,
+}
+
+impl<T> SpatialPage<T> {
+    /// Se piden `per_page + 1` filas: la sobrante solo indica que hay más
+    pub fn from_rows(mut rows: Vec<WithDistance<T>>, page: PageRequest) -> Self {
+        let has_more = rows.len() > page.per_page as usize;
+        rows.truncate(page.per_page as usize);
+        Self { items: rows, page: page.page, per_page: page.per_page, has_more }
+    }
+}
+
+/// Consulta espacial sobre una tabla con columna geométrica en SRID 4326
+#[derive(Debug, Clone)]
+pub struct SpatialQuery<'a> {
+    pub table: &'a str,
+    /// Lista de columnas del SELECT, sin la distancia
+    pub columns: &'a str,
+    pub geometry_column: &'a str,
+    pub area: SpatialArea,
+    pub page: PageRequest,
+}
+
+impl SpatialQuery<'_> {
+    fn push_center(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        let (latitude, longitude) = self.area.center();
+        builder.push("ST_SetSRID(ST_MakePoint(");
+        builder.push_bind(longitude);
+        builder.push(", ");
+        builder.push_bind(latitude);
+        builder.push("), 4326)::geography");
+    }
+
+    pub fn build(&self) -> QueryBuilder<'static, Postgres> {
+        let geometry = self.geometry_column;
+        let (min_lat, min_lon, max_lat, max_lon) = self.area.envelope();
+
+        let mut builder = QueryBuilder::new(format!("SELECT {}, ST_Distance({}::geography, ", self.columns, geometry));
+        self.push_center(&mut builder);
+        builder.push(format!(") AS distance_m FROM {} WHERE {} && ST_MakeEnvelope(", self.table, geometry));
+        builder.push_bind(min_lon);
+        builder.push(", ");
+        builder.push_bind(min_lat);
+        builder.push(", ");
+        builder.push_bind(max_lon);
+        builder.push(", ");
+        builder.push_bind(max_lat);
+        builder.push(", 4326)");
+
+        if let SpatialArea::Radius { radius_m, .. } = self.area {
+            builder.push(format!(" AND ST_DWithin({}::geography, ", geometry));
+            self.push_center(&mut builder);
+            builder.push(", ");
+            builder.push_bind(radius_m);
+            builder.push(")");
+        }
+
+        builder.push(" ORDER BY distance_m LIMIT ");
+        builder.push_bind(self.page.per_page as i64 + 1);
+        builder.push(" OFFSET ");
+        builder.push_bind(self.page.offset());
+        builder
+    }
+
+    pub async fn fetch<T>(&self, pool: &PgPool) -> Result<SpatialPage<T>, AppError>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let rows = self
+            .build()
+            .build_query_as::<WithDistance<T>>()
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Error en consulta espacial sobre {}: {}", self.table, e)))?;
+
+        Ok(SpatialPage::from_rows(rows, self.page))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(area: SpatialArea, page: PageRequest) -> SpatialQuery<'static> {
+        SpatialQuery { table: "addresses", columns: "id, official_label", geometry_column: "coordinates", area, page }
+    }
+
+    #[test]
+    fn test_radius_query_filters_by_index_and_orders_by_distance() {
+        let area = SpatialArea::Radius { latitude: 48.8566, longitude: 2.3522, radius_m: 500.0 };
+        let builder = query(area, PageRequest::new(Some(3), Some(20))).build();
+        let sql = builder.sql().to_string();
+
+        assert_eq!(
+            sql,
+            "SELECT id, official_label, ST_Distance(coordinates::geography, ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography) AS distance_m \
+             FROM addresses WHERE coordinates && ST_MakeEnvelope($3, $4, $5, $6, 4326) \
+             AND ST_DWithin(coordinates::geography, ST_SetSRID(ST_MakePoint($7, $8), 4326)::geography, $9) \
+             ORDER BY distance_m LIMIT $10 OFFSET $11"
+        );
+        assert_eq!(PageRequest::new(Some(3), Some(20)).offset(), 40);
+
+        let (min_lat, min_lon, max_lat, max_lon) = area.envelope();
+        assert!((max_lat - min_lat - 2.0 * 500.0 / METERS_PER_DEGREE).abs() < 1e-9);
+        assert!(max_lon - min_lon > max_lat - min_lat);
+    }
+
+    #[test]
+    fn test_bounds_query_measures_from_center() {
+        let area = SpatialArea::Bounds { min_lat: 48.8, min_lon: 2.2, max_lat: 48.9, max_lon: 2.4 };
+        let builder = query(area, PageRequest::default()).build();
+
+        assert!(!builder.sql().contains("ST_DWithin"));
+        let (latitude, longitude) = area.center();
+        assert!((latitude - 48.85).abs() < 1e-9 && (longitude - 2.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_page_keeps_distance_order_and_detects_more() {
+        let page = PageRequest::new(Some(0), Some(2));
+        assert_eq!(page, PageRequest { page: 1, per_page: 2 });
+        assert_eq!(PageRequest::new(None, Some(10_000)).per_page, MAX_PAGE_SIZE);
+
+        let rows = vec![
+            WithDistance { item: "CP1", distance_m: 12.5 },
+            WithDistance { item: "CP2", distance_m: 80.0 },
+            WithDistance { item: "CP3", distance_m: 140.0 },
+        ];
+        let result = SpatialPage::from_rows(rows, page);
+
+        assert!(result.has_more);
+        assert_eq!(result.items.iter().map(|r| r.item).collect::<Vec<_>>(), vec!["CP1", "CP2"]);
+        assert_eq!(result.items[1].distance_m, 80.0);
+
+        let last = SpatialPage::from_rows(vec![WithDistance { item: "CP3", distance_m: 140.0 }], PageRequest { page: 2, per_page: 2 });
+        assert!(!last.has_more);
+    }
+
+    #[derive(Debug, FromRow)]
+    struct Stop {
+        reference: String,
+    }
+
+    /// Contra PostGIS real: `DATABASE_URL=... cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn test_postgis_returns_ordered_pages_with_distances() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL");
+        // Una sola conexión: la tabla temporal solo existe en ella
+        let pool = sqlx::postgres::PgPoolOptions::new().max_connections(1).connect(&url).await.unwrap();
+        sqlx::query("CREATE TEMP TABLE spatial_stops (reference TEXT, coordinates geometry(Point, 4326))")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Paradas al este del centro, insertadas desordenadas
+        let (latitude, longitude): (f64, f64) = (48.8566, 2.3522);
+        let meters_per_lon_degree = METERS_PER_DEGREE * latitude.to_radians().cos();
+        for (reference, meters) in [("CP600", 600.0), ("CP100", 100.0), ("CP2000", 2000.0), ("CP300", 300.0)] {
+            sqlx::query("INSERT INTO spatial_stops VALUES ($1, ST_SetSRID(ST_MakePoint($2, $3), 4326))")
+                .bind(reference)
+                .bind(longitude + meters / meters_per_lon_degree)
+                .bind(latitude)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let query = |page| SpatialQuery {
+            table: "spatial_stops",
+            columns: "reference",
+            geometry_column: "coordinates",
+            area: SpatialArea::Radius { latitude, longitude, radius_m: 1000.0 },
+            page,
+        };
+
+        let first: SpatialPage<Stop> = query(PageRequest::new(Some(1), Some(2))).fetch(&pool).await.unwrap();
+        assert!(first.has_more);
+        assert_eq!(first.items.iter().map(|r| r.item.reference.as_str()).collect::<Vec<_>>(), vec!["CP100", "CP300"]);
+        assert!((first.items[0].distance_m - 100.0).abs() < 2.0);
+        assert!((first.items[1].distance_m - 300.0).abs() < 5.0);
+
+        let second: SpatialPage<Stop> = query(PageRequest::new(Some(2), Some(2))).fetch(&pool).await.unwrap();
+        assert!(!second.has_more);
+        assert_eq!(second.items.iter().map(|r| r.item.reference.as_str()).collect::<Vec<_>>(), vec!["CP600"]);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::models::tournee_snapshot::{PackageState, TourneeSnapshot};
+use crate::utils::errors::AppError;
+use chrono::{NaiveDate, Utc};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct TourneeSnapshotRepository {
+    pool: PgPool,
+}
+
+impl TourneeSnapshotRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        societe: &str,
+        matricule: &str,
+        date: NaiveDate,
+        packages: Vec<PackageState>,
+    ) -> Result<TourneeSnapshot, AppError> {
+        let snapshot = sqlx::query_as::<_, TourneeSnapshot>(
+            r#"
+            INSERT INTO tournee_snapshots (id, societe, matricule, date_tournee, taken_at, packages)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(societe)
+        .bind(matricule)
+        .bind(date)
+        .bind(Utc::now())
+        .bind(Json(packages))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error creating tournee snapshot: {}", e)))?;
+
+        Ok(snapshot)
+    }
+
+    /// Fotos de una tournée en orden cronológico
+    pub async fn list(&self, societe: &str, matricule: &str, date: NaiveDate) -> Result<Vec<TourneeSnapshot>, AppError> {
+        let snapshots = sqlx::query_as::<_, TourneeSnapshot>(
+            r#"
+            SELECT * FROM tournee_snapshots
+            WHERE societe = $1 AND matricule = $2 AND date_tournee = $3
+            ORDER BY taken_at
+            "#
+        )
+        .bind(societe)
+        .bind(matricule)
+        .bind(date)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Error listing tournee snapshots: {}", e)))?;
+
+        Ok(snapshots)
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<TourneeSnapshot>, AppError> {
+        let snapshot = sqlx::query_as::<_, TourneeSnapshot>("SELECT * FROM tournee_snapshots WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Error finding tournee snapshot: {}", e)))?;
+
+        Ok(snapshot)
+    }
+}
@@ -4,10 +4,16 @@ use axum::{
     Json, Router,
 };
 use crate::controllers::address_controller::AddressController;
-use crate::dto::address_dto::{SaveAddressRequest, AddressResponse, SearchAddressRequest};
+use crate::dto::address_dto::{SaveAddressRequest, AddressResponse, SearchAddressRequest, GeocodeCandidatesRequest, CleanPreviewRequest, PinCoordinatesRequest, PendingValidationsQuery, GeocacheEvictionQuery, GeocacheEvictionResponse, NearbyAddressesQuery, AddressesInBoundsQuery};
 use crate::dto::company_dto::ApiResponse;
+use crate::models::address::Address;
+use crate::models::address_validation::AddressValidation;
+use crate::repositories::spatial_query::SpatialPage;
+use crate::services::address_cleaning_service::CleaningReport;
+use crate::services::geocoding_service::GeocodeCandidate;
 use crate::state::AppState;
 use crate::utils::errors::AppError;
+use crate::utils::app_json::AppJson;
 use uuid::Uuid;
 use serde::Deserialize;
 
@@ -15,16 +21,24 @@ pub fn create_address_router() -> Router<AppState> {
     Router::new()
         .route("/", post(save_address))
         .route("/search", get(search_addresses))
+        .route("/near", get(addresses_near))
+        .route("/in-bounds", get(addresses_in_bounds))
         .route("/geocode", post(geocode_address))
+        .route("/candidates", post(geocode_candidates))
+        .route("/clean-preview", post(clean_preview))
+        .route("/validations/pending", get(pending_validations))
+        .route("/geocache", delete(evict_geocache))
         .route("/:id", get(get_address))
         .route("/:id", put(update_address_details))
         .route("/:id", delete(delete_address))
+        // `:id` de una validación de dirección (ver /validations/pending)
+        .route("/:id/coordinates", put(pin_coordinates))
         .route("/route/:route_id", get(list_by_route))
 }
 
 async fn save_address(
     State(state): State<AppState>,
-    Json(request): Json<SaveAddressRequest>,
+    AppJson(request): AppJson<SaveAddressRequest>,
 ) -> Result<Json<ApiResponse<AddressResponse>>, AppError> {
     let controller = AddressController::new(state.pool.clone());
     let response = controller.save(request).await?;
@@ -34,7 +48,7 @@ async fn save_address(
 async fn get_address(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> Result<Json<AddressResponse>, AppError> {
+) -> Result<Json<Address>, AppError> {
     let controller = AddressController::new(state.pool.clone());
     let response = controller.get_by_id(id).await?;
     Ok(Json(response))
@@ -58,15 +72,79 @@ async fn search_addresses(
     Ok(Json(response))
 }
 
+async fn addresses_near(
+    State(state): State<AppState>,
+    Query(query): Query<NearbyAddressesQuery>,
+) -> Result<Json<SpatialPage<Address>>, AppError> {
+    let controller = AddressController::new(state.pool.clone());
+    let response = controller.near(query).await?;
+    Ok(Json(response))
+}
+
+async fn addresses_in_bounds(
+    State(state): State<AppState>,
+    Query(query): Query<AddressesInBoundsQuery>,
+) -> Result<Json<SpatialPage<Address>>, AppError> {
+    let controller = AddressController::new(state.pool.clone());
+    let response = controller.in_bounds(query).await?;
+    Ok(Json(response))
+}
+
 async fn geocode_address(
     State(state): State<AppState>,
-    Json(request): Json<GeocodeRequest>,
+    AppJson(request): AppJson<GeocodeRequest>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
     let controller = AddressController::new(state.pool.clone());
     let response = controller.geocode_address(request.address).await?;
     Ok(Json(response))
 }
 
+async fn geocode_candidates(
+    State(state): State<AppState>,
+    AppJson(request): AppJson<GeocodeCandidatesRequest>,
+) -> Result<Json<ApiResponse<Vec<GeocodeCandidate>>>, AppError> {
+    let controller = AddressController::new(state.pool.clone());
+    let response = controller.geocode_candidates(request).await?;
+    Ok(Json(response))
+}
+
+async fn clean_preview(
+    State(state): State<AppState>,
+    AppJson(request): AppJson<CleanPreviewRequest>,
+) -> Result<Json<ApiResponse<CleaningReport>>, AppError> {
+    let controller = AddressController::new(state.pool.clone());
+    let response = controller.clean_preview(request)?;
+    Ok(Json(response))
+}
+
+async fn pending_validations(
+    State(state): State<AppState>,
+    Query(query): Query<PendingValidationsQuery>,
+) -> Result<Json<Vec<AddressValidation>>, AppError> {
+    let controller = AddressController::new(state.pool.clone());
+    let response = controller.pending_validations(query.limit).await?;
+    Ok(Json(response))
+}
+
+async fn evict_geocache(
+    State(state): State<AppState>,
+    Query(query): Query<GeocacheEvictionQuery>,
+) -> Result<Json<ApiResponse<GeocacheEvictionResponse>>, AppError> {
+    let controller = AddressController::new(state.pool.clone());
+    let response = controller.evict_geocache(query, &state.geocode_cache).await?;
+    Ok(Json(response))
+}
+
+async fn pin_coordinates(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<PinCoordinatesRequest>,
+) -> Result<Json<ApiResponse<AddressValidation>>, AppError> {
+    let controller = AddressController::new(state.pool.clone());
+    let response = controller.pin_coordinates(id, request, &state.config.coordinate_bounds).await?;
+    Ok(Json(response))
+}
+
 #[derive(Debug, Deserialize)]
 struct GeocodeRequest {
     address: String,
@@ -82,7 +160,7 @@ struct UpdateDetailsRequest {
 async fn update_address_details(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-    Json(request): Json<UpdateDetailsRequest>,
+    AppJson(request): AppJson<UpdateDetailsRequest>,
 ) -> Result<Json<ApiResponse<AddressResponse>>, AppError> {
     let controller = AddressController::new(state.pool.clone());
     let response = controller.update_details(
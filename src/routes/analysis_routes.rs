@@ -0,0 +1,49 @@
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use crate::controllers::analysis_controller::AnalysisController;
+use crate::dto::analysis_dto::{ReattemptQuery, ValidationTrendsQuery, ValidationTrendsResponse, WorklistFormat};
+use crate::services::export_service::CSV_CONTENT_TYPE;
+use crate::services::reattempt_service;
+use crate::state::AppState;
+use crate::utils::errors::AppError;
+
+pub fn create_analysis_router() -> Router<AppState> {
+    Router::new()
+        .route("/reattempts", get(get_reattempts))
+        .route("/validation-trends", get(get_validation_trends))
+}
+
+async fn get_reattempts(
+    State(state): State<AppState>,
+    Query(query): Query<ReattemptQuery>,
+) -> Result<Response, AppError> {
+    let response = AnalysisController::reattempt_worklist(&query, &state).await?;
+
+    match query.format {
+        WorklistFormat::Json => Ok(Json(response).into_response()),
+        WorklistFormat::Csv => {
+            let filename = format!("reattempts_{}_{}.csv", response.societe, response.date);
+            Ok((
+                [
+                    (header::CONTENT_TYPE, CSV_CONTENT_TYPE.to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+                ],
+                reattempt_service::to_csv(&response.groups),
+            )
+                .into_response())
+        }
+    }
+}
+
+async fn get_validation_trends(
+    State(state): State<AppState>,
+    Query(query): Query<ValidationTrendsQuery>,
+) -> Result<Json<ValidationTrendsResponse>, AppError> {
+    let response = AnalysisController::validation_trends(&query, &state).await?;
+    Ok(Json(response))
+}
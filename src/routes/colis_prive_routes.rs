@@ -1,57 +1,145 @@
 use axum::{
-    extract::State,
-    routing::{get, post},
+    extract::{Path, Query, State},
+    routing::{delete, get, post},
     Json, Router,
-    http::StatusCode,
+    body::Bytes,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
 use std::sync::Arc;
 use crate::controllers::colis_prive_controller::ColisPriveController;
+use crate::controllers::package_label_controller::PackageLabelController;
 use crate::dto::colis_prive_dto::*;
 use crate::state::AppState;
 use crate::utils::errors::AppError;
+use crate::utils::app_json::AppJson;
+use crate::utils::etag::json_with_etag;
+use crate::utils::idempotency::idempotency_key;
+use crate::utils::admin_key::require_admin_key;
 use crate::services::address_matching_service::AddressMatchingService;
 use crate::services::package_processing_service::PackageProcessingService;
+use crate::services::package_label_service::{filter_by_label, normalize_label};
+use crate::services::status_webhook_service::SIGNATURE_HEADER;
+use crate::services::gpx_export_service::{GPX_CONTENT_TYPE, SKIPPED_STOPS_HEADER};
+use crate::repositories::package_label_repository::PackageLabelRepository;
+use crate::dto::package_dto::GroupedPackagesResponse;
 use crate::models::package::GroupedPackages;
+use crate::models::delivery_progress::StopOutcome;
 use tracing::{info, error};
+use validator::Validate;
 
 pub fn create_colis_prive_routes() -> Router<AppState> {
     Router::new()
         .route("/auth", post(authenticate))
+        .route("/token", delete(logout))
         .route("/packages", post(get_packages))
+        .route("/packages/batch", post(get_packages_batch))
         .route("/optimize", post(optimize_route))
+        .route("/merge-tournees", post(merge_tournees))
+        .route("/optimization/:matricule/latest", get(get_latest_optimization))
+        .route("/optimization/:matricule/changes", get(get_order_changes))
+        .route("/optimization/:matricule/apply", post(apply_optimization))
+        .route("/packages/:reference/labels", get(get_package_labels).post(add_package_label))
+        .route("/packages/:reference/labels/:label", delete(remove_package_label))
+        .route("/packages/:reference/delivered", post(mark_delivered))
+        .route("/packages/:reference/failed", post(mark_failed))
+        .route("/eta/:matricule", get(get_eta))
+        .route("/route/:matricule/navlink", get(get_navigation_links))
+        .route("/quality/:matricule", get(get_geocoding_quality))
+        .route("/full/:matricule", get(get_full_tournee))
+        .route("/snapshots/:matricule", get(list_snapshots).post(take_snapshot))
+        .route("/snapshots/:matricule/diff", get(diff_snapshots))
+        .route("/export/:matricule", get(export_tournee))
+        .route("/export/:matricule/gpx", get(export_route_gpx))
         .route("/companies", get(get_companies))
+        .route("/societes", get(get_allowed_societes))
+        .route("/societes/refresh", post(refresh_allowed_societes))
+        .route("/societes/:societe/tokens", delete(revoke_societe_tokens))
+        .route("/webhook/status", post(status_webhook))
         .route("/health", get(health_check))
 }
 
 async fn authenticate(
     State(state): State<AppState>,
-    Json(request): Json<ColisPriveAuthRequest>,
-) -> Json<ColisPriveAuthResponse> {
+    AppJson(request): AppJson<ColisPriveAuthRequest>,
+) -> Result<Json<ColisPriveAuthResponse>, AppError> {
+    // Validar campos antes de llamar a Colis Privé (422 con errores por campo)
+    let request = request.trimmed();
+    request.validate()?;
+    state.societe_allowlist.check(&request.societe)?;
+    state.colis_prive_rate_limits.check_auth(&request.societe, &request.username)?;
+
     let controller = ColisPriveController::new(&state);
     match controller.authenticate(request).await {
-        Ok(response) => Json(response),
-        Err(e) => Json(ColisPriveAuthResponse {
+        Ok(response) => Ok(Json(response)),
+        Err(e) => Ok(Json(ColisPriveAuthResponse {
             success: false,
             message: None,
             authentication: None,
             error: Some(e.to_string()),
-        }),
+        })),
     }
 }
 
+async fn logout(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AppJson(request): AppJson<ColisPriveLogoutRequest>,
+) -> Result<Json<ColisPriveLogoutResponse>, AppError> {
+    require_admin_key(state.config.admin_api_key.as_deref(), &headers)?;
+    let request = request.trimmed();
+    request.validate()?;
+
+    let controller = ColisPriveController::new(&state);
+    Ok(Json(controller.logout(request).await))
+}
+
 async fn get_packages(
     State(state): State<AppState>,
-    Json(request): Json<GetPackagesRequest>,
-) -> Result<Json<GroupedPackages>, AppError> {
+    Query(filter): Query<PackagesFilterQuery>,
+    headers: HeaderMap,
+    AppJson(request): AppJson<GetPackagesRequest>,
+) -> Result<Response, AppError> {
+    let response = grouped_packages(&state, filter, request).await?;
+    json_with_etag(&headers, &response)
+}
+
+async fn get_packages_batch(
+    State(state): State<AppState>,
+    AppJson(request): AppJson<PackagesBatchRequest>,
+) -> Result<Json<PackagesBatchResponse>, AppError> {
+    // Una descarga de tournée por fecha pedida
+    state.colis_prive_rate_limits.check_tournee(&request.societe, &request.matricule, request.dates.len())?;
+    let controller = ColisPriveController::new(&state);
+    let response = controller.get_packages_batch(request, &state).await?;
+    Ok(Json(response))
+}
+
+async fn grouped_packages(
+    state: &AppState,
+    filter: PackagesFilterQuery,
+    request: GetPackagesRequest,
+) -> Result<GroupedPackagesResponse, AppError> {
     info!("📦 Solicitud de paquetes agrupados para: {}:{}", request.societe, request.matricule);
+    state.colis_prive_rate_limits.check_tournee(&request.societe, &request.matricule, 1)?;
     
     // 1. Obtener paquetes de Colis Privé usando el controller existente
-    let controller = ColisPriveController::new(&state);
-    let packages_response = controller.get_packages(request, &state).await?;
+    let controller = ColisPriveController::new(state);
+    let mut packages_response = controller.get_packages(request, state).await?;
+
+    // Filtrar por etiqueta si se pide (?label=)
+    if let Some(label) = filter.label.as_deref() {
+        let label = normalize_label(label)?;
+        let labelled = PackageLabelRepository::new(state.pool.clone())
+            .references_with_label(&label)
+            .await?;
+        packages_response.packages = filter_by_label(packages_response.packages, &labelled);
+        info!("🏷️ {} paquetes con etiqueta '{}'", packages_response.packages.len(), label);
+    }
     
     if packages_response.packages.is_empty() {
         info!("📭 No hay paquetes disponibles");
-        return Ok(Json(GroupedPackages::new()));
+        return Ok(GroupedPackages::new().into());
     }
     
     info!("📦 {} paquetes obtenidos de Colis Privé", packages_response.packages.len());
@@ -130,27 +218,266 @@ async fn get_packages(
         grouped_packages.groups.len(), 
         grouped_packages.total_packages);
     
-    Ok(Json(grouped_packages))
+    Ok(grouped_packages.into())
 }
 
 async fn optimize_route(
     State(state): State<AppState>,
-    Json(request): Json<OptimizeRouteRequest>,
+    Query(query): Query<OptimizeQuery>,
+    AppJson(request): AppJson<OptimizeRouteRequest>,
+) -> Result<Json<OptimizeRouteResponse>, AppError> {
+    state.colis_prive_rate_limits.check_tournee(&request.societe, &request.matricule, 1)?;
+    let controller = ColisPriveController::new(&state);
+    let response = controller.optimize_route(request, query, &state).await?;
+    Ok(Json(response))
+}
+
+async fn merge_tournees(
+    State(state): State<AppState>,
+    AppJson(request): AppJson<MergeTourneesRequest>,
+) -> Result<Json<MergeTourneesResponse>, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let response = controller.merge_tournees(request).await?;
+    Ok(Json(response))
+}
+
+async fn get_latest_optimization(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<OptimizationHistoryQuery>,
 ) -> Result<Json<OptimizeRouteResponse>, AppError> {
     let controller = ColisPriveController::new(&state);
-    let response = controller.optimize_route(request, &state).await?;
+    let response = controller.get_latest_optimization(&matricule, query, &state).await?;
     Ok(Json(response))
 }
 
-async fn get_companies() -> Result<Json<CompaniesListResponse>, AppError> {
+async fn get_order_changes(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<OptimizationHistoryQuery>,
+) -> Result<Json<OrderChangesResponse>, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let response = controller.get_order_changes(&matricule, query, &state).await?;
+    Ok(Json(response))
+}
+
+async fn apply_optimization(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    AppJson(request): AppJson<ApplyOptimizationRequest>,
+) -> Result<Json<ApplyOptimizationResponse>, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let response = controller.apply_optimization(&matricule, request, &state).await?;
+    Ok(Json(response))
+}
+
+async fn get_package_labels(
+    State(state): State<AppState>,
+    Path(reference): Path<String>,
+) -> Result<Json<PackageLabelsResponse>, AppError> {
+    let controller = PackageLabelController::new(state.pool.clone());
+    Ok(Json(controller.list(&reference).await?))
+}
+
+async fn add_package_label(
+    State(state): State<AppState>,
+    Path(reference): Path<String>,
+    AppJson(request): AppJson<AddPackageLabelRequest>,
+) -> Result<Json<PackageLabelsResponse>, AppError> {
+    let controller = PackageLabelController::new(state.pool.clone());
+    Ok(Json(controller.add(&reference, request).await?))
+}
+
+async fn remove_package_label(
+    State(state): State<AppState>,
+    Path((reference, label)): Path<(String, String)>,
+) -> Result<Json<PackageLabelsResponse>, AppError> {
+    let controller = PackageLabelController::new(state.pool.clone());
+    Ok(Json(controller.remove(&reference, &label).await?))
+}
+
+async fn mark_delivered(
+    State(state): State<AppState>,
+    Path(reference): Path<String>,
+    headers: HeaderMap,
+    AppJson(request): AppJson<StopProgressRequest>,
+) -> Result<Json<StopProgressResponse>, AppError> {
+    let idempotency_key = idempotency_key(&headers)?;
+    let controller = ColisPriveController::new(&state);
+    let response = controller
+        .record_stop(&reference, StopOutcome::Delivered, request, idempotency_key.as_deref(), &state)
+        .await?;
+    Ok(Json(response))
+}
+
+async fn mark_failed(
+    State(state): State<AppState>,
+    Path(reference): Path<String>,
+    headers: HeaderMap,
+    AppJson(request): AppJson<StopProgressRequest>,
+) -> Result<Json<StopProgressResponse>, AppError> {
+    let idempotency_key = idempotency_key(&headers)?;
+    let controller = ColisPriveController::new(&state);
+    let response = controller
+        .record_stop(&reference, StopOutcome::Failed, request, idempotency_key.as_deref(), &state)
+        .await?;
+    Ok(Json(response))
+}
+
+async fn get_eta(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<EtaQuery>,
+) -> Result<Json<EtaResponse>, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let response = controller.estimate_eta(&matricule, query, &state).await?;
+    Ok(Json(response))
+}
+
+async fn get_navigation_links(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<NavLinkQuery>,
+) -> Result<Json<NavLinkResponse>, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let response = controller.navigation_links(&matricule, query, &state).await?;
+    Ok(Json(response))
+}
+
+async fn get_full_tournee(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<FullTourneeQuery>,
+) -> Result<Json<FullTourneeResponse>, AppError> {
+    state.colis_prive_rate_limits.check_tournee(&query.societe, &matricule, 1)?;
+    let controller = ColisPriveController::new(&state);
+    let response = controller.get_full_tournee(&matricule, query, &state).await?;
+    Ok(Json(response))
+}
+
+async fn take_snapshot(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    AppJson(request): AppJson<TourneeSnapshotRequest>,
+) -> Result<Json<TourneeSnapshotResponse>, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let response = controller.take_snapshot(&matricule, request, &state).await?;
+    Ok(Json(response))
+}
+
+async fn list_snapshots(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<OptimizationHistoryQuery>,
+) -> Result<Json<TourneeSnapshotsResponse>, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let response = controller.list_snapshots(&matricule, query, &state).await?;
+    Ok(Json(response))
+}
+
+async fn diff_snapshots(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<SnapshotDiffQuery>,
+) -> Result<Json<SnapshotDiffResponse>, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let response = controller.diff_snapshots(&matricule, query, &state).await?;
+    Ok(Json(response))
+}
+
+async fn get_geocoding_quality(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<QualityQuery>,
+) -> Result<Json<GeocodingQualityResponse>, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let response = controller.get_geocoding_quality(&matricule, query, &state).await?;
+    Ok(Json(response))
+}
+
+async fn export_tournee(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let (body, content_type, filename) = controller.export_tournee(&matricule, query, &state).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    ))
+}
+
+async fn export_route_gpx(
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<OptimizationHistoryQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let controller = ColisPriveController::new(&state);
+    let (route, filename) = controller.export_route_gpx(&matricule, query, &state).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, GPX_CONTENT_TYPE.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+            (header::HeaderName::from_static(SKIPPED_STOPS_HEADER), route.skipped.to_string()),
+        ],
+        route.document,
+    ))
+}
+
+async fn get_companies(headers: HeaderMap) -> Result<Response, AppError> {
     let response = ColisPriveController::get_companies().await?;
+    json_with_etag(&headers, &response)
+}
+
+async fn get_allowed_societes(State(state): State<AppState>) -> Json<AllowedSocietesResponse> {
+    Json(AllowedSocietesResponse {
+        success: true,
+        enabled: state.societe_allowlist.is_enabled(),
+        societes: state.societe_allowlist.allowed(),
+    })
+}
+
+async fn refresh_allowed_societes(
+    State(state): State<AppState>,
+) -> Result<Json<AllowedSocietesResponse>, AppError> {
+    let response = ColisPriveController::refresh_allowed_societes(&state).await?;
+    Ok(Json(response))
+}
+
+async fn revoke_societe_tokens(
+    State(state): State<AppState>,
+    Path(societe): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<SocieteTokensRevokedResponse>, AppError> {
+    require_admin_key(state.config.admin_api_key.as_deref(), &headers)?;
+    let controller = ColisPriveController::new(&state);
+    let response = controller.revoke_societe_tokens(&societe).await?;
+    Ok(Json(response))
+}
+
+/// Webhook de estados: la firma se calcula sobre el cuerpo en crudo
+async fn status_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<StatusWebhookResponse>, AppError> {
+    let signature = headers.get(SIGNATURE_HEADER).and_then(|v| v.to_str().ok());
+    let response = ColisPriveController::receive_status_webhook(&state, signature, &body).await?;
     Ok(Json(response))
 }
 
-async fn health_check() -> Json<serde_json::Value> {
+async fn health_check(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let ssl_bypass_hosts = state.colis_prive_clients.policy().bypass_hosts();
     Json(serde_json::json!({
         "status": "ok",
         "service": "colis-prive",
+        "ssl_bypass_enabled": !ssl_bypass_hosts.is_empty(),
+        "ssl_bypass_hosts": ssl_bypass_hosts,
+        "geocoding_cache": state.geocode_cache.stats(),
         "timestamp": chrono::Utc::now().to_rfc3339()
     }))
 }
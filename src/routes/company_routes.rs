@@ -8,6 +8,7 @@ use crate::dto::company_dto::{RegisterCompanyRequest, CompanyResponse, ApiRespon
 use crate::dto::auth_dto::{LoginRequest, LoginResponse};
 use crate::state::AppState;
 use crate::utils::errors::AppError;
+use crate::utils::app_json::AppJson;
 
 pub fn create_company_router() -> Router<AppState> {
     Router::new()
@@ -18,7 +19,7 @@ pub fn create_company_router() -> Router<AppState> {
 
 async fn register(
     State(state): State<AppState>,
-    Json(request): Json<RegisterCompanyRequest>,
+    AppJson(request): AppJson<RegisterCompanyRequest>,
 ) -> Result<Json<ApiResponse<CompanyResponse>>, AppError> {
     let controller = CompanyController::new(state.pool.clone());
     let response = controller.register(request).await?;
@@ -27,7 +28,7 @@ async fn register(
 
 async fn login(
     State(state): State<AppState>,
-    Json(request): Json<LoginRequest>,
+    AppJson(request): AppJson<LoginRequest>,
 ) -> Result<Json<LoginResponse>, AppError> {
     let controller = CompanyController::new(state.pool.clone());
     let response = controller.login(request).await?;
@@ -0,0 +1,80 @@
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use crate::controllers::depot_controller::DepotController;
+use crate::dto::depot_dto::{CreateDepotRequest, DepotResponse, UpdateDepotRequest};
+use crate::dto::company_dto::ApiResponse;
+use crate::state::AppState;
+use crate::utils::errors::AppError;
+use crate::utils::app_json::AppJson;
+use uuid::Uuid;
+
+pub fn create_depot_router() -> Router<AppState> {
+    Router::new()
+        .route("/", post(create_depot))
+        .route("/", get(list_depots))
+        .route("/:id", get(get_depot))
+        .route("/:id", put(update_depot))
+        .route("/:id", delete(delete_depot))
+}
+
+// TODO: Extraer company_id del JWT token cuando implementemos middleware de auth
+// Por ahora usamos el mismo company_id de ejemplo que los vehículos
+async fn get_company_id_from_jwt() -> Uuid {
+    // Placeholder - en producción esto vendría del JWT
+    Uuid::parse_str("00000000-0000-0000-0000-000000000000").unwrap()
+}
+
+fn controller(state: &AppState) -> DepotController {
+    DepotController::new(state.pool.clone(), state.config.coordinate_bounds.clone())
+}
+
+async fn create_depot(
+    State(state): State<AppState>,
+    AppJson(request): AppJson<CreateDepotRequest>,
+) -> Result<Json<ApiResponse<DepotResponse>>, AppError> {
+    let company_id = get_company_id_from_jwt().await; // TODO: Extraer del JWT
+    let response = controller(&state).create(company_id, request).await?;
+    Ok(Json(response))
+}
+
+async fn get_depot(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DepotResponse>, AppError> {
+    let company_id = get_company_id_from_jwt().await; // TODO: Extraer del JWT
+    let response = controller(&state).get_by_id(id, company_id).await?;
+    Ok(Json(response))
+}
+
+async fn list_depots(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<DepotResponse>>, AppError> {
+    let company_id = get_company_id_from_jwt().await; // TODO: Extraer del JWT
+    let response = controller(&state).list_by_company(company_id).await?;
+    Ok(Json(response))
+}
+
+async fn update_depot(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    AppJson(request): AppJson<UpdateDepotRequest>,
+) -> Result<Json<ApiResponse<DepotResponse>>, AppError> {
+    let company_id = get_company_id_from_jwt().await; // TODO: Extraer del JWT
+    let response = controller(&state).update(id, company_id, request).await?;
+    Ok(Json(response))
+}
+
+async fn delete_depot(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let company_id = get_company_id_from_jwt().await; // TODO: Extraer del JWT
+    controller(&state).delete(id, company_id).await?;
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Almacén eliminado exitosamente"
+    })))
+}
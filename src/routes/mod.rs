@@ -1,7 +1,11 @@
 pub mod company_routes;
 pub mod vehicle_routes;
+pub mod depot_routes;
 pub mod address_routes;
 pub mod colis_prive_routes;
 pub mod package_routes;
+pub mod analysis_routes;
+pub mod root_routes;
+pub mod tournee_routes;
 // pub mod mapbox_optimization_routes; // Deshabilitado hasta tener acceso a Mapbox v2 Beta
 
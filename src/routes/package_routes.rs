@@ -1,7 +1,8 @@
 use axum::{
-    extract::{State, Path},
-    http::StatusCode,
-    response::Json,
+    body::Body,
+    extract::{Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
     routing::{get, put, post},
     Router,
 };
@@ -9,19 +10,28 @@ use std::sync::Arc;
 use serde::Deserialize;
 use crate::services::package_processing_service::PackageProcessingService;
 use crate::services::address_matching_service::AddressMatchingService;
+use crate::services::csv_import_service::import_csv;
+use crate::services::export_service::{package_csv_stream, CSV_CONTENT_TYPE};
+use crate::services::package_label_service::{filter_by_label, normalize_label};
+use crate::repositories::package_label_repository::PackageLabelRepository;
+use crate::repositories::package_repository::PackageRepository;
+use crate::services::geocoding_service::GeocodingService;
 use crate::controllers::colis_prive_controller::ColisPriveController;
 use crate::dto::colis_prive_dto::GetPackagesRequest;
+use crate::dto::package_dto::{GroupedPackagesResponse, PackageImportResponse, PackagesExportQuery};
+use crate::models::address::AddressAccess;
 use crate::models::package::GroupedPackages;
 use crate::state::AppState;
 use crate::utils::errors::AppError;
+use crate::utils::app_json::AppJson;
 use tracing::{info, error};
 use uuid::Uuid;
 
 /// Obtiene paquetes agrupados de Colis Privé
 pub async fn get_grouped_packages(
     State(app_state): State<AppState>,
-    Json(request): Json<GetPackagesRequest>,
-) -> Result<Json<GroupedPackages>, (StatusCode, Json<serde_json::Value>)> {
+    AppJson(request): AppJson<GetPackagesRequest>,
+) -> Result<Json<GroupedPackagesResponse>, (StatusCode, Json<serde_json::Value>)> {
     info!("📦 Solicitud de paquetes agrupados recibida para: {}:{}", 
         request.societe, request.matricule);
     
@@ -42,7 +52,7 @@ pub async fn get_grouped_packages(
     // Por ahora, si no hay paquetes, retornar vacío
     if packages_response.packages.is_empty() {
         info!("📭 No hay paquetes disponibles");
-        return Ok(Json(GroupedPackages::new()));
+        return Ok(Json(GroupedPackages::new().into()));
     }
     
     info!("📦 {} paquetes obtenidos de Colis Privé", packages_response.packages.len());
@@ -125,7 +135,102 @@ pub async fn get_grouped_packages(
         grouped_packages.groups.len(), 
         grouped_packages.total_packages);
     
-    Ok(Json(grouped_packages))
+    Ok(Json(grouped_packages.into()))
+}
+
+/// Contenido del fichero subido en el campo `file` de un formulario multipart
+async fn read_csv_upload(mut multipart: Multipart) -> Result<String, AppError> {
+    let invalid = |e: axum::extract::multipart::MultipartError| {
+        AppError::BadRequest(format!("Formulario multipart inválido: {}", e))
+    };
+
+    while let Some(field) = multipart.next_field().await.map_err(invalid)? {
+        if field.name() == Some("file") {
+            return field.text().await.map_err(invalid);
+        }
+    }
+
+    Err(AppError::ValidationError("Falta el campo 'file' con el CSV".to_string()))
+}
+
+// TODO: Extraer company_id del JWT token cuando implementemos middleware de auth
+// Por ahora usamos un company_id hardcoded de ejemplo
+async fn get_company_id_from_jwt() -> Uuid {
+    // Placeholder - en producción esto vendría del JWT
+    Uuid::parse_str("00000000-0000-0000-0000-000000000000").unwrap()
+}
+
+/// Alta masiva de paquetes de la empresa desde un CSV (`multipart/form-data`,
+/// campo `file`): las filas sin coordenadas se geocodifican y las válidas se
+/// insertan en una sola transacción; las rechazadas (validación, geocoding o
+/// tracking_number ya existente) se informan sin deshacer las demás
+pub async fn import_packages(
+    State(app_state): State<AppState>,
+    multipart: Multipart,
+) -> Result<Json<PackageImportResponse>, AppError> {
+    let company_id = get_company_id_from_jwt().await; // TODO: Extraer del JWT
+    let body = read_csv_upload(multipart).await?;
+    let geocoding_service = app_state.config.mapbox_token.clone().map(|token| {
+        GeocodingService::new(token)
+            .with_locale(app_state.config.geocoding_locale.clone())
+            .with_cache(app_state.geocode_cache.clone())
+    });
+
+    let results = import_csv(
+        &body,
+        &app_state.config.coordinate_bounds,
+        app_state.config.csv_import_max_rows,
+        geocoding_service.as_ref(),
+        &PackageRepository::new(app_state.pool.clone()),
+        company_id,
+    )
+    .await?;
+
+    let imported = results.iter().filter(|result| result.imported).count();
+    let failed = results.len() - imported;
+    info!("✅ Importación CSV para {}: {} paquetes importados, {} filas con errores", company_id, imported, failed);
+
+    Ok(Json(PackageImportResponse {
+        success: true,
+        imported,
+        failed,
+        rows: results,
+    }))
+}
+
+/// Exporta los paquetes de la tournée como CSV para hojas de cálculo
+/// (mismos filtros que la lista de paquetes: société, matricule, fecha y ?label=)
+pub async fn export_packages_csv(
+    State(app_state): State<AppState>,
+    Query(query): Query<PackagesExportQuery>,
+) -> Result<Response, AppError> {
+    let controller = ColisPriveController::new(&app_state);
+    let date = query.date.clone().unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%d").to_string());
+    let request = GetPackagesRequest {
+        matricule: query.matricule.clone(),
+        societe: query.societe.clone(),
+        date: query.date.clone(),
+    };
+    let mut packages = controller.get_packages(request, &app_state).await?.packages;
+
+    if let Some(label) = query.label.as_deref() {
+        let label = normalize_label(label)?;
+        let labelled = PackageLabelRepository::new(app_state.pool.clone())
+            .references_with_label(&label)
+            .await?;
+        packages = filter_by_label(packages, &labelled);
+    }
+    info!("📤 Exportando {} paquetes de {}:{} a CSV", packages.len(), query.societe, query.matricule);
+
+    let filename = format!("packages_{}_{}_{}.csv", query.societe, query.matricule, date);
+    Ok((
+        [
+            (header::CONTENT_TYPE, CSV_CONTENT_TYPE.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(package_csv_stream(packages)),
+    )
+        .into_response())
 }
 
 /// Obtiene estadísticas de procesamiento
@@ -169,7 +274,7 @@ pub async fn get_processing_stats(
 pub async fn update_address_driver_data(
     Path(address_id): Path<Uuid>,
     State(app_state): State<AppState>,
-    Json(update_data): Json<UpdateDriverDataRequest>,
+    AppJson(update_data): AppJson<UpdateDriverDataRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     info!("🔄 Actualizando datos del chofer para dirección: {}", address_id);
     
@@ -184,10 +289,16 @@ pub async fn update_address_driver_data(
         }
     };
     
+    let access = AddressAccess {
+        door_code: update_data.door_code,
+        has_mailbox_access: update_data.has_mailbox_access,
+        floor: update_data.floor,
+        concierge_notes: update_data.concierge_notes,
+    };
+    
     match address_matcher.update_driver_data(
         address_id,
-        update_data.door_code,
-        update_data.has_mailbox_access,
+        access,
         update_data.driver_notes,
         update_data.updated_by,
     ).await {
@@ -210,6 +321,10 @@ pub fn package_routes() -> Router<AppState> {
     Router::new()
         .route("/packages/grouped", post(get_grouped_packages))
         .route("/packages/stats", get(get_processing_stats))
+        .route("/packages/import", post(import_packages))
+        // Alias de /packages/import
+        .route("/packages/import/csv", post(import_packages))
+        .route("/packages/export.csv", get(export_packages_csv))
         .route("/addresses/:address_id/driver-data", put(update_address_driver_data))
 }
 
@@ -218,5 +333,9 @@ pub struct UpdateDriverDataRequest {
     pub door_code: Option<String>,
     pub has_mailbox_access: bool,
     pub driver_notes: Option<String>,
+    #[serde(default)]
+    pub floor: Option<String>,
+    #[serde(default)]
+    pub concierge_notes: Option<String>,
     pub updated_by: String,
 }
\ No newline at end of file
@@ -0,0 +1,86 @@
+//! Raíz de la API y respuesta para rutas desconocidas
+//!
+//! Sin esto, `/` y cualquier ruta inexistente devuelven el 404 vacío de axum,
+//! que no ayuda a quien integra la API y prueba rutas a mano.
+
+use axum::{
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Json},
+    routing::get,
+    Router,
+};
+use serde_json::{json, Value};
+
+use crate::state::AppState;
+
+pub fn create_root_router() -> Router<AppState> {
+    Router::new().route("/", get(root))
+}
+
+/// Presentación mínima de la API con enlace a la documentación
+pub async fn root() -> Json<Value> {
+    Json(json!({
+        "name": "Delivery Route Optimizer API",
+        "version": env!("CARGO_PKG_VERSION"),
+        "description": "Tournées de Colis Privé: paquetes, geocoding y optimización de rutas",
+        "docs": "/docs",
+        "health": "/colis-prive/health",
+    }))
+}
+
+/// 404 en JSON con la ruta pedida (fallback del router principal)
+pub async fn not_found(uri: Uri) -> impl IntoResponse {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": {
+                "code": "not_found",
+                "path": uri.path(),
+            }
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    /// Misma forma que el router de main.rs: "/" junto a las rutas anidadas en la raíz
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(root))
+            .nest("/", Router::new().route("/packages/stats", get(|| async { "ok" })))
+            .nest("/colis-prive", Router::new().route("/health", get(|| async { "ok" })))
+            .fallback(not_found)
+    }
+
+    async fn call(path: &str) -> (StatusCode, Value) {
+        let response = app()
+            .oneshot(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_path_is_a_json_404() {
+        let (status, body) = call("/colis-prive/paquets?date=2025-01-15").await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, json!({ "error": { "code": "not_found", "path": "/colis-prive/paquets" } }));
+    }
+
+    #[tokio::test]
+    async fn test_root_describes_the_api() {
+        let (status, body) = call("/").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["docs"], "/docs");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    }
+}
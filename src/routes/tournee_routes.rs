@@ -0,0 +1,64 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use crate::controllers::colis_prive_controller::ColisPriveController;
+use crate::dto::colis_prive_dto::OptimizationHistoryQuery;
+use crate::services::tournee_live_service::StopEvent;
+use crate::state::AppState;
+use crate::utils::errors::AppError;
+
+pub fn create_tournee_router() -> Router<AppState> {
+    Router::new()
+        .route("/:matricule/live", get(live_tournee))
+}
+
+/// WebSocket con un evento JSON por cada parada entregada o fallida de la tournée
+async fn live_tournee(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Path(matricule): Path<String>,
+    Query(query): Query<OptimizationHistoryQuery>,
+) -> Result<Response, AppError> {
+    // Suscribirse antes del upgrade para responder 503 si la tournée está llena
+    let (tournee, events) = ColisPriveController::subscribe_live(&matricule, query, &state)?;
+    let hub = state.tournee_live.clone();
+
+    Ok(ws.on_upgrade(move |socket| async move {
+        forward_events(socket, events).await;
+        hub.release(&tournee);
+        log::info!("📴 Dispatcher desconectado de la tournée {}", tournee);
+    }))
+}
+
+/// Reenviar los eventos al cliente hasta que cierre la conexión
+async fn forward_events(mut socket: WebSocket, mut events: broadcast::Receiver<StopEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let Ok(text) = serde_json::to_string(&event) else { continue };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                // Cliente lento: se saltan los eventos perdidos y se sigue
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("⚠️ Suscriptor en directo retrasado: {} eventos perdidos", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                // Los mensajes del cliente se ignoran; solo interesa saber si sigue conectado
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
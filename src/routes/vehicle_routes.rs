@@ -8,6 +8,7 @@ use crate::dto::vehicle_dto::{CreateVehicleRequest, UpdateVehicleRequest, Vehicl
 use crate::dto::company_dto::ApiResponse;
 use crate::state::AppState;
 use crate::utils::errors::AppError;
+use crate::utils::app_json::AppJson;
 use uuid::Uuid;
 
 pub fn create_vehicle_router() -> Router<AppState> {
@@ -28,7 +29,7 @@ async fn get_company_id_from_jwt() -> Uuid {
 
 async fn create_vehicle(
     State(state): State<AppState>,
-    Json(request): Json<CreateVehicleRequest>,
+    AppJson(request): AppJson<CreateVehicleRequest>,
 ) -> Result<Json<ApiResponse<VehicleResponse>>, AppError> {
     let company_id = get_company_id_from_jwt().await; // TODO: Extraer del JWT
     let controller = VehicleController::new(state.pool.clone());
@@ -58,7 +59,7 @@ async fn list_vehicles(
 async fn update_vehicle(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-    Json(request): Json<UpdateVehicleRequest>,
+    AppJson(request): AppJson<UpdateVehicleRequest>,
 ) -> Result<Json<ApiResponse<VehicleResponse>>, AppError> {
     let company_id = get_company_id_from_jwt().await; // TODO: Extraer del JWT
     let controller = VehicleController::new(state.pool.clone());
@@ -0,0 +1,310 @@
+//! Limpieza de direcciones de Colis Privé
+//!
+//! Normaliza mayúsculas y espacios, recoloca el número cuando viene al final
+//! ("RUE DE RIVOLI 12" → "12 RUE DE RIVOLI") y conserva los sufijos de número
+//! franceses (bis, ter, quater...): "12 BIS" es otro edificio que "12".
+//!
+//! Las expresiones regulares del crate `regex` son de tiempo lineal; además
+//! la entrada se recorta a `max_length` caracteres para acotar el trabajo
+//! con direcciones anómalas.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::Serialize;
+
+/// Sufijos de número habituales en direcciones francesas
+pub const DEFAULT_NUMBER_SUFFIXES: [&str; 4] = ["BIS", "TER", "QUATER", "QUINQUIES"];
+
+/// Longitud máxima (en caracteres) de una dirección antes de limpiarla
+pub const DEFAULT_MAX_ADDRESS_LENGTH: usize = 256;
+
+/// Reglas de limpieza
+#[derive(Debug, Clone)]
+pub struct AddressCleaningRules {
+    /// Sufijos que forman parte del número ("12 BIS")
+    pub number_suffixes: Vec<String>,
+    /// Mover al principio un número que aparece al final
+    pub move_trailing_number: bool,
+    /// Las direcciones más largas se recortan con un aviso
+    pub max_length: usize,
+}
+
+impl Default for AddressCleaningRules {
+    fn default() -> Self {
+        Self {
+            number_suffixes: DEFAULT_NUMBER_SUFFIXES.iter().map(|s| s.to_string()).collect(),
+            move_trailing_number: true,
+            max_length: DEFAULT_MAX_ADDRESS_LENGTH,
+        }
+    }
+}
+
+/// Resultado detallado de una limpieza: qué reglas se aplicaron y avisos
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CleaningReport {
+    pub original: String,
+    pub cleaned: String,
+    /// Reglas que modificaron la dirección, en orden de aplicación
+    pub transformations: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+pub struct AddressCleaner {
+    rules: AddressCleaningRules,
+    /// "12BIS" → "12 BIS"
+    attached_suffix: Option<Regex>,
+    /// Número (con sufijo opcional) al principio
+    leading_number: Regex,
+    /// Número (con sufijo opcional) al final
+    trailing_number: Regex,
+}
+
+impl AddressCleaner {
+    pub fn new(rules: AddressCleaningRules) -> Self {
+        let suffixes = rules
+            .number_suffixes
+            .iter()
+            .map(|s| regex::escape(&s.trim().to_uppercase()))
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("|");
+
+        // Sin sufijos configurados solo se reconoce la letra ("12B")
+        let number = if suffixes.is_empty() {
+            r"\d+[A-Z]?".to_string()
+        } else {
+            format!(r"\d+(?:\s(?:{})\b|[A-Z]\b)?", suffixes)
+        };
+
+        Self {
+            attached_suffix: (!suffixes.is_empty())
+                .then(|| Regex::new(&format!(r"\b(\d+)({})\b", suffixes)).unwrap()),
+            leading_number: Regex::new(&format!(r"^({})\s+(.+)$", number)).unwrap(),
+            trailing_number: Regex::new(&format!(r"^(\D.*?)\s+({})$", number)).unwrap(),
+            rules,
+        }
+    }
+
+    pub fn rules(&self) -> &AddressCleaningRules {
+        &self.rules
+    }
+
+    /// Limpiar una dirección ("rue de rivoli  12 bis" → "12 BIS RUE DE RIVOLI")
+    pub fn clean(&self, raw: &str) -> String {
+        self.clean_with_report(raw).cleaned
+    }
+
+    /// Limpiar una dirección registrando cada regla aplicada
+    pub fn clean_with_report(&self, raw: &str) -> CleaningReport {
+        let mut transformations = Vec::new();
+        let mut warnings = Vec::new();
+
+        let input = match raw.char_indices().nth(self.rules.max_length) {
+            Some((cut, _)) => {
+                let length = raw.chars().count();
+                log::warn!("⚠️ Dirección de {} caracteres recortada a {}", length, self.rules.max_length);
+                warnings.push(format!("Dirección recortada ({} caracteres, máximo {})", length, self.rules.max_length));
+                transformations.push("truncated".to_string());
+                &raw[..cut]
+            }
+            None => raw,
+        };
+
+        let mut address = input.to_uppercase();
+        if address != input {
+            transformations.push("uppercase".to_string());
+        }
+
+        if address.contains(',') {
+            address = address.replace(',', " ");
+            transformations.push("commas_removed".to_string());
+        }
+
+        let normalized = address.split_whitespace().collect::<Vec<_>>().join(" ");
+        if normalized != address {
+            transformations.push("whitespace_normalized".to_string());
+        }
+        address = normalized;
+
+        if let Some(attached) = &self.attached_suffix {
+            let separated = attached.replace_all(&address, "$1 $2").into_owned();
+            if separated != address {
+                transformations.push("suffix_separated".to_string());
+                address = separated;
+            }
+        }
+
+        if self.rules.move_trailing_number {
+            if let Some(captures) = self.trailing_number.captures(&address) {
+                address = format!("{} {}", &captures[2], &captures[1]);
+                transformations.push("trailing_number_moved".to_string());
+            }
+        }
+
+        // "12 12 RUE X" o "12 RUE X 12" → "12 RUE X"
+        let words: Vec<&str> = address.split(' ').collect();
+        if words.len() > 2 && words[0].starts_with(|c: char| c.is_ascii_digit()) {
+            let number = words[0];
+            let duplicate = if words[1] == number {
+                Some(1)
+            } else if words[words.len() - 1] == number {
+                Some(words.len() - 1)
+            } else {
+                None
+            };
+
+            if let Some(index) = duplicate {
+                warnings.push(format!("Números duplicados eliminados ({})", number));
+                transformations.push("duplicate_number_removed".to_string());
+                address = words
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != index)
+                    .map(|(_, word)| *word)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+            }
+        }
+
+        if address.is_empty() {
+            warnings.push("Dirección vacía".to_string());
+        } else if self.split_number(&address).0.is_none() {
+            warnings.push("Sin número de calle".to_string());
+        }
+
+        CleaningReport {
+            original: raw.to_string(),
+            cleaned: address,
+            transformations,
+            warnings,
+        }
+    }
+
+    /// Separar número (con sufijo) y calle: "12 BIS RUE X" → ("12 BIS", "RUE X")
+    pub fn split_number(&self, address: &str) -> (Option<String>, String) {
+        match self.leading_number.captures(address) {
+            Some(captures) => (Some(captures[1].to_string()), captures[2].to_string()),
+            None => (None, address.to_string()),
+        }
+    }
+}
+
+fn default_cleaner() -> &'static AddressCleaner {
+    static CLEANER: OnceLock<AddressCleaner> = OnceLock::new();
+    CLEANER.get_or_init(|| AddressCleaner::new(AddressCleaningRules::default()))
+}
+
+/// Limpiar una dirección con las reglas por defecto, devolviendo los avisos
+/// de lo que se corrigió automáticamente
+pub fn clean_address(raw: &str) -> (String, Vec<String>) {
+    let report = default_cleaner().clean_with_report(raw);
+    (report.cleaned, report.warnings)
+}
+
+/// Limpiar con las reglas por defecto, detallando las transformaciones
+pub fn clean_address_report(raw: &str) -> CleaningReport {
+    default_cleaner().clean_with_report(raw)
+}
+
+/// Separar número y calle con las reglas por defecto
+pub fn split_street_number(address: &str) -> (Option<String>, String) {
+    default_cleaner().split_number(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_suffixes_are_preserved() {
+        assert_eq!(clean_address("12 bis rue de la Paix").0, "12 BIS RUE DE LA PAIX");
+        assert_eq!(clean_address("14 TER  avenue Foch").0, "14 TER AVENUE FOCH");
+        assert_eq!(clean_address("3 quater, impasse des Lilas").0, "3 QUATER IMPASSE DES LILAS");
+    }
+
+    #[test]
+    fn test_trailing_number_keeps_its_suffix() {
+        assert_eq!(clean_address("rue de la Paix 12 bis").0, "12 BIS RUE DE LA PAIX");
+        assert_eq!(clean_address("avenue Foch 14ter").0, "14 TER AVENUE FOCH");
+        assert_eq!(clean_address("rue de Rivoli 3").0, "3 RUE DE RIVOLI");
+    }
+
+    #[test]
+    fn test_split_number_includes_suffix() {
+        assert_eq!(split_street_number("12 BIS RUE X"), (Some("12 BIS".to_string()), "RUE X".to_string()));
+        assert_eq!(split_street_number("3 QUATER RUE Y"), (Some("3 QUATER".to_string()), "RUE Y".to_string()));
+        assert_eq!(split_street_number("12B RUE X"), (Some("12B".to_string()), "RUE X".to_string()));
+        assert_eq!(split_street_number("RUE X"), (None, "RUE X".to_string()));
+    }
+
+    #[test]
+    fn test_street_words_starting_like_a_suffix_are_not_suffixes() {
+        assert_eq!(
+            split_street_number("12 TERRASSES DU PARC"),
+            (Some("12".to_string()), "TERRASSES DU PARC".to_string())
+        );
+        assert_eq!(clean_address("12terrasses du parc").0, "12TERRASSES DU PARC");
+    }
+
+    #[test]
+    fn test_report_lists_each_rule_that_fired() {
+        let report = clean_address_report("rue de la paix,  12bis");
+
+        assert_eq!(report.cleaned, "12 BIS RUE DE LA PAIX");
+        assert_eq!(
+            report.transformations,
+            vec!["uppercase", "commas_removed", "whitespace_normalized", "suffix_separated", "trailing_number_moved"]
+        );
+        assert!(report.warnings.is_empty());
+
+        let clean = clean_address_report("12 RUE X");
+        assert!(clean.transformations.is_empty());
+
+        let without_number = clean_address_report("RUE X");
+        assert_eq!(without_number.warnings, vec!["Sin número de calle"]);
+    }
+
+    #[test]
+    fn test_duplicate_number_is_removed_with_warning() {
+        let (cleaned, warnings) = clean_address("12 12 rue de la Paix");
+
+        assert_eq!(cleaned, "12 RUE DE LA PAIX");
+        assert!(warnings.iter().any(|w| w.to_lowercase().contains("números duplicados")));
+
+        let (cleaned, warnings) = clean_address("12 rue de la Paix 12");
+        assert_eq!(cleaned, "12 RUE DE LA PAIX");
+        assert_eq!(warnings.len(), 1);
+
+        assert!(clean_address("12 rue de la Paix").1.is_empty());
+    }
+
+    #[test]
+    fn test_suffixes_can_be_disabled() {
+        let cleaner = AddressCleaner::new(AddressCleaningRules {
+            number_suffixes: Vec::new(),
+            move_trailing_number: false,
+            ..AddressCleaningRules::default()
+        });
+
+        assert_eq!(cleaner.clean("rue de Rivoli 3"), "RUE DE RIVOLI 3");
+        assert_eq!(cleaner.split_number("12 BIS RUE X"), (Some("12".to_string()), "BIS RUE X".to_string()));
+    }
+
+    #[test]
+    fn test_overlong_address_is_truncated_quickly_and_flagged() {
+        let raw = format!("12 rue {} 75002 Paris", "1 bis 2ter, ".repeat(200_000));
+
+        let started = std::time::Instant::now();
+        let report = clean_address_report(&raw);
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+        assert!(report.cleaned.chars().count() <= DEFAULT_MAX_ADDRESS_LENGTH);
+        assert_eq!(report.transformations[0], "truncated");
+        assert!(report.warnings[0].starts_with("Dirección recortada"));
+
+        let cleaner = AddressCleaner::new(AddressCleaningRules { max_length: 10, ..AddressCleaningRules::default() });
+        assert_eq!(cleaner.clean("12 rue éèàù de la Paix"), "12 RUE ÉÈÀ");
+        assert!(clean_address("12 rue de la Paix").1.is_empty());
+    }
+}
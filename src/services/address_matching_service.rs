@@ -1,4 +1,5 @@
-use crate::models::address::{Address, AddressSearch, ColisPriveAddress};
+use crate::models::address::{Address, AddressAccess, AddressSearch, ColisPriveAddress};
+use crate::repositories::address_repository::AddressRepository;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -59,7 +60,9 @@ impl AddressMatchingService {
                 ST_X(coordinates) as longitude,
                 door_code,
                 has_mailbox_access,
-                driver_notes
+                driver_notes,
+                floor,
+                concierge_notes
             FROM addresses
             ORDER BY created_at DESC
         "#;
@@ -83,6 +86,8 @@ impl AddressMatchingService {
                 door_code: row.get("door_code"),
                 has_mailbox_access: row.get("has_mailbox_access"),
                 driver_notes: row.get("driver_notes"),
+                floor: row.get("floor"),
+                concierge_notes: row.get("concierge_notes"),
                 created_at: chrono::Utc::now(),
                 updated_at: chrono::Utc::now(),
                 last_updated_by: None,
@@ -158,23 +163,18 @@ impl AddressMatchingService {
         Ok(new_address)
     }
     
-    /// Actualiza los datos del chofer para una dirección
+    /// Actualiza los datos de acceso (código, BAL, planta, gardien) de una dirección
     pub async fn update_driver_data(
         &self,
         address_id: uuid::Uuid,
-        door_code: Option<String>,
-        has_mailbox_access: bool,
+        access: AddressAccess,
         driver_notes: Option<String>,
         updated_by: String,
     ) -> Result<Address> {
         // Actualizar en BD
-        let updated_address = self.update_address_driver_data_in_db(
-            address_id,
-            door_code.clone(),
-            has_mailbox_access,
-            driver_notes.clone(),
-            updated_by.clone(),
-        ).await?;
+        let updated_address = AddressRepository::new((*self.pool).clone())
+            .update_access(address_id, &access, driver_notes, Some(updated_by))
+            .await?;
         
         // Actualizar cache
         let search_key = updated_address.search_key();
@@ -224,39 +224,11 @@ impl AddressMatchingService {
             door_code,
             has_mailbox_access,
             driver_notes,
+            floor: None,
+            concierge_notes: None,
             last_updated_by,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         })
     }
-    
-    /// Actualiza datos del chofer en la BD
-    async fn update_address_driver_data_in_db(
-        &self,
-        address_id: uuid::Uuid,
-        door_code: Option<String>,
-        has_mailbox_access: bool,
-        driver_notes: Option<String>,
-        updated_by: String,
-    ) -> Result<Address> {
-        // Por ahora retornamos una dirección mock
-        // Se implementará cuando la tabla exista
-        Ok(Address {
-            id: address_id,
-            company_id: None,
-            official_label: "Mock Address".to_string(),
-            street_name: "Mock Street".to_string(),
-            street_number: None,
-            postcode: "75000".to_string(),
-            city: "Paris".to_string(),
-            latitude: 0.0,
-            longitude: 0.0,
-            door_code,
-            has_mailbox_access,
-            driver_notes,
-            last_updated_by: Some(updated_by),
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-        })
-    }
 }
@@ -0,0 +1,92 @@
+//! Reutilización de validaciones de direcciones
+//!
+//! Las direcciones que el geocoding no resuelve quedan guardadas como
+//! `requires_manual`; cuando un dispatcher fija sus coordenadas pasan a
+//! `manual_confirmed` y las tournées siguientes las usan sin volver a
+//! geocodificar.
+
+use crate::dto::colis_prive_dto::PackageData;
+use crate::models::address_validation::AddressValidation;
+use crate::services::colis_prive_service::ValidationMethod;
+use crate::utils::errors::AppError;
+use crate::utils::geo::{CoordinateBounds, CoordinateCheck};
+
+/// Aplicar al paquete una validación guardada con coordenadas; devuelve si se usó
+pub fn apply_stored_validation(package: &mut PackageData, stored: &AddressValidation) -> bool {
+    let (Some((latitude, longitude)), Some(method)) = (stored.coordinates(), stored.method()) else {
+        return false;
+    };
+
+    package.latitude = Some(latitude);
+    package.longitude = Some(longitude);
+    package.validation_method = Some(method.as_str().to_string());
+    package.validation_confidence = stored.confidence;
+    true
+}
+
+/// Coordenadas fijadas a mano: deben caer en alguna región atendida
+pub fn validate_pinned_coordinates(latitude: f64, longitude: f64, bounds: &CoordinateBounds) -> Result<(), AppError> {
+    match bounds.check(latitude, longitude) {
+        CoordinateCheck::Valid => Ok(()),
+        CoordinateCheck::Swapped => Err(AppError::ValidationError(format!(
+            "Coordenadas ({}, {}) invertidas: latitud y longitud intercambiadas",
+            latitude, longitude
+        ))),
+        CoordinateCheck::OutOfRange => Err(AppError::ValidationError(format!(
+            "Coordenadas ({}, {}) fuera de las regiones atendidas",
+            latitude, longitude
+        ))),
+    }
+}
+
+/// Método con el que se guarda el resultado de geocodificar una dirección
+pub fn geocoding_outcome_method(found: bool) -> ValidationMethod {
+    if found {
+        ValidationMethod::CompletedAuto
+    } else {
+        ValidationMethod::RequiresManual
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn stored(method: ValidationMethod, coordinates: Option<(f64, f64)>) -> AddressValidation {
+        AddressValidation {
+            id: Uuid::new_v4(),
+            normalized_address: "12 rue de la paix 75002 paris".to_string(),
+            original_address: "12 RUE DE LA PAIX, 75002, PARIS".to_string(),
+            latitude: coordinates.map(|c| c.0),
+            longitude: coordinates.map(|c| c.1),
+            validation_method: method.as_str().to_string(),
+            confidence: coordinates.map(|_| 1.0),
+            confirmed_by: Some("dispatcher".to_string()),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_manual_confirmation_is_reused() {
+        let mut package = PackageData { reference_colis: "CP1".to_string(), ..Default::default() };
+
+        assert!(!apply_stored_validation(&mut package, &stored(ValidationMethod::RequiresManual, None)));
+        assert!(package.latitude.is_none());
+
+        assert!(apply_stored_validation(&mut package, &stored(ValidationMethod::ManualConfirmed, Some((48.8686, 2.3314)))));
+        assert_eq!((package.latitude, package.longitude), (Some(48.8686), Some(2.3314)));
+        assert_eq!(package.validation_method.as_deref(), Some("manual_confirmed"));
+        assert_eq!(ValidationMethod::parse("manual_confirmed"), Some(ValidationMethod::ManualConfirmed));
+    }
+
+    #[test]
+    fn test_pinned_coordinates_must_be_in_a_served_region() {
+        let bounds = CoordinateBounds::default();
+
+        assert!(validate_pinned_coordinates(48.8686, 2.3314, &bounds).is_ok());
+        assert!(matches!(validate_pinned_coordinates(2.3314, 48.8686, &bounds), Err(AppError::ValidationError(_))));
+        assert!(matches!(validate_pinned_coordinates(0.0, 0.0, &bounds), Err(AppError::ValidationError(_))));
+    }
+}
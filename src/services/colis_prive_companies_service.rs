@@ -1,7 +1,27 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use std::time::Duration;
 use log;
 
+use crate::utils::errors::AppError;
+
+/// Timeout del referencial de empresas (evita colgar la request indefinidamente)
+const COMPANIES_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Cliente HTTP compartido (reutiliza conexiones entre llamadas)
+fn shared_client() -> Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| {
+            Client::builder()
+                .timeout(COMPANIES_TIMEOUT)
+                .build()
+                .expect("Failed to create HTTP client")
+        })
+        .clone()
+}
+
 // Estructura de la compañía tal como viene de la API de Colis Privé
 #[derive(Debug, Deserialize)]
 pub struct ColisPriveCompanyRawValue {
@@ -45,13 +65,14 @@ pub struct ColisPriveCompaniesService {
 
 impl ColisPriveCompaniesService {
     pub fn new(base_url: String) -> Self {
-        Self {
-            client: Client::new(),
-            base_url,
-        }
+        Self::with_client(shared_client(), base_url)
+    }
+
+    pub fn with_client(client: Client, base_url: String) -> Self {
+        Self { client, base_url }
     }
 
-    pub async fn get_companies(&self) -> Result<Vec<ColisPriveCompany>, Box<dyn std::error::Error>> {
+    pub async fn get_companies(&self) -> Result<Vec<ColisPriveCompany>, AppError> {
         let url = format!("{}/REST/ClientExtranetlightByTypeClient?TypeClient=PRESTATAIRECOLIS", self.base_url);
         
         log::info!("🏢 Llamando a Colis Privé (API real): {}", url);
@@ -74,11 +95,28 @@ impl ColisPriveCompaniesService {
             .header("Sec-Fetch-Site", "same-site")
             .header("Sec-GPC", "1")
             .send()
-            .await?;
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    AppError::ExternalApi(format!("Timeout obteniendo empresas de Colis Privé: {}", e))
+                } else {
+                    AppError::ExternalApi(format!("Error obteniendo empresas de Colis Privé: {}", e))
+                }
+            })?;
         
         log::info!("📡 Respuesta recibida: {}", response.status());
+
+        if !response.status().is_success() {
+            return Err(AppError::ExternalApi(format!(
+                "Colis Privé respondió {} al obtener empresas",
+                response.status()
+            )));
+        }
         
-        let raw_response: ColisPriveCompaniesResponseRaw = response.json().await?;
+        let raw_response: ColisPriveCompaniesResponseRaw = response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalApi(format!("Error parseando empresas de Colis Privé: {}", e)))?;
         
         log::info!("📊 Empresas recibidas: {}", raw_response.l_cli.len());
         
@@ -99,7 +137,7 @@ impl ColisPriveCompaniesService {
 }
 
 // Función helper pública para obtener empresas
-pub async fn fetch_all_companies() -> Result<Vec<crate::models::colis_prive_company::ColisPriveCompany>, crate::utils::errors::AppError> {
+pub async fn fetch_all_companies() -> Result<Vec<crate::models::colis_prive_company::ColisPriveCompany>, AppError> {
     let service = ColisPriveCompaniesService::new(
         std::env::var("COLIS_PRIVE_REFERENTIEL_URL")
             .unwrap_or_else(|_| "https://wsreferentiel-v2.colisprive.com/WS_RefDistributeur/RefDistributeurConsolideExtranetToExterne.svc".to_string())
@@ -114,6 +152,40 @@ pub async fn fetch_all_companies() -> Result<Vec<crate::models::colis_prive_comp
                 description: None,
             }).collect()
         })
-        .map_err(|e| crate::utils::errors::AppError::ExternalApi(format!("Error fetching companies: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+
+    #[tokio::test]
+    async fn test_get_companies_times_out_as_external_api_error() {
+        // Servidor que tarda más que el timeout del cliente
+        let app = Router::new().route(
+            "/REST/ClientExtranetlightByTypeClient",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "{}"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let client = Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let service = ColisPriveCompaniesService::with_client(client, format!("http://{}", addr));
+
+        let started = std::time::Instant::now();
+        let result = service.get_companies().await;
+
+        assert!(matches!(result, Err(AppError::ExternalApi(_))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
 }
 
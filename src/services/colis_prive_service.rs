@@ -1,9 +1,38 @@
 use crate::config::environment::EnvironmentConfig;
-use crate::dto::colis_prive_dto;
-use crate::utils::errors::AppError;
-use reqwest::Client;
+use crate::dto::colis_prive_dto::{self, DeliverySlot};
+use crate::services::delivery_details_service::extract_delivery_slot;
+use crate::utils::errors::{AppError, OptimizationError};
+use crate::utils::geo::{CoordinateBounds, CoordinateCheck};
+use crate::utils::retry::retry_transient;
+use crate::utils::number::{deserialize_lenient_i32, value_as_f64, value_as_i64};
+use crate::utils::tls::HostClients;
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc, Duration};
+use chrono::{DateTime, NaiveDate, Utc, Duration};
+
+/// Nombre del servicio en los errores de optimización
+const OPTIMIZATION_SERVICE: &str = "Colis Privé";
+
+/// Timeout de autenticación y tournée
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Timeout de la optimización (Colis Privé tarda con tournées grandes)
+pub const OPTIMIZE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(90);
+
+/// Cabeceras que envía la web oficial de gestión de tournées
+const BROWSER_HEADERS: [(&str, &str); 12] = [
+    ("Accept", "application/json, text/plain, */*"),
+    ("Accept-Language", "fr-FR,fr;q=0.6"),
+    ("Origin", "https://gestiontournee.colisprive.com"),
+    ("Referer", "https://gestiontournee.colisprive.com/"),
+    ("Sec-Fetch-Dest", "empty"),
+    ("Sec-Fetch-Mode", "cors"),
+    ("Sec-Fetch-Site", "same-site"),
+    ("Sec-GPC", "1"),
+    ("User-Agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36"),
+    ("sec-ch-ua", "\"Chromium\";v=\"141\", \"Not=A?Brand\";v=\"24\", \"Brave\";v=\"141\""),
+    ("sec-ch-ua-mobile", "?0"),
+    ("sec-ch-ua-platform", "\"macOS\""),
+];
 
 // Re-exports para compatibilidad con código legacy
 pub use crate::dto::colis_prive_dto::PackageData;
@@ -53,11 +82,20 @@ pub struct GetPackagesResponse {
     pub address_validation: Option<AddressValidationSummary>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Resumen de validación de direcciones de una tournée.
+///
+/// Siempre incluye los contadores por coordenadas y las referencias de los
+/// paquetes sin coordenadas (los que no se pueden enrutar); los contadores por método de
+/// validación solo aparecen si se registró algún método (o se piden con
+/// `with_method_counts`), de modo que el JSON de ambos usos es estable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AddressValidationSummary {
     pub total_packages: usize,
     pub with_coordinates: usize,
     pub without_coordinates: usize,
+    /// Referencias de los paquetes sin coordenadas utilizables
+    #[serde(default)]
+    pub uncoordinated_references: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auto_validated: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -71,9 +109,197 @@ pub struct AddressValidationSummary {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub requires_manual: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub manual_confirmed: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub warnings: Option<Vec<String>>,
 }
 
+impl AddressValidationSummary {
+    pub fn builder() -> AddressValidationSummaryBuilder {
+        AddressValidationSummaryBuilder::default()
+    }
+
+    /// Resumen a partir de paquetes ya procesados (coordenadas + `validation_method`)
+    pub fn from_packages(packages: &[PackageData]) -> Self {
+        packages
+            .iter()
+            .fold(Self::builder(), |builder, package| {
+                let has_coordinates = package.coord_y_destinataire.or(package.latitude).is_some()
+                    && package.coord_x_destinataire.or(package.longitude).is_some();
+                let method = package.validation_method.as_deref().and_then(ValidationMethod::parse);
+                builder.package(&package.reference_colis, has_coordinates, method)
+            })
+            .build()
+    }
+}
+
+/// Método con el que se validó (o no) la dirección de un paquete
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationMethod {
+    AutoValidated,
+    CleanedAuto,
+    CompletedAuto,
+    PartialFound,
+    GeocodingError,
+    RequiresManual,
+    /// Coordenadas fijadas a mano por un dispatcher
+    ManualConfirmed,
+}
+
+impl ValidationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AutoValidated => "auto_validated",
+            Self::CleanedAuto => "cleaned_auto",
+            Self::CompletedAuto => "completed_auto",
+            Self::PartialFound => "partial_found",
+            Self::GeocodingError => "geocoding_error",
+            Self::RequiresManual => "requires_manual",
+            Self::ManualConfirmed => "manual_confirmed",
+        }
+    }
+
+    /// Leer el valor guardado en `PackageData::validation_method`
+    /// ("geocoded" es el valor histórico de coordenadas completadas con Mapbox)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto_validated" => Some(Self::AutoValidated),
+            "cleaned_auto" => Some(Self::CleanedAuto),
+            "completed_auto" | "geocoded" => Some(Self::CompletedAuto),
+            "partial_found" => Some(Self::PartialFound),
+            "geocoding_error" => Some(Self::GeocodingError),
+            "requires_manual" => Some(Self::RequiresManual),
+            "manual_confirmed" => Some(Self::ManualConfirmed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AddressValidationSummaryBuilder {
+    with_coordinates: usize,
+    uncoordinated_references: Vec<String>,
+    method_counts: Option<[usize; 7]>,
+    warnings: Vec<String>,
+}
+
+impl AddressValidationSummaryBuilder {
+    /// Contar un paquete
+    pub fn package(mut self, reference: &str, has_coordinates: bool, method: Option<ValidationMethod>) -> Self {
+        if has_coordinates {
+            self.with_coordinates += 1;
+        } else {
+            self.uncoordinated_references.push(reference.to_string());
+        }
+        if let Some(method) = method {
+            self = self.with_method_counts();
+            if let Some(counts) = self.method_counts.as_mut() {
+                counts[method as usize] += 1;
+            }
+        }
+        self
+    }
+
+    /// Incluir los contadores por método aunque estén a cero
+    pub fn with_method_counts(mut self) -> Self {
+        self.method_counts.get_or_insert([0; 7]);
+        self
+    }
+
+    pub fn warning(mut self, warning: impl Into<String>) -> Self {
+        self.warnings.push(warning.into());
+        self
+    }
+
+    pub fn build(self) -> AddressValidationSummary {
+        let count = |method: ValidationMethod| self.method_counts.map(|counts| counts[method as usize]);
+
+        AddressValidationSummary {
+            total_packages: self.with_coordinates + self.uncoordinated_references.len(),
+            with_coordinates: self.with_coordinates,
+            without_coordinates: self.uncoordinated_references.len(),
+            uncoordinated_references: self.uncoordinated_references,
+            auto_validated: count(ValidationMethod::AutoValidated),
+            cleaned_auto: count(ValidationMethod::CleanedAuto),
+            completed_auto: count(ValidationMethod::CompletedAuto),
+            partial_found: count(ValidationMethod::PartialFound),
+            geocoding_errors: count(ValidationMethod::GeocodingError),
+            requires_manual: count(ValidationMethod::RequiresManual),
+            manual_confirmed: count(ValidationMethod::ManualConfirmed),
+            warnings: (!self.warnings.is_empty()).then_some(self.warnings),
+        }
+    }
+}
+
+fn invalid_matricule(matricule: &str, message: String) -> AppError {
+    log::warn!("⚠️ Matricule inválido '{}': {}", matricule, message);
+
+    let mut error = validator::ValidationError::new("invalid_matricule");
+    error.message = Some(message.into());
+    error.add_param("value".into(), &matricule);
+
+    let mut errors = validator::ValidationErrors::new();
+    errors.add("matricule", error);
+    AppError::Validation(errors)
+}
+
+/// Matricule completo `SOCIETE_CODE` a partir de un código suelto ("A187518")
+/// o ya prefijado con la société; rechaza lo que daría un CodeTournee roto
+pub fn full_matricule(societe: &str, matricule: &str) -> Result<String, AppError> {
+    let societe = societe.trim();
+    let trimmed = matricule.trim();
+
+    let (prefix, code) = match trimmed.split_once('_') {
+        Some((prefix, code)) => (Some(prefix), code),
+        None => (None, trimmed),
+    };
+
+    if let Some(prefix) = prefix {
+        if prefix != societe {
+            return Err(invalid_matricule(
+                matricule,
+                format!("El prefijo '{}' no corresponde a la société {}", prefix, societe),
+            ));
+        }
+    }
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(invalid_matricule(
+            matricule,
+            "El matricule debe ser un código alfanumérico (p.ej. A187518), opcionalmente prefijado con la société".to_string(),
+        ));
+    }
+
+    Ok(format!("{}_{}", societe, code))
+}
+
+/// Payload de `optimiserTourneeAvecValidation`: la fecha de la tournée fija
+/// DateHeureDebut y CodeTournee (la hora de salida es la actual)
+fn optimize_request_payload(
+    societe: &str,
+    matricule: &str,
+    date: NaiveDate,
+    now: DateTime<Utc>,
+) -> Result<serde_json::Value, AppError> {
+    let full_matricule = full_matricule(societe, matricule)?;
+    let start = date.and_time(now.time()).and_utc();
+
+    // Usar exactamente el mismo formato que la página oficial
+    Ok(serde_json::json!({
+        "CodeSociete": societe,
+        "Matricule": full_matricule,
+        "DateHeureDebut": start.to_rfc3339(),
+        "CoordX": null,
+        "CoordY": null,
+        "CoordRetourX": null,
+        "CoordRetourY": null,
+        "CodeTournee": format!("{}-{}", full_matricule, date.format("%Y%m%d")),
+        "IsModeOptimToutCPConfondus": false,
+        "PauseHeureDebut": null,
+        "PauseDuree": null
+    }))
+}
+
 #[derive(Debug, Serialize)]
 struct AuthApiRequest {
     #[serde(rename = "identifiant")]
@@ -138,7 +364,7 @@ struct OptimizationApiResponse {
 
 #[derive(Debug, Deserialize)]
 struct LieuArticle {
-    #[serde(rename = "numeroOrdre")]
+    #[serde(rename = "numeroOrdre", default, deserialize_with = "deserialize_lenient_i32")]
     numero_ordre: Option<i32>,
     #[serde(rename = "refExterneArticle")]
     ref_externe_article: Option<String>,
@@ -159,10 +385,11 @@ struct LieuArticle {
 }
 
 pub struct ColisPriveService {
-    client: Client,
+    clients: HostClients,
     config: EnvironmentConfig,
 }
 
+#[derive(Clone)]
 pub struct AuthenticationResult {
     pub sso_token: String,
     pub matricule_chauffeur: String,
@@ -177,8 +404,35 @@ pub struct OptimizationResult {
 }
 
 impl ColisPriveService {
-    pub fn new(client: Client, config: EnvironmentConfig) -> Self {
-        Self { client, config }
+    pub fn new(clients: HostClients, config: EnvironmentConfig) -> Self {
+        Self { clients, config }
+    }
+
+    /// POST JSON a Colis Privé con las cabeceras del navegador, usando el
+    /// cliente compartido del host (pool de conexiones y política TLS).
+    /// Devuelve el cuerpo aunque el estado HTTP sea de error: Colis Privé
+    /// explica los fallos en el propio JSON.
+    async fn post_json(
+        &self,
+        url: &str,
+        payload: &serde_json::Value,
+        sso_token: Option<&str>,
+        timeout: std::time::Duration,
+    ) -> Result<(reqwest::StatusCode, String), reqwest::Error> {
+        let mut request = self.clients.client_for(url).post(url).timeout(timeout).json(payload);
+        for (name, value) in BROWSER_HEADERS {
+            request = request.header(name, value);
+        }
+        if let Some(token) = sso_token {
+            request = request.header("SsoHopps", token);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            log::warn!("⚠️ Colis Privé respondió {} para {}", status, url);
+        }
+        Ok((status, response.text().await?))
     }
 
     pub async fn authenticate(
@@ -200,72 +454,18 @@ impl ColisPriveService {
 
         let auth_url = format!("{}/api/auth/login/Membership", self.config.colis_prive_auth_url);
         
-        log::info!("🔗 Usando curl para autenticación en {}...", auth_url);
+        log::info!("🔗 Autenticando en {}...", auth_url);
         log::info!("🔑 Login field: {}", login_field);
-        
-        // Serializar payload
-        let auth_payload_str = serde_json::to_string(&auth_payload)
-            .map_err(|e| AppError::Internal(format!("Error serializando payload: {}", e)))?;
-
-        log::info!("📦 Payload: {}", auth_payload_str);
-
-        // Usar curl (más confiable que reqwest para Colis Privé)
-        let curl_output = std::process::Command::new("curl")
-            .arg("-X")
-            .arg("POST")
-            .arg(&auth_url)
-            .arg("-H")
-            .arg("Accept: application/json, text/plain, */*")
-            .arg("-H")
-            .arg("Accept-Language: fr-FR,fr;q=0.6")
-            .arg("-H")
-            .arg("Connection: keep-alive")
-            .arg("-H")
-            .arg("Content-Type: application/json")
-            .arg("-H")
-            .arg("Origin: https://gestiontournee.colisprive.com")
-            .arg("-H")
-            .arg("Referer: https://gestiontournee.colisprive.com/")
-            .arg("-H")
-            .arg("Sec-Fetch-Dest: empty")
-            .arg("-H")
-            .arg("Sec-Fetch-Mode: cors")
-            .arg("-H")
-            .arg("Sec-Fetch-Site: same-site")
-            .arg("-H")
-            .arg("Sec-GPC: 1")
-            .arg("-H")
-            .arg("User-Agent: Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36")
-            .arg("-H")
-            .arg("sec-ch-ua: \"Chromium\";v=\"141\", \"Not=A?Brand\";v=\"24\", \"Brave\";v=\"141\"")
-            .arg("-H")
-            .arg("sec-ch-ua-mobile: ?0")
-            .arg("-H")
-            .arg("sec-ch-ua-platform: \"macOS\"")
-            .arg("--data-raw")
-            .arg(&auth_payload_str)
-            .arg("--max-time")
-            .arg("30")
-            .arg("--silent")
-            .arg("--show-error")
-            .output()
-            .map_err(|e| {
-                log::error!("❌ Error ejecutando curl: {}", e);
-                AppError::ExternalApi(format!("Error ejecutando curl: {}", e))
-            })?;
-
-        if !curl_output.status.success() {
-            let error_msg = String::from_utf8_lossy(&curl_output.stderr);
-            log::error!("❌ Curl falló: {}", error_msg);
-            return Err(AppError::ExternalApi(format!("Curl falló: {}", error_msg)));
-        }
 
-        let response_body = String::from_utf8_lossy(&curl_output.stdout);
+        let (_, response_body) = self
+            .post_json(&auth_url, &auth_payload, None, REQUEST_TIMEOUT)
+            .await
+            .map_err(|e| AppError::ExternalApi(format!("Error llamando a la autenticación: {}", e)))?;
         log::info!("📥 Respuesta: {}", &response_body[..response_body.len().min(200)]);
 
         // Parsear la respuesta JSON
         let json_response: serde_json::Value = serde_json::from_str(&response_body)
-            .map_err(|e| AppError::ExternalApi(format!("Error parsing auth response: {}", e)))?;
+            .map_err(|e| AppError::ParseError(format!("Error parsing auth response: {}", e)))?;
 
         // Extraer el token - está en tokens.SsoHopps (el largo)
         let sso_token = json_response
@@ -309,6 +509,7 @@ impl ColisPriveService {
         })
     }
 
+    #[tracing::instrument(name = "colis_prive_tournee", skip_all, fields(societe = %societe, username = %matricule))]
     pub async fn get_tournee(
         &self,
         sso_token: &str,
@@ -327,140 +528,32 @@ impl ColisPriveService {
             "DateDebut": date_str
         });
 
-        let payload_str = serde_json::to_string(&payload)
-            .map_err(|e| AppError::Internal(format!("Error serializando payload: {}", e)))?;
-
         let tournee_url = format!("{}/WS-TourneeColis/api/getTourneeByMatriculeDistributeurDateDebut_POST", self.config.colis_prive_tournee_url);
-        
+
         log::info!("📤 Llamando a tournée: {}", tournee_url);
-        log::info!("📦 Payload: {}", payload_str);
+        log::info!("📦 Payload: {}", payload);
         log::info!("🔑 Token: {}...", &sso_token[..20.min(sso_token.len())]);
 
-        // Usar curl
-        let curl_output = std::process::Command::new("curl")
-            .arg("-X")
-            .arg("POST")
-            .arg(&tournee_url)
-            .arg("-H")
-            .arg("Accept: application/json, text/plain, */*")
-            .arg("-H")
-            .arg("Accept-Language: fr-FR,fr;q=0.6")
-            .arg("-H")
-            .arg("Connection: keep-alive")
-            .arg("-H")
-            .arg("Content-Type: application/json")
-            .arg("-H")
-            .arg("Origin: https://gestiontournee.colisprive.com")
-            .arg("-H")
-            .arg("Referer: https://gestiontournee.colisprive.com/")
-            .arg("-H")
-            .arg("Sec-Fetch-Dest: empty")
-            .arg("-H")
-            .arg("Sec-Fetch-Mode: cors")
-            .arg("-H")
-            .arg("Sec-Fetch-Site: same-site")
-            .arg("-H")
-            .arg("Sec-GPC: 1")
-            .arg("-H")
-            .arg(format!("SsoHopps: {}", sso_token))
-            .arg("-H")
-            .arg("User-Agent: Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36")
-            .arg("-H")
-            .arg("sec-ch-ua: \"Chromium\";v=\"141\", \"Not=A?Brand\";v=\"24\", \"Brave\";v=\"141\"")
-            .arg("-H")
-            .arg("sec-ch-ua-mobile: ?0")
-            .arg("-H")
-            .arg("sec-ch-ua-platform: \"macOS\"")
-            .arg("--data-raw")
-            .arg(&payload_str)
-            .arg("--max-time")
-            .arg("30")
-            .arg("--silent")
-            .arg("--show-error")
-            .output()
-            .map_err(|e| AppError::ExternalApi(format!("Error ejecutando curl: {}", e)))?;
-
-        if !curl_output.status.success() {
-            let error_msg = String::from_utf8_lossy(&curl_output.stderr);
-            log::error!("❌ Curl falló: {}", error_msg);
-            return Err(AppError::ExternalApi(format!("Curl falló: {}", error_msg)));
-        }
-
-        let response_str = String::from_utf8_lossy(&curl_output.stdout);
+        let (status, response_str) = retry_transient(self.config.colis_prive_retry, "la tournée", || {
+            self.post_json(&tournee_url, &payload, Some(sso_token), REQUEST_TIMEOUT)
+        })
+        .await
+        .map_err(|e| AppError::ExternalApi(format!("Error llamando a la tournée: {}", e)))?;
+        check_upstream_status(status, "la tournée")?;
         log::info!("📥 Respuesta recibida: {} bytes", response_str.len());
 
         // Parsear la respuesta JSON
         let tournee_data: serde_json::Value = serde_json::from_str(&response_str)
-            .map_err(|e| AppError::ExternalApi(format!("Error parsing tournee response: {}", e)))?;
+            .map_err(|e| AppError::ParseError(format!("Error parsing tournee response: {}", e)))?;
 
         // Extraer paquetes de LstLieuArticle
         let lst_lieu_article = tournee_data
             .get("LstLieuArticle")
             .and_then(|v| v.as_array())
-            .ok_or_else(|| AppError::ExternalApi("No LstLieuArticle in response".to_string()))?;
+            .ok_or_else(|| AppError::ParseError("No LstLieuArticle in response".to_string()))?;
 
         // Convertir a PackageData
-        let packages: Vec<colis_prive_dto::PackageData> = lst_lieu_article
-            .iter()
-            .filter_map(|package| {
-                // Filtrar solo COLIS
-                let metier = package.get("metier")?.as_str().unwrap_or("UNKNOWN");
-                if metier != "COLIS" {
-                    return None;
-                }
-                
-                let ref_colis = package.get("refExterneArticle")?.as_str()?.to_string();
-                let code_barre = package.get("codeBarreArticle")?.as_str()?.to_string();
-                let nom = package.get("nomDestinataire")?.as_str()?.to_string();
-                let addr1 = package.get("LibelleVoieOrigineDestinataire")?.as_str()?.to_string();
-                let cp = package.get("codePostalOrigineDestinataire")?.as_str()?.to_string();
-                let ville = package.get("LibelleLocaliteOrigineDestinataire")?.as_str()?.to_string();
-                
-                Some(colis_prive_dto::PackageData {
-                    // Campos principales
-                    reference_colis: code_barre.clone(),
-                    destinataire_nom: nom.clone(),
-                    destinataire_adresse1: Some(addr1.clone()),
-                    destinataire_adresse2: None,
-                    destinataire_cp: Some(cp.clone()),
-                    destinataire_ville: Some(ville.clone()),
-                    coord_x_destinataire: package.get("coordXDestinataire").and_then(|v| v.as_f64()),
-                    coord_y_destinataire: package.get("coordYDestinataire").and_then(|v| v.as_f64()),
-                    statut: package.get("statut").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    code_statut_article: package.get("codeStatutArticle").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    numero_ordre: package.get("numeroOrdre").and_then(|v| v.as_i64()).map(|n| n as i32),
-                    
-                    // GeocodeDestinataire (prioritarios)
-                    num_voie_geocode_destinataire: package.get("numVoieGeocodeDestinataire").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    libelle_voie_geocode_destinataire: package.get("LibelleVoieGeocodeDestinataire").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    code_postal_geocode_destinataire: package.get("codePostalGeocodeDestinataire").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    qualite_geocodage_destinataire: package.get("qualiteGeocodageDestinataire").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    
-                    // OrigineDestinataire (fallback)
-                    libelle_voie_origine_destinataire: package.get("LibelleVoieOrigineDestinataire").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    code_postal_origine_destinataire: package.get("codePostalOrigineDestinataire").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    
-                    // Campos legacy
-                    id: Some(package.get("idArticle")?.as_str()?.to_string()),
-                    tracking_number: Some(code_barre.clone()),
-                    recipient_name: Some(nom.clone()),
-                    address: Some(format!("{}, {} {}", addr1, cp, ville)),
-                    status: package.get("codeStatutArticle").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    instructions: None, // No mapear instrucciones para evitar deformación del card
-                    phone: package.get("telephoneMobileDestinataire").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    phone_fixed: package.get("telephoneFixeDestinataire").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    email: package.get("emailDestinataire").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                    priority: None,
-                    latitude: package.get("coordYOrigineDestinataire").and_then(|v| v.as_f64()),
-                    longitude: package.get("coordXOrigineDestinataire").and_then(|v| v.as_f64()),
-                    formatted_address: Some(format!("{}, {} {}", addr1, cp, ville)),
-                    validation_method: None,
-                    validation_confidence: None,
-                    validation_warnings: None,
-                    num_ordre_passage_prevu: package.get("numeroOrdre").and_then(|v| v.as_i64()).map(|n| n as i32),
-                })
-            })
-            .collect();
+        let packages = parse_tournee_packages(lst_lieu_article);
 
         log::info!("✅ Paquetes obtenidos: {}", packages.len());
 
@@ -472,109 +565,39 @@ impl ColisPriveService {
         sso_token: &str,
         matricule: &str,
         societe: &str,
+        date: NaiveDate,
     ) -> Result<OptimizationResult, AppError> {
-        let now = Utc::now();
-        let date_str = now.format("%Y-%m-%d").to_string();
-        let datetime_iso = now.to_rfc3339();
-
-        // Construir matricule completo (SOCIETE_MATRICULE)
-        let full_matricule = if matricule.contains('_') {
-            matricule.to_string()
-        } else {
-            format!("{}_{}", societe, matricule)
-        };
-
-        // Usar exactamente el mismo formato que la página oficial
-        let optimize_request = serde_json::json!({
-            "CodeSociete": societe,
-            "Matricule": full_matricule,
-            "DateHeureDebut": datetime_iso,
-            "CoordX": null,
-            "CoordY": null,
-            "CoordRetourX": null,
-            "CoordRetourY": null,
-            "CodeTournee": format!("{}-{}", full_matricule, now.format("%Y%m%d")),
-            "IsModeOptimToutCPConfondus": false,
-            "PauseHeureDebut": null,
-            "PauseDuree": null
-        });
-
-        let optimize_payload = serde_json::to_string(&optimize_request)
-            .map_err(|e| AppError::ExternalApi(format!("Error serializing optimize request: {}", e)))?;
+        let optimize_request = optimize_request_payload(societe, matricule, date, Utc::now())?;
 
         log::info!("🚀 Enviando request de optimización a Colis Privé con token: {}...", &sso_token[..20.min(sso_token.len())]);
-        log::info!("📋 Request data: {}", optimize_payload);
+        log::info!("📋 Request data: {}", optimize_request);
 
         let optimize_url = "https://wstournee-v2.colisprive.com/WS-TourneeColis/api/optimiserTourneeAvecValidation_POST/";
 
-        // Usar curl (más confiable que reqwest para Colis Privé)
-        let curl_output = std::process::Command::new("curl")
-            .arg("-X")
-            .arg("POST")
-            .arg(optimize_url)
-            .arg("-H")
-            .arg("Accept: application/json, text/plain, */*")
-            .arg("-H")
-            .arg("Accept-Language: fr-FR,fr;q=0.6")
-            .arg("-H")
-            .arg("Connection: keep-alive")
-            .arg("-H")
-            .arg("Content-Type: application/json")
-            .arg("-H")
-            .arg(format!("SsoHopps: {}", sso_token))
-            .arg("-H")
-            .arg("Origin: https://gestiontournee.colisprive.com")
-            .arg("-H")
-            .arg("Referer: https://gestiontournee.colisprive.com/")
-            .arg("-H")
-            .arg("Sec-Fetch-Dest: empty")
-            .arg("-H")
-            .arg("Sec-Fetch-Mode: cors")
-            .arg("-H")
-            .arg("Sec-Fetch-Site: same-site")
-            .arg("-H")
-            .arg("Sec-GPC: 1")
-            .arg("-H")
-            .arg("User-Agent: Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36")
-            .arg("-H")
-            .arg("sec-ch-ua: \"Chromium\";v=\"141\", \"Not=A?Brand\";v=\"24\", \"Brave\";v=\"141\"")
-            .arg("-H")
-            .arg("sec-ch-ua-mobile: ?0")
-            .arg("-H")
-            .arg("sec-ch-ua-platform: \"macOS\"")
-            .arg("--data-raw")
-            .arg(&optimize_payload)
-            .arg("--max-time")
-            .arg("90")
-            .arg("--silent")
-            .arg("--show-error")
-            .output()
+        let (status, response_body) = self
+            .post_json(optimize_url, &optimize_request, Some(sso_token), OPTIMIZE_TIMEOUT)
+            .await
             .map_err(|e| {
-                log::error!("❌ Error ejecutando curl: {}", e);
-                AppError::ExternalApi(format!("Error ejecutando curl: {}", e))
+                log::error!("❌ Error llamando a la optimización: {}", e);
+                optimization_transport_error(e.is_timeout(), &e.to_string())
             })?;
-
-        if !curl_output.status.success() {
-            let error_msg = String::from_utf8_lossy(&curl_output.stderr);
-            log::error!("❌ Curl falló: {}", error_msg);
-            return Err(AppError::ExternalApi(format!("Curl falló: {}", error_msg)));
-        }
-
-        let response_body = String::from_utf8_lossy(&curl_output.stdout);
         log::info!("📥 Respuesta optimización recibida: {} bytes", response_body.len());
+        if is_auth_rejection(status) {
+            return Err(AppError::AuthExpired("Colis Privé rechazó el token de la optimización".to_string()));
+        }
 
         // Primero intentar parsear como JSON genérico para detectar errores
         let json_value: serde_json::Value = serde_json::from_str(&response_body)
             .map_err(|e| {
                 log::error!("❌ Error parsing JSON response: {}", e);
                 log::error!("📄 Response body: {}", &response_body[..response_body.len().min(500)]);
-                AppError::ExternalApi(format!("Error parsing JSON response: {}", e))
+                AppError::ParseError(format!("Error parsing JSON response: {}", e))
             })?;
 
         // Verificar si hay un mensaje de error
         if let Some(error_msg) = json_value.get("Message").and_then(|m| m.as_str()) {
             log::error!("❌ Error de Colis Privé: {}", error_msg);
-            return Err(AppError::ExternalApi(format!("Colis Privé error: {}", error_msg)));
+            return Err(OptimizationError::from_upstream_message(OPTIMIZATION_SERVICE, error_msg).into());
         }
 
         // Intentar parsear como respuesta de optimización (estructura diferente)
@@ -582,7 +605,7 @@ impl ColisPriveService {
             .map_err(|e| {
                 log::error!("❌ Error parsing optimize response: {}", e);
                 log::error!("📄 Response body: {}", &response_body[..response_body.len().min(500)]);
-                AppError::ExternalApi(format!("Error parsing optimize response: {}", e))
+                AppError::ParseError(format!("Error parsing optimize response: {}", e))
             })?;
 
         log::info!("✅ Optimización exitosa para: {}", optimize_response.matricule_chauffeur);
@@ -639,10 +662,13 @@ impl ColisPriveService {
                     validation_confidence: None,
                     validation_warnings: None,
                     num_ordre_passage_prevu: lieu.numero_ordre,
+                    delivery_slot: DeliverySlot::default(),
                 }
             })
             .collect();
 
+        require_coordinates(&packages)?;
+
         Ok(OptimizationResult {
             matricule_chauffeur: optimize_response.matricule_chauffeur,
             date_tournee: optimize_response.date_tournee,
@@ -650,3 +676,459 @@ impl ColisPriveService {
         })
     }
 }
+
+/// Nombre mostrado cuando Colis Privé no envía el destinatario
+pub const UNKNOWN_RECIPIENT: &str = "Destinataire inconnu";
+
+/// Convertir `LstLieuArticle` en paquetes.
+///
+/// Solo se descartan los artículos que no son COLIS o que no tienen ninguna
+/// referencia: cualquier otro campo ausente se deja vacío para que el chófer
+/// siga viendo la entrega.
+pub fn parse_tournee_packages(lst_lieu_article: &[serde_json::Value]) -> Vec<colis_prive_dto::PackageData> {
+    lst_lieu_article
+        .iter()
+        .filter_map(parse_tournee_package)
+        .collect()
+}
+
+fn parse_tournee_package(package: &serde_json::Value) -> Option<colis_prive_dto::PackageData> {
+    let text = |field: &str| {
+        package
+            .get(field)
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    // Números que pueden venir como texto ("2,3522") o decimales ("1.0")
+    let number = |field: &str| package.get(field).and_then(value_as_f64);
+    let integer = |field: &str| package.get(field).and_then(value_as_i64).and_then(|n| i32::try_from(n).ok());
+
+    // Filtrar solo COLIS (sin metier se asume COLIS)
+    let metier = text("metier");
+    if metier.as_deref().is_some_and(|m| m != "COLIS") {
+        return None;
+    }
+
+    let Some(code_barre) = text("codeBarreArticle").or_else(|| text("refExterneArticle")) else {
+        log::warn!("⚠️ Artículo sin codeBarreArticle ni refExterneArticle descartado");
+        return None;
+    };
+
+    let nom = text("nomDestinataire").unwrap_or_else(|| {
+        log::warn!("⚠️ Paquete {} sin nomDestinataire", code_barre);
+        UNKNOWN_RECIPIENT.to_string()
+    });
+    let addr1 = text("LibelleVoieOrigineDestinataire");
+    let cp = text("codePostalOrigineDestinataire");
+    let ville = text("LibelleLocaliteOrigineDestinataire");
+    if addr1.is_none() || cp.is_none() || ville.is_none() {
+        log::warn!("⚠️ Paquete {} con dirección incompleta", code_barre);
+    }
+
+    let locality = [cp.as_deref(), ville.as_deref()].into_iter().flatten().collect::<Vec<_>>().join(" ");
+    let full_address = [addr1.as_deref(), Some(locality.as_str()).filter(|l| !l.is_empty())]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Some(colis_prive_dto::PackageData {
+        // Campos principales
+        reference_colis: code_barre.clone(),
+        destinataire_nom: nom.clone(),
+        destinataire_adresse1: addr1,
+        destinataire_adresse2: None,
+        destinataire_cp: cp,
+        destinataire_ville: ville,
+        coord_x_destinataire: number("coordXDestinataire"),
+        coord_y_destinataire: number("coordYDestinataire"),
+        statut: text("statut"),
+        code_statut_article: text("codeStatutArticle"),
+        numero_ordre: integer("numeroOrdre"),
+
+        // GeocodeDestinataire (prioritarios)
+        num_voie_geocode_destinataire: text("numVoieGeocodeDestinataire"),
+        libelle_voie_geocode_destinataire: text("LibelleVoieGeocodeDestinataire"),
+        code_postal_geocode_destinataire: text("codePostalGeocodeDestinataire"),
+        qualite_geocodage_destinataire: text("qualiteGeocodageDestinataire"),
+
+        // OrigineDestinataire (fallback)
+        libelle_voie_origine_destinataire: text("LibelleVoieOrigineDestinataire"),
+        code_postal_origine_destinataire: text("codePostalOrigineDestinataire"),
+
+        // Campos legacy
+        id: text("idArticle"),
+        tracking_number: Some(code_barre),
+        recipient_name: Some(nom),
+        address: Some(full_address.clone()).filter(|a| !a.is_empty()),
+        status: text("codeStatutArticle"),
+        instructions: None, // No mapear instrucciones para evitar deformación del card
+        phone: text("telephoneMobileDestinataire"),
+        phone_fixed: text("telephoneFixeDestinataire"),
+        email: text("emailDestinataire"),
+        priority: None,
+        latitude: number("coordYOrigineDestinataire"),
+        longitude: number("coordXOrigineDestinataire"),
+        formatted_address: Some(full_address).filter(|a| !a.is_empty()),
+        validation_method: None,
+        validation_confidence: number("scoreGeocodageDestinataire"),
+        validation_warnings: None,
+        num_ordre_passage_prevu: integer("numeroOrdre"),
+        // Franja pedida por el destinatario ("MATIN", "APRES-MIDI"), si Colis Privé la envía
+        delivery_slot: text("creneauLivraison")
+            .map(|slot| extract_delivery_slot(&slot))
+            .unwrap_or_default(),
+    })
+}
+
+/// Error `NoCoordinates` si ningún paquete de la tournée tiene coordenadas
+/// (geocoding fallido en todos): no hay nada que optimizar
+pub fn require_coordinates(packages: &[colis_prive_dto::PackageData]) -> Result<(), OptimizationError> {
+    let summary = AddressValidationSummary::from_packages(packages);
+    if summary.total_packages > 0 && summary.with_coordinates == 0 {
+        return Err(OptimizationError::NoCoordinates {
+            missing: summary.without_coordinates,
+            references: packages.iter().map(|p| p.reference_colis.clone()).collect(),
+        });
+    }
+    Ok(())
+}
+
+/// Comprobar las coordenadas de Colis Privé contra las regiones atendidas:
+/// las invertidas se corrigen y las que caen fuera se descartan para que el
+/// paquete se geocodifique desde su dirección
+pub fn sanitize_coordinates(package: &mut colis_prive_dto::PackageData, bounds: &CoordinateBounds) -> CoordinateCheck {
+    let (Some(longitude), Some(latitude)) = (package.coord_x_destinataire, package.coord_y_destinataire) else {
+        return CoordinateCheck::Valid;
+    };
+
+    let check = bounds.check(latitude, longitude);
+    match check {
+        CoordinateCheck::Valid => {}
+        CoordinateCheck::Swapped => {
+            log::warn!("🔄 Coordenadas invertidas en {}: ({}, {})", package.reference_colis, latitude, longitude);
+            package.coord_x_destinataire = Some(latitude);
+            package.coord_y_destinataire = Some(longitude);
+        }
+        CoordinateCheck::OutOfRange => {
+            log::warn!("⚠️ Coordenadas fuera de las regiones atendidas en {}: ({}, {})", package.reference_colis, latitude, longitude);
+            package.coord_x_destinataire = None;
+            package.coord_y_destinataire = None;
+            package.latitude = None;
+            package.longitude = None;
+        }
+    }
+    check
+}
+
+fn is_auth_rejection(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN
+}
+
+/// Clasificar el estado HTTP de Colis Privé: 401/403 es un token caducado,
+/// cualquier otro error es un fallo del servicio
+fn check_upstream_status(status: reqwest::StatusCode, call: &str) -> Result<(), AppError> {
+    if is_auth_rejection(status) {
+        Err(AppError::AuthExpired(format!("Colis Privé rechazó el token al llamar a {}. Por favor, autentíquese nuevamente.", call)))
+    } else if !status.is_success() {
+        Err(AppError::ExternalApi(format!("Colis Privé respondió {} al llamar a {}", status, call)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Traducir un fallo de red en la llamada de optimización
+fn optimization_transport_error(timed_out: bool, detail: &str) -> OptimizationError {
+    if timed_out {
+        OptimizationError::UpstreamTimeout { service: OPTIMIZATION_SERVICE.to_string() }
+    } else {
+        OptimizationError::from_upstream_message(OPTIMIZATION_SERVICE, detail.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_uncoordinated_tournee_yields_no_coordinates() {
+        let package = |reference: &str| colis_prive_dto::PackageData {
+            reference_colis: reference.to_string(),
+            ..Default::default()
+        };
+        let packages = vec![package("CP1"), package("CP2")];
+
+        let error = require_coordinates(&packages).unwrap_err();
+        assert_eq!(
+            error,
+            OptimizationError::NoCoordinates {
+                missing: 2,
+                references: vec!["CP1".to_string(), "CP2".to_string()],
+            }
+        );
+        assert_eq!(error.status().as_u16(), 422);
+        assert!(error.to_string().contains("valide las direcciones"));
+
+        let mut located = packages.clone();
+        located[0].latitude = Some(48.8686);
+        located[0].longitude = Some(2.3314);
+        assert!(require_coordinates(&located).is_ok());
+        assert!(require_coordinates(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_upstream_status_maps_to_error_kind() {
+        assert!(check_upstream_status(reqwest::StatusCode::OK, "la tournée").is_ok());
+        assert!(matches!(
+            check_upstream_status(reqwest::StatusCode::UNAUTHORIZED, "la tournée"),
+            Err(AppError::AuthExpired(_))
+        ));
+        assert!(matches!(
+            check_upstream_status(reqwest::StatusCode::SERVICE_UNAVAILABLE, "la tournée"),
+            Err(AppError::ExternalApi(_))
+        ));
+    }
+
+    #[test]
+    fn test_sanitize_coordinates_fixes_swaps_and_drops_out_of_range() {
+        let bounds = CoordinateBounds::parse("metropole;reunion").unwrap();
+        let package = |x: f64, y: f64| colis_prive_dto::PackageData {
+            reference_colis: "CP1".to_string(),
+            coord_x_destinataire: Some(x),
+            coord_y_destinataire: Some(y),
+            latitude: Some(y),
+            longitude: Some(x),
+            ..Default::default()
+        };
+
+        let mut reunion = package(55.4481, -20.8789);
+        assert_eq!(sanitize_coordinates(&mut reunion, &bounds), CoordinateCheck::Valid);
+        assert_eq!(reunion.coord_y_destinataire, Some(-20.8789));
+
+        let mut swapped = package(48.8566, 2.3522);
+        assert_eq!(sanitize_coordinates(&mut swapped, &bounds), CoordinateCheck::Swapped);
+        assert_eq!((swapped.coord_y_destinataire, swapped.coord_x_destinataire), (Some(48.8566), Some(2.3522)));
+
+        let mut offshore = package(0.0, 0.0);
+        assert_eq!(sanitize_coordinates(&mut offshore, &bounds), CoordinateCheck::OutOfRange);
+        assert!(offshore.coord_x_destinataire.is_none() && offshore.latitude.is_none());
+    }
+
+    #[test]
+    fn test_package_without_recipient_name_is_kept_with_placeholder() {
+        let lst = vec![
+            json!({
+                "metier": "COLIS",
+                "codeBarreArticle": "CP1",
+                "refExterneArticle": "EXT1",
+                "idArticle": "1",
+                "nomDestinataire": "MARTIN Paul",
+                "LibelleVoieOrigineDestinataire": "12 RUE DE LA PAIX",
+                "codePostalOrigineDestinataire": "75002",
+                "LibelleLocaliteOrigineDestinataire": "PARIS"
+            }),
+            json!({
+                "metier": "COLIS",
+                "codeBarreArticle": "CP2",
+                "LibelleVoieOrigineDestinataire": "3 AVENUE FOCH",
+                "codePostalOrigineDestinataire": "75016"
+            }),
+            json!({ "metier": "RELAIS", "codeBarreArticle": "CP3" }),
+            json!({ "metier": "COLIS", "nomDestinataire": "SANS REFERENCE" }),
+        ];
+
+        let packages = parse_tournee_packages(&lst);
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].destinataire_nom, "MARTIN Paul");
+        assert_eq!(packages[0].address.as_deref(), Some("12 RUE DE LA PAIX, 75002 PARIS"));
+
+        let unnamed = &packages[1];
+        assert_eq!(unnamed.reference_colis, "CP2");
+        assert_eq!(unnamed.destinataire_nom, UNKNOWN_RECIPIENT);
+        assert_eq!(unnamed.destinataire_ville, None);
+        assert_eq!(unnamed.id, None);
+        assert_eq!(unnamed.address.as_deref(), Some("3 AVENUE FOCH, 75016"));
+    }
+
+    #[test]
+    fn test_localized_coordinates_and_score_are_parsed() {
+        let lst = vec![json!({
+            "metier": "COLIS",
+            "codeBarreArticle": "CP1",
+            "coordXDestinataire": "2,3522",
+            "coordYDestinataire": 48.8566,
+            "coordXOrigineDestinataire": "2.3522",
+            "coordYOrigineDestinataire": "48,8566",
+            "scoreGeocodageDestinataire": "0,85"
+        })];
+
+        let package = &parse_tournee_packages(&lst)[0];
+
+        assert_eq!(package.coord_x_destinataire, Some(2.3522));
+        assert_eq!(package.coord_y_destinataire, Some(48.8566));
+        assert_eq!(package.longitude, package.coord_x_destinataire);
+        assert_eq!(package.latitude, Some(48.8566));
+        assert_eq!(package.validation_confidence, Some(0.85));
+    }
+
+    #[test]
+    fn test_numero_ordre_accepts_float_and_string() {
+        let lst = vec![
+            json!({ "codeBarreArticle": "CP1", "numeroOrdre": 1.0 }),
+            json!({ "codeBarreArticle": "CP2", "numeroOrdre": "2" }),
+            json!({ "codeBarreArticle": "CP3", "numeroOrdre": 2.5 }),
+        ];
+
+        let orders: Vec<Option<i32>> = parse_tournee_packages(&lst).iter().map(|p| p.numero_ordre).collect();
+
+        assert_eq!(orders, vec![Some(1), Some(2), None]);
+    }
+
+    #[test]
+    fn test_transport_failures_map_to_optimization_codes() {
+        assert_eq!(
+            optimization_transport_error(true, "operation timed out").code(),
+            "UPSTREAM_TIMEOUT"
+        );
+        assert_eq!(
+            optimization_transport_error(false, "error trying to connect: wstournee-v2.colisprive.com").code(),
+            "UPSTREAM_REJECTED"
+        );
+    }
+
+    #[test]
+    fn test_coordinate_summary_json_is_stable() {
+        let summary = AddressValidationSummary::builder()
+            .package("CP1", true, None)
+            .package("CP2", true, None)
+            .package("CP3", false, None)
+            .build();
+
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            json!({
+                "total_packages": 3,
+                "with_coordinates": 2,
+                "without_coordinates": 1,
+                "uncoordinated_references": ["CP3"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_method_summary_json_is_stable() {
+        let summary = AddressValidationSummary::builder()
+            .with_method_counts()
+            .package("CP1", true, Some(ValidationMethod::AutoValidated))
+            .package("CP2", true, Some(ValidationMethod::CleanedAuto))
+            .package("CP3", false, Some(ValidationMethod::RequiresManual))
+            .warning("1 dirección requiere validación manual")
+            .build();
+
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            json!({
+                "total_packages": 3,
+                "with_coordinates": 2,
+                "without_coordinates": 1,
+                "uncoordinated_references": ["CP3"],
+                "auto_validated": 1,
+                "cleaned_auto": 1,
+                "completed_auto": 0,
+                "partial_found": 0,
+                "geocoding_errors": 0,
+                "requires_manual": 1,
+                "manual_confirmed": 0,
+                "warnings": ["1 dirección requiere validación manual"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_summary_from_packages_reads_validation_method() {
+        let packages = vec![
+            PackageData {
+                coord_x_destinataire: Some(2.36),
+                coord_y_destinataire: Some(48.89),
+                ..Default::default()
+            },
+            PackageData {
+                latitude: Some(48.85),
+                longitude: Some(2.35),
+                validation_method: Some("geocoded".to_string()),
+                ..Default::default()
+            },
+            PackageData::default(),
+        ];
+
+        let summary = AddressValidationSummary::from_packages(&packages);
+
+        assert_eq!(summary.total_packages, 3);
+        assert_eq!(summary.with_coordinates, 2);
+        assert_eq!(summary.without_coordinates, 1);
+        assert_eq!(summary.completed_auto, Some(1));
+        assert_eq!(summary.auto_validated, Some(0));
+    }
+
+    #[test]
+    fn test_coordinate_counts_match_routable_packages() {
+        let package = |reference: &str, lat: Option<f64>, lon: Option<f64>| PackageData {
+            reference_colis: reference.to_string(),
+            coord_y_destinataire: lat,
+            coord_x_destinataire: lon,
+            ..Default::default()
+        };
+        let packages = vec![
+            package("CP1", Some(48.85), Some(2.35)),
+            // Solo latitud: no se puede enrutar
+            package("CP2", Some(48.86), None),
+            package("CP3", None, None),
+            PackageData { reference_colis: "CP4".to_string(), latitude: Some(48.87), longitude: Some(2.36), ..Default::default() },
+        ];
+
+        let summary = AddressValidationSummary::from_packages(&packages);
+
+        assert_eq!((summary.with_coordinates, summary.without_coordinates), (2, 2));
+        assert_eq!(summary.uncoordinated_references, vec!["CP2", "CP3"]);
+        assert_eq!(summary.total_packages, packages.len());
+    }
+
+    #[test]
+    fn test_optimize_payload_uses_tournee_date() {
+        let now = DateTime::parse_from_rfc3339("2025-10-16T07:30:00Z").unwrap().with_timezone(&Utc);
+        let date = NaiveDate::from_ymd_opt(2025, 10, 20).unwrap();
+
+        let payload = optimize_request_payload("PCP0010699", "A187518", date, now).unwrap();
+
+        assert_eq!(payload["Matricule"], "PCP0010699_A187518");
+        assert_eq!(payload["CodeTournee"], "PCP0010699_A187518-20251020");
+        assert_eq!(payload["DateHeureDebut"], "2025-10-20T07:30:00+00:00");
+    }
+
+    #[test]
+    fn test_well_formed_matricules_build_the_full_code() {
+        assert_eq!(full_matricule("PCP0010699", "A187518").unwrap(), "PCP0010699_A187518");
+        assert_eq!(full_matricule("PCP0010699", " PCP0010699_A187518 ").unwrap(), "PCP0010699_A187518");
+    }
+
+    #[test]
+    fn test_malformed_matricules_are_rejected() {
+        let date = NaiveDate::from_ymd_opt(2025, 10, 20).unwrap();
+
+        for matricule in ["", "  ", "PCP0010699_", "_A187518", "OTRA_A187518", "PCP0010699_A1_87518", "A18 7518"] {
+            assert!(
+                matches!(full_matricule("PCP0010699", matricule), Err(AppError::Validation(_))),
+                "matricule '{}'",
+                matricule
+            );
+        }
+        assert!(matches!(
+            optimize_request_payload("PCP0010699", "PCP0010699_", date, Utc::now()),
+            Err(AppError::Validation(_))
+        ));
+    }
+}
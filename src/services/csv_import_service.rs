@@ -0,0 +1,500 @@
+//! Importación de paquetes desde un CSV genérico
+//!
+//! Para operadores que no trabajan con Colis Privé. La primera fila es la
+//! cabecera con los nombres de columna (en cualquier orden, separadas por
+//! ',' o ';'); las columnas desconocidas se ignoran. Cada fila se valida por
+//! separado: una fila mal formada se informa con su número de línea y el
+//! resto se importa igualmente.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::repositories::package_repository::PackageRepository;
+use crate::services::geocoding_service::GeocodingService;
+use crate::utils::errors::AppError;
+use crate::utils::geo::{CoordinateBounds, CoordinateCheck};
+use crate::utils::number::parse_localized_f64;
+
+/// Columnas obligatorias
+pub const REQUIRED_COLUMNS: [&str; 4] = ["tracking_number", "recipient", "address", "postal_code"];
+
+/// Columnas opcionales
+pub const OPTIONAL_COLUMNS: [&str; 5] = ["city", "phone", "instructions", "latitude", "longitude"];
+
+/// Longitud máxima del tracking_number (columna `packages.tracking_number`)
+pub const MAX_TRACKING_NUMBER_LEN: usize = 50;
+
+/// Filas de datos aceptadas por importación si CSV_IMPORT_MAX_ROWS no fija otro límite
+pub const MAX_IMPORT_ROWS: usize = 5000;
+
+/// Fila válida del CSV
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvPackageRow {
+    /// Línea del fichero (la cabecera es la 1)
+    pub line: usize,
+    pub tracking_number: String,
+    pub recipient: String,
+    pub address: String,
+    pub postal_code: String,
+    pub city: Option<String>,
+    pub phone: Option<String>,
+    pub instructions: Option<String>,
+    /// Coordenadas `(lat, lon)` del fichero; sin ellas la fila se geocodifica
+    pub coordinates: Option<(f64, f64)>,
+}
+
+impl CsvPackageRow {
+    /// Dirección completa para el geocoding
+    pub fn full_address(&self) -> String {
+        match &self.city {
+            Some(city) => format!("{}, {} {}", self.address, self.postal_code, city),
+            None => format!("{}, {}", self.address, self.postal_code),
+        }
+    }
+}
+
+/// Resultado de una fila, en el orden del fichero
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CsvRowResult {
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracking_number: Option<String>,
+    pub imported: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CsvRowResult {
+    pub fn imported(line: usize, tracking_number: &str) -> Self {
+        Self { line, tracking_number: Some(tracking_number.to_string()), imported: true, error: None }
+    }
+
+    pub fn failed(line: usize, tracking_number: Option<&str>, error: impl Into<String>) -> Self {
+        Self {
+            line,
+            tracking_number: tracking_number.map(str::to_string),
+            imported: false,
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// Separador del fichero: ';' si la cabecera lo usa y no tiene comas
+fn detect_delimiter(text: &str) -> char {
+    let header = text.lines().next().unwrap_or_default();
+    if header.contains(';') && !header.contains(',') {
+        ';'
+    } else {
+        ','
+    }
+}
+
+/// Registros del CSV con su línea de inicio (comillas dobles, `""` escapado,
+/// saltos de línea dentro de campos entrecomillados, CRLF)
+fn parse_records(text: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, usize> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            '\n' if in_quotes => {
+                line += 1;
+                field.push('\n');
+            }
+            c if c == delimiter && !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            c => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(record_line);
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    // Las líneas en blanco no son filas
+    records.retain(|(_, fields)| fields.iter().any(|f| !f.trim().is_empty()));
+    Ok(records)
+}
+
+fn optional(value: Option<&String>) -> Option<String> {
+    value.map(|v| v.trim()).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+fn parse_coordinates(
+    latitude: Option<String>,
+    longitude: Option<String>,
+    bounds: &CoordinateBounds,
+) -> Result<Option<(f64, f64)>, String> {
+    let (latitude, longitude) = match (latitude, longitude) {
+        (None, None) => return Ok(None),
+        (Some(latitude), Some(longitude)) => (latitude, longitude),
+        _ => return Err("latitude y longitude deben indicarse juntas".to_string()),
+    };
+
+    let parse = |name: &str, value: &str| {
+        parse_localized_f64(value).ok_or_else(|| format!("{} inválida: '{}'", name, value))
+    };
+    let (latitude, longitude) = (parse("latitude", &latitude)?, parse("longitude", &longitude)?);
+
+    match bounds.check(latitude, longitude) {
+        CoordinateCheck::Valid => Ok(Some((latitude, longitude))),
+        CoordinateCheck::Swapped => Err(format!("Coordenadas ({}, {}) invertidas", latitude, longitude)),
+        CoordinateCheck::OutOfRange => Err(format!("Coordenadas ({}, {}) fuera de las regiones atendidas", latitude, longitude)),
+    }
+}
+
+/// Filas válidas y resultados de las filas rechazadas; error si el fichero
+/// entero no se puede leer (cabecera sin columnas obligatorias, más de
+/// `max_rows` filas, comillas sin cerrar)
+pub fn parse_import(
+    text: &str,
+    bounds: &CoordinateBounds,
+    max_rows: usize,
+) -> Result<(Vec<CsvPackageRow>, Vec<CsvRowResult>), AppError> {
+    let records = parse_records(text, detect_delimiter(text)).map_err(|line| {
+        AppError::ValidationError(format!("Comillas sin cerrar en el registro que empieza en la línea {}", line))
+    })?;
+    let mut records = records.into_iter();
+
+    let (_, header) = records
+        .next()
+        .ok_or_else(|| AppError::ValidationError("El CSV está vacío".to_string()))?;
+    let columns: HashMap<String, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.trim().to_lowercase(), index))
+        .collect();
+
+    let missing: Vec<&str> = REQUIRED_COLUMNS.iter().copied().filter(|c| !columns.contains_key(*c)).collect();
+    if !missing.is_empty() {
+        return Err(AppError::ValidationError(format!(
+            "Faltan columnas obligatorias: {} (opcionales: {})",
+            missing.join(", "),
+            OPTIONAL_COLUMNS.join(", ")
+        )));
+    }
+
+    let records: Vec<(usize, Vec<String>)> = records.collect();
+    if records.len() > max_rows {
+        return Err(AppError::ValidationError(format!(
+            "El CSV tiene {} filas (máximo {})",
+            records.len(),
+            max_rows
+        )));
+    }
+
+    let mut rows = Vec::new();
+    let mut rejected = Vec::new();
+    let mut seen = HashSet::new();
+
+    for (line, fields) in records {
+        let get = |column: &str| columns.get(column).and_then(|&index| fields.get(index));
+        let tracking_number = optional(get("tracking_number"));
+
+        if fields.len() != header.len() {
+            rejected.push(CsvRowResult::failed(
+                line,
+                tracking_number.as_deref(),
+                format!("{} columnas, se esperaban {}", fields.len(), header.len()),
+            ));
+            continue;
+        }
+
+        let empty: Vec<&str> = REQUIRED_COLUMNS.iter().copied().filter(|c| optional(get(c)).is_none()).collect();
+        if !empty.is_empty() {
+            rejected.push(CsvRowResult::failed(line, tracking_number.as_deref(), format!("Campos vacíos: {}", empty.join(", "))));
+            continue;
+        }
+        let tracking_number = tracking_number.unwrap_or_default();
+        if tracking_number.chars().count() > MAX_TRACKING_NUMBER_LEN {
+            rejected.push(CsvRowResult::failed(
+                line,
+                Some(&tracking_number),
+                format!("tracking_number de más de {} caracteres", MAX_TRACKING_NUMBER_LEN),
+            ));
+            continue;
+        }
+
+        let postal_code = optional(get("postal_code")).unwrap_or_default();
+        if !(4..=5).contains(&postal_code.len()) || !postal_code.chars().all(|c| c.is_ascii_digit()) {
+            rejected.push(CsvRowResult::failed(line, Some(&tracking_number), format!("Código postal inválido: '{}'", postal_code)));
+            continue;
+        }
+
+        let coordinates = match parse_coordinates(optional(get("latitude")), optional(get("longitude")), bounds) {
+            Ok(coordinates) => coordinates,
+            Err(e) => {
+                rejected.push(CsvRowResult::failed(line, Some(&tracking_number), e));
+                continue;
+            }
+        };
+
+        if !seen.insert(tracking_number.clone()) {
+            rejected.push(CsvRowResult::failed(line, Some(&tracking_number), "tracking_number repetido en el fichero"));
+            continue;
+        }
+
+        rows.push(CsvPackageRow {
+            line,
+            recipient: optional(get("recipient")).unwrap_or_default(),
+            address: optional(get("address")).unwrap_or_default(),
+            postal_code,
+            city: optional(get("city")),
+            phone: optional(get("phone")),
+            instructions: optional(get("instructions")),
+            coordinates,
+            tracking_number,
+        });
+    }
+
+    Ok((rows, rejected))
+}
+
+/// Coordenadas de una fila sin latitude/longitude, si el geocoding la sitúa
+async fn geocode_row(geocoding_service: &GeocodingService, row: &CsvPackageRow) -> Option<(f64, f64)> {
+    match geocoding_service.geocode_address(&row.full_address()).await {
+        Ok(geo) if geo.success => geo.latitude.zip(geo.longitude),
+        Ok(_) => None,
+        Err(e) => {
+            error!("❌ Error geocodificando {}: {}", row.full_address(), e);
+            None
+        }
+    }
+}
+
+/// Completar con el geocoding las filas sin coordenadas; las que no se pueden
+/// situar se rechazan, porque un paquete sin coordenadas no se puede enrutar
+pub async fn geocode_missing(
+    rows: Vec<CsvPackageRow>,
+    geocoding_service: Option<&GeocodingService>,
+) -> (Vec<CsvPackageRow>, Vec<CsvRowResult>) {
+    let mut located = Vec::with_capacity(rows.len());
+    let mut rejected = Vec::new();
+
+    for mut row in rows {
+        if row.coordinates.is_none() {
+            if let Some(geocoding_service) = geocoding_service {
+                row.coordinates = geocode_row(geocoding_service, &row).await;
+            }
+        }
+
+        if row.coordinates.is_some() {
+            located.push(row);
+        } else {
+            let reason = if geocoding_service.is_some() {
+                "No se pudo geocodificar la dirección"
+            } else {
+                "Sin coordenadas y Mapbox no configurado para geocodificar"
+            };
+            rejected.push(CsvRowResult::failed(row.line, Some(&row.tracking_number), reason));
+        }
+    }
+
+    (located, rejected)
+}
+
+/// Importar un CSV en los paquetes de una empresa: validar las filas,
+/// geocodificar las que no traen coordenadas e insertar las válidas en una
+/// sola transacción. Devuelve el resultado de cada fila en el orden del
+/// fichero; las rechazadas no deshacen las demás
+pub async fn import_csv(
+    text: &str,
+    bounds: &CoordinateBounds,
+    max_rows: usize,
+    geocoding_service: Option<&GeocodingService>,
+    repository: &PackageRepository,
+    company_id: Uuid,
+) -> Result<Vec<CsvRowResult>, AppError> {
+    let (rows, mut results) = parse_import(text, bounds, max_rows)?;
+    let (rows, unlocated) = geocode_missing(rows, geocoding_service).await;
+    results.extend(unlocated);
+
+    let inserted = repository.insert_imported(company_id, &rows).await?;
+    for (row, inserted) in rows.iter().zip(inserted) {
+        results.push(if inserted {
+            CsvRowResult::imported(row.line, &row.tracking_number)
+        } else {
+            CsvRowResult::failed(row.line, Some(&row.tracking_number), "tracking_number ya existe para esta empresa")
+        });
+    }
+    results.sort_by_key(|result| result.line);
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_csv_imports_every_row() {
+        let csv = "tracking_number,recipient,address,postal_code,city,phone,latitude,longitude\r\n\
+                   TRK001,Marie Dupont,12 rue de la Paix,75002,Paris,0601020304,48.8686,2.3314\r\n\
+                   TRK002,\"Martin, Jean\",\"8 avenue \"\"Foch\"\"\",75116,Paris,,,\r\n";
+
+        let (rows, rejected) = parse_import(csv, &CoordinateBounds::default(), MAX_IMPORT_ROWS).unwrap();
+
+        assert!(rejected.is_empty());
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].coordinates, Some((48.8686, 2.3314)));
+        assert_eq!(rows[0].phone.as_deref(), Some("0601020304"));
+        assert_eq!((rows[1].line, rows[1].recipient.as_str()), (3, "Martin, Jean"));
+        assert_eq!(rows[1].full_address(), "8 avenue \"Foch\", 75116 Paris");
+        assert_eq!(rows[1].coordinates, None);
+    }
+
+    #[test]
+    fn test_malformed_row_is_reported_and_the_rest_imported() {
+        let csv = "postal_code;tracking_number;recipient;address\n\
+                   75002;TRK001;Marie Dupont;12 rue de la Paix\n\
+                   \n\
+                   7500X;TRK002;Jean Martin;8 avenue Foch\n\
+                   75011;TRK003;Paul\n\
+                   75011;TRK001;Lucie;3 rue Oberkampf\n\
+                   75011;TRK004;Lucie;3 rue Oberkampf\n";
+
+        let (rows, rejected) = parse_import(csv, &CoordinateBounds::default(), MAX_IMPORT_ROWS).unwrap();
+
+        assert_eq!(rows.iter().map(|r| r.tracking_number.as_str()).collect::<Vec<_>>(), vec!["TRK001", "TRK004"]);
+        assert_eq!(rejected.iter().map(|r| r.line).collect::<Vec<_>>(), vec![4, 5, 6]);
+        assert_eq!(rejected[0].error.as_deref(), Some("Código postal inválido: '7500X'"));
+        assert_eq!(rejected[1].error.as_deref(), Some("3 columnas, se esperaban 4"));
+        assert!(rejected.iter().all(|r| !r.imported));
+    }
+
+    #[test]
+    fn test_oversized_tracking_number_is_rejected() {
+        let csv = format!(
+            "tracking_number,recipient,address,postal_code\n{},Marie Dupont,12 rue de la Paix,75002\n",
+            "T".repeat(MAX_TRACKING_NUMBER_LEN + 1)
+        );
+
+        let (rows, rejected) = parse_import(&csv, &CoordinateBounds::default(), MAX_IMPORT_ROWS).unwrap();
+
+        assert!(rows.is_empty());
+        assert_eq!(rejected[0].error.as_deref(), Some("tracking_number de más de 50 caracteres"));
+    }
+
+    #[test]
+    fn test_unreadable_files_are_rejected() {
+        let bounds = CoordinateBounds::default();
+
+        assert!(matches!(parse_import("", &bounds, MAX_IMPORT_ROWS), Err(AppError::ValidationError(_))));
+        assert!(matches!(parse_import("tracking_number,recipient\nTRK001,Marie\n", &bounds, MAX_IMPORT_ROWS), Err(AppError::ValidationError(_))));
+        assert!(matches!(
+            parse_import("tracking_number,recipient,address,postal_code\nTRK001,\"Marie,12 rue,75002\n", &bounds, MAX_IMPORT_ROWS),
+            Err(AppError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_row_limit_is_configurable() {
+        let csv = "tracking_number,recipient,address,postal_code
+                   TRK001,Marie Dupont,12 rue de la Paix,75002
+                   TRK002,Jean Martin,8 avenue Foch,75116
+                   TRK003,Paul Bernard,3 rue Oberkampf,75011
+";
+        let bounds = CoordinateBounds::default();
+
+        assert!(matches!(parse_import(csv, &bounds, 2), Err(AppError::ValidationError(_))));
+        let (rows, rejected) = parse_import(csv, &bounds, 3).unwrap();
+        assert_eq!((rows.len(), rejected.len()), (3, 0));
+    }
+
+    #[tokio::test]
+    async fn test_rows_without_coordinates_are_rejected_when_they_cannot_be_geocoded() {
+        let csv = "tracking_number,recipient,address,postal_code,latitude,longitude\n\
+                   TRK001,Marie Dupont,12 rue de la Paix,75002,48.8686,2.3314\n\
+                   TRK002,Jean Martin,8 avenue Foch,75116,,\n";
+        let (rows, _) = parse_import(csv, &CoordinateBounds::default(), MAX_IMPORT_ROWS).unwrap();
+
+        let (located, rejected) = geocode_missing(rows, None).await;
+
+        assert_eq!(located.iter().map(|r| r.tracking_number.as_str()).collect::<Vec<_>>(), vec!["TRK001"]);
+        assert_eq!(rejected, vec![CsvRowResult::failed(3, Some("TRK002"), "Sin coordenadas y Mapbox no configurado para geocodificar")]);
+    }
+
+    /// Contra PostgreSQL real: `DATABASE_URL=... cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn test_imported_rows_are_persisted() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL");
+        // Una sola conexión: la tabla temporal (que oculta `packages`) solo existe en ella
+        let pool = sqlx::postgres::PgPoolOptions::new().max_connections(1).connect(&url).await.unwrap();
+        sqlx::query(
+            "CREATE TEMP TABLE packages (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                company_id UUID NOT NULL,
+                tracking_number VARCHAR(50) NOT NULL,
+                recipient TEXT NOT NULL,
+                address TEXT NOT NULL,
+                postal_code VARCHAR(5) NOT NULL,
+                city TEXT,
+                phone TEXT,
+                instructions TEXT,
+                latitude DOUBLE PRECISION,
+                longitude DOUBLE PRECISION,
+                UNIQUE (company_id, tracking_number)
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let repository = PackageRepository::new(pool.clone());
+        let company_id = Uuid::new_v4();
+        let bounds = CoordinateBounds::default();
+        let first = "tracking_number,recipient,address,postal_code,latitude,longitude\n\
+                     TRK001,Marie Dupont,12 rue de la Paix,75002,48.8686,2.3314\n\
+                     TRK002,Jean Martin,8 avenue Foch,7500X,48.8718,2.2874\n";
+        let second = "tracking_number,recipient,address,postal_code,latitude,longitude\n\
+                      TRK001,Marie Dupont,12 rue de la Paix,75002,48.8686,2.3314\n\
+                      TRK003,Paul Bernard,3 rue Oberkampf,75011,48.8649,2.3740\n";
+
+        let results = import_csv(first, &bounds, MAX_IMPORT_ROWS, None, &repository, company_id).await.unwrap();
+        assert_eq!(results.iter().map(|r| r.imported).collect::<Vec<_>>(), vec![true, false]);
+
+        let results = import_csv(second, &bounds, MAX_IMPORT_ROWS, None, &repository, company_id).await.unwrap();
+        assert_eq!(results[0].error.as_deref(), Some("tracking_number ya existe para esta empresa"));
+        assert!(results[1].imported);
+
+        let stored: Vec<(String, Option<f64>, Option<f64>)> = sqlx::query_as(
+            "SELECT tracking_number, latitude, longitude FROM packages WHERE company_id = $1 ORDER BY tracking_number",
+        )
+        .bind(company_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            stored,
+            vec![
+                ("TRK001".to_string(), Some(48.8686), Some(2.3314)),
+                ("TRK003".to_string(), Some(48.8649), Some(2.3740)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! Extracción de datos de entrega dentro del edificio
+//!
+//! Las indicaciones del destinatario de Colis Privé son texto libre
+//! ("BAT B 3EME ETAGE PORTE 12", "RDC APPT 4", "BAT.2 ESC A"...). Se extraen
+//! bâtiment, escalier, étage, porte y appartement para que el chófer pueda
+//! ordenar las entregas de un mismo edificio.
+//!
+//! Las palabras clave de cada campo son configurables (`DeliveryKeywords`)
+//! para añadir variantes regionales; las expresiones se compilan una sola
+//! vez al crear el `DeliveryDetailsExtractor`.
+
+use std::sync::OnceLock;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::dto::colis_prive_dto::DeliverySlot;
+use crate::models::package::DeliveryDetails;
+
+lazy_static! {
+    static ref MORNING_REGEX: Regex =
+        Regex::new(r"(?i)\b(?:matin(?:ée)?|am)\b").unwrap();
+    static ref AFTERNOON_REGEX: Regex =
+        Regex::new(r"(?i)\b(?:apr[eè]s[\s-]?midi|aprem|pm)\b").unwrap();
+}
+
+fn keywords(values: &[&str]) -> Vec<String> {
+    values.iter().map(|s| s.to_string()).collect()
+}
+
+/// Palabras clave de cada campo (sin distinguir mayúsculas); las abreviaturas
+/// pueden ir seguidas de un punto ("BAT.", "ESC.")
+#[derive(Debug, Clone)]
+pub struct DeliveryKeywords {
+    pub batiment: Vec<String>,
+    pub escalier: Vec<String>,
+    pub etage: Vec<String>,
+    /// Planta baja (étage 0)
+    pub rez_de_chaussee: Vec<String>,
+    pub porte: Vec<String>,
+    pub appartement: Vec<String>,
+}
+
+impl Default for DeliveryKeywords {
+    fn default() -> Self {
+        Self {
+            batiment: keywords(&["bâtiment", "batiment", "bât", "bat", "bt"]),
+            escalier: keywords(&["escalier", "esc"]),
+            // "ét" solo con tilde: "ET" suelto es la conjunción ("12 ET 14 RUE...")
+            etage: keywords(&["étage", "etage", "ét"]),
+            rez_de_chaussee: keywords(&["rez-de-chaussée", "rez de chaussée", "rez-de-chaussee", "rez de chaussee", "rdc"]),
+            porte: keywords(&["porte"]),
+            appartement: keywords(&["appartement", "appart", "appt", "apt", "app"]),
+        }
+    }
+}
+
+/// Alternativas de una lista de palabras clave, las más largas primero
+/// ("batiment" antes que "bat")
+fn alternation(keywords: &[String]) -> String {
+    let mut keywords: Vec<String> = keywords
+        .iter()
+        .map(|k| k.trim().to_lowercase())
+        .filter(|k| !k.is_empty())
+        .collect();
+    keywords.sort_by(|a, b| b.chars().count().cmp(&a.chars().count()).then(a.cmp(b)));
+    keywords.dedup();
+    keywords.iter().map(|k| regex::escape(k)).collect::<Vec<_>>().join("|")
+}
+
+/// Palabra clave seguida de su valor: tras un separador ("BAT B", "BAT.2",
+/// "PORTE: 12", "PORTE N°12") o pegado si es un número ("BAT2"). Una palabra
+/// que solo empieza por la clave ("BATTERIE", "APPEL") no cuenta.
+fn value_regex(keywords: &[String], max_len: usize) -> Option<Regex> {
+    let alternation = alternation(keywords);
+    if alternation.is_empty() {
+        return None;
+    }
+    let pattern = format!(
+        r"(?i)\b(?:{})(?:[\s.:]+(?:n(?:°|o\.)\s*)?([A-Z0-9]{{1,{}}})|(\d{{1,{}}}))\b",
+        alternation, max_len, max_len
+    );
+    Some(Regex::new(&pattern).expect("palabras clave escapadas"))
+}
+
+pub struct DeliveryDetailsExtractor {
+    batiment: Option<Regex>,
+    escalier: Option<Regex>,
+    /// "3EME ETAGE", "1er ét."
+    etage_number_first: Option<Regex>,
+    /// "ETAGE: 5", "ÉT.3"
+    etage_label_first: Option<Regex>,
+    rez_de_chaussee: Option<Regex>,
+    porte: Option<Regex>,
+    appartement: Option<Regex>,
+}
+
+impl DeliveryDetailsExtractor {
+    pub fn new(keywords: &DeliveryKeywords) -> Self {
+        let etage = alternation(&keywords.etage);
+        let (etage_number_first, etage_label_first) = if etage.is_empty() {
+            (None, None)
+        } else {
+            (
+                Some(
+                    Regex::new(&format!(r"(?i)\b(\d{{1,2}})\s*(?:er|ere|ère|e|eme|ème|è)?\s*(?:{})(?:\b|\.)", etage))
+                        .expect("palabras clave escapadas"),
+                ),
+                value_regex(&keywords.etage, 2),
+            )
+        };
+        let rez_de_chaussee = Some(alternation(&keywords.rez_de_chaussee))
+            .filter(|alternation| !alternation.is_empty())
+            .map(|alternation| Regex::new(&format!(r"(?i)\b(?:{})\b", alternation)).expect("palabras clave escapadas"));
+
+        Self {
+            batiment: value_regex(&keywords.batiment, 4),
+            escalier: value_regex(&keywords.escalier, 4),
+            etage_number_first,
+            etage_label_first,
+            rez_de_chaussee,
+            porte: value_regex(&keywords.porte, 6),
+            appartement: value_regex(&keywords.appartement, 6),
+        }
+    }
+
+    /// Extraer bâtiment, escalier, étage, porte y appartement de un texto libre
+    pub fn extract(&self, text: &str) -> DeliveryDetails {
+        let capture = |regex: &Option<Regex>| {
+            let captures = regex.as_ref()?.captures(text)?;
+            captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .map(|m| m.as_str().to_uppercase())
+        };
+
+        let etage = capture(&self.etage_number_first)
+            .or_else(|| capture(&self.etage_label_first))
+            .and_then(|value| value.parse().ok())
+            .or_else(|| {
+                self.rez_de_chaussee
+                    .as_ref()
+                    .is_some_and(|regex| regex.is_match(text))
+                    .then_some(0)
+            });
+
+        DeliveryDetails {
+            batiment: capture(&self.batiment),
+            escalier: capture(&self.escalier),
+            etage,
+            porte: capture(&self.porte),
+            appartement: capture(&self.appartement),
+        }
+    }
+}
+
+fn default_extractor() -> &'static DeliveryDetailsExtractor {
+    static EXTRACTOR: OnceLock<DeliveryDetailsExtractor> = OnceLock::new();
+    EXTRACTOR.get_or_init(|| DeliveryDetailsExtractor::new(&DeliveryKeywords::default()))
+}
+
+/// Extraer los datos de entrega con las palabras clave por defecto
+pub fn extract_delivery_details(text: &str) -> DeliveryDetails {
+    default_extractor().extract(text)
+}
+
+/// Franja pedida en un texto libre ("MATIN UNIQUEMENT", "après-midi").
+/// Si menciona las dos o ninguna, cualquier hora vale.
+pub fn extract_delivery_slot(text: &str) -> DeliverySlot {
+    match (MORNING_REGEX.is_match(text), AFTERNOON_REGEX.is_match(text)) {
+        (true, false) => DeliverySlot::Morning,
+        (false, true) => DeliverySlot::Afternoon,
+        _ => DeliverySlot::AnyTime,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extracts_all_fields() {
+        let details = extract_delivery_details("Bat B 3eme etage porte 12, code 1234A");
+
+        assert_eq!(details.batiment.as_deref(), Some("B"));
+        assert_eq!(details.etage, Some(3));
+        assert_eq!(details.porte.as_deref(), Some("12"));
+        assert_eq!(details.appartement, None);
+    }
+
+    #[test]
+    fn test_label_first_and_ground_floor() {
+        assert_eq!(extract_delivery_details("ETAGE: 5 APPT 52").etage, Some(5));
+        assert_eq!(extract_delivery_details("ETAGE: 5 APPT 52").appartement.as_deref(), Some("52"));
+        assert_eq!(extract_delivery_details("RDC gauche").etage, Some(0));
+        assert_eq!(extract_delivery_details("1er étage").etage, Some(1));
+    }
+
+    #[test]
+    fn test_delivery_slot_from_text() {
+        assert_eq!(extract_delivery_slot("MATIN UNIQUEMENT"), DeliverySlot::Morning);
+        assert_eq!(extract_delivery_slot("Livrer l'après-midi SVP"), DeliverySlot::Afternoon);
+        assert_eq!(extract_delivery_slot("APRES MIDI"), DeliverySlot::Afternoon);
+        assert_eq!(extract_delivery_slot("matin ou après-midi"), DeliverySlot::AnyTime);
+        assert_eq!(extract_delivery_slot("Bat B 3eme etage"), DeliverySlot::AnyTime);
+    }
+
+    #[test]
+    fn test_text_without_details_is_empty() {
+        assert!(extract_delivery_details("Laisser chez le gardien").is_empty());
+        assert!(extract_delivery_details("APPELER AVANT, BATTERIE FAIBLE").is_empty());
+    }
+
+    #[test]
+    fn test_abbreviations_and_attached_numbers() {
+        let details = extract_delivery_details("BAT.2 ESC. A 3EME ETAGE");
+        assert_eq!(details.batiment.as_deref(), Some("2"));
+        assert_eq!(details.escalier.as_deref(), Some("A"));
+        assert_eq!(details.etage, Some(3));
+
+        let details = extract_delivery_details("BÂT C, 4ème ét., porte N°12");
+        assert_eq!(details.batiment.as_deref(), Some("C"));
+        assert_eq!(details.etage, Some(4));
+        assert_eq!(details.porte.as_deref(), Some("12"));
+
+        assert_eq!(extract_delivery_details("BAT2 ÉT.5 APPT52").etage, Some(5));
+        assert_eq!(extract_delivery_details("BAT2 ÉT.5 APPT52").batiment.as_deref(), Some("2"));
+        assert_eq!(extract_delivery_details("BAT2 ÉT.5 APPT52").appartement.as_deref(), Some("52"));
+        assert_eq!(extract_delivery_details("3EME ETAGE").etage, Some(3));
+        // "ET" sin tilde es la conjunción, no una planta
+        assert_eq!(extract_delivery_details("12 ET 14 RUE DES LILAS").etage, None);
+    }
+
+    #[test]
+    fn test_regional_keywords_extend_the_defaults() {
+        let mut keywords = DeliveryKeywords::default();
+        keywords.batiment.push("bloc".to_string());
+        keywords.appartement.push("bte".to_string());
+        let extractor = DeliveryDetailsExtractor::new(&keywords);
+
+        let details = extractor.extract("Bloc D, 2e étage, bte 7");
+        assert_eq!(details.batiment.as_deref(), Some("D"));
+        assert_eq!(details.etage, Some(2));
+        assert_eq!(details.appartement.as_deref(), Some("7"));
+        assert_eq!(extract_delivery_details("Bloc D bte 7").batiment, None);
+
+        keywords.etage.clear();
+        assert_eq!(DeliveryDetailsExtractor::new(&keywords).extract("3EME ETAGE").etage, None);
+    }
+}
@@ -0,0 +1,59 @@
+//! Geocerca de entregas
+//!
+//! Al marcar un paquete como entregado el chofer puede enviar dónde estaba.
+//! Si esa posición queda a más del radio configurado de las coordenadas del
+//! paquete, la entrega se marca para auditarla (escaneos "entregado" hechos
+//! lejos de la dirección).
+
+use crate::utils::errors::AppError;
+use crate::utils::geo::haversine_km;
+
+/// Posición informada por el chofer; ambas coordenadas o ninguna (400 si falta una)
+pub fn reported_location(latitude: Option<f64>, longitude: Option<f64>) -> Result<Option<(f64, f64)>, AppError> {
+    match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => {
+            if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                return Err(AppError::ValidationError(format!(
+                    "Coordenadas de entrega inválidas: {}, {}",
+                    latitude, longitude
+                )));
+            }
+            Ok(Some((latitude, longitude)))
+        }
+        (None, None) => Ok(None),
+        _ => Err(AppError::ValidationError(
+            "delivery_lat y delivery_lng deben enviarse juntas".to_string(),
+        )),
+    }
+}
+
+/// Distancia en metros entre la entrega y el paquete si supera `radius_m`
+pub fn outside_geofence(reported: (f64, f64), package: (f64, f64), radius_m: f64) -> Option<f64> {
+    let distance_m = haversine_km(reported.0, reported.1, package.0, package.1) * 1000.0;
+    (distance_m > radius_m).then_some(distance_m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE: (f64, f64) = (48.8686, 2.3314);
+
+    #[test]
+    fn test_delivery_far_from_the_address_is_flagged() {
+        // ~110 m al norte: dentro del radio por defecto
+        assert_eq!(outside_geofence((48.8696, 2.3314), PACKAGE, 200.0), None);
+
+        // ~1,1 km al norte
+        let distance = outside_geofence((48.8786, 2.3314), PACKAGE, 200.0).unwrap();
+        assert!((1000.0..1200.0).contains(&distance), "{} m", distance);
+    }
+
+    #[test]
+    fn test_reported_location_requires_both_coordinates() {
+        assert_eq!(reported_location(None, None).unwrap(), None);
+        assert_eq!(reported_location(Some(48.8686), Some(2.3314)).unwrap(), Some(PACKAGE));
+        assert!(matches!(reported_location(Some(48.8686), None), Err(AppError::ValidationError(_))));
+        assert!(reported_location(Some(148.0), Some(2.3314)).is_err());
+    }
+}
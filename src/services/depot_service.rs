@@ -0,0 +1,66 @@
+//! Almacenes de salida de las rutas
+//!
+//! Cada empresa registra sus almacenes (`/depot`); la optimización sale y
+//! vuelve al almacén por defecto de la société. WAREHOUSE_LOCATION queda como
+//! almacén global para las sociétés que aún no tienen ninguno.
+
+use sqlx::PgPool;
+
+use crate::repositories::depot_repository::DepotRepository;
+use crate::services::address_validation_service::validate_pinned_coordinates;
+use crate::utils::errors::AppError;
+use crate::utils::geo::CoordinateBounds;
+
+/// Nombre y coordenadas de un almacén: nombre no vacío y coordenadas en una región atendida
+pub fn validate_depot(name: &str, latitude: f64, longitude: f64, bounds: &CoordinateBounds) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::ValidationError("El nombre del almacén es requerido".to_string()));
+    }
+    validate_pinned_coordinates(latitude, longitude, bounds)
+}
+
+/// Société normalizada como en `company_settings` ("pcp0010699 " → "PCP0010699"); vacía = ninguna
+pub fn normalize_societe(societe: Option<String>) -> Option<String> {
+    societe
+        .map(|s| s.trim().to_uppercase())
+        .filter(|s| !s.is_empty())
+}
+
+/// Almacén `(lat, lon)` de salida de las tournées de una société: el suyo o, si
+/// no tiene, el configurado globalmente
+pub async fn societe_depot(pool: &PgPool, societe: &str, fallback: Option<(f64, f64)>) -> Option<(f64, f64)> {
+    match DepotRepository::new(pool.clone()).default_for_societe(societe).await {
+        Ok(Some(depot)) => {
+            log::info!("🏭 Almacén '{}' para {}", depot.name, societe);
+            Some(depot.location())
+        }
+        Ok(None) => fallback,
+        Err(e) => {
+            log::warn!("⚠️ No se pudo leer el almacén de {}: {}", societe, e);
+            fallback
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depot_needs_a_name_and_served_coordinates() {
+        let bounds = CoordinateBounds::default();
+
+        assert!(validate_depot("Entrepôt Lyon", 45.7640, 4.8357, &bounds).is_ok());
+        assert!(matches!(validate_depot("  ", 45.7640, 4.8357, &bounds), Err(AppError::ValidationError(_))));
+        // Invertidas y fuera de Francia metropolitana
+        assert!(validate_depot("Entrepôt Lyon", 4.8357, 45.7640, &bounds).is_err());
+        assert!(validate_depot("Entrepôt Madrid", 40.4168, -3.7038, &bounds).is_err());
+    }
+
+    #[test]
+    fn test_societe_is_normalized() {
+        assert_eq!(normalize_societe(Some(" pcp0010699 ".to_string())), Some("PCP0010699".to_string()));
+        assert_eq!(normalize_societe(Some("   ".to_string())), None);
+        assert_eq!(normalize_societe(None), None);
+    }
+}
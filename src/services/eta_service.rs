@@ -0,0 +1,354 @@
+//! Estimación de fin de tournée
+//!
+//! Extrapola la hora de fin a partir del tiempo medio por parada observado
+//! hasta ahora. Sin muestras suficientes (tournée recién empezada) se usa la
+//! estimación planificada: distancia restante a velocidad media más un tiempo
+//! fijo por parada.
+//!
+//! Las paradas cuya hora estimada cae fuera del horario de trabajo se marcan
+//! como no programables hoy, para que el dispatcher sepa que debe partir la ruta.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::Serialize;
+
+use crate::dto::colis_prive_dto::PackageData;
+use crate::models::delivery_progress::DeliveryProgress;
+use crate::utils::geo::haversine_km;
+
+/// Velocidad media planificada en ciudad (km/h)
+const PLANNED_SPEED_KMH: f64 = 25.0;
+
+/// Tiempo planificado por parada (aparcar, entregar, volver), en segundos
+const PLANNED_STOP_SECONDS: f64 = 180.0;
+
+/// Cómo se ha calculado la hora de fin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EtaMethod {
+    /// Tiempo medio por parada observado en la tournée
+    Extrapolated,
+    /// Sin muestras suficientes: valores planificados
+    Planned,
+}
+
+/// Horario de trabajo del repartidor, en hora local con desfase fijo respecto a UTC
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorkingHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Desfase de la hora local respecto a UTC, en minutos (Francia: 60 en invierno)
+    pub utc_offset_minutes: i32,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        Self {
+            start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+            utc_offset_minutes: 60,
+        }
+    }
+}
+
+impl WorkingHours {
+    /// Inicio y fin (UTC) de la jornada del día local de `now`
+    pub fn window(&self, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let offset = Duration::minutes(self.utc_offset_minutes as i64);
+        let local_date = (now + offset).date_naive();
+        (
+            local_date.and_time(self.start).and_utc() - offset,
+            local_date.and_time(self.end).and_utc() - offset,
+        )
+    }
+
+    /// Inicio (UTC) de la jornada de una fecha local
+    pub fn day_start(&self, date: NaiveDate) -> DateTime<Utc> {
+        date.and_time(self.start).and_utc() - Duration::minutes(self.utc_offset_minutes as i64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EtaEstimate {
+    pub method: EtaMethod,
+    pub estimated_finish: DateTime<Utc>,
+    pub completed_stops: usize,
+    pub remaining_stops: usize,
+    pub remaining_distance_km: f64,
+    pub average_stop_seconds: Option<f64>,
+    /// Fin de la jornada de hoy (UTC)
+    pub working_hours_end: DateTime<Utc>,
+    /// Paquetes cuya hora estimada supera el fin de la jornada
+    pub unschedulable: Vec<String>,
+}
+
+/// Estimar la hora de fin de una ruta ordenada según el progreso registrado,
+/// limitada al horario de trabajo
+pub fn estimate_completion(
+    route: &[PackageData],
+    progress: Option<&DeliveryProgress>,
+    now: DateTime<Utc>,
+    hours: &WorkingHours,
+) -> EtaEstimate {
+    let completed = progress.map(|p| p.completed.as_slice()).unwrap_or_default();
+    let remaining: Vec<&PackageData> = route
+        .iter()
+        .filter(|package| !progress.is_some_and(|p| p.is_completed(&package.reference_colis)))
+        .collect();
+
+    // El recorrido restante parte de la última parada visitada
+    let last_position = completed
+        .last()
+        .and_then(|stop| route.iter().find(|p| p.reference_colis == stop.reference_colis))
+        .and_then(coordinates);
+    let remaining_distance_km = path_distance_km(last_position.into_iter().chain(remaining.iter().filter_map(|p| coordinates(p))));
+
+    // Antes de la jornada el recorrido empieza a la hora de inicio
+    let (day_start, day_end) = hours.window(now);
+    let start = now.max(day_start);
+
+    let average_stop_seconds = average_stop_seconds(progress);
+    let (method, stop_etas) = match average_stop_seconds {
+        Some(average) => {
+            let from = completed.last().map_or(start, |stop| stop.completed_at.max(start));
+            let etas = (1..=remaining.len())
+                .map(|position| from + seconds(average * position as f64))
+                .collect();
+            (EtaMethod::Extrapolated, etas)
+        }
+        None => (EtaMethod::Planned, planned_etas(&remaining, last_position, start)),
+    };
+
+    // Las paradas que terminan después del fin de la jornada no se programan hoy
+    let scheduled = stop_etas.iter().take_while(|eta| **eta <= day_end).count();
+    let estimated_finish = stop_etas[..scheduled].last().copied().unwrap_or(start);
+    let unschedulable: Vec<String> = remaining[scheduled..]
+        .iter()
+        .map(|package| package.reference_colis.clone())
+        .collect();
+
+    EtaEstimate {
+        method,
+        estimated_finish,
+        completed_stops: completed.len(),
+        remaining_stops: remaining.len(),
+        remaining_distance_km,
+        average_stop_seconds,
+        working_hours_end: day_end,
+        unschedulable,
+    }
+}
+
+/// Hora planificada de vuelta al almacén: salida de `depot` a `departure`,
+/// la ruta en orden y el trayecto desde la última parada con coordenadas
+pub fn planned_return_eta(route: &[PackageData], depot: (f64, f64), departure: DateTime<Utc>) -> DateTime<Utc> {
+    let stops: Vec<&PackageData> = route.iter().collect();
+    let last_stop_eta = planned_etas(&stops, Some(depot), departure).last().copied().unwrap_or(departure);
+    let last_position = route.iter().rev().find_map(coordinates).unwrap_or(depot);
+    let return_km = path_distance_km([last_position, depot].into_iter());
+    last_stop_eta + seconds(return_km / PLANNED_SPEED_KMH * 3600.0)
+}
+
+/// Duración planificada de una ruta, separando conducción y tiempo en las paradas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PlannedDuration {
+    pub total_duration_minutes: f64,
+    pub total_drive_minutes: f64,
+    /// Tiempo fijo por parada de todos los paquetes
+    pub total_service_minutes: f64,
+}
+
+/// Duración planificada de una ruta ordenada: desde el almacén y de vuelta si
+/// lo hay, o desde la primera parada. La conducción es el total menos las paradas
+pub fn planned_duration(route: &[PackageData], depot: Option<(f64, f64)>) -> PlannedDuration {
+    let departure = DateTime::<Utc>::UNIX_EPOCH;
+    let finish = match depot {
+        Some(depot) => planned_return_eta(route, depot, departure),
+        None => {
+            let stops: Vec<&PackageData> = route.iter().collect();
+            planned_etas(&stops, None, departure).last().copied().unwrap_or(departure)
+        }
+    };
+
+    let total_seconds = (finish - departure).num_seconds() as f64;
+    let service_seconds = route.len() as f64 * PLANNED_STOP_SECONDS;
+    PlannedDuration {
+        total_duration_minutes: total_seconds / 60.0,
+        total_drive_minutes: (total_seconds - service_seconds).max(0.0) / 60.0,
+        total_service_minutes: service_seconds / 60.0,
+    }
+}
+
+/// Hora estimada de cada parada restante: trayecto a velocidad media desde la
+/// última parada con coordenadas más el tiempo fijo por parada
+fn planned_etas(
+    remaining: &[&PackageData],
+    mut position: Option<(f64, f64)>,
+    start: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let mut elapsed = 0.0;
+    remaining
+        .iter()
+        .map(|package| {
+            if let Some(next) = coordinates(package) {
+                if let Some(previous) = position {
+                    elapsed += path_distance_km([previous, next].into_iter()) / PLANNED_SPEED_KMH * 3600.0;
+                }
+                position = Some(next);
+            }
+            elapsed += PLANNED_STOP_SECONDS;
+            start + seconds(elapsed)
+        })
+        .collect()
+}
+
+/// Tiempo medio entre paradas consecutivas (necesita al menos dos)
+fn average_stop_seconds(progress: Option<&DeliveryProgress>) -> Option<f64> {
+    let completed = &progress?.completed;
+    if completed.len() < 2 {
+        return None;
+    }
+
+    let first = completed.first()?.completed_at;
+    let last = completed.last()?.completed_at;
+    let elapsed = (last - first).num_seconds() as f64;
+
+    Some(elapsed / (completed.len() - 1) as f64)
+}
+
+fn coordinates(package: &PackageData) -> Option<(f64, f64)> {
+    let latitude = package.coord_y_destinataire.or(package.latitude)?;
+    let longitude = package.coord_x_destinataire.or(package.longitude)?;
+    Some((latitude, longitude))
+}
+
+fn path_distance_km(points: impl Iterator<Item = (f64, f64)>) -> f64 {
+    let points: Vec<(f64, f64)> = points.collect();
+    points
+        .windows(2)
+        .map(|pair| haversine_km(pair[0].0, pair[0].1, pair[1].0, pair[1].1))
+        .sum()
+}
+
+fn seconds(value: f64) -> Duration {
+    Duration::seconds(value.round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::delivery_progress::StopOutcome;
+    use chrono::TimeZone;
+
+    fn route(count: usize) -> Vec<PackageData> {
+        (0..count)
+            .map(|i| PackageData {
+                reference_colis: format!("CP{}", i + 1),
+                coord_y_destinataire: Some(48.85),
+                coord_x_destinataire: Some(2.30 + i as f64 * 0.01),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 15, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_extrapolates_from_completed_stops() {
+        let route = route(10);
+        let mut progress = DeliveryProgress::new("PCP0010699", "A187518", "2025-01-15");
+        // 4 paradas a las 9:00, 9:05, 9:10 y 9:15 → 5 minutos por parada
+        for (i, minute) in [0, 5, 10, 15].into_iter().enumerate() {
+            progress.record(&format!("CP{}", i + 1), StopOutcome::Delivered, None, at(9, minute));
+        }
+
+        let eta = estimate_completion(&route, Some(&progress), at(9, 16), &WorkingHours::default());
+
+        assert_eq!(eta.method, EtaMethod::Extrapolated);
+        assert_eq!(eta.completed_stops, 4);
+        assert_eq!(eta.remaining_stops, 6);
+        assert_eq!(eta.average_stop_seconds, Some(300.0));
+        // 6 paradas restantes x 5 min desde ahora (9:16)
+        assert_eq!(eta.estimated_finish, at(9, 46));
+        assert!(eta.remaining_distance_km > 4.0 && eta.remaining_distance_km < 5.0);
+    }
+
+    #[test]
+    fn test_just_started_falls_back_to_planned() {
+        let route = route(3);
+        let mut progress = DeliveryProgress::new("PCP0010699", "A187518", "2025-01-15");
+        progress.record("CP1", StopOutcome::Delivered, None, at(9, 0));
+
+        let eta = estimate_completion(&route, Some(&progress), at(9, 0), &WorkingHours::default());
+
+        assert_eq!(eta.method, EtaMethod::Planned);
+        assert_eq!(eta.remaining_stops, 2);
+        assert!(eta.average_stop_seconds.is_none());
+        // Al menos el tiempo fijo de las 2 paradas restantes
+        assert!(eta.estimated_finish >= at(9, 6));
+    }
+
+    #[test]
+    fn test_failed_stops_count_as_completed() {
+        let route = route(3);
+        let mut progress = DeliveryProgress::new("PCP0010699", "A187518", "2025-01-15");
+        progress.record("CP1", StopOutcome::Delivered, None, at(9, 0));
+        progress.record("CP2", StopOutcome::Failed, None, at(9, 10));
+
+        let eta = estimate_completion(&route, Some(&progress), at(9, 10), &WorkingHours::default());
+
+        assert_eq!(eta.remaining_stops, 1);
+        assert_eq!(eta.estimated_finish, at(9, 20));
+    }
+
+    #[test]
+    fn test_stops_past_working_hours_are_unschedulable() {
+        // 60 paradas planificadas a partir de las 17:00 locales (16:00 UTC):
+        // solo queda una hora de trabajo antes de las 18:00
+        let route = route(60);
+        let hours = WorkingHours::default();
+
+        let eta = estimate_completion(&route, None, at(16, 0), &hours);
+
+        assert_eq!(eta.working_hours_end, at(17, 0));
+        assert_eq!(eta.remaining_stops, 60);
+        assert!(eta.estimated_finish <= eta.working_hours_end);
+
+        let scheduled = 60 - eta.unschedulable.len();
+        // ~105 s de trayecto + 180 s por parada: 12 paradas en una hora
+        assert_eq!(scheduled, 12);
+        let expected: Vec<String> = (scheduled + 1..=60).map(|i| format!("CP{}", i)).collect();
+        assert_eq!(eta.unschedulable, expected);
+    }
+
+    #[test]
+    fn test_route_before_opening_starts_at_working_hours() {
+        let route = route(2);
+
+        let eta = estimate_completion(&route, None, at(5, 0), &WorkingHours::default());
+
+        assert!(eta.unschedulable.is_empty());
+        assert!(eta.estimated_finish > at(7, 0));
+    }
+
+    #[test]
+    fn test_planned_duration_splits_drive_and_service_time() {
+        let route = route(10);
+
+        for depot in [None, Some((48.8566, 2.3522))] {
+            let duration = planned_duration(&route, depot);
+
+            assert!(duration.total_drive_minutes > 0.0);
+            assert_eq!(duration.total_service_minutes, 10.0 * PLANNED_STOP_SECONDS / 60.0);
+            assert!(
+                (duration.total_drive_minutes + duration.total_service_minutes - duration.total_duration_minutes).abs() < 1e-9,
+                "{:?}",
+                duration
+            );
+        }
+
+        assert_eq!(planned_duration(&[], None), PlannedDuration::default());
+    }
+}
@@ -0,0 +1,357 @@
+//! Exportación de tournées
+//!
+//! Convierte los paquetes de una tournée en filas planas y las serializa
+//! como CSV o como libro Excel (.xlsx) con una hoja de resumen.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+
+use futures::stream::{self, Stream, StreamExt};
+use rust_xlsxwriter::{Format, Workbook};
+
+use crate::dto::colis_prive_dto::PackageData;
+use crate::services::geocoding_quality_service::package_method;
+use crate::utils::errors::AppError;
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+pub const XLSX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+pub const PACKAGES_SHEET: &str = "Tournée";
+pub const SUMMARY_SHEET: &str = "Résumé";
+
+/// Cabeceras comunes a CSV y Excel
+pub const EXPORT_HEADERS: [&str; 10] = [
+    "Ordre",
+    "Référence colis",
+    "Destinataire",
+    "Adresse",
+    "Code postal",
+    "Ville",
+    "Statut",
+    "Téléphone",
+    "Latitude",
+    "Longitude",
+];
+
+/// Cabeceras del CSV de paquetes para hojas de cálculo; coinciden con las
+/// columnas de la importación CSV para poder reimportarlo
+pub const PACKAGE_CSV_HEADERS: [&str; 7] = [
+    "tracking_number",
+    "recipient",
+    "address",
+    "latitude",
+    "longitude",
+    "status",
+    "validation_method",
+];
+
+/// Etiqueta usada cuando un paquete no tiene estado o código postal
+const UNKNOWN: &str = "Inconnu";
+
+/// Fila exportada de una tournée
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportRow {
+    pub position: Option<i32>,
+    pub reference_colis: String,
+    pub destinataire_nom: String,
+    pub adresse: String,
+    pub code_postal: String,
+    pub ville: String,
+    pub statut: String,
+    pub telephone: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+impl From<&PackageData> for ExportRow {
+    fn from(package: &PackageData) -> Self {
+        let adresse = [&package.destinataire_adresse1, &package.destinataire_adresse2]
+            .into_iter()
+            .flatten()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Self {
+            position: package.numero_ordre.or(package.num_ordre_passage_prevu),
+            reference_colis: package.reference_colis.clone(),
+            destinataire_nom: package.destinataire_nom.clone(),
+            adresse,
+            code_postal: package.destinataire_cp.clone().unwrap_or_default(),
+            ville: package.destinataire_ville.clone().unwrap_or_default(),
+            statut: package
+                .code_statut_article
+                .clone()
+                .or_else(|| package.statut.clone())
+                .unwrap_or_default(),
+            telephone: package
+                .phone
+                .clone()
+                .or_else(|| package.phone_fixed.clone())
+                .unwrap_or_default(),
+            latitude: package.coord_y_destinataire.or(package.latitude),
+            longitude: package.coord_x_destinataire.or(package.longitude),
+        }
+    }
+}
+
+impl ExportRow {
+    fn cells(&self) -> [String; 10] {
+        [
+            self.position.map(|p| p.to_string()).unwrap_or_default(),
+            self.reference_colis.clone(),
+            self.destinataire_nom.clone(),
+            self.adresse.clone(),
+            self.code_postal.clone(),
+            self.ville.clone(),
+            self.statut.clone(),
+            self.telephone.clone(),
+            self.latitude.map(|v| v.to_string()).unwrap_or_default(),
+            self.longitude.map(|v| v.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+/// Filas de exportación en el orden de la tournée
+pub fn build_rows(packages: &[PackageData]) -> Vec<ExportRow> {
+    packages.iter().map(ExportRow::from).collect()
+}
+
+/// Serializar las filas como CSV (RFC 4180)
+pub fn to_csv(rows: &[ExportRow]) -> String {
+    let mut csv = String::new();
+    push_csv_line(&mut csv, EXPORT_HEADERS.iter().map(|h| h.to_string()));
+    for row in rows {
+        push_csv_line(&mut csv, row.cells().into_iter());
+    }
+    csv
+}
+
+pub(crate) fn push_csv_line(csv: &mut String, fields: impl Iterator<Item = String>) {
+    let line = fields.map(|field| escape_csv(&field)).collect::<Vec<_>>().join(",");
+    csv.push_str(&line);
+    csv.push_str("\r\n");
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn package_csv_line(package: &PackageData) -> String {
+    let row = ExportRow::from(package);
+    let locality = [row.code_postal.as_str(), row.ville.as_str()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let address = [row.adresse.as_str(), locality.as_str()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut line = String::new();
+    push_csv_line(
+        &mut line,
+        [
+            row.reference_colis,
+            row.destinataire_nom,
+            address,
+            row.latitude.map(|v| v.to_string()).unwrap_or_default(),
+            row.longitude.map(|v| v.to_string()).unwrap_or_default(),
+            row.statut,
+            package_method(package).as_str().to_string(),
+        ]
+        .into_iter(),
+    );
+    line
+}
+
+/// CSV de paquetes (RFC 4180) como stream: cada línea se serializa al
+/// enviarla, sin construir el fichero entero en memoria
+pub fn package_csv_stream(packages: Vec<PackageData>) -> impl Stream<Item = Result<String, Infallible>> {
+    let mut header = String::new();
+    push_csv_line(&mut header, PACKAGE_CSV_HEADERS.iter().map(|h| h.to_string()));
+
+    stream::once(async move { header })
+        .chain(stream::iter(packages).map(|package| package_csv_line(&package)))
+        .map(Ok)
+}
+
+/// Número de paquetes por estado y por código postal
+pub fn summarize(rows: &[ExportRow]) -> (BTreeMap<String, usize>, BTreeMap<String, usize>) {
+    let mut by_status = BTreeMap::new();
+    let mut by_postal_code = BTreeMap::new();
+
+    for row in rows {
+        *by_status.entry(label_or_unknown(&row.statut)).or_insert(0) += 1;
+        *by_postal_code.entry(label_or_unknown(&row.code_postal)).or_insert(0) += 1;
+    }
+
+    (by_status, by_postal_code)
+}
+
+fn label_or_unknown(value: &str) -> String {
+    if value.is_empty() {
+        UNKNOWN.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Construir en memoria un libro Excel con la tournée y una hoja de resumen
+pub fn to_xlsx(rows: &[ExportRow]) -> Result<Vec<u8>, AppError> {
+    let mut workbook = Workbook::new();
+    let header = Format::new().set_bold();
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(PACKAGES_SHEET).map_err(xlsx_error)?;
+    for (col, title) in EXPORT_HEADERS.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *title, &header).map_err(xlsx_error)?;
+    }
+    sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+
+    for (index, row) in rows.iter().enumerate() {
+        let r = index as u32 + 1;
+        if let Some(position) = row.position {
+            sheet.write_number(r, 0, position).map_err(xlsx_error)?;
+        }
+        sheet.write_string(r, 1, &row.reference_colis).map_err(xlsx_error)?;
+        sheet.write_string(r, 2, &row.destinataire_nom).map_err(xlsx_error)?;
+        sheet.write_string(r, 3, &row.adresse).map_err(xlsx_error)?;
+        sheet.write_string(r, 4, &row.code_postal).map_err(xlsx_error)?;
+        sheet.write_string(r, 5, &row.ville).map_err(xlsx_error)?;
+        sheet.write_string(r, 6, &row.statut).map_err(xlsx_error)?;
+        sheet.write_string(r, 7, &row.telephone).map_err(xlsx_error)?;
+        if let Some(latitude) = row.latitude {
+            sheet.write_number(r, 8, latitude).map_err(xlsx_error)?;
+        }
+        if let Some(longitude) = row.longitude {
+            sheet.write_number(r, 9, longitude).map_err(xlsx_error)?;
+        }
+    }
+    sheet.autofit();
+
+    let (by_status, by_postal_code) = summarize(rows);
+    let summary = workbook.add_worksheet();
+    summary.set_name(SUMMARY_SHEET).map_err(xlsx_error)?;
+    summary.write_string_with_format(0, 0, "Total colis", &header).map_err(xlsx_error)?;
+    summary.write_number(0, 1, rows.len() as f64).map_err(xlsx_error)?;
+
+    let mut r = 2;
+    for (title, counts) in [("Statut", &by_status), ("Code postal", &by_postal_code)] {
+        summary.write_string_with_format(r, 0, title, &header).map_err(xlsx_error)?;
+        summary.write_string_with_format(r, 1, "Colis", &header).map_err(xlsx_error)?;
+        r += 1;
+        for (label, count) in counts {
+            summary.write_string(r, 0, label).map_err(xlsx_error)?;
+            summary.write_number(r, 1, *count as f64).map_err(xlsx_error)?;
+            r += 1;
+        }
+        r += 1;
+    }
+    summary.autofit();
+
+    workbook.save_to_buffer().map_err(xlsx_error)
+}
+
+fn xlsx_error(e: rust_xlsxwriter::XlsxError) -> AppError {
+    AppError::Internal(format!("Error generando Excel: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    fn sample_packages() -> Vec<PackageData> {
+        vec![
+            PackageData {
+                reference_colis: "CP1".to_string(),
+                destinataire_nom: "DUPONT, Jean".to_string(),
+                destinataire_adresse1: Some("4 RUE GASTON TISSANDIER".to_string()),
+                destinataire_cp: Some("75018".to_string()),
+                destinataire_ville: Some("PARIS".to_string()),
+                code_statut_article: Some("LIV".to_string()),
+                numero_ordre: Some(1),
+                coord_x_destinataire: Some(2.36),
+                coord_y_destinataire: Some(48.89),
+                ..Default::default()
+            },
+            PackageData {
+                reference_colis: "CP2".to_string(),
+                destinataire_nom: "MARTIN".to_string(),
+                destinataire_cp: Some("75018".to_string()),
+                statut: Some("ECH".to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn read_entry(xlsx: &[u8], name: &str) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(xlsx)).unwrap();
+        let mut content = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn test_csv_has_header_and_escapes_fields() {
+        let csv = to_csv(&build_rows(&sample_packages()));
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+
+        assert_eq!(lines[0], EXPORT_HEADERS.join(","));
+        assert!(lines[1].starts_with("1,CP1,\"DUPONT, Jean\",4 RUE GASTON TISSANDIER,75018,PARIS,LIV,"));
+        assert!(lines[2].starts_with(",CP2,MARTIN,,75018,,ECH,"));
+    }
+
+    #[tokio::test]
+    async fn test_package_csv_stream_quotes_addresses() {
+        let mut packages = sample_packages();
+        packages[0].validation_method = Some("auto_validated".to_string());
+
+        let chunks: Vec<String> = package_csv_stream(packages).map(Result::unwrap).collect().await;
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0], format!("{}\r\n", PACKAGE_CSV_HEADERS.join(",")));
+        assert_eq!(
+            chunks[1],
+            "CP1,\"DUPONT, Jean\",\"4 RUE GASTON TISSANDIER, 75018 PARIS\",48.89,2.36,LIV,auto_validated\r\n"
+        );
+        assert_eq!(chunks[2], "CP2,MARTIN,75018,,,ECH,requires_manual\r\n");
+    }
+
+    #[test]
+    fn test_summary_counts_by_status_and_postal_code() {
+        let (by_status, by_postal_code) = summarize(&build_rows(&sample_packages()));
+
+        assert_eq!(by_status.get("LIV"), Some(&1));
+        assert_eq!(by_status.get("ECH"), Some(&1));
+        assert_eq!(by_postal_code.get("75018"), Some(&2));
+    }
+
+    #[test]
+    fn test_xlsx_has_expected_sheets_and_header_row() {
+        let xlsx = to_xlsx(&build_rows(&sample_packages())).unwrap();
+
+        // Un .xlsx es un zip; comprobar firma y contenido del libro
+        assert_eq!(&xlsx[..2], b"PK");
+
+        let workbook = read_entry(&xlsx, "xl/workbook.xml");
+        assert!(workbook.contains(&format!("name=\"{}\"", PACKAGES_SHEET)));
+        assert!(workbook.contains(&format!("name=\"{}\"", SUMMARY_SHEET)));
+
+        let sheet = read_entry(&xlsx, "xl/worksheets/sheet1.xml");
+        assert!(sheet.contains("state=\"frozen\""));
+
+        let strings = read_entry(&xlsx, "xl/sharedStrings.xml");
+        for header in EXPORT_HEADERS {
+            assert!(strings.contains(header), "cabecera ausente: {}", header);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Tournée completa en una sola llamada
+//!
+//! Junta los paquetes ya validados con, si se pide, el detalle de los
+//! prioritarios (etiquetas y datos de edificio) para ahorrar idas y vueltas a
+//! la app móvil.
+
+use std::collections::HashMap;
+
+use crate::dto::colis_prive_dto::PackageData;
+use crate::dto::package_dto::{FullPackageDto, PackageDetailDto};
+use crate::services::delivery_details_service::extract_delivery_details;
+
+/// Paquetes consolidados; `priority_labels` (referencia → etiquetas) solo se
+/// pasa si se pidió el detalle, y solo esos paquetes lo reciben
+pub fn consolidate(
+    packages: Vec<PackageData>,
+    priority_labels: Option<&HashMap<String, Vec<String>>>,
+) -> Vec<FullPackageDto> {
+    packages
+        .into_iter()
+        .map(|package| {
+            let detail = priority_labels
+                .and_then(|labels| labels.get(&package.reference_colis))
+                .map(|labels| PackageDetailDto {
+                    labels: labels.clone(),
+                    delivery: extract_delivery_details(&address_lines(&package)),
+                });
+            FullPackageDto { package: package.into(), detail }
+        })
+        .collect()
+}
+
+/// Líneas de dirección donde suelen venir bâtiment, étage o porte
+fn address_lines(package: &PackageData) -> String {
+    [package.destinataire_adresse1.as_deref(), package.destinataire_adresse2.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(reference: &str, address2: Option<&str>) -> PackageData {
+        PackageData {
+            reference_colis: reference.to_string(),
+            destinataire_nom: "Client".to_string(),
+            destinataire_adresse1: Some("12 RUE DE LA PAIX".to_string()),
+            destinataire_adresse2: address2.map(str::to_string),
+            coord_x_destinataire: Some(2.3314),
+            coord_y_destinataire: Some(48.8686),
+            validation_method: Some("auto_validated".to_string()),
+            validation_confidence: Some(0.95),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_consolidated_packages_carry_validation_and_requested_detail() {
+        let packages = vec![package("RCS1", Some("BAT B 3EME ETAGE")), package("CP2", None)];
+        let labels = HashMap::from([("RCS1".to_string(), vec!["rcs".to_string()])]);
+
+        let without_detail = consolidate(packages.clone(), None);
+        assert!(without_detail.iter().all(|p| p.detail.is_none()));
+
+        let full = consolidate(packages, Some(&labels));
+        let json = serde_json::to_value(&full).unwrap();
+
+        assert_eq!(json[0]["reference_colis"], "RCS1");
+        assert_eq!(json[0]["validation_method"], "auto_validated");
+        assert_eq!(json[0]["validation_confidence"], 0.95);
+        assert_eq!(json[0]["detail"]["labels"][0], "rcs");
+        assert_eq!(json[0]["detail"]["delivery"]["batiment"], "B");
+        assert_eq!(json[0]["detail"]["delivery"]["etage"], 3);
+        assert!(json[1].get("detail").is_none());
+        assert_eq!(json[1]["latitude"], 48.8686);
+    }
+}
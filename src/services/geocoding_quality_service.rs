@@ -0,0 +1,201 @@
+//! Calidad del geocoding de una tournée
+//!
+//! Chequeo previo a la salida del repartidor: cuántas direcciones tienen
+//! confianza alta, media o baja y qué paquetes conviene revisar antes de salir.
+//! No geocodifica nada: clasifica lo que ya trae la tournée.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::dto::colis_prive_dto::PackageData;
+use crate::services::colis_prive_service::ValidationMethod;
+
+/// Puntuación mínima para considerar la dirección de confianza alta
+const HIGH_CONFIDENCE_SCORE: f64 = 0.8;
+
+/// Puntuación mínima para confianza media (por debajo es baja)
+const MEDIUM_CONFIDENCE_SCORE: f64 = 0.5;
+
+const ALL_METHODS: [ValidationMethod; 7] = [
+    ValidationMethod::AutoValidated,
+    ValidationMethod::CleanedAuto,
+    ValidationMethod::CompletedAuto,
+    ValidationMethod::PartialFound,
+    ValidationMethod::GeocodingError,
+    ValidationMethod::RequiresManual,
+    ValidationMethod::ManualConfirmed,
+];
+
+/// Nivel de confianza de la dirección según `validation_confidence`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationConfidence {
+    High,
+    Medium,
+    Low,
+    /// Sin puntuación de Colis Privé ni de Mapbox
+    Unknown,
+}
+
+impl ValidationConfidence {
+    pub fn from_score(score: Option<f64>) -> Self {
+        match score {
+            Some(score) if score >= HIGH_CONFIDENCE_SCORE => Self::High,
+            Some(score) if score >= MEDIUM_CONFIDENCE_SCORE => Self::Medium,
+            Some(_) => Self::Low,
+            None => Self::Unknown,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::High => "high",
+            Self::Medium => "medium",
+            Self::Low => "low",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// Paquete cuya dirección conviene revisar antes de salir
+#[derive(Debug, Clone, Serialize)]
+pub struct LowConfidencePackage {
+    pub reference_colis: String,
+    pub address: String,
+    pub method: ValidationMethod,
+    pub confidence: ValidationConfidence,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeocodingQualityReport {
+    pub total_packages: usize,
+    pub by_method: BTreeMap<&'static str, usize>,
+    pub by_confidence: BTreeMap<&'static str, usize>,
+    pub low_confidence: Vec<LowConfidencePackage>,
+}
+
+/// Método de validación de un paquete; sin valor guardado se deduce de las
+/// coordenadas (las de Colis Privé cuentan como validadas)
+pub fn package_method(package: &PackageData) -> ValidationMethod {
+    if let Some(method) = package.validation_method.as_deref().and_then(ValidationMethod::parse) {
+        return method;
+    }
+
+    let has_coordinates = package.coord_y_destinataire.or(package.latitude).is_some()
+        && package.coord_x_destinataire.or(package.longitude).is_some();
+    if has_coordinates {
+        ValidationMethod::AutoValidated
+    } else {
+        ValidationMethod::RequiresManual
+    }
+}
+
+fn needs_attention(method: ValidationMethod, confidence: ValidationConfidence) -> bool {
+    confidence == ValidationConfidence::Low
+        || matches!(method, ValidationMethod::GeocodingError | ValidationMethod::RequiresManual)
+}
+
+fn package_address(package: &PackageData) -> String {
+    [
+        package.destinataire_adresse1.as_deref(),
+        package.destinataire_cp.as_deref(),
+        package.destinataire_ville.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(str::trim)
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Distribución por método y confianza, con los paquetes a revisar
+pub fn quality_report(packages: &[PackageData]) -> GeocodingQualityReport {
+    let mut by_method: BTreeMap<&'static str, usize> = ALL_METHODS.iter().map(|m| (m.as_str(), 0)).collect();
+    let mut by_confidence: BTreeMap<&'static str, usize> = [
+        ValidationConfidence::High,
+        ValidationConfidence::Medium,
+        ValidationConfidence::Low,
+        ValidationConfidence::Unknown,
+    ]
+    .iter()
+    .map(|c| (c.as_str(), 0))
+    .collect();
+    let mut low_confidence = Vec::new();
+
+    for package in packages {
+        let method = package_method(package);
+        let confidence = ValidationConfidence::from_score(package.validation_confidence);
+        *by_method.entry(method.as_str()).or_default() += 1;
+        *by_confidence.entry(confidence.as_str()).or_default() += 1;
+
+        if needs_attention(method, confidence) {
+            low_confidence.push(LowConfidencePackage {
+                reference_colis: package.reference_colis.clone(),
+                address: package_address(package),
+                method,
+                confidence,
+                score: package.validation_confidence,
+            });
+        }
+    }
+
+    GeocodingQualityReport {
+        total_packages: packages.len(),
+        by_method,
+        by_confidence,
+        low_confidence,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(reference: &str, method: Option<&str>, score: Option<f64>, coordinates: bool) -> PackageData {
+        PackageData {
+            reference_colis: reference.to_string(),
+            destinataire_nom: "Client".to_string(),
+            destinataire_adresse1: Some("12 RUE DE LA PAIX".to_string()),
+            destinataire_cp: Some("75002".to_string()),
+            destinataire_ville: Some("PARIS".to_string()),
+            validation_method: method.map(str::to_string),
+            validation_confidence: score,
+            coord_x_destinataire: coordinates.then_some(2.3314),
+            coord_y_destinataire: coordinates.then_some(48.8686),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mixed_quality_tournee_distribution() {
+        let packages = vec![
+            package("A", None, Some(0.95), true),
+            package("B", None, Some(0.85), true),
+            package("C", Some("geocoded"), Some(0.9), true),
+            package("D", Some("partial_found"), Some(0.6), true),
+            package("E", Some("partial_found"), Some(0.3), true),
+            package("F", None, None, false),
+        ];
+
+        let report = quality_report(&packages);
+
+        assert_eq!(report.total_packages, 6);
+        assert_eq!(report.by_method["auto_validated"], 2);
+        assert_eq!(report.by_method["completed_auto"], 1);
+        assert_eq!(report.by_method["partial_found"], 2);
+        assert_eq!(report.by_method["requires_manual"], 1);
+        assert_eq!(report.by_method["geocoding_error"], 0);
+        assert_eq!(report.by_confidence["high"], 3);
+        assert_eq!(report.by_confidence["medium"], 1);
+        assert_eq!(report.by_confidence["low"], 1);
+        assert_eq!(report.by_confidence["unknown"], 1);
+
+        let flagged: Vec<&str> = report.low_confidence.iter().map(|p| p.reference_colis.as_str()).collect();
+        assert_eq!(flagged, vec!["E", "F"]);
+        assert_eq!(report.low_confidence[0].address, "12 RUE DE LA PAIX, 75002, PARIS");
+    }
+}
@@ -1,12 +1,14 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::cache::geocoding_cache::GeocodingCache;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeocodingRequest {
     pub address: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeocodingResponse {
     pub success: bool,
     pub latitude: Option<f64>,
@@ -16,6 +18,19 @@ pub struct GeocodingResponse {
     pub error: Option<String>,
 }
 
+/// Candidato de geocodificación para direcciones ambiguas
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GeocodeCandidate {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub formatted_address: Option<String>,
+    /// Relevancia entre 0 y 1 (1 = coincidencia exacta)
+    pub relevance: f64,
+}
+
+/// Número máximo de resultados que admite Mapbox por petición
+pub const MAX_CANDIDATES: usize = 10;
+
 #[derive(Debug, Deserialize)]
 struct MapboxGeocodingResponse {
     #[serde(rename = "type")]
@@ -29,6 +44,9 @@ struct MapboxFeature {
     feature_type: String,
     geometry: MapboxGeometry,
     properties: MapboxProperties,
+    /// Solo en la API v5; en v6 se usa `properties.match_code.confidence`
+    #[serde(default)]
+    relevance: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -45,11 +63,126 @@ struct MapboxProperties {
     name: Option<String>,
     #[serde(rename = "place_name")]
     place_name: Option<String>,
+    #[serde(default)]
+    match_code: Option<MapboxMatchCode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MapboxMatchCode {
+    confidence: Option<String>,
+}
+
+impl MapboxFeature {
+    fn relevance(&self) -> f64 {
+        if let Some(relevance) = self.relevance {
+            return relevance;
+        }
+
+        match self.properties.match_code.as_ref().and_then(|m| m.confidence.as_deref()) {
+            Some("exact") => 1.0,
+            Some("high") => 0.9,
+            Some("medium") => 0.6,
+            Some("low") => 0.3,
+            _ => 0.0,
+        }
+    }
+
+    fn formatted_address(&self) -> Option<String> {
+        self.properties.full_address.clone()
+            .or_else(|| self.properties.place_name.clone())
+            .or_else(|| self.properties.name.clone())
+    }
+}
+
+/// Extraer los candidatos de una respuesta de Mapbox, ordenados por relevancia
+fn parse_candidates(body: &str, limit: usize) -> Result<Vec<GeocodeCandidate>> {
+    let mapbox_response: MapboxGeocodingResponse = serde_json::from_str(body)
+        .map_err(|e| anyhow!("Failed to parse geocoding response: {}", e))?;
+
+    let mut candidates: Vec<GeocodeCandidate> = mapbox_response
+        .features
+        .iter()
+        .filter(|feature| feature.geometry.coordinates.len() >= 2)
+        .map(|feature| GeocodeCandidate {
+            latitude: feature.geometry.coordinates[1],
+            longitude: feature.geometry.coordinates[0],
+            formatted_address: feature.formatted_address(),
+            relevance: feature.relevance(),
+        })
+        .collect();
+
+    // Orden estable: a igual relevancia se respeta el orden de Mapbox
+    candidates.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+    candidates.truncate(limit);
+
+    Ok(candidates)
+}
+
+/// Respuesta de un reverse geocoding a partir de la respuesta de Mapbox
+fn reverse_response(body: &str, latitude: f64, longitude: f64) -> Result<GeocodingResponse> {
+    let Some(candidate) = parse_candidates(body, 1)?.into_iter().next() else {
+        log::warn!("⚠️ No address found at ({}, {})", latitude, longitude);
+        return Ok(GeocodingResponse {
+            success: false,
+            latitude: None,
+            longitude: None,
+            formatted_address: None,
+            message: Some("No address found at these coordinates".to_string()),
+            error: None,
+        });
+    };
+
+    log::info!("✅ Reverse geocoding successful: ({}, {}) -> {:?}", latitude, longitude, candidate.formatted_address);
+    Ok(GeocodingResponse {
+        success: true,
+        latitude: Some(candidate.latitude),
+        longitude: Some(candidate.longitude),
+        formatted_address: candidate.formatted_address,
+        message: Some("Reverse geocoding successful".to_string()),
+        error: None,
+    })
+}
+
+/// Idioma de las etiquetas y país al que se restringen los resultados de Mapbox
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeocodingLocale {
+    pub language: String,
+    pub country: String,
+}
+
+impl Default for GeocodingLocale {
+    fn default() -> Self {
+        Self {
+            language: "fr".to_string(),
+            country: "fr".to_string(),
+        }
+    }
+}
+
+impl GeocodingLocale {
+    /// Leer `GEOCODING_LANGUAGE` y `GEOCODING_COUNTRY` (por defecto fr/fr)
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let read = |name: &str, fallback: String| {
+            std::env::var(name)
+                .ok()
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .unwrap_or(fallback)
+        };
+
+        Self {
+            language: read("GEOCODING_LANGUAGE", default.language),
+            country: read("GEOCODING_COUNTRY", default.country),
+        }
+    }
 }
 
 pub struct GeocodingService {
     mapbox_token: String,
     client: reqwest::Client,
+    locale: GeocodingLocale,
+    cache: Option<GeocodingCache>,
 }
 
 impl GeocodingService {
@@ -62,21 +195,65 @@ impl GeocodingService {
         Self {
             mapbox_token,
             client,
+            locale: GeocodingLocale::default(),
+            cache: None,
         }
     }
 
+    pub fn with_locale(mut self, locale: GeocodingLocale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Consultar el cache de direcciones antes de llamar a Mapbox
+    pub fn with_cache(mut self, cache: GeocodingCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// URL de búsqueda forward de Mapbox con idioma y país configurados
+    fn forward_url(&self, address: &str, limit: usize) -> String {
+        format!(
+            "https://api.mapbox.com/search/geocode/v6/forward?q={}&access_token={}&language={}&country={}&limit={}",
+            urlencoding::encode(address),
+            self.mapbox_token,
+            urlencoding::encode(&self.locale.language),
+            urlencoding::encode(&self.locale.country),
+            limit
+        )
+    }
+
+    /// URL de búsqueda reverse de Mapbox (solo direcciones) con idioma y país configurados
+    fn reverse_url(&self, latitude: f64, longitude: f64) -> String {
+        format!(
+            "https://api.mapbox.com/search/geocode/v6/reverse?longitude={}&latitude={}&access_token={}&language={}&country={}&types=address&limit=1",
+            longitude,
+            latitude,
+            self.mapbox_token,
+            urlencoding::encode(&self.locale.language),
+            urlencoding::encode(&self.locale.country),
+        )
+    }
+
     pub async fn geocode_address(&self, address: &str) -> Result<GeocodingResponse> {
+        if let Some(cache) = &self.cache {
+            if let Some(cached) = cache.get(address).await {
+                return Ok(cached);
+            }
+        }
+
+        let response = self.geocode_with_mapbox(address).await?;
+        if let Some(cache) = &self.cache {
+            cache.put(address, &response).await;
+        }
+        Ok(response)
+    }
+
+    async fn geocode_with_mapbox(&self, address: &str) -> Result<GeocodingResponse> {
         log::info!("🗺️ Geocoding address: {}", address);
 
-        // URL encode la dirección
-        let encoded_address = urlencoding::encode(address);
-        
         // Construir la URL según la documentación oficial
-        let url = format!(
-            "https://api.mapbox.com/search/geocode/v6/forward?q={}&access_token={}&country=fr&limit=1",
-            encoded_address,
-            self.mapbox_token
-        );
+        let url = self.forward_url(address, 1);
 
         log::info!("🌐 Making request to: {}", url);
 
@@ -145,6 +322,61 @@ impl GeocodingService {
         })
     }
 
+    /// Dirección legible más cercana a unas coordenadas (p.ej. la posición GPS del
+    /// chofer cuando la dirección del paquete falta o no se puede geocodificar).
+    /// Las coordenadas de la respuesta son las de la dirección encontrada
+    pub async fn reverse_geocode(&self, latitude: f64, longitude: f64) -> Result<GeocodingResponse> {
+        log::info!("🗺️ Reverse geocoding: ({}, {})", latitude, longitude);
+
+        let response = self.client
+            .get(self.reverse_url(latitude, longitude))
+            .header("User-Agent", "DeliveryRouting/1.0")
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            log::error!("❌ Reverse geocoding failed with status {}: {}", status, error_text);
+            return Ok(GeocodingResponse {
+                success: false,
+                latitude: None,
+                longitude: None,
+                formatted_address: None,
+                message: None,
+                error: Some(format!("Reverse geocoding failed: {}", status)),
+            });
+        }
+
+        reverse_response(&response.text().await?, latitude, longitude)
+    }
+
+    /// Devolver los `limit` mejores candidatos para una dirección ambigua
+    pub async fn geocode_candidates(&self, address: &str, limit: usize) -> Result<Vec<GeocodeCandidate>> {
+        let limit = limit.clamp(1, MAX_CANDIDATES);
+        log::info!("🗺️ Geocoding candidates ({}) for address: {}", limit, address);
+
+        let url = self.forward_url(address, limit);
+
+        let response = self.client
+            .get(&url)
+            .header("User-Agent", "DeliveryRouting/1.0")
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            log::error!("❌ Geocoding failed with status {}: {}", status, error_text);
+            return Err(anyhow!("Geocoding failed: {}", status));
+        }
+
+        let candidates = parse_candidates(&response.text().await?, limit)?;
+        log::info!("✅ {} candidates found for: {}", candidates.len(), address);
+
+        Ok(candidates)
+    }
+
     pub async fn batch_geocode(&self, addresses: Vec<String>) -> Result<Vec<GeocodingResponse>> {
         log::info!("🗺️ Batch geocoding {} addresses", addresses.len());
         
@@ -192,6 +424,73 @@ impl GeocodingService {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_language_and_country_are_appended_to_url() {
+        let service = GeocodingService::new("token".to_string());
+        let url = service.forward_url("12 rue de la Paix", 1);
+        assert!(url.contains("&language=fr&country=fr&"), "{}", url);
+
+        let service = service.with_locale(GeocodingLocale {
+            language: "nl".to_string(),
+            country: "be".to_string(),
+        });
+        let url = service.forward_url("Grote Markt 1, Brussel", 3);
+        assert!(url.contains("q=Grote%20Markt%201%2C%20Brussel"), "{}", url);
+        assert!(url.contains("&language=nl&country=be&limit=3"), "{}", url);
+    }
+
+    #[test]
+    fn test_candidates_are_ordered_by_relevance() {
+        let body = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "geometry": {"type": "Point", "coordinates": [2.33, 48.86]},
+                 "properties": {"full_address": "12 Rue de la Paix, 75002 Paris", "match_code": {"confidence": "medium"}}},
+                {"type": "Feature", "geometry": {"type": "Point", "coordinates": [4.83, 45.76]},
+                 "properties": {"full_address": "12 Rue de la Paix, 69001 Lyon", "match_code": {"confidence": "exact"}}},
+                {"type": "Feature", "geometry": {"type": "Point", "coordinates": [5.37, 43.29]},
+                 "properties": {"name": "Rue de la Paix, Marseille"}, "relevance": 0.45},
+                {"type": "Feature", "geometry": {"type": "Point", "coordinates": []},
+                 "properties": {"name": "Sin coordenadas"}, "relevance": 1.0}
+            ]
+        }"#;
+
+        let candidates = parse_candidates(body, 3).unwrap();
+
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].formatted_address.as_deref(), Some("12 Rue de la Paix, 69001 Lyon"));
+        assert_eq!((candidates[0].latitude, candidates[0].longitude), (45.76, 4.83));
+        assert_eq!(candidates[1].relevance, 0.6);
+        assert_eq!(candidates[2].formatted_address.as_deref(), Some("Rue de la Paix, Marseille"));
+        assert!(candidates.windows(2).all(|pair| pair[0].relevance >= pair[1].relevance));
+
+        assert_eq!(parse_candidates(body, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_reverse_geocoding_url_and_response() {
+        let service = GeocodingService::new("token".to_string());
+        let url = service.reverse_url(48.8686, 2.3314);
+        assert!(url.starts_with("https://api.mapbox.com/search/geocode/v6/reverse?longitude=2.3314&latitude=48.8686&"), "{}", url);
+        assert!(url.contains("&language=fr&country=fr&types=address&limit=1"), "{}", url);
+
+        let body = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "geometry": {"type": "Point", "coordinates": [2.33142, 48.86858]},
+                 "properties": {"full_address": "12 Rue de la Paix, 75002 Paris", "name": "12 Rue de la Paix"}}
+            ]
+        }"#;
+        let response = reverse_response(body, 48.8686, 2.3314).unwrap();
+        assert!(response.success);
+        assert_eq!(response.formatted_address.as_deref(), Some("12 Rue de la Paix, 75002 Paris"));
+        assert_eq!((response.latitude, response.longitude), (Some(48.86858), Some(2.33142)));
+
+        let empty = reverse_response(r#"{"type": "FeatureCollection", "features": []}"#, 0.0, 0.0).unwrap();
+        assert!(!empty.success);
+        assert!(empty.formatted_address.is_none());
+    }
+
     #[tokio::test]
     async fn test_geocoding_service() {
         // Este test requiere un token válido de Mapbox
@@ -0,0 +1,189 @@
+//! Exportación GPX de la ruta optimizada
+//!
+//! Algunos choferes cargan la ruta en un GPS Garmin independiente: se genera
+//! un documento GPX 1.1 con un `<rtept>` por parada, en el orden de
+//! `num_ordre_passage_prevu`. Los paquetes sin coordenadas no se pueden
+//! cargar en el GPS y se omiten (se cuentan para avisar al chofer).
+
+use crate::dto::colis_prive_dto::PackageData;
+
+pub const GPX_CONTENT_TYPE: &str = "application/gpx+xml";
+
+/// Cabecera con el número de paradas omitidas por no tener coordenadas
+pub const SKIPPED_STOPS_HEADER: &str = "x-skipped-stops";
+
+const GPX_NAMESPACE: &str = "http://www.topografix.com/GPX/1/1";
+const GPX_SCHEMA_LOCATION: &str = "http://www.topografix.com/GPX/1/1 http://www.topografix.com/GPX/1/1/gpx.xsd";
+const GPX_CREATOR: &str = "delivery_routing";
+
+/// Documento GPX y paradas que no se pudieron incluir
+#[derive(Debug)]
+pub struct GpxRoute {
+    pub document: String,
+    pub skipped: usize,
+}
+
+/// Construir la ruta GPX `name` con las paradas en orden de pasada
+pub fn build_route_gpx(name: &str, packages: &[PackageData]) -> GpxRoute {
+    let mut stops: Vec<&PackageData> = packages.iter().collect();
+    // Las paradas sin orden previsto van al final, conservando su posición relativa
+    stops.sort_by_key(|package| package.num_ordre_passage_prevu.unwrap_or(i32::MAX));
+
+    let mut points = String::new();
+    let mut skipped = 0;
+    for package in stops {
+        let Some((latitude, longitude)) = stop_coordinates(package) else {
+            skipped += 1;
+            continue;
+        };
+        points.push_str(&format!(
+            "    <rtept lat=\"{:.6}\" lon=\"{:.6}\">\n      <name>{}</name>\n      <desc>{}</desc>\n    </rtept>\n",
+            latitude,
+            longitude,
+            escape_xml(&package.destinataire_nom),
+            escape_xml(&package.reference_colis),
+        ));
+    }
+
+    if skipped > 0 {
+        log::warn!("⚠️ GPX '{}': {} paradas sin coordenadas omitidas", name, skipped);
+    }
+
+    let document = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <gpx version=\"1.1\" creator=\"{}\" xmlns=\"{}\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"{}\">\n  \
+         <rte>\n    <name>{}</name>\n{}  </rte>\n</gpx>\n",
+        GPX_CREATOR,
+        GPX_NAMESPACE,
+        GPX_SCHEMA_LOCATION,
+        escape_xml(name),
+        points,
+    );
+
+    GpxRoute { document, skipped }
+}
+
+/// Coordenadas `(lat, lon)` de la parada: primero las de Colis Privé, luego las geocodificadas
+fn stop_coordinates(package: &PackageData) -> Option<(f64, f64)> {
+    let latitude = package.coord_y_destinataire.or(package.latitude)?;
+    let longitude = package.coord_x_destinataire.or(package.longitude)?;
+    Some((latitude, longitude))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n'))
+        .fold(String::with_capacity(text.len()), |mut escaped, c| {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&apos;"),
+                _ => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(reference: &str, name: &str, order: Option<i32>, coordinates: Option<(f64, f64)>) -> PackageData {
+        PackageData {
+            reference_colis: reference.to_string(),
+            destinataire_nom: name.to_string(),
+            num_ordre_passage_prevu: order,
+            coord_y_destinataire: coordinates.map(|(lat, _)| lat),
+            coord_x_destinataire: coordinates.map(|(_, lon)| lon),
+            ..Default::default()
+        }
+    }
+
+    /// Elementos abiertos y cerrados en orden; falla si el anidamiento no cuadra
+    fn element_tree(document: &str) -> Vec<String> {
+        let mut open: Vec<String> = Vec::new();
+        let mut seen = Vec::new();
+        for tag in document.split('<').skip(1).map(|chunk| chunk.split('>').next().unwrap()) {
+            if tag.starts_with('?') {
+                continue;
+            }
+            if let Some(closing) = tag.strip_prefix('/') {
+                assert_eq!(open.pop().as_deref(), Some(closing), "cierre inesperado </{}>", closing);
+                continue;
+            }
+            let name = tag.split_whitespace().next().unwrap().to_string();
+            let path = format!("{}/{}", open.join("/"), name);
+            seen.push(path.trim_start_matches('/').to_string());
+            if !tag.ends_with('/') {
+                open.push(name);
+            }
+        }
+        assert!(open.is_empty(), "elementos sin cerrar: {:?}", open);
+        seen
+    }
+
+    #[test]
+    fn test_gpx_follows_the_1_1_schema_structure() {
+        let packages = vec![
+            stop("CP002", "Martin & Fils", Some(2), Some((48.8566, 2.3522))),
+            stop("CP001", "Dupont <Bât A>", Some(1), Some((48.8600, 2.3400))),
+        ];
+        let gpx = build_route_gpx("A187518 2025-01-15", &packages).document;
+
+        assert!(gpx.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        assert!(gpx.contains("<gpx version=\"1.1\" creator=\"delivery_routing\" xmlns=\"http://www.topografix.com/GPX/1/1\""));
+
+        // gpxType: un solo <rte> con <name> antes de los <rtept>; wptType: <name> antes de <desc>
+        let tree = element_tree(&gpx);
+        assert_eq!(
+            tree,
+            vec![
+                "gpx", "gpx/rte", "gpx/rte/name",
+                "gpx/rte/rtept", "gpx/rte/rtept/name", "gpx/rte/rtept/desc",
+                "gpx/rte/rtept", "gpx/rte/rtept/name", "gpx/rte/rtept/desc",
+            ]
+        );
+
+        // lat/lon dentro de los rangos de latitudeType y longitudeType
+        for point in gpx.split("<rtept ").skip(1) {
+            let attribute = |key: &str| -> f64 {
+                let start = point.find(&format!("{}=\"", key)).unwrap() + key.len() + 2;
+                point[start..].split('"').next().unwrap().parse().unwrap()
+            };
+            assert!((-90.0..=90.0).contains(&attribute("lat")));
+            assert!((-180.0..180.0).contains(&attribute("lon")));
+        }
+    }
+
+    #[test]
+    fn test_stops_are_ordered_and_escaped() {
+        let packages = vec![
+            stop("CP002", "Martin & Fils", Some(2), Some((48.8566, 2.3522))),
+            stop("CP001", "Dupont <Bât A>", Some(1), Some((48.8600, 2.3400))),
+        ];
+        let gpx = build_route_gpx("ruta", &packages).document;
+
+        let first = gpx.find("<desc>CP001</desc>").unwrap();
+        let second = gpx.find("<desc>CP002</desc>").unwrap();
+        assert!(first < second);
+        assert!(gpx.contains("<name>Dupont &lt;Bât A&gt;</name>"));
+        assert!(gpx.contains("<name>Martin &amp; Fils</name>"));
+        assert!(gpx.contains("<rtept lat=\"48.860000\" lon=\"2.340000\">"));
+    }
+
+    #[test]
+    fn test_stops_without_coordinates_are_counted_and_omitted() {
+        let packages = vec![
+            stop("CP001", "Dupont", Some(1), Some((48.86, 2.34))),
+            stop("CP002", "Martin", Some(2), None),
+            stop("CP003", "Bernard", None, None),
+        ];
+        let route = build_route_gpx("ruta", &packages);
+
+        assert_eq!(route.skipped, 2);
+        assert_eq!(route.document.matches("<rtept ").count(), 1);
+        assert!(!route.document.contains("CP002"));
+    }
+}
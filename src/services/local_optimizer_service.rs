@@ -0,0 +1,629 @@
+//! Optimizador local de rutas
+//!
+//! Construye una ruta inicial con vecino más cercano y la mejora con 2-opt
+//! sobre una matriz de costes: duraciones reales de conducción (Mapbox Matrix
+//! API, con tráfico) o, como respaldo, distancia haversine entre paradas.
+//!
+//! Las paradas prioritarias (RCS: comercios que cierran pronto) se adelantan
+//! con un peso blando: al coste de la ruta se suma `peso × coste acumulado`
+//! hasta cada parada prioritaria, sin anular la eficiencia geográfica.
+//!
+//! Las franjas de entrega (mañana/tarde) también son blandas: cada paquete de
+//! mañana en la segunda mitad de la ruta, o de tarde en la primera, suma
+//! `SLOT_PENALTY` tramos medios al coste.
+//!
+//! El 2-opt se detiene en un óptimo local o, antes, al agotar el presupuesto
+//! de tiempo, tras demasiados movimientos sin mejora o al alcanzar la mejora
+//! objetivo (ver `StoppingCriteria`).
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::cache::redis_client::RedisClient;
+use crate::dto::colis_prive_dto::DeliverySlot;
+use crate::services::mapbox_matrix_service::{stop_set_id, MapboxMatrixService};
+use crate::utils::geo::haversine_km;
+
+/// TTL de la matriz cacheada: el tráfico cambia a lo largo del día
+const MATRIX_CACHE_TTL: u64 = 900;
+
+/// Mejora mínima para aceptar un movimiento 2-opt (evita ciclos por redondeo)
+const IMPROVEMENT_EPSILON: f64 = 1e-9;
+
+/// Penalización por paquete fuera de su franja, en tramos medios de la matriz
+const SLOT_PENALTY: f64 = 1.0;
+
+/// Parada a optimizar
+#[derive(Debug, Clone)]
+pub struct RouteStop {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Entrega prioritaria (RCS...) que conviene hacer pronto
+    pub high_priority: bool,
+    pub delivery_slot: DeliverySlot,
+}
+
+/// Origen de los costes usados por el optimizador
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostSource {
+    /// Duraciones de conducción (segundos) de Mapbox Matrix
+    Duration,
+    /// Distancia en línea recta (km)
+    Distance,
+}
+
+/// Matriz de costes entre paradas (`values[i][j]` = coste de ir de i a j)
+#[derive(Debug, Clone)]
+pub struct CostMatrix {
+    values: Vec<Vec<f64>>,
+    source: CostSource,
+}
+
+impl CostMatrix {
+    /// Matriz de distancias haversine
+    pub fn haversine(stops: &[RouteStop]) -> Self {
+        let values = stops
+            .iter()
+            .map(|from| {
+                stops
+                    .iter()
+                    .map(|to| haversine_km(from.latitude, from.longitude, to.latitude, to.longitude))
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            values,
+            source: CostSource::Distance,
+        }
+    }
+
+    /// Matriz de duraciones ya calculadas (p.ej. Mapbox Matrix)
+    pub fn from_durations(values: Vec<Vec<f64>>) -> Self {
+        Self {
+            values,
+            source: CostSource::Duration,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn source(&self) -> CostSource {
+        self.source
+    }
+
+    pub fn cost(&self, from: usize, to: usize) -> f64 {
+        self.values[from][to]
+    }
+
+    /// Coste medio de un tramo entre paradas distintas
+    pub fn mean_cost(&self) -> f64 {
+        let n = self.size();
+        if n < 2 {
+            return 0.0;
+        }
+
+        let total: f64 = self.values.iter().flatten().sum();
+        total / (n * (n - 1)) as f64
+    }
+}
+
+/// Criterios de parada del 2-opt: se detiene con el primero que se cumpla
+#[derive(Debug, Clone, Copy)]
+pub struct StoppingCriteria {
+    pub time_budget: Duration,
+    /// Movimientos evaluados seguidos sin mejora
+    pub max_no_improvement: usize,
+    /// Mejora relativa sobre la ruta inicial a partir de la cual basta (0.15 = 15 %)
+    pub target_improvement: Option<f64>,
+}
+
+impl Default for StoppingCriteria {
+    fn default() -> Self {
+        Self {
+            time_budget: Duration::from_millis(2000),
+            max_no_improvement: 10_000,
+            target_improvement: None,
+        }
+    }
+}
+
+/// Motivo por el que terminó el 2-opt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    LocalOptimum,
+    TimeBudget,
+    NoImprovement,
+    TargetReached,
+}
+
+/// Métricas de la optimización local (se devuelven en la respuesta)
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalOptimizerStats {
+    /// Coste de la ruta de vecino más cercano
+    pub initial_cost: f64,
+    pub total_cost: f64,
+    /// (inicial - final) / inicial; puede ser negativa si las prioridades o
+    /// las franjas alargan la ruta
+    pub improvement_ratio: f64,
+    pub iterations: usize,
+    pub elapsed_ms: u64,
+    pub stop_reason: StopReason,
+    pub cost_source: CostSource,
+}
+
+/// Resultado de la optimización local
+#[derive(Debug, Clone)]
+pub struct LocalOptimizationResult {
+    /// Índices de las paradas en el orden optimizado
+    pub order: Vec<usize>,
+    pub total_cost: f64,
+    pub cost_source: CostSource,
+    pub stats: LocalOptimizerStats,
+}
+
+/// Optimizar con prioridades y franjas de entrega (ambas restricciones blandas).
+///
+/// `priority_weight` adelanta las paradas marcadas en `priorities`
+/// (0 = solo coste geográfico)
+pub fn optimize_with_constraints(
+    matrix: &CostMatrix,
+    priorities: &[bool],
+    priority_weight: f64,
+    slots: &[DeliverySlot],
+    criteria: &StoppingCriteria,
+) -> LocalOptimizationResult {
+    let started = Instant::now();
+    let slot_penalty = SLOT_PENALTY * matrix.mean_cost();
+    let initial = nearest_neighbor(matrix);
+    let initial_cost = route_cost(matrix, &initial);
+    let outcome = two_opt(
+        initial,
+        |order| {
+            weighted_route_cost(matrix, order, priorities, priority_weight)
+                + slot_penalty * slot_violations(order, slots) as f64
+        },
+        criteria,
+    );
+    let total_cost = route_cost(matrix, &outcome.order);
+    let improvement_ratio = if initial_cost > 0.0 {
+        (initial_cost - total_cost) / initial_cost
+    } else {
+        0.0
+    };
+
+    LocalOptimizationResult {
+        order: outcome.order,
+        total_cost,
+        cost_source: matrix.source(),
+        stats: LocalOptimizerStats {
+            initial_cost,
+            total_cost,
+            improvement_ratio,
+            iterations: outcome.iterations,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            stop_reason: outcome.stop_reason,
+            cost_source: matrix.source(),
+        },
+    }
+}
+
+/// Coste total de recorrer las paradas en el orden dado
+pub fn route_cost(matrix: &CostMatrix, order: &[usize]) -> f64 {
+    order
+        .windows(2)
+        .map(|pair| matrix.cost(pair[0], pair[1]))
+        .sum()
+}
+
+/// Coste de la ruta más `priority_weight` × coste acumulado al llegar a cada parada prioritaria
+fn weighted_route_cost(matrix: &CostMatrix, order: &[usize], priorities: &[bool], priority_weight: f64) -> f64 {
+    if priority_weight <= 0.0 || !priorities.contains(&true) {
+        return route_cost(matrix, order);
+    }
+
+    let mut travelled = 0.0;
+    let mut penalty = 0.0;
+    for pair in order.windows(2) {
+        travelled += matrix.cost(pair[0], pair[1]);
+        if priorities.get(pair[1]).copied().unwrap_or(false) {
+            penalty += travelled;
+        }
+    }
+
+    travelled + priority_weight * penalty
+}
+
+/// Paquetes de mañana en la segunda mitad de la ruta o de tarde en la primera
+/// (la parada de salida no cuenta)
+fn slot_violations(order: &[usize], slots: &[DeliverySlot]) -> usize {
+    let deliveries = order.len().saturating_sub(1);
+    let first_half = deliveries.div_ceil(2);
+
+    order
+        .iter()
+        .skip(1)
+        .enumerate()
+        .filter(|&(position, &stop)| match slots.get(stop) {
+            Some(DeliverySlot::Morning) => position >= first_half,
+            Some(DeliverySlot::Afternoon) => position < first_half,
+            _ => false,
+        })
+        .count()
+}
+
+fn nearest_neighbor(matrix: &CostMatrix) -> Vec<usize> {
+    let n = matrix.size();
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut visited = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    let mut current = 0;
+    visited[current] = true;
+    order.push(current);
+
+    while order.len() < n {
+        let next = (0..n)
+            .filter(|&j| !visited[j])
+            .min_by(|&a, &b| matrix.cost(current, a).total_cmp(&matrix.cost(current, b)))
+            .expect("quedan paradas sin visitar");
+        visited[next] = true;
+        order.push(next);
+        current = next;
+    }
+
+    order
+}
+
+struct TwoOptOutcome {
+    order: Vec<usize>,
+    iterations: usize,
+    stop_reason: StopReason,
+}
+
+/// Mejora 2-opt manteniendo fija la primera parada.
+///
+/// Se evalúa el objetivo completo en cada movimiento porque la matriz de
+/// duraciones no es simétrica (invertir un tramo cambia su coste) y el peso
+/// de las paradas prioritarias depende de su posición.
+fn two_opt(
+    mut order: Vec<usize>,
+    objective: impl Fn(&[usize]) -> f64,
+    criteria: &StoppingCriteria,
+) -> TwoOptOutcome {
+    let n = order.len();
+    if n < 4 {
+        return TwoOptOutcome { order, iterations: 0, stop_reason: StopReason::LocalOptimum };
+    }
+
+    let started = Instant::now();
+    let initial_cost = objective(&order);
+    let mut best_cost = initial_cost;
+    let mut iterations = 0;
+    let mut since_improvement = 0;
+
+    let stop_reason = 'search: loop {
+        let mut improved = false;
+        for i in 1..n - 1 {
+            for k in i + 1..n {
+                if started.elapsed() >= criteria.time_budget {
+                    break 'search StopReason::TimeBudget;
+                }
+                if since_improvement >= criteria.max_no_improvement {
+                    break 'search StopReason::NoImprovement;
+                }
+                iterations += 1;
+
+                order[i..=k].reverse();
+                let cost = objective(&order);
+                if cost + IMPROVEMENT_EPSILON < best_cost {
+                    best_cost = cost;
+                    improved = true;
+                    since_improvement = 0;
+                    let reached = criteria
+                        .target_improvement
+                        .is_some_and(|target| initial_cost > 0.0 && (initial_cost - best_cost) / initial_cost >= target);
+                    if reached {
+                        break 'search StopReason::TargetReached;
+                    }
+                } else {
+                    order[i..=k].reverse();
+                    since_improvement += 1;
+                }
+            }
+        }
+        if !improved {
+            break StopReason::LocalOptimum;
+        }
+    };
+
+    TwoOptOutcome { order, iterations, stop_reason }
+}
+
+#[derive(Default)]
+pub struct LocalOptimizerService {
+    matrix_service: Option<MapboxMatrixService>,
+    redis: Option<RedisClient>,
+    priority_weight: f64,
+    stopping: StoppingCriteria,
+}
+
+impl LocalOptimizerService {
+    /// Optimizador con distancia haversine
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Optimizador con duraciones de tráfico de Mapbox Matrix (cacheadas en Redis)
+    pub fn with_traffic(matrix_service: MapboxMatrixService, redis: Option<RedisClient>) -> Self {
+        Self {
+            matrix_service: Some(matrix_service),
+            redis,
+            ..Self::default()
+        }
+    }
+
+    /// Peso de las paradas prioritarias (0 = ignorar prioridades)
+    pub fn with_priority_weight(mut self, priority_weight: f64) -> Self {
+        self.priority_weight = priority_weight.max(0.0);
+        self
+    }
+
+    /// Presupuesto de tiempo, límite sin mejora y mejora objetivo del 2-opt
+    pub fn with_stopping_criteria(mut self, stopping: StoppingCriteria) -> Self {
+        self.stopping = stopping;
+        self
+    }
+
+    pub async fn optimize(&self, stops: &[RouteStop]) -> LocalOptimizationResult {
+        let matrix = self.build_cost_matrix(stops).await;
+        let priorities: Vec<bool> = stops.iter().map(|s| s.high_priority).collect();
+        let slots: Vec<DeliverySlot> = stops.iter().map(|s| s.delivery_slot).collect();
+        let result = optimize_with_constraints(&matrix, &priorities, self.priority_weight, &slots, &self.stopping);
+
+        log::info!(
+            "✅ Optimización local: {} paradas, coste {:.2} ({:?}), mejora {:.1}% en {} ms ({:?})",
+            stops.len(),
+            result.total_cost,
+            result.cost_source,
+            result.stats.improvement_ratio * 100.0,
+            result.stats.elapsed_ms,
+            result.stats.stop_reason
+        );
+
+        result
+    }
+
+    /// Matriz de duraciones si está disponible, haversine en caso contrario
+    async fn build_cost_matrix(&self, stops: &[RouteStop]) -> CostMatrix {
+        let Some(matrix_service) = &self.matrix_service else {
+            return CostMatrix::haversine(stops);
+        };
+
+        if stops.len() < 2 {
+            return CostMatrix::haversine(stops);
+        }
+
+        if stops.len() > matrix_service.max_coordinates() {
+            log::warn!(
+                "⚠️ {} paradas superan el límite de Matrix API ({}), usando haversine",
+                stops.len(),
+                matrix_service.max_coordinates()
+            );
+            return CostMatrix::haversine(stops);
+        }
+
+        let coordinates: Vec<(f64, f64)> = stops.iter().map(|s| (s.longitude, s.latitude)).collect();
+        let cache_key = self
+            .redis
+            .as_ref()
+            .map(|redis| redis.matrix_key(&stop_set_id(&coordinates)));
+
+        if let (Some(redis), Some(key)) = (&self.redis, &cache_key) {
+            if let Ok(Some(durations)) = redis.get::<Vec<Vec<f64>>>(key).await {
+                return CostMatrix::from_durations(durations);
+            }
+        }
+
+        match matrix_service.fetch_durations(&coordinates).await {
+            Ok(durations) => {
+                if let (Some(redis), Some(key)) = (&self.redis, &cache_key) {
+                    if let Err(e) = redis.set(key, &durations, MATRIX_CACHE_TTL).await {
+                        log::warn!("⚠️ No se pudo cachear la matriz: {}", e);
+                    }
+                }
+                CostMatrix::from_durations(durations)
+            }
+            Err(e) => {
+                log::warn!("⚠️ Matrix API no disponible ({}), usando haversine", e);
+                CostMatrix::haversine(stops)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stops_in_line(count: usize) -> Vec<RouteStop> {
+        (0..count)
+            .map(|i| RouteStop {
+                latitude: 48.85,
+                longitude: 2.30 + i as f64 * 0.01,
+                high_priority: false,
+                delivery_slot: DeliverySlot::AnyTime,
+            })
+            .collect()
+    }
+
+    /// Optimización sin prioridades ni franjas
+    fn optimize(matrix: &CostMatrix) -> LocalOptimizationResult {
+        optimize_with_constraints(matrix, &[], 0.0, &[], &StoppingCriteria::default())
+    }
+
+    #[test]
+    fn test_haversine_follows_straight_line() {
+        let result = optimize(&CostMatrix::haversine(&stops_in_line(4)));
+        assert_eq!(result.order, vec![0, 1, 2, 3]);
+        assert_eq!(result.cost_source, CostSource::Distance);
+    }
+
+    #[test]
+    fn test_uses_provided_durations_over_distances() {
+        // En línea recta el orden sería 0-1-2-3, pero cruzar hasta 1 es lento
+        // (puente, tráfico): las duraciones hacen preferible 0-3-2-1.
+        let durations = vec![
+            vec![0.0, 900.0, 600.0, 60.0],
+            vec![900.0, 0.0, 60.0, 900.0],
+            vec![600.0, 60.0, 0.0, 60.0],
+            vec![60.0, 900.0, 60.0, 0.0],
+        ];
+
+        let result = optimize(&CostMatrix::from_durations(durations));
+
+        assert_eq!(result.order, vec![0, 3, 2, 1]);
+        assert_eq!(result.cost_source, CostSource::Duration);
+        assert_eq!(result.total_cost, 180.0);
+    }
+
+    #[test]
+    fn test_two_opt_removes_crossing() {
+        let stops = stops_in_line(5);
+        let matrix = CostMatrix::haversine(&stops);
+        let improved = two_opt(vec![0, 3, 2, 1, 4], |order| route_cost(&matrix, order), &StoppingCriteria::default());
+        assert_eq!(improved.order, vec![0, 1, 2, 3, 4]);
+        assert_eq!(improved.stop_reason, StopReason::LocalOptimum);
+    }
+
+    #[test]
+    fn test_priority_weight_moves_rcs_stop_earlier() {
+        // Salida en (0,0), tres paradas hacia el este y un RCS (4) al norte
+        let points = [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0), (0.0, 2.0)];
+        let durations: Vec<Vec<f64>> = points
+            .iter()
+            .map(|a: &(f64, f64)| points.iter().map(|b| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()).collect())
+            .collect();
+        let matrix = CostMatrix::from_durations(durations);
+        let priorities = [false, false, false, false, true];
+        let position = |order: &[usize]| order.iter().position(|&i| i == 4).unwrap();
+
+        let criteria = StoppingCriteria::default();
+        let by_distance = optimize_with_constraints(&matrix, &priorities, 0.0, &[], &criteria);
+        let prioritized = optimize_with_constraints(&matrix, &priorities, 2.0, &[], &criteria);
+
+        assert_eq!(by_distance.order, vec![0, 1, 2, 3, 4]);
+        assert!(position(&prioritized.order) < position(&by_distance.order));
+        // La salida sigue fija y no se descarta ninguna parada
+        assert_eq!(prioritized.order[0], 0);
+        assert_eq!(prioritized.order.len(), 5);
+    }
+
+    #[test]
+    fn test_afternoon_slots_are_scheduled_after_morning_slots() {
+        // Salida en 0; las entregas de tarde (1, 2) están más cerca que las de mañana (3, 4)
+        let durations: Vec<Vec<f64>> = (0..5)
+            .map(|a: i32| (0..5).map(|b: i32| (a - b).abs() as f64).collect())
+            .collect();
+        let matrix = CostMatrix::from_durations(durations);
+        let slots = [
+            DeliverySlot::AnyTime,
+            DeliverySlot::Afternoon,
+            DeliverySlot::Afternoon,
+            DeliverySlot::Morning,
+            DeliverySlot::Morning,
+        ];
+        let position = |order: &[usize], stop: usize| order.iter().position(|&i| i == stop).unwrap();
+
+        assert_eq!(optimize(&matrix).order, vec![0, 1, 2, 3, 4]);
+
+        let result = optimize_with_constraints(&matrix, &[], 0.0, &slots, &StoppingCriteria::default());
+
+        assert_eq!(result.order[0], 0);
+        for morning in [3, 4] {
+            for afternoon in [1, 2] {
+                assert!(position(&result.order, morning) < position(&result.order, afternoon));
+            }
+        }
+    }
+
+    /// Paradas pseudoaleatorias reproducibles alrededor de París
+    fn scattered_stops(count: usize) -> Vec<RouteStop> {
+        let mut seed: u64 = 42;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as f64 / (1u64 << 31) as f64
+        };
+        (0..count)
+            .map(|_| RouteStop {
+                latitude: 48.80 + next() * 0.1,
+                longitude: 2.25 + next() * 0.2,
+                high_priority: false,
+                delivery_slot: DeliverySlot::AnyTime,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reports_improvement_over_nearest_neighbor() {
+        let matrix = CostMatrix::haversine(&scattered_stops(60));
+        let result = optimize(&matrix);
+
+        assert_eq!(result.stats.initial_cost, route_cost(&matrix, &nearest_neighbor(&matrix)));
+        assert!(result.stats.improvement_ratio > 0.0);
+        assert!(result.total_cost < result.stats.initial_cost);
+        assert_eq!(result.stats.stop_reason, StopReason::LocalOptimum);
+    }
+
+    #[test]
+    fn test_respects_time_budget() {
+        let matrix = CostMatrix::haversine(&scattered_stops(400));
+        let criteria = StoppingCriteria {
+            time_budget: Duration::from_millis(5),
+            max_no_improvement: usize::MAX,
+            target_improvement: None,
+        };
+
+        let result = optimize_with_constraints(&matrix, &[], 0.0, &[], &criteria);
+
+        assert_eq!(result.stats.stop_reason, StopReason::TimeBudget);
+        assert!(result.stats.elapsed_ms < 500, "tardó {} ms", result.stats.elapsed_ms);
+        assert!(result.stats.improvement_ratio >= 0.0);
+        assert_eq!(result.order.len(), 400);
+    }
+
+    #[test]
+    fn test_stops_at_target_improvement() {
+        let matrix = CostMatrix::haversine(&scattered_stops(60));
+        let criteria = StoppingCriteria {
+            target_improvement: Some(0.01),
+            ..StoppingCriteria::default()
+        };
+
+        let result = optimize_with_constraints(&matrix, &[], 0.0, &[], &criteria);
+
+        assert_eq!(result.stats.stop_reason, StopReason::TargetReached);
+        assert!(result.stats.improvement_ratio >= 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_haversine_above_matrix_limit() {
+        let service = LocalOptimizerService::with_traffic(
+            MapboxMatrixService::new("test-token".to_string()),
+            None,
+        );
+        let stops = stops_in_line(crate::services::mapbox_matrix_service::MAX_TRAFFIC_COORDINATES + 1);
+
+        let result = service.optimize(&stops).await;
+
+        assert_eq!(result.cost_source, CostSource::Distance);
+        assert_eq!(result.order.len(), stops.len());
+    }
+}
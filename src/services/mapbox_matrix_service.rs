@@ -0,0 +1,137 @@
+//! Servicio de Mapbox Matrix API
+//!
+//! Obtiene la matriz de duraciones reales de conducción (con tráfico)
+//! entre un conjunto de paradas.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Perfil de conducción con tráfico en tiempo real
+const TRAFFIC_PROFILE: &str = "mapbox/driving-traffic";
+
+/// Límite de coordenadas por petición para el perfil `driving-traffic`
+pub const MAX_TRAFFIC_COORDINATES: usize = 10;
+
+/// Duración usada cuando Mapbox no encuentra ruta entre dos paradas (segundos)
+const UNREACHABLE_DURATION: f64 = 86_400.0;
+
+#[derive(Debug, Deserialize)]
+struct MatrixApiResponse {
+    code: String,
+    durations: Option<Vec<Vec<Option<f64>>>>,
+    message: Option<String>,
+}
+
+pub struct MapboxMatrixService {
+    mapbox_token: String,
+    client: reqwest::Client,
+}
+
+impl MapboxMatrixService {
+    pub fn new(mapbox_token: String) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(15))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            mapbox_token,
+            client,
+        }
+    }
+
+    /// Número máximo de paradas que admite una sola petición
+    pub fn max_coordinates(&self) -> usize {
+        MAX_TRAFFIC_COORDINATES
+    }
+
+    /// Obtener la matriz de duraciones (segundos) entre coordenadas `(lon, lat)`
+    pub async fn fetch_durations(&self, coordinates: &[(f64, f64)]) -> Result<Vec<Vec<f64>>> {
+        if coordinates.len() < 2 {
+            return Err(anyhow!("Se necesitan al menos 2 coordenadas"));
+        }
+        if coordinates.len() > MAX_TRAFFIC_COORDINATES {
+            return Err(anyhow!(
+                "Demasiadas coordenadas para Matrix API: {} (máximo {})",
+                coordinates.len(),
+                MAX_TRAFFIC_COORDINATES
+            ));
+        }
+
+        let coords = coordinates
+            .iter()
+            .map(|(lon, lat)| format!("{:.6},{:.6}", lon, lat))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let url = format!(
+            "https://api.mapbox.com/directions-matrix/v1/{}/{}?annotations=duration&access_token={}",
+            TRAFFIC_PROFILE, coords, self.mapbox_token
+        );
+
+        log::info!("🚦 Solicitando matriz de duraciones para {} paradas", coordinates.len());
+
+        let response = self.client.get(&url).send().await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Matrix API error {}: {}", status, error_text));
+        }
+
+        let matrix: MatrixApiResponse = response.json().await?;
+
+        if matrix.code != "Ok" {
+            return Err(anyhow!(
+                "Matrix API code {}: {}",
+                matrix.code,
+                matrix.message.unwrap_or_default()
+            ));
+        }
+
+        let durations = matrix
+            .durations
+            .ok_or_else(|| anyhow!("Matrix API sin durations"))?;
+
+        Ok(durations
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|d| d.unwrap_or(UNREACHABLE_DURATION))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// Identificador estable de un conjunto ordenado de paradas, para cachear su matriz.
+///
+/// SHA-256 en lugar de `DefaultHasher`, cuya salida puede cambiar entre
+/// versiones de Rust y dejaría huérfanas las matrices guardadas en Redis.
+/// El orden cuenta: la fila i de la matriz es la parada i.
+pub fn stop_set_id(coordinates: &[(f64, f64)]) -> String {
+    let mut hasher = Sha256::new();
+    for (lon, lat) in coordinates {
+        hasher.update(format!("{:.5},{:.5};", lon, lat).as_bytes());
+    }
+    let hash: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}:{}", coordinates.len(), hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_set_id_is_stable_and_order_sensitive() {
+        let stops = [(2.3522, 48.8566), (2.3314, 48.8686)];
+
+        // Fijado: un cambio de toolchain no debe invalidar las matrices cacheadas
+        assert_eq!(
+            stop_set_id(&stops),
+            "2:1a2bc8e04a5898ffa6b1f88efff521eb6a919bf9e1d3c573a7b65e9d014f68e2"
+        );
+        assert_ne!(stop_set_id(&stops), stop_set_id(&[stops[1], stops[0]]));
+    }
+}
@@ -3,11 +3,21 @@
 //! Este módulo maneja la comunicación con la API de optimización de rutas de Mapbox.
 
 use anyhow::{anyhow, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use reqwest::Client;
 use std::time::Duration;
 
+use crate::dto::colis_prive_dto::PackageData;
 use crate::dto::mapbox_optimization_dto::*;
+use crate::utils::errors::OptimizationError;
+
+/// API v2 soporta hasta 1000 locations
+const MAX_STOPS: usize = 1000;
+
+const SERVICE_NAME: &str = "Mapbox Optimization";
+
+/// Tiempo de parada por defecto (segundos) si el paquete no indica el suyo
+const DEFAULT_SERVICE_DURATION_SECS: u32 = 120;
 
 pub struct MapboxOptimizationService {
     mapbox_token: String,
@@ -32,32 +42,28 @@ impl MapboxOptimizationService {
         &self,
         packages: Vec<OptimizationPackage>,
         warehouse_location: Option<(f64, f64)>, // (longitude, latitude)
-    ) -> Result<OptimizationResponse> {
+    ) -> Result<OptimizationResponse, OptimizationError> {
         log::info!("🚀 Iniciando optimización con Mapbox v2 para {} paquetes", packages.len());
 
-        // Validar que tenemos paquetes con coordenadas
-        let packages_with_coords: Vec<_> = packages.iter()
-            .filter(|pkg| pkg.coord_x_destinataire.is_some() && pkg.coord_y_destinataire.is_some())
-            .collect();
-
-        if packages_with_coords.is_empty() {
-            return Ok(OptimizationResponse {
-                success: false,
-                message: Some("No hay paquetes con coordenadas válidas para optimizar".to_string()),
-                data: None,
-            });
+        // Tournée vacía: nada que ordenar
+        if packages.is_empty() {
+            log::info!("ℹ️ Tournée sin paquetes, no se llama a Mapbox");
+            return Ok(optimization_response(Vec::new(), Vec::new()));
         }
 
-        // API v2 soporta hasta 1000 locations
-        if packages_with_coords.len() > 1000 {
-            log::warn!("⚠️ API v2 limita a 1000 locations, usando solo las primeras 1000");
-        }
-
-        let packages_to_optimize: Vec<_> = packages_with_coords.iter().take(1000).cloned().collect();
+        let packages_to_optimize = select_stops(&packages)?;
         log::info!("📍 Optimizando {} paquetes con coordenadas válidas", packages_to_optimize.len());
 
+        // Una sola parada: su orden óptimo es ella misma y un problema de ida y
+        // vuelta con un único servicio es degenerado para el optimizador
+        if let [single] = packages_to_optimize.as_slice() {
+            log::info!("ℹ️ Una sola parada ({}), no se llama a Mapbox", single.reference_colis);
+            return Ok(optimization_response(vec![single_stop(single)], Vec::new()));
+        }
+
         // Construir routing problem document para v2
-        let routing_problem = self.build_routing_problem_v2(&packages_to_optimize, warehouse_location)?;
+        let routing_problem = self.build_routing_problem_v2(&packages_to_optimize, warehouse_location)
+            .map_err(|e| rejected(e.to_string()))?;
 
         log::info!("📋 Enviando routing problem a Mapbox Optimization API v2");
 
@@ -73,167 +79,14 @@ impl MapboxOptimizationService {
         log::info!("🎯 Solución obtenida de Mapbox v2");
 
         // Paso 3: Procesar la solución y convertir a nuestro formato
-        let optimized_packages = self.process_solution_v2(&solution, &packages_to_optimize)?;
+        let optimized_packages = self.process_solution_v2(&solution, &packages_to_optimize)
+            .map_err(|e| rejected(e.to_string()))?;
 
         log::info!("✅ Optimización completada: {} paquetes optimizados", optimized_packages.len());
 
-        Ok(OptimizationResponse {
-            success: true,
-            message: Some("Ruta optimizada exitosamente con Mapbox v2".to_string()),
-            data: Some(OptimizationData {
-                matricule_chauffeur: None,
-                date_tournee: Some(Utc::now().to_rfc3339()),
-                optimized_packages,
-            }),
-        })
-    }
-
-    /// Llamar a Mapbox Optimization API v1
-    async fn call_optimization_v1(&self, coordinates: &str) -> Result<MapboxOptimizationResponse> {
-        let url = format!(
-            "https://api.mapbox.com/optimized-trips/v1/mapbox/driving/{}?roundtrip=true&access_token={}",
-            coordinates, self.mapbox_token
-        );
-
-        log::info!("📤 Enviando a: {}", url);
-
-        let response = self.client
-            .get(&url)
-            .header("User-Agent", "RouteOptimizer/1.0")
-            .send()
-            .await?;
-
-        let status = response.status();
-        let response_text = response.text().await?;
-
-        log::info!("📡 Mapbox response status: {}", status);
-        log::debug!("📄 Mapbox response body: {}", response_text);
-
-        if !status.is_success() {
-            return Err(anyhow!("Mapbox API error {}: {}", status, response_text));
-        }
-
-        let optimization_response: MapboxOptimizationResponse = serde_json::from_str(&response_text)
-            .map_err(|e| anyhow!("Error parsing Mapbox optimization response: {}", e))?;
+        let dropped_services = dropped_services(&optimized_packages, &packages_to_optimize);
 
-        if optimization_response.code != "Ok" {
-            return Err(anyhow!("Mapbox optimization failed: {} - {}", 
-                optimization_response.code, 
-                optimization_response.message.unwrap_or_default()));
-        }
-
-        Ok(optimization_response)
-    }
-
-    /// Esperar por la solución de optimización
-    async fn wait_for_solution(&self, job_id: &str) -> Result<MapboxOptimizationResponse> {
-        let url = format!(
-            "https://api.mapbox.com/optimized-trips/v1/{}?access_token={}",
-            job_id, self.mapbox_token
-        );
-
-        let mut attempts = 0;
-        let max_attempts = 60; // Máximo 5 minutos (5 segundos * 60)
-        let delay = Duration::from_secs(5);
-
-        loop {
-            attempts += 1;
-            log::info!("⏳ Esperando solución de Mapbox (intento {}/{})", attempts, max_attempts);
-
-            let response = self.client
-                .get(&url)
-                .header("User-Agent", "RouteOptimizer/1.0")
-                .send()
-                .await?;
-
-            let status = response.status();
-            let response_text = response.text().await?;
-
-            if status == 202 {
-                // Aún procesando
-                if attempts >= max_attempts {
-                    return Err(anyhow!("Timeout esperando solución de Mapbox después de {} intentos", max_attempts));
-                }
-                tokio::time::sleep(delay).await;
-                continue;
-            }
-
-            if status == 200 {
-                // Solución lista
-                let solution: MapboxOptimizationResponse = serde_json::from_str(&response_text)
-                    .map_err(|e| anyhow!("Error parsing Mapbox solution: {}", e))?;
-                return Ok(solution);
-            }
-
-            return Err(anyhow!("Mapbox API error {}: {}", status, response_text));
-        }
-    }
-
-    /// Procesar la solución de Mapbox y convertir a nuestro formato
-    async fn process_solution(
-        &self,
-        solution: &MapboxOptimizationResponse,
-        packages: Vec<&OptimizationPackage>,
-    ) -> Result<Vec<OptimizedPackage>> {
-        let mut optimized_packages = Vec::new();
-
-        for route in &solution.routes {
-            let mut order = 1;
-            
-            for stop in &route.stops {
-                if stop.stop_type == "service" && stop.services.is_some() {
-                    // Este es un servicio de entrega
-                    for service_name in stop.services.as_ref().unwrap() {
-                        // Extraer ID del paquete del nombre del servicio (delivery-{id})
-                        if let Some(package_id) = service_name.strip_prefix("delivery-") {
-                            // Buscar el paquete original
-                            if let Some(original_package) = packages.iter().find(|p| p.id == package_id) {
-                                let mut optimized_package: OptimizedPackage = (*original_package).clone().into();
-                                
-                                // Asignar orden de optimización
-                                optimized_package.numero_ordre = Some(order);
-                                optimized_package.num_ordre_passage_prevu = Some(order);
-                                
-                                // Asignar ETA
-                                optimized_package.eta = Some(stop.eta.clone());
-                                
-                                optimized_packages.push(optimized_package);
-                                order += 1;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(optimized_packages)
-    }
-
-    /// Procesar la solución de Mapbox v1 y convertir a nuestro formato
-    async fn process_solution_v1(
-        &self,
-        solution: &MapboxOptimizationResponse,
-        packages: Vec<&OptimizationPackage>,
-    ) -> Result<Vec<OptimizedPackage>> {
-        let mut optimized_packages = Vec::new();
-
-        if let Some(waypoints) = &solution.waypoints {
-            for (order, waypoint) in waypoints.iter().enumerate() {
-                // El waypoint_index nos dice qué paquete original es
-                if waypoint.waypoint_index < packages.len() {
-                    let original_package = packages[waypoint.waypoint_index];
-                    let mut optimized_package: OptimizedPackage = original_package.clone().into();
-                    
-                    // Asignar orden de optimización (empezando desde 1)
-                    optimized_package.numero_ordre = Some((order + 1) as i32);
-                    optimized_package.num_ordre_passage_prevu = Some((order + 1) as i32);
-                    
-                    optimized_packages.push(optimized_package);
-                }
-            }
-        }
-
-        Ok(optimized_packages)
+        Ok(optimization_response(optimized_packages, dropped_services))
     }
 
     /// Construir routing problem document para v2
@@ -277,8 +130,15 @@ impl MapboxOptimizationService {
             services.push(MapboxService {
                 name: format!("service-{}", idx),
                 location: location_name,
-                duration: 120, // 2 minutos por entrega (ajustable)
+                duration: pkg.service_duration_secs.unwrap_or(DEFAULT_SERVICE_DURATION_SECS),
                 size: None,
+                service_times: pkg.time_window.as_ref().map(|(earliest, latest)| {
+                    vec![MapboxTimeWindow {
+                        earliest: earliest.clone(),
+                        latest: latest.clone(),
+                        window_type: "strict".to_string(),
+                    }]
+                }),
             });
         }
 
@@ -314,7 +174,7 @@ impl MapboxOptimizationService {
     async fn submit_routing_problem_v2(
         &self,
         routing_problem: &MapboxOptimizationRequest,
-    ) -> Result<MapboxSubmitResponse> {
+    ) -> Result<MapboxSubmitResponse, OptimizationError> {
         let url = format!(
             "https://api.mapbox.com/optimized-trips/v2?access_token={}",
             self.mapbox_token
@@ -328,23 +188,25 @@ impl MapboxOptimizationService {
             .header("Content-Type", "application/json")
             .header("User-Agent", "RouteOptimizer/1.0")
             .send()
-            .await?;
+            .await
+            .map_err(request_error)?;
 
         let status = response.status();
         
         if status.as_u16() != 202 {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Mapbox v2 submit error {}: {}", status, error_text));
+            let error_text = response.text().await.unwrap_or_default();
+            log::error!("❌ Mapbox v2 submit error {}: {}", status, error_text);
+            return Err(OptimizationError::from_upstream_message(SERVICE_NAME, &format!("{}: {}", status, error_text)));
         }
 
-        let submit_response: MapboxSubmitResponse = response.json().await?;
+        let submit_response: MapboxSubmitResponse = response.json().await.map_err(request_error)?;
         log::info!("✅ Submitted successfully, status: {}", submit_response.status);
 
         Ok(submit_response)
     }
 
     /// Polling para obtener la solución v2 (GET)
-    async fn poll_solution_v2(&self, job_id: &str) -> Result<MapboxOptimizationV2Response> {
+    async fn poll_solution_v2(&self, job_id: &str) -> Result<MapboxOptimizationV2Response, OptimizationError> {
         let url = format!(
             "https://api.mapbox.com/optimized-trips/v2/{}?access_token={}",
             job_id, self.mapbox_token
@@ -360,7 +222,8 @@ impl MapboxOptimizationService {
                 .get(&url)
                 .header("User-Agent", "RouteOptimizer/1.0")
                 .send()
-                .await?;
+                .await
+                .map_err(request_error)?;
 
             let status = response.status();
 
@@ -373,80 +236,548 @@ impl MapboxOptimizationService {
 
             if status.as_u16() == 200 {
                 // Solución lista
-                let solution: MapboxOptimizationV2Response = response.json().await?;
+                let body = response.text().await.map_err(request_error)?;
+                let solution = parse_solution_v2(&body)?;
                 log::info!("✅ Solución lista después de {} intentos", attempt);
                 return Ok(solution);
             }
 
             // Otro error
-            let error_text = response.text().await?;
-            return Err(anyhow!("Mapbox v2 poll error {}: {}", status, error_text));
+            let error_text = response.text().await.unwrap_or_default();
+            log::error!("❌ Mapbox v2 poll error {}: {}", status, error_text);
+            return Err(OptimizationError::from_upstream_message(SERVICE_NAME, &format!("{}: {}", status, error_text)));
         }
 
-        Err(anyhow!("Timeout esperando solución después de {} intentos", max_attempts))
+        log::error!("❌ Timeout esperando solución después de {} intentos", max_attempts);
+        Err(OptimizationError::UpstreamTimeout { service: SERVICE_NAME.to_string() })
     }
 
     /// Procesar solución v2 y convertir a nuestro formato
+    ///
+    /// - Ningún servicio descartado (`dropped` ausente o vacío): ruta normal.
+    /// - Descarte parcial: paquetes en ruta primero, descartados al final marcados `dropped`.
+    /// - Todo descartado (sin rutas): todos los paquetes marcados `dropped`.
     fn process_solution_v2(
         &self,
         solution: &MapboxOptimizationV2Response,
         packages: &[OptimizationPackage],
     ) -> Result<Vec<OptimizedPackage>> {
-        let mut optimized_packages = Vec::new();
-
-        // Verificar si hay servicios dropped
-        if let Some(dropped) = &solution.dropped {
-            if let Some(services) = &dropped.services {
-                if !services.is_empty() {
-                    log::warn!("⚠️ {} servicios no pudieron ser incluidos en la solución", services.len());
-                }
+        let dropped_indices = match solution.dropped.as_ref().and_then(|d| d.services.as_ref()) {
+            None => {
+                log::debug!("ℹ️ La solución no informa servicios descartados");
+                Vec::new()
             }
+            Some(services) => services
+                .iter()
+                .filter_map(|name| service_index(name))
+                .filter(|&idx| idx < packages.len())
+                .collect(),
+        };
+
+        if !dropped_indices.is_empty() {
+            log::warn!("⚠️ {} servicios no pudieron ser incluidos en la solución", dropped_indices.len());
         }
 
-        // Obtener la primera ruta (asumimos un solo vehículo)
-        let route = solution.routes.first()
-            .ok_or_else(|| anyhow!("No hay rutas en la solución"))?;
-
-        log::info!("📍 Procesando ruta con {} stops", route.stops.len());
-
-        // Procesar cada stop de tipo "service"
-        let mut order = 1;
-        for stop in &route.stops {
-            if stop.stop_type == "service" {
-                if let Some(service_names) = &stop.services {
-                    for service_name in service_names {
-                        // Extraer el índice del nombre del servicio (ej: "service-0" → 0)
-                        if let Some(idx_str) = service_name.strip_prefix("service-") {
-                            if let Ok(pkg_idx) = idx_str.parse::<usize>() {
-                                if let Some(pkg) = packages.get(pkg_idx) {
-                                    let mut optimized_pkg = OptimizedPackage::from(pkg.clone());
-                                    optimized_pkg.numero_ordre = Some(order);
-                                    optimized_pkg.num_ordre_passage_prevu = Some(order);
-                                    optimized_pkg.eta = Some(stop.eta.clone());
-                                    
-                                    optimized_packages.push(optimized_pkg);
-                                    order += 1;
-                                }
-                            }
-                        }
+        let mut optimized_packages = Vec::new();
+        let mut routed_indices = std::collections::HashSet::new();
+
+        // Asumimos un solo vehículo: solo la primera ruta (si existe)
+        if let Some(route) = solution.routes.first() {
+            log::info!("📍 Procesando ruta con {} stops", route.stops.len());
+
+            let mut order = 1;
+            for stop in route.stops.iter().filter(|stop| stop.stop_type == "service") {
+                for pkg_idx in stop.services.iter().flatten().filter_map(|name| service_index(name)) {
+                    if let Some(pkg) = packages.get(pkg_idx) {
+                        let mut optimized_pkg = OptimizedPackage::from(pkg.clone());
+                        optimized_pkg.numero_ordre = Some(order);
+                        optimized_pkg.num_ordre_passage_prevu = Some(order);
+                        optimized_pkg.eta = Some(stop.eta.clone());
+
+                        optimized_packages.push(optimized_pkg);
+                        routed_indices.insert(pkg_idx);
+                        order += 1;
                     }
                 }
             }
         }
 
-        if optimized_packages.is_empty() {
+        if optimized_packages.is_empty() && dropped_indices.is_empty() {
             return Err(anyhow!("No se pudieron extraer paquetes optimizados de la solución"));
         }
 
-        log::info!("✅ {} paquetes procesados de la solución v2", optimized_packages.len());
+        if optimized_packages.is_empty() {
+            log::warn!("⚠️ Mapbox descartó todos los servicios de la solución");
+        }
+
+        // Paquetes descartados (o ausentes de la ruta) al final, sin orden asignado
+        for (idx, pkg) in packages.iter().enumerate() {
+            if !routed_indices.contains(&idx) {
+                if !dropped_indices.contains(&idx) {
+                    log::warn!("⚠️ Paquete {} ausente de la solución, marcado como descartado", pkg.reference_colis);
+                }
+                let mut dropped_pkg = OptimizedPackage::from(pkg.clone());
+                dropped_pkg.dropped = true;
+                optimized_packages.push(dropped_pkg);
+            }
+        }
+
+        log::info!("✅ {} paquetes procesados de la solución v2 ({} en ruta)", optimized_packages.len(), routed_indices.len());
         Ok(optimized_packages)
     }
 }
 
+fn optimization_response(optimized_packages: Vec<OptimizedPackage>, dropped_services: Vec<DroppedService>) -> OptimizationResponse {
+    OptimizationResponse {
+        success: true,
+        message: Some("Ruta optimizada exitosamente con Mapbox v2".to_string()),
+        data: Some(OptimizationData {
+            matricule_chauffeur: None,
+            date_tournee: Some(Utc::now().to_rfc3339()),
+            optimized_packages,
+            dropped_services,
+        }),
+    }
+}
+
+/// Única parada de la ruta, en la posición 1
+fn single_stop(package: &OptimizationPackage) -> OptimizedPackage {
+    let mut stop = OptimizedPackage::from(package.clone());
+    stop.numero_ordre = Some(1);
+    stop.num_ordre_passage_prevu = Some(1);
+    stop
+}
+
+/// Extraer el índice del nombre del servicio (ej: "service-0" → 0)
+fn service_index(service_name: &str) -> Option<usize> {
+    service_name.strip_prefix("service-")?.parse().ok()
+}
+
+/// Paquetes con coordenadas a optimizar; error si no hay ninguno o si superan el límite
+fn select_stops(packages: &[OptimizationPackage]) -> Result<Vec<OptimizationPackage>, OptimizationError> {
+    let (located, unlocated): (Vec<&OptimizationPackage>, Vec<&OptimizationPackage>) = packages
+        .iter()
+        .partition(|pkg| pkg.coord_x_destinataire.is_some() && pkg.coord_y_destinataire.is_some());
+
+    if located.is_empty() {
+        return Err(OptimizationError::NoCoordinates {
+            missing: unlocated.len(),
+            references: unlocated.iter().map(|pkg| pkg.reference_colis.clone()).collect(),
+        });
+    }
+
+    if !unlocated.is_empty() {
+        log::warn!("⚠️ {} paquetes sin coordenadas no se optimizan", unlocated.len());
+    }
+
+    if located.len() > MAX_STOPS {
+        return Err(OptimizationError::TooManyStops {
+            stops: located.len(),
+            max: MAX_STOPS,
+        });
+    }
+
+    Ok(located.into_iter().cloned().collect())
+}
+
+/// Paquete de tournée como parada de Mapbox, con sus coordenadas `(lat, lon)`
+pub fn tournee_stop(package: &PackageData, (latitude, longitude): (f64, f64)) -> OptimizationPackage {
+    OptimizationPackage {
+        id: package.id.clone().unwrap_or_else(|| package.reference_colis.clone()),
+        reference_colis: package.reference_colis.clone(),
+        destinataire_nom: package.destinataire_nom.clone(),
+        destinataire_adresse1: package.destinataire_adresse1.clone(),
+        destinataire_cp: package.destinataire_cp.clone(),
+        destinataire_ville: package.destinataire_ville.clone(),
+        coord_x_destinataire: Some(longitude),
+        coord_y_destinataire: Some(latitude),
+        statut: package.statut.clone(),
+        service_duration_secs: None,
+        time_window: None,
+    }
+}
+
+/// Reordenar los paquetes de la tournée según la solución de Mapbox; los que
+/// no están en la ruta (descartados) quedan al final, en su orden original y
+/// sin orden de paso
+pub fn apply_solution(packages: Vec<PackageData>, solution: &[OptimizedPackage]) -> Vec<PackageData> {
+    let mut pending: Vec<Option<PackageData>> = packages.into_iter().map(Some).collect();
+    let mut ordered = Vec::with_capacity(pending.len());
+
+    for stop in solution.iter().filter(|stop| !stop.dropped) {
+        let Some(slot) = pending
+            .iter_mut()
+            .find(|slot| slot.as_ref().is_some_and(|p| p.reference_colis == stop.reference_colis))
+        else {
+            continue;
+        };
+        let mut package = slot.take().expect("paquete ya ordenado");
+        let position = ordered.len() as i32 + 1;
+        package.numero_ordre = Some(position);
+        package.num_ordre_passage_prevu = Some(position);
+        ordered.push(package);
+    }
+
+    ordered.extend(pending.into_iter().flatten().map(|mut package| {
+        package.numero_ordre = None;
+        package.num_ordre_passage_prevu = None;
+        package
+    }));
+    ordered
+}
+
+/// Comprobar las ventanas horarias de los paquetes (RFC 3339, inicio antes del fin)
+pub fn validate_time_windows(packages: &[OptimizationPackage]) -> std::result::Result<(), String> {
+    for pkg in packages {
+        let Some((start, end)) = &pkg.time_window else {
+            continue;
+        };
+        let parse = |value: &str| {
+            DateTime::parse_from_rfc3339(value.trim()).map_err(|_| {
+                format!("Ventana horaria inválida para {}: '{}' no es RFC 3339", pkg.reference_colis, value)
+            })
+        };
+        if parse(start)? >= parse(end)? {
+            return Err(format!(
+                "Ventana horaria inválida para {}: el inicio debe ser anterior al fin",
+                pkg.reference_colis
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Paquetes descartados con su motivo: con ventana horaria se asume que no era alcanzable
+fn dropped_services(optimized: &[OptimizedPackage], packages: &[OptimizationPackage]) -> Vec<DroppedService> {
+    optimized
+        .iter()
+        .filter(|pkg| pkg.dropped)
+        .filter_map(|dropped| packages.iter().find(|pkg| pkg.reference_colis == dropped.reference_colis))
+        .map(|pkg| DroppedService {
+            id: pkg.id.clone(),
+            reference_colis: pkg.reference_colis.clone(),
+            reason: if pkg.time_window.is_some() {
+                DropReason::TimeWindowInfeasible
+            } else {
+                DropReason::NotRouted
+            },
+        })
+        .collect()
+}
+
+/// Interpretar el cuerpo de una respuesta 200 de Mapbox.
+///
+/// Un 200 con un documento de error, o una solución sin rutas ni servicios
+/// descartados, se rechaza con un error claro (el cuerpo queda en el log).
+fn parse_solution_v2(body: &str) -> Result<MapboxOptimizationV2Response, OptimizationError> {
+    let solution: MapboxOptimizationV2Response = serde_json::from_str(body).map_err(|e| {
+        log::error!("❌ Respuesta 200 de Mapbox con formato inesperado ({}): {}", e, body);
+        rejected("Respuesta de optimización con formato inesperado".to_string())
+    })?;
+
+    let has_dropped = solution
+        .dropped
+        .as_ref()
+        .and_then(|d| d.services.as_ref())
+        .is_some_and(|services| !services.is_empty());
+
+    if solution.routes.is_empty() && !has_dropped {
+        log::error!("❌ Solución de Mapbox sin rutas: {}", body);
+        return Err(rejected("La solución de optimización no contiene rutas".to_string()));
+    }
+
+    Ok(solution)
+}
+
+fn request_error(e: reqwest::Error) -> OptimizationError {
+    if e.is_timeout() {
+        OptimizationError::UpstreamTimeout { service: SERVICE_NAME.to_string() }
+    } else {
+        rejected(e.to_string())
+    }
+}
+
+fn rejected(reason: String) -> OptimizationError {
+    OptimizationError::UpstreamRejected {
+        service: SERVICE_NAME.to_string(),
+        reason,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn test_packages(count: usize) -> Vec<OptimizationPackage> {
+        (0..count)
+            .map(|i| OptimizationPackage {
+                id: format!("pkg{}", i),
+                reference_colis: format!("REF{:03}", i),
+                destinataire_nom: format!("Test User {}", i),
+                destinataire_adresse1: None,
+                destinataire_cp: None,
+                destinataire_ville: None,
+                coord_x_destinataire: Some(2.35 + i as f64 * 0.01),
+                coord_y_destinataire: Some(48.85),
+                statut: None,
+                service_duration_secs: None,
+                time_window: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_packages_without_coordinates_yield_no_coordinates() {
+        let mut packages = test_packages(3);
+        for pkg in &mut packages {
+            pkg.coord_x_destinataire = None;
+        }
+
+        let error = select_stops(&packages).unwrap_err();
+
+        assert_eq!(error.code(), "NO_COORDINATES");
+        assert_eq!(
+            error,
+            OptimizationError::NoCoordinates {
+                missing: 3,
+                references: vec!["REF000".to_string(), "REF001".to_string(), "REF002".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_too_many_stops_yields_too_many_stops() {
+        let error = select_stops(&test_packages(MAX_STOPS + 1)).unwrap_err();
+
+        assert_eq!(error.code(), "TOO_MANY_STOPS");
+        assert_eq!(select_stops(&test_packages(MAX_STOPS)).unwrap().len(), MAX_STOPS);
+    }
+
+    fn solution(value: serde_json::Value) -> MapboxOptimizationV2Response {
+        serde_json::from_value(value).expect("solución de prueba inválida")
+    }
+
+    fn service_stop(service: &str, eta: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "service",
+            "location": "delivery",
+            "eta": eta,
+            "odometer": 0.0,
+            "services": [service]
+        })
+    }
+
+    #[test]
+    fn test_process_solution_none_dropped() {
+        let service = MapboxOptimizationService::new("test-token".to_string());
+        let solution = solution(serde_json::json!({
+            "routes": [{
+                "vehicle": "vehicle-1",
+                "stops": [
+                    service_stop("service-1", "2025-01-01T08:10:00Z"),
+                    service_stop("service-0", "2025-01-01T08:20:00Z")
+                ]
+            }]
+        }));
+
+        let result = service.process_solution_v2(&solution, &test_packages(2)).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].reference_colis, "REF001");
+        assert_eq!(result[0].numero_ordre, Some(1));
+        assert_eq!(result[1].reference_colis, "REF000");
+        assert!(result.iter().all(|p| !p.dropped));
+    }
+
+    #[test]
+    fn test_process_solution_partial_dropped() {
+        let service = MapboxOptimizationService::new("test-token".to_string());
+        let solution = solution(serde_json::json!({
+            "dropped": { "services": ["service-1"] },
+            "routes": [{
+                "vehicle": "vehicle-1",
+                "stops": [
+                    service_stop("service-2", "2025-01-01T08:10:00Z"),
+                    service_stop("service-0", "2025-01-01T08:20:00Z")
+                ]
+            }]
+        }));
+
+        let result = service.process_solution_v2(&solution, &test_packages(3)).unwrap();
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0].reference_colis, "REF002");
+        assert_eq!(result[1].reference_colis, "REF000");
+        assert!(!result[0].dropped && !result[1].dropped);
+        assert_eq!(result[2].reference_colis, "REF001");
+        assert!(result[2].dropped);
+        assert_eq!(result[2].numero_ordre, None);
+    }
+
+    #[test]
+    fn test_process_solution_all_dropped() {
+        let service = MapboxOptimizationService::new("test-token".to_string());
+        let solution = solution(serde_json::json!({
+            "dropped": { "services": ["service-0", "service-1"], "shipments": [] },
+            "routes": []
+        }));
+
+        let result = service.process_solution_v2(&solution, &test_packages(2)).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().all(|p| p.dropped && p.numero_ordre.is_none()));
+    }
+
+    #[test]
+    fn test_process_solution_empty_without_dropped_is_error() {
+        let service = MapboxOptimizationService::new("test-token".to_string());
+        let solution = solution(serde_json::json!({ "dropped": { "services": [] }, "routes": [] }));
+
+        assert!(service.process_solution_v2(&solution, &test_packages(2)).is_err());
+    }
+
+    #[test]
+    fn test_malformed_solution_body_is_rejected() {
+        for body in [
+            r#"{"message": "Not Found", "code": "NotFound"}"#,
+            "<html>Bad Gateway</html>",
+            r#"{"routes": []}"#,
+        ] {
+            let error = parse_solution_v2(body).unwrap_err();
+
+            assert_eq!(error.code(), "UPSTREAM_REJECTED");
+            assert!(matches!(error, OptimizationError::UpstreamRejected { ref service, .. } if service == SERVICE_NAME));
+        }
+
+        assert!(parse_solution_v2(r#"{"dropped": {"services": ["service-0"]}, "routes": []}"#).is_ok());
+    }
+
+    #[test]
+    fn test_service_duration_and_time_window_are_emitted() {
+        let service = MapboxOptimizationService::new("test-token".to_string());
+        let mut packages = test_packages(2);
+        packages[0].service_duration_secs = Some(600);
+        packages[1].time_window = Some(("2025-01-15T08:00:00+01:00".to_string(), "2025-01-15T12:00:00+01:00".to_string()));
+
+        let problem = service.build_routing_problem_v2(&packages, None).unwrap();
+
+        assert_eq!(problem.services[0].duration, 600);
+        assert!(problem.services[0].service_times.is_none());
+        assert_eq!(problem.services[1].duration, DEFAULT_SERVICE_DURATION_SECS);
+        let json = serde_json::to_value(&problem.services[1]).unwrap();
+        assert_eq!(json["service_times"][0]["earliest"], "2025-01-15T08:00:00+01:00");
+        assert_eq!(json["service_times"][0]["type"], "strict");
+    }
+
+    #[test]
+    fn test_dropped_services_report_time_window_reason() {
+        let service = MapboxOptimizationService::new("test-token".to_string());
+        let mut packages = test_packages(3);
+        packages[1].time_window = Some(("2025-01-15T08:00:00+01:00".to_string(), "2025-01-15T08:05:00+01:00".to_string()));
+        let solution = solution(serde_json::json!({
+            "dropped": { "services": ["service-1", "service-2"] },
+            "routes": [{ "vehicle": "vehicle-1", "stops": [service_stop("service-0", "2025-01-01T08:10:00Z")] }]
+        }));
+
+        let optimized = service.process_solution_v2(&solution, &packages).unwrap();
+        let dropped = dropped_services(&optimized, &packages);
+
+        assert_eq!(
+            dropped,
+            vec![
+                DroppedService { id: "pkg1".to_string(), reference_colis: "REF001".to_string(), reason: DropReason::TimeWindowInfeasible },
+                DroppedService { id: "pkg2".to_string(), reference_colis: "REF002".to_string(), reason: DropReason::NotRouted },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tournee_packages_follow_mapbox_order() {
+        let tournee: Vec<PackageData> = ["CP1", "CP2", "CP3", "CP4"]
+            .iter()
+            .map(|reference| PackageData {
+                reference_colis: reference.to_string(),
+                numero_ordre: Some(9),
+                ..Default::default()
+            })
+            .collect();
+
+        let stop = tournee_stop(&tournee[0], (48.8686, 2.3314));
+        assert_eq!((stop.id.as_str(), stop.coord_x_destinataire, stop.coord_y_destinataire), ("CP1", Some(2.3314), Some(48.8686)));
+
+        let routed = |reference: &str, dropped: bool| {
+            let mut pkg = OptimizedPackage::from(OptimizationPackage {
+                reference_colis: reference.to_string(),
+                ..test_packages(1).remove(0)
+            });
+            pkg.dropped = dropped;
+            pkg
+        };
+        let solution = vec![routed("CP3", false), routed("CP1", false), routed("CP4", false), routed("CP2", true)];
+
+        let ordered = apply_solution(tournee, &solution);
+
+        assert_eq!(ordered.iter().map(|p| p.reference_colis.as_str()).collect::<Vec<_>>(), vec!["CP3", "CP1", "CP4", "CP2"]);
+        assert_eq!(ordered.iter().map(|p| p.numero_ordre).collect::<Vec<_>>(), vec![Some(1), Some(2), Some(3), None]);
+        assert_eq!(ordered[2].num_ordre_passage_prevu, Some(3));
+    }
+
+    #[test]
+    fn test_invalid_time_windows_are_rejected() {
+        let mut packages = test_packages(1);
+        assert!(validate_time_windows(&packages).is_ok());
+
+        packages[0].time_window = Some(("08:00".to_string(), "12:00".to_string()));
+        assert!(validate_time_windows(&packages).is_err());
+
+        packages[0].time_window = Some(("2025-01-15T12:00:00+01:00".to_string(), "2025-01-15T08:00:00+01:00".to_string()));
+        assert!(validate_time_windows(&packages).unwrap_err().contains("REF000"));
+    }
+
+    // Con un token inválido cualquier llamada a Mapbox fallaría: que la
+    // respuesta sea correcta demuestra que no hubo llamada
+    fn offline_service() -> MapboxOptimizationService {
+        MapboxOptimizationService::new("token-invalido".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_empty_tournee_returns_empty_order_without_calling_mapbox() {
+        let response = offline_service().optimize_route(Vec::new(), None).await.unwrap();
+
+        assert!(response.success);
+        let data = response.data.unwrap();
+        assert!(data.optimized_packages.is_empty());
+        assert!(data.dropped_services.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_single_package_is_order_one_without_calling_mapbox() {
+        let response = offline_service()
+            .optimize_route(test_packages(1), Some((2.3522, 48.8566)))
+            .await
+            .unwrap();
+
+        let data = response.data.unwrap();
+        assert_eq!(data.optimized_packages.len(), 1);
+        let stop = &data.optimized_packages[0];
+        assert_eq!(stop.reference_colis, "REF000");
+        assert_eq!(stop.numero_ordre, Some(1));
+        assert_eq!(stop.num_ordre_passage_prevu, Some(1));
+        assert!(!stop.dropped);
+        assert!(data.dropped_services.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_single_located_package_among_unlocated_is_not_sent() {
+        let mut packages = test_packages(2);
+        packages[1].coord_x_destinataire = None;
+
+        let data = offline_service().optimize_route(packages, None).await.unwrap().data.unwrap();
+
+        assert_eq!(data.optimized_packages.len(), 1);
+        assert_eq!(data.optimized_packages[0].reference_colis, "REF000");
+    }
+
     #[tokio::test]
     async fn test_mapbox_optimization_service() {
         // Este test requiere un token válido de Mapbox
@@ -470,6 +801,8 @@ mod tests {
                 coord_x_destinataire: Some(2.3522),
                 coord_y_destinataire: Some(48.8566),
                 statut: Some("pending".to_string()),
+                service_duration_secs: None,
+                time_window: None,
             },
             OptimizationPackage {
                 id: "pkg2".to_string(),
@@ -481,6 +814,8 @@ mod tests {
                 coord_x_destinataire: Some(2.3601),
                 coord_y_destinataire: Some(48.8576),
                 statut: Some("pending".to_string()),
+                service_duration_secs: None,
+                time_window: None,
             },
         ];
 
@@ -7,6 +7,34 @@ pub mod colis_prive_companies_service;
 pub mod geocoding_service;
 pub mod address_matching_service;
 pub mod package_processing_service;
+pub mod delivery_details_service;
+pub mod address_cleaning_service;
 pub mod address_cache_service;
-// pub mod mapbox_optimization_service; // Deshabilitado hasta tener acceso a Mapbox v2 Beta
+pub mod local_optimizer_service;
+pub mod mapbox_matrix_service;
+pub mod optimization_history_service;
+pub mod export_service;
+pub mod societe_allowlist_service;
+pub mod eta_service;
+pub mod package_label_service;
+pub mod status_webhook_service;
+pub mod tournee_merge_service;
+pub mod tournee_fetch_service;
+pub mod optimization_provider_service;
+pub mod mapbox_optimization_service;
+pub mod geocoding_quality_service;
+pub mod reattempt_service;
+pub mod full_tournee_service;
+pub mod packages_batch_service;
+pub mod navigation_service;
+pub mod address_validation_service;
+pub mod validation_trends_service;
+pub mod csv_import_service;
+pub mod gpx_export_service;
+pub mod route_depot_service;
+pub mod depot_service;
+pub mod tournee_snapshot_service;
+pub mod tournee_live_service;
+pub mod route_split_service;
+pub mod delivery_geofence_service;
 // pub mod hybrid_processor; // Comentado - legacy, necesita refactoring
\ No newline at end of file
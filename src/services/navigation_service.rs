@@ -0,0 +1,131 @@
+//! Enlaces de navegación de una ruta optimizada
+//!
+//! Convierte el orden optimizado en deep links de Google Maps, Waze o Apple
+//! Plans. Las apps limitan el número de paradas por enlace, así que la ruta
+//! se parte en varios enlaces consecutivos; cada uno sale de la posición
+//! actual del repartidor (el final del enlace anterior).
+
+use serde::{Deserialize, Serialize};
+
+/// App de navegación de destino
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NavigationApp {
+    #[default]
+    Google,
+    Waze,
+    Apple,
+}
+
+impl NavigationApp {
+    /// Paradas por enlace: Google acepta 9 waypoints más el destino, Waze y
+    /// Apple Plans solo un destino
+    pub fn max_stops_per_link(self) -> usize {
+        match self {
+            NavigationApp::Google => 10,
+            NavigationApp::Waze | NavigationApp::Apple => 1,
+        }
+    }
+}
+
+/// Un enlace y las posiciones de la ruta optimizada que cubre
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NavigationLink {
+    pub url: String,
+    pub indices: Vec<usize>,
+}
+
+/// Parada de navegación: varios paquetes seguidos en el mismo punto son una sola
+struct Waypoint {
+    latitude: f64,
+    longitude: f64,
+    indices: Vec<usize>,
+}
+
+fn coordinate(latitude: f64, longitude: f64) -> String {
+    format!("{:.6},{:.6}", latitude, longitude)
+}
+
+fn link_url(app: NavigationApp, waypoints: &[Waypoint]) -> String {
+    let (destination, intermediate) = waypoints.split_last().expect("enlace sin paradas");
+    let destination = coordinate(destination.latitude, destination.longitude);
+
+    match app {
+        NavigationApp::Google => {
+            let mut url = format!(
+                "https://www.google.com/maps/dir/?api=1&destination={}&travelmode=driving",
+                destination
+            );
+            if !intermediate.is_empty() {
+                let stops: Vec<String> = intermediate.iter().map(|w| coordinate(w.latitude, w.longitude)).collect();
+                url.push_str("&waypoints=");
+                url.push_str(&stops.join("%7C"));
+            }
+            url
+        }
+        NavigationApp::Waze => format!("https://waze.com/ul?ll={}&navigate=yes", destination),
+        NavigationApp::Apple => format!("https://maps.apple.com/?daddr={}&dirflg=d", destination),
+    }
+}
+
+/// Enlaces para las coordenadas `(lat, lon)` en orden optimizado; las
+/// posiciones sin coordenadas se saltan
+pub fn build_links(app: NavigationApp, stops: &[Option<(f64, f64)>]) -> Vec<NavigationLink> {
+    let mut waypoints: Vec<Waypoint> = Vec::new();
+    for (index, stop) in stops.iter().enumerate() {
+        let Some((latitude, longitude)) = *stop else { continue };
+        match waypoints.last_mut() {
+            Some(last) if last.latitude == latitude && last.longitude == longitude => last.indices.push(index),
+            _ => waypoints.push(Waypoint { latitude, longitude, indices: vec![index] }),
+        }
+    }
+
+    waypoints
+        .chunks(app.max_stops_per_link())
+        .map(|chunk| NavigationLink {
+            url: link_url(app, chunk),
+            indices: chunk.iter().flat_map(|w| w.indices.iter().copied()).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_google_link_for_small_route() {
+        let stops = vec![
+            Some((48.8686, 2.3314)),
+            None,
+            Some((48.8566, 2.3522)),
+            Some((48.8566, 2.3522)),
+            Some((48.8738, 2.295)),
+        ];
+
+        let links = build_links(NavigationApp::Google, &stops);
+
+        assert_eq!(links.len(), 1);
+        assert_eq!(
+            links[0].url,
+            "https://www.google.com/maps/dir/?api=1&destination=48.873800,2.295000&travelmode=driving\
+             &waypoints=48.868600,2.331400%7C48.856600,2.352200"
+        );
+        assert_eq!(links[0].indices, vec![0, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_links_are_chunked_past_the_app_limit() {
+        let stops: Vec<Option<(f64, f64)>> = (0..25).map(|i| Some((48.8 + i as f64 * 0.001, 2.3))).collect();
+
+        let google = build_links(NavigationApp::Google, &stops);
+        assert_eq!(google.len(), 3);
+        assert_eq!(google[0].indices, (0..10).collect::<Vec<_>>());
+        assert_eq!(google[2].indices, (20..25).collect::<Vec<_>>());
+        assert!(google[1].url.contains("destination=48.819000,2.300000"));
+
+        let waze = build_links(NavigationApp::Waze, &stops[..3]);
+        assert_eq!(waze.len(), 3);
+        assert_eq!(waze[1].url, "https://waze.com/ul?ll=48.801000,2.300000&navigate=yes");
+    }
+}
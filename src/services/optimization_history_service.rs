@@ -0,0 +1,240 @@
+//! Historial de optimizaciones
+//!
+//! Compara el orden de una re-optimización con el orden guardado
+//! anteriormente para indicar al chófer qué paquetes cambiaron de posición.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::dto::colis_prive_dto::{OptimizationEngine, OrderChangeDirection, OrderDiff, PackageOrderChange};
+use crate::models::optimization::{tournee_hash, StoredOptimization};
+use crate::utils::errors::AppError;
+
+/// Decidir si una optimización guardada puede devolverse en lugar de recalcular.
+///
+/// Con `force` siempre se recalcula; si no, se reutiliza un resultado del mismo
+/// motor con antigüedad menor que `max_age`.
+pub fn reusable_optimization(
+    cached: Option<StoredOptimization>,
+    engine: OptimizationEngine,
+    force: bool,
+    max_age: Duration,
+    now: DateTime<Utc>,
+) -> Option<StoredOptimization> {
+    if force {
+        return None;
+    }
+
+    cached.filter(|stored| stored.engine == engine && stored.age(now) < max_age)
+}
+
+/// Optimización guardada de una tournée, o 404 si no hay ninguna para esa fecha
+pub fn stored_or_not_found(
+    stored: Option<StoredOptimization>,
+    societe: &str,
+    matricule: &str,
+    date: &str,
+) -> Result<StoredOptimization, AppError> {
+    stored.ok_or_else(|| {
+        AppError::NotFound(format!("No hay optimización guardada para {}:{} el {}", societe, matricule, date))
+    })
+}
+
+/// Calcular los paquetes que cambiaron de posición entre dos órdenes (posiciones 1..n)
+pub fn compute_order_diff(previous: &[String], current: &[String]) -> OrderDiff {
+    let previous_positions: HashMap<&str, usize> = previous
+        .iter()
+        .enumerate()
+        .map(|(index, reference)| (reference.as_str(), index + 1))
+        .collect();
+    let current_references: std::collections::HashSet<&str> =
+        current.iter().map(|r| r.as_str()).collect();
+
+    let mut diff = OrderDiff::default();
+
+    for (index, reference) in current.iter().enumerate() {
+        let new_position = index + 1;
+        match previous_positions.get(reference.as_str()) {
+            Some(&previous_position) if previous_position == new_position => diff.unchanged += 1,
+            Some(&previous_position) => {
+                let shift = previous_position as i64 - new_position as i64;
+                diff.moved.push(PackageOrderChange {
+                    reference_colis: reference.clone(),
+                    previous_position,
+                    new_position,
+                    shift,
+                    direction: if shift > 0 {
+                        OrderChangeDirection::Up
+                    } else {
+                        OrderChangeDirection::Down
+                    },
+                });
+            }
+            None => diff.added.push(reference.clone()),
+        }
+    }
+
+    diff.removed = previous
+        .iter()
+        .filter(|reference| !current_references.contains(reference.as_str()))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+/// Comprobar que la tournée actual tiene los mismos paquetes que la optimizada.
+///
+/// Si se añadieron o quitaron paquetes desde la optimización, aplicar el orden
+/// perdería o desordenaría paquetes: se devuelve 409 para forzar re-optimizar.
+pub fn check_tournee_unchanged(expected_hash: &str, current_references: &[String]) -> Result<(), AppError> {
+    let current_hash = tournee_hash(current_references);
+    if current_hash != expected_hash {
+        return Err(AppError::Conflict(
+            "La tournée cambió desde la optimización (paquetes añadidos o quitados). Vuelva a optimizar.".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refs(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn cached_optimization(engine: OptimizationEngine) -> StoredOptimization {
+        StoredOptimization::new("PCP0010699", "A187518", "PCP0010699_A187518", "2025-01-15", engine, Vec::new())
+    }
+
+    #[test]
+    fn test_recent_cached_optimization_is_reused() {
+        let cached = cached_optimization(OptimizationEngine::Local);
+        let now = cached.created_at + Duration::minutes(2);
+
+        let reused = reusable_optimization(Some(cached), OptimizationEngine::Local, false, Duration::minutes(10), now);
+
+        assert!(reused.is_some());
+    }
+
+    #[test]
+    fn test_force_recomputes_even_with_cached_result() {
+        let cached = cached_optimization(OptimizationEngine::Local);
+        let now = cached.created_at + Duration::minutes(2);
+
+        let reused = reusable_optimization(Some(cached), OptimizationEngine::Local, true, Duration::minutes(10), now);
+
+        assert!(reused.is_none());
+    }
+
+    #[test]
+    fn test_stale_or_other_engine_result_is_not_reused() {
+        let cached = cached_optimization(OptimizationEngine::Local);
+        let later = cached.created_at + Duration::minutes(30);
+        assert!(reusable_optimization(Some(cached.clone()), OptimizationEngine::Local, false, Duration::minutes(10), later).is_none());
+
+        let now = cached.created_at + Duration::minutes(1);
+        assert!(reusable_optimization(Some(cached), OptimizationEngine::ColisPrive, false, Duration::minutes(10), now).is_none());
+    }
+
+    #[test]
+    fn test_latest_stored_optimization_is_returned() {
+        let stored = cached_optimization(OptimizationEngine::Local);
+        let expected_hash = stored.tournee_hash();
+
+        let latest = stored_or_not_found(Some(stored), "PCP0010699", "A187518", "2025-01-15").unwrap();
+
+        assert_eq!(latest.matricule, "A187518");
+        assert_eq!(latest.tournee_hash(), expected_hash);
+    }
+
+    #[test]
+    fn test_missing_stored_optimization_is_404() {
+        use axum::response::IntoResponse;
+
+        let error = stored_or_not_found(None, "PCP0010699", "A187518", "2025-01-15").unwrap_err();
+
+        assert_eq!(error.into_response().status(), axum::http::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_order_diff_reports_moved_packages() {
+        let before = refs(&["A", "B", "C", "D", "E"]);
+        let after = refs(&["A", "D", "B", "C", "E"]);
+
+        let diff = compute_order_diff(&before, &after);
+
+        assert_eq!(diff.unchanged, 2);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            diff.moved,
+            vec![
+                PackageOrderChange {
+                    reference_colis: "D".to_string(),
+                    previous_position: 4,
+                    new_position: 2,
+                    shift: 2,
+                    direction: OrderChangeDirection::Up,
+                },
+                PackageOrderChange {
+                    reference_colis: "B".to_string(),
+                    previous_position: 2,
+                    new_position: 3,
+                    shift: -1,
+                    direction: OrderChangeDirection::Down,
+                },
+                PackageOrderChange {
+                    reference_colis: "C".to_string(),
+                    previous_position: 3,
+                    new_position: 4,
+                    shift: -1,
+                    direction: OrderChangeDirection::Down,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_diff_reports_added_and_removed() {
+        let diff = compute_order_diff(&refs(&["A", "B", "C"]), &refs(&["A", "C", "X"]));
+
+        assert_eq!(diff.added, refs(&["X"]));
+        assert_eq!(diff.removed, refs(&["B"]));
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.moved[0].reference_colis, "C");
+    }
+
+    #[test]
+    fn test_apply_against_unchanged_tournee_succeeds() {
+        let optimized = StoredOptimization::new(
+            "PCP0010699",
+            "A187518",
+            "PCP0010699_A187518",
+            "2025-01-15",
+            OptimizationEngine::Local,
+            Vec::new(),
+        );
+        let hash = tournee_hash(&refs(&["CP1", "CP2", "CP3"]));
+
+        // El orden de la tournée actual no importa, solo el conjunto de paquetes
+        assert!(check_tournee_unchanged(&hash, &refs(&["CP3", "CP1", "CP2"])).is_ok());
+        assert!(check_tournee_unchanged(&optimized.tournee_hash(), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_apply_against_changed_tournee_conflicts() {
+        let hash = tournee_hash(&refs(&["CP1", "CP2", "CP3"]));
+
+        let added = check_tournee_unchanged(&hash, &refs(&["CP1", "CP2", "CP3", "CP4"]));
+        let removed = check_tournee_unchanged(&hash, &refs(&["CP1", "CP3"]));
+
+        assert!(matches!(added, Err(AppError::Conflict(_))));
+        assert!(matches!(removed, Err(AppError::Conflict(_))));
+    }
+}
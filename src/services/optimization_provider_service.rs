@@ -0,0 +1,90 @@
+//! Orden de proveedores de optimización
+//!
+//! Cada société puede fijar su orden en `company_settings` (p.ej. "local,colisprive");
+//! sin configuración se usa el orden global `OPTIMIZATION_PROVIDER_ORDER`. Si un
+//! proveedor falla se prueba el siguiente. Un `?engine=` explícito fuerza un único
+//! proveedor, sin alternativas.
+
+use crate::dto::colis_prive_dto::OptimizationEngine;
+
+/// Orden por defecto: solo el optimizador de Colis Privé
+pub const DEFAULT_PROVIDER_ORDER: [OptimizationEngine; 1] = [OptimizationEngine::ColisPrive];
+
+/// Leer una lista separada por comas; los valores desconocidos y repetidos se ignoran
+pub fn parse_provider_order(raw: &str) -> Vec<OptimizationEngine> {
+    let mut order = Vec::new();
+
+    for name in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        match OptimizationEngine::parse(name) {
+            Some(engine) if !order.contains(&engine) => order.push(engine),
+            Some(_) => {}
+            None => log::warn!("⚠️ Proveedor de optimización desconocido ignorado: {}", name),
+        }
+    }
+
+    order
+}
+
+/// Proveedores a probar, en orden
+pub fn resolve_provider_order(
+    requested: Option<OptimizationEngine>,
+    company: Option<&[OptimizationEngine]>,
+    global: &[OptimizationEngine],
+) -> Vec<OptimizationEngine> {
+    if let Some(engine) = requested {
+        return vec![engine];
+    }
+
+    [company.unwrap_or_default(), global, &DEFAULT_PROVIDER_ORDER]
+        .into_iter()
+        .find(|order| !order.is_empty())
+        .unwrap_or_default()
+        .to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_companies_route_to_their_preferred_provider() {
+        let global = [OptimizationEngine::ColisPrive];
+        let company_a = parse_provider_order("local, colisprive");
+        let company_b = parse_provider_order("colisprive,local");
+
+        let order_a = resolve_provider_order(None, Some(&company_a), &global);
+        let order_b = resolve_provider_order(None, Some(&company_b), &global);
+
+        assert_eq!(order_a, vec![OptimizationEngine::Local, OptimizationEngine::ColisPrive]);
+        assert_eq!(order_b, vec![OptimizationEngine::ColisPrive, OptimizationEngine::Local]);
+        assert_ne!(order_a[0], order_b[0]);
+    }
+
+    #[test]
+    fn test_unset_company_falls_back_to_global_order() {
+        let global = parse_provider_order("local");
+
+        assert_eq!(resolve_provider_order(None, None, &global), vec![OptimizationEngine::Local]);
+        assert_eq!(resolve_provider_order(None, Some(&[]), &global), vec![OptimizationEngine::Local]);
+        assert_eq!(resolve_provider_order(None, None, &[]), DEFAULT_PROVIDER_ORDER.to_vec());
+    }
+
+    #[test]
+    fn test_explicit_engine_overrides_company_order() {
+        let company = parse_provider_order("local,colisprive");
+
+        assert_eq!(
+            resolve_provider_order(Some(OptimizationEngine::ColisPrive), Some(&company), &[]),
+            vec![OptimizationEngine::ColisPrive]
+        );
+    }
+
+    #[test]
+    fn test_unknown_and_duplicate_providers_are_ignored() {
+        assert_eq!(parse_provider_order("mapquest,local,LOCAL,"), vec![OptimizationEngine::Local]);
+        assert_eq!(
+            parse_provider_order("mapbox,colisprive,Mapbox"),
+            vec![OptimizationEngine::Mapbox, OptimizationEngine::ColisPrive]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! Etiquetas libres de paquetes
+//!
+//! Los dispatchers etiquetan paquetes ("fragile", "signature-required-vip",
+//! "reattempt"...) para flujos operativos ad hoc y filtran la lista por ellas.
+
+use std::collections::HashSet;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::dto::colis_prive_dto::PackageData;
+use crate::utils::errors::AppError;
+
+pub const MAX_LABEL_LENGTH: usize = 40;
+
+/// Etiquetas que marcan un paquete como prioritario en el optimizador local
+pub const PRIORITY_LABELS: [&str; 2] = ["rcs", "priority"];
+
+lazy_static! {
+    static ref LABEL_REGEX: Regex = Regex::new(r"^[a-z0-9][a-z0-9_-]*$").unwrap();
+}
+
+/// Normalizar una etiqueta (minúsculas, sin espacios alrededor) y validar longitud y caracteres
+pub fn normalize_label(raw: &str) -> Result<String, AppError> {
+    let label = raw.trim().to_lowercase();
+
+    if label.is_empty() || label.chars().count() > MAX_LABEL_LENGTH || !LABEL_REGEX.is_match(&label) {
+        let mut error = ValidationError::new("invalid_label");
+        error.message = Some(
+            format!(
+                "La etiqueta debe tener entre 1 y {} caracteres: letras, números, '-' o '_'",
+                MAX_LABEL_LENGTH
+            )
+            .into(),
+        );
+        error.add_param("value".into(), &raw);
+
+        let mut errors = ValidationErrors::new();
+        errors.add("label", error);
+        return Err(AppError::Validation(errors));
+    }
+
+    Ok(label)
+}
+
+/// Conservar solo los paquetes cuya referencia tiene la etiqueta
+pub fn filter_by_label(packages: Vec<PackageData>, labelled: &HashSet<String>) -> Vec<PackageData> {
+    packages
+        .into_iter()
+        .filter(|package| labelled.contains(&package.reference_colis))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(reference: &str) -> PackageData {
+        PackageData {
+            reference_colis: reference.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_label_is_normalized() {
+        assert_eq!(normalize_label("  Signature-Required-VIP ").unwrap(), "signature-required-vip");
+        assert_eq!(normalize_label("reattempt_2").unwrap(), "reattempt_2");
+    }
+
+    #[test]
+    fn test_invalid_labels_are_rejected() {
+        for invalid in ["", "   ", "fragile!", "deux mots", "-fragile", &"x".repeat(MAX_LABEL_LENGTH + 1)] {
+            assert!(
+                matches!(normalize_label(invalid), Err(AppError::Validation(_))),
+                "etiqueta aceptada: {:?}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_keeps_only_labelled_packages() {
+        let mut labelled: HashSet<String> = ["CP2".to_string()].into_iter().collect();
+        let packages = vec![package("CP1"), package("CP2"), package("CP3")];
+
+        let filtered = filter_by_label(packages.clone(), &labelled);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].reference_colis, "CP2");
+
+        // Tras quitar la etiqueta el filtro ya no devuelve el paquete
+        labelled.remove("CP2");
+        assert!(filter_by_label(packages, &labelled).is_empty());
+    }
+}
@@ -1,9 +1,11 @@
 use crate::models::package::{
     ColisPrivePackage, ProcessedPackage, GroupedPackages, 
-    SinglePackage, DeliveryGroup, CustomerGroup, PackageInfo
+    SinglePackage, DeliveryGroup, CustomerGroup, PackageInfo, SubStop
 };
 use crate::models::address::ColisPriveAddress;
+use crate::services::address_cleaning_service::split_street_number;
 use crate::services::address_matching_service::AddressMatchingService;
+use crate::services::delivery_details_service::extract_delivery_details;
 use std::collections::HashMap;
 use anyhow::Result;
 use tracing::{info, warn, error};
@@ -91,20 +93,17 @@ impl PackageProcessingService {
             (libelle, cp, None, true)
         };
         
-        // PASO 2: Extraer número y limpiar libelle
+        // PASO 2: Extraer número (con sufijo bis/ter/quater) y limpiar libelle
+        let (numero_en_libelle, calle) = split_street_number(&libelle_voie);
         let (numero_final, libelle_limpio) = if let Some(num) = num_voie {
             // Si tiene número separado, quitar el número del libelle si está duplicado
-            let libelle_sin_numero = if let Some(captures) = regex::Regex::new(r"^(\d+[A-Z]?)\s+(.+)").unwrap().captures(&libelle_voie) {
-                // El número está en el libelle, extraer solo la calle
-                captures.get(2).map(|m| m.as_str().to_string()).unwrap_or(libelle_voie.clone())
-            } else {
-                libelle_voie.clone()
-            };
-            (num, libelle_sin_numero)
-        } else if let Some(captures) = regex::Regex::new(r"^(\d+[A-Z]?)\s+(.+)").unwrap().captures(&libelle_voie) {
+            // (conservando el sufijo del libelle: num_voie "12" + "12 BIS RUE X" → "12 BIS")
+            match numero_en_libelle {
+                Some(numero) if numero.chars().take_while(|c| c.is_ascii_digit()).collect::<String>() == num.trim() => (numero, calle),
+                _ => (num, calle),
+            }
+        } else if let Some(num) = numero_en_libelle {
             // No tiene número separado, pero está en el libelle
-            let num = captures.get(1).map(|m| m.as_str().to_string()).unwrap_or_default();
-            let calle = captures.get(2).map(|m| m.as_str().to_string()).unwrap_or(libelle_voie.clone());
             (num, calle)
         } else {
             // No tiene número ni separado ni en libelle
@@ -131,6 +130,9 @@ impl PackageProcessingService {
             longitude: if is_problematic { 0.0 } else { colis_package.longitude },
             mailbox_access: false,
             driver_notes: String::new(),
+            door_code: None,
+            floor: None,
+            concierge_notes: None,
             address_id: None,
             code_statut_article: colis_package.code_statut_article,
             is_problematic,
@@ -149,13 +151,8 @@ impl PackageProcessingService {
             // Buscar dirección oficial
             match self.address_matcher.find_colis_prive_address(&colis_addr).await {
             Some(official_address) => {
-                // ✅ MATCH ENCONTRADO - usar datos oficiales
-                processed.official_label = official_address.official_label.clone();
-                processed.latitude = official_address.latitude;
-                processed.longitude = official_address.longitude;
-                processed.mailbox_access = official_address.has_mailbox_access;
-                processed.driver_notes = official_address.driver_notes.unwrap_or_default();
-                processed.address_id = Some(official_address.id);
+                // ✅ MATCH ENCONTRADO - usar datos oficiales y códigos guardados
+                processed.attach_address(&official_address);
                 
                 info!("✅ Match BD encontrado para {}: {}", 
                     tracking, 
@@ -172,12 +169,7 @@ impl PackageProcessingService {
                     company_id,
                 ).await {
                     Ok(new_address) => {
-                        processed.official_label = new_address.official_label.clone();
-                        processed.latitude = new_address.latitude;
-                        processed.longitude = new_address.longitude;
-                        processed.mailbox_access = new_address.has_mailbox_access;
-                        processed.driver_notes = new_address.driver_notes.unwrap_or_default();
-                        processed.address_id = Some(new_address.id);
+                        processed.attach_address(&new_address);
                         
                         info!("🆕 Nueva dirección creada en BD: {}", new_address.official_label);
                     }
@@ -237,9 +229,11 @@ impl PackageProcessingService {
         let first_package_info = {
             let pkg = &packages[0];
             (pkg.official_label.clone(), pkg.latitude, pkg.longitude, 
-             pkg.mailbox_access, pkg.driver_notes.clone())
+             pkg.mailbox_access, pkg.driver_notes.clone(),
+             pkg.door_code.clone(), pkg.floor.clone(), pkg.concierge_notes.clone())
         };
         let total_packages = packages.len();
+        let sub_stops = build_sub_stops(&packages);
         
         // Agrupar por cliente
         let mut customer_groups: HashMap<String, Vec<ProcessedPackage>> = HashMap::new();
@@ -276,7 +270,11 @@ impl PackageProcessingService {
             longitude: first_package_info.2,
             mailbox_access: first_package_info.3,
             driver_notes: first_package_info.4,
+            door_code: first_package_info.5,
+            floor: first_package_info.6,
+            concierge_notes: first_package_info.7,
             customers,
+            sub_stops,
             total_packages,
         })
     }
@@ -286,3 +284,140 @@ impl PackageProcessingService {
         self.address_matcher.get_cache_stats().await
     }
 }
+
+/// Una parada por paquete con sus datos de edificio, ordenadas por planta
+/// (los paquetes sin planta conocida van al final)
+fn build_sub_stops(packages: &[ProcessedPackage]) -> Vec<SubStop> {
+    let mut sub_stops: Vec<SubStop> = packages
+        .iter()
+        .map(|pkg| SubStop {
+            package_id: pkg.id,
+            tracking: pkg.tracking.clone(),
+            customer_name: pkg.customer_name.clone(),
+            details: pkg
+                .customer_indication
+                .as_deref()
+                .map(extract_delivery_details)
+                .unwrap_or_default(),
+        })
+        .collect();
+
+    sub_stops.sort_by(|a, b| {
+        (a.details.etage.is_none(), a.details.etage, &a.details.batiment, &a.details.escalier, &a.details.porte, &a.details.appartement, &a.tracking)
+            .cmp(&(b.details.etage.is_none(), b.details.etage, &b.details.batiment, &b.details.escalier, &b.details.porte, &b.details.appartement, &b.tracking))
+    });
+
+    sub_stops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::address::{Address, AddressAccess};
+
+    fn package(tracking: &str, indication: Option<&str>) -> ProcessedPackage {
+        ProcessedPackage {
+            id: Uuid::new_v4(),
+            tracking: tracking.to_string(),
+            customer_name: format!("CLIENT {}", tracking),
+            phone_number: None,
+            customer_indication: indication.map(|s| s.to_string()),
+            official_label: "10 Rue de Rivoli 75004".to_string(),
+            latitude: 48.8556,
+            longitude: 2.3580,
+            mailbox_access: false,
+            driver_notes: String::new(),
+            door_code: None,
+            floor: None,
+            concierge_notes: None,
+            address_id: None,
+            code_statut_article: None,
+            is_problematic: false,
+        }
+    }
+
+    #[test]
+    fn test_sub_stops_are_ordered_by_floor() {
+        let packages = vec![
+            package("CP1", Some("1er etage porte gauche")),
+            package("CP3", Some("3eme etage appt 31")),
+            package("CP2", Some("Etage 2 porte 21")),
+        ];
+
+        let sub_stops = build_sub_stops(&packages);
+        let floors: Vec<Option<i32>> = sub_stops.iter().map(|s| s.details.etage).collect();
+
+        assert_eq!(floors, vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(sub_stops[1].tracking, "CP2");
+        assert_eq!(sub_stops[1].details.porte.as_deref(), Some("21"));
+        assert_eq!(sub_stops[2].details.appartement.as_deref(), Some("31"));
+    }
+
+    fn stored_address(access: AddressAccess) -> Address {
+        Address {
+            id: Uuid::new_v4(),
+            company_id: None,
+            official_label: "12 Rue Hermel 75018 Paris".to_string(),
+            street_name: "Rue Hermel".to_string(),
+            street_number: Some("12".to_string()),
+            postcode: "75018".to_string(),
+            city: "Paris".to_string(),
+            latitude: 48.8920,
+            longitude: 2.3470,
+            door_code: access.door_code,
+            has_mailbox_access: access.has_mailbox_access,
+            driver_notes: None,
+            floor: access.floor,
+            concierge_notes: access.concierge_notes,
+            last_updated_by: Some("D001".to_string()),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_stored_codes_attach_to_new_package_at_same_address() {
+        let access = AddressAccess {
+            door_code: Some("A1234".to_string()),
+            has_mailbox_access: true,
+            floor: Some("3".to_string()),
+            concierge_notes: Some("Loge au fond de la cour".to_string()),
+        };
+        let stored = stored_address(access.clone());
+
+        // Un paquete de otra tournée con la misma dirección (en otro formato)
+        let colis_addr = ColisPriveAddress {
+            num_voie: Some("12".to_string()),
+            libelle_voie: "RUE  HERMEL".to_string(),
+            code_postal: "75018".to_string(),
+            latitude: 48.8921,
+            longitude: 2.3471,
+        };
+        assert_eq!(colis_addr.search_key(), stored.search_key());
+
+        let mut processed = package("CP9", None);
+        processed.attach_address(&stored);
+
+        assert_eq!(processed.address_id, Some(stored.id));
+        assert_eq!(processed.door_code.as_deref(), Some("A1234"));
+        assert_eq!(processed.floor.as_deref(), Some("3"));
+        assert!(processed.mailbox_access);
+
+        let single = SinglePackage::from(processed);
+        assert_eq!(single.concierge_notes, access.concierge_notes);
+    }
+
+    #[test]
+    fn test_sub_stops_without_floor_go_last() {
+        let packages = vec![
+            package("CP1", None),
+            package("CP2", Some("RDC")),
+        ];
+
+        let sub_stops = build_sub_stops(&packages);
+
+        assert_eq!(sub_stops[0].tracking, "CP2");
+        assert_eq!(sub_stops[0].details.etage, Some(0));
+        assert!(sub_stops[1].details.is_empty());
+    }
+}
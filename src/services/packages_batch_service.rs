@@ -0,0 +1,118 @@
+//! Paquetes de varias fechas en un lote
+//!
+//! Los repartidores precargan los próximos días de una vez. Cada fecha se
+//! pide por separado a Colis Privé (como mucho `MAX_IN_FLIGHT` a la vez) y un
+//! fallo en una fecha no aborta las demás: queda como error en su entrada.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+
+use futures::stream::{self, StreamExt};
+
+use crate::dto::colis_prive_dto::{DatePackagesResult, PackagesResponse};
+use crate::utils::errors::AppError;
+
+/// Peticiones simultáneas a Colis Privé por lote
+pub const MAX_IN_FLIGHT: usize = 4;
+
+/// Fechas admitidas por lote
+pub const MAX_DATES: usize = 14;
+
+/// Fechas sin repetir (en orden) o error si el lote está vacío o es demasiado grande
+pub fn unique_dates(dates: &[String]) -> Result<Vec<String>, AppError> {
+    let mut unique: Vec<String> = Vec::new();
+    for date in dates.iter().map(|d| d.trim().to_string()) {
+        if !unique.contains(&date) {
+            unique.push(date);
+        }
+    }
+
+    if unique.is_empty() {
+        return Err(AppError::ValidationError("Indique al menos una fecha".to_string()));
+    }
+    if unique.len() > MAX_DATES {
+        return Err(AppError::ValidationError(format!(
+            "Demasiadas fechas: {} (máximo {})",
+            unique.len(),
+            MAX_DATES
+        )));
+    }
+    Ok(unique)
+}
+
+/// Pedir cada fecha con `fetch`, como mucho `MAX_IN_FLIGHT` a la vez
+pub async fn fetch_dates<F, Fut>(dates: Vec<String>, fetch: F) -> BTreeMap<String, DatePackagesResult>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<PackagesResponse, AppError>>,
+{
+    stream::iter(dates)
+        .map(|date| {
+            let request = fetch(date.clone());
+            async move {
+                let result = request.await;
+                if let Err(e) = &result {
+                    log::warn!("⚠️ Paquetes del {} no disponibles: {}", date, e);
+                }
+                (date, DatePackagesResult::from(result))
+            }
+        })
+        .buffer_unordered(MAX_IN_FLIGHT)
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::colis_prive_dto::PackageData;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_failed_date_does_not_abort_the_others() {
+        let dates: Vec<String> = (10..18).map(|day| format!("2025-01-{}", day)).collect();
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+
+        let results = fetch_dates(dates, |date| {
+            let in_flight = &in_flight;
+            let max_in_flight = &max_in_flight;
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                if date == "2025-01-12" {
+                    return Err(AppError::ExternalApi("Colis Privé no respondió".to_string()));
+                }
+                let packages = vec![PackageData { reference_colis: format!("CP-{}", date), ..Default::default() }];
+                Ok(PackagesResponse { success: true, total: packages.len(), packages, address_validation: None })
+            }
+        })
+        .await;
+
+        assert_eq!(results.len(), 8);
+        assert!(max_in_flight.load(Ordering::SeqCst) <= MAX_IN_FLIGHT);
+
+        let failed = &results["2025-01-12"];
+        assert!(!failed.success);
+        assert!(failed.error.as_deref().unwrap().contains("Colis Privé no respondió"));
+
+        let ok = &results["2025-01-13"];
+        assert!(ok.success && ok.error.is_none());
+        assert_eq!(ok.packages[0].reference_colis, "CP-2025-01-13");
+        assert_eq!(results.values().filter(|r| r.success).count(), 7);
+    }
+
+    #[test]
+    fn test_dates_are_deduplicated_and_bounded() {
+        let dates = vec!["2025-01-10".to_string(), " 2025-01-10 ".to_string(), "2025-01-11".to_string()];
+        assert_eq!(unique_dates(&dates).unwrap(), vec!["2025-01-10", "2025-01-11"]);
+
+        assert!(unique_dates(&[]).is_err());
+        let too_many: Vec<String> = (1..=MAX_DATES + 1).map(|day| format!("2025-01-{:02}", day)).collect();
+        assert!(unique_dates(&too_many).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! Lista de reintentos
+//!
+//! Al final de la jornada reúne los paquetes fallidos de todos los
+//! repartidores de una société para planificar los reintentos del día
+//! siguiente, agrupados por repartidor o por código postal.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::colis_prive_dto::PackageData;
+use crate::models::delivery_progress::{DeliveryProgress, StopOutcome};
+use crate::services::export_service::{push_csv_line, ExportRow};
+
+/// Cabeceras del CSV de reintentos
+pub const REATTEMPT_HEADERS: [&str; 9] = [
+    "Groupe",
+    "Matricule",
+    "Référence colis",
+    "Destinataire",
+    "Adresse",
+    "Code postal",
+    "Ville",
+    "Motif",
+    "Tentatives",
+];
+
+/// Criterio de agrupación de la lista
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReattemptGrouping {
+    #[default]
+    Driver,
+    PostalCode,
+}
+
+/// Paquete a reintentar
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReattemptItem {
+    pub reference_colis: String,
+    pub matricule: String,
+    pub destinataire_nom: String,
+    pub adresse: String,
+    pub code_postal: String,
+    pub ville: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub attempts: u32,
+    pub failed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReattemptGroup {
+    /// Matricule o código postal según la agrupación
+    pub key: String,
+    pub packages: Vec<ReattemptItem>,
+}
+
+/// Paquetes cuyo último resultado del día es un fallo, con la dirección de
+/// la ruta del repartidor (si está guardada)
+pub fn build_worklist(tournees: &[(DeliveryProgress, Vec<PackageData>)], grouping: ReattemptGrouping) -> Vec<ReattemptGroup> {
+    let mut groups: BTreeMap<String, Vec<ReattemptItem>> = BTreeMap::new();
+
+    for (progress, packages) in tournees {
+        let by_reference: HashMap<&str, &PackageData> = packages
+            .iter()
+            .map(|package| (package.reference_colis.as_str(), package))
+            .collect();
+
+        for stop in progress.completed.iter().filter(|stop| stop.outcome == StopOutcome::Failed) {
+            let row = by_reference.get(stop.reference_colis.as_str()).map(|package| ExportRow::from(*package));
+            let item = ReattemptItem {
+                reference_colis: stop.reference_colis.clone(),
+                matricule: progress.matricule.clone(),
+                destinataire_nom: row.as_ref().map(|r| r.destinataire_nom.clone()).unwrap_or_default(),
+                adresse: row.as_ref().map(|r| r.adresse.clone()).unwrap_or_default(),
+                code_postal: row.as_ref().map(|r| r.code_postal.clone()).unwrap_or_default(),
+                ville: row.as_ref().map(|r| r.ville.clone()).unwrap_or_default(),
+                reason: stop.reason.clone(),
+                attempts: stop.attempts,
+                failed_at: stop.completed_at,
+            };
+
+            let key = match grouping {
+                ReattemptGrouping::Driver => item.matricule.clone(),
+                ReattemptGrouping::PostalCode => item.code_postal.clone(),
+            };
+            groups.entry(key).or_default().push(item);
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, mut packages)| {
+            packages.sort_by(|a, b| b.attempts.cmp(&a.attempts).then(a.failed_at.cmp(&b.failed_at)));
+            ReattemptGroup { key, packages }
+        })
+        .collect()
+}
+
+/// Serializar la lista como CSV (una fila por paquete)
+pub fn to_csv(groups: &[ReattemptGroup]) -> String {
+    let mut csv = String::new();
+    push_csv_line(&mut csv, REATTEMPT_HEADERS.iter().map(|h| h.to_string()));
+    for group in groups {
+        for item in &group.packages {
+            push_csv_line(
+                &mut csv,
+                [
+                    group.key.clone(),
+                    item.matricule.clone(),
+                    item.reference_colis.clone(),
+                    item.destinataire_nom.clone(),
+                    item.adresse.clone(),
+                    item.code_postal.clone(),
+                    item.ville.clone(),
+                    item.reason.clone().unwrap_or_default(),
+                    item.attempts.to_string(),
+                ]
+                .into_iter(),
+            );
+        }
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 10, 16, hour, minute, 0).unwrap()
+    }
+
+    fn package(reference: &str, cp: &str) -> PackageData {
+        PackageData {
+            reference_colis: reference.to_string(),
+            destinataire_nom: format!("Client {}", reference),
+            destinataire_adresse1: Some("4 RUE GASTON TISSANDIER".to_string()),
+            destinataire_cp: Some(cp.to_string()),
+            destinataire_ville: Some("PARIS".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn tournees() -> Vec<(DeliveryProgress, Vec<PackageData>)> {
+        let mut first = DeliveryProgress::new("PCP0010699", "A187518", "2025-10-16");
+        first.record("CP1", StopOutcome::Delivered, None, at(9, 0));
+        first.record("CP2", StopOutcome::Failed, Some("Absent".to_string()), at(9, 10));
+        first.record("CP2", StopOutcome::Failed, Some("Absent".to_string()), at(15, 0));
+        // Fallo y después entrega: ya no hay que reintentar
+        first.record("CP3", StopOutcome::Failed, None, at(10, 0));
+        first.record("CP3", StopOutcome::Delivered, None, at(16, 0));
+
+        let mut second = DeliveryProgress::new("PCP0010699", "B200100", "2025-10-16");
+        second.record("CP4", StopOutcome::Failed, Some("Adresse erronée".to_string()), at(11, 0));
+        second.record("CP5", StopOutcome::Delivered, None, at(11, 30));
+
+        vec![
+            (first, vec![package("CP1", "75018"), package("CP2", "75018"), package("CP3", "75018")]),
+            (second, vec![package("CP4", "75011"), package("CP5", "75011")]),
+        ]
+    }
+
+    #[test]
+    fn test_worklist_contains_only_failed_packages_by_driver() {
+        let groups = build_worklist(&tournees(), ReattemptGrouping::Driver);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].key, "A187518");
+        assert_eq!(groups[0].packages.len(), 1);
+        assert_eq!(groups[0].packages[0].reference_colis, "CP2");
+        assert_eq!(groups[0].packages[0].attempts, 2);
+        assert_eq!(groups[0].packages[0].reason.as_deref(), Some("Absent"));
+        assert_eq!(groups[0].packages[0].code_postal, "75018");
+        assert_eq!(groups[1].key, "B200100");
+        assert_eq!(groups[1].packages[0].reference_colis, "CP4");
+    }
+
+    #[test]
+    fn test_worklist_by_postal_code_and_csv() {
+        let groups = build_worklist(&tournees(), ReattemptGrouping::PostalCode);
+        let keys: Vec<&str> = groups.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["75011", "75018"]);
+
+        let csv = to_csv(&groups);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("75011,B200100,CP4,"));
+        assert!(lines[2].ends_with(",Absent,2"));
+    }
+}
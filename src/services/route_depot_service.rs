@@ -0,0 +1,87 @@
+//! Almacén de salida y vuelta en las respuestas de optimización
+//!
+//! La respuesta solo lista las paradas de entrega; con un almacén (el de la
+//! société o WAREHOUSE_LOCATION) se añaden la salida y la vuelta para que el cliente
+//! pueda dibujar la ruta completa, incluido el trayecto de regreso. Las rutas
+//! son de ida y vuelta: la furgoneta sale del almacén y vuelve a él.
+
+use chrono::{DateTime, Utc};
+
+use crate::dto::colis_prive_dto::{DepotStop, DepotStopKind, PackageData};
+use crate::services::eta_service::planned_return_eta;
+
+/// Salida y vuelta al almacén `(lat, lon)` de una ruta ordenada; vacío sin almacén
+pub fn depot_stops(route: &[PackageData], depot: Option<(f64, f64)>, departure: DateTime<Utc>) -> Vec<DepotStop> {
+    let Some((latitude, longitude)) = depot else {
+        return Vec::new();
+    };
+
+    vec![
+        DepotStop { kind: DepotStopKind::Start, latitude, longitude, eta: departure },
+        DepotStop {
+            kind: DepotStopKind::Return,
+            latitude,
+            longitude,
+            eta: planned_return_eta(route, (latitude, longitude), departure),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const WAREHOUSE: (f64, f64) = (48.8566, 2.3522);
+
+    fn route() -> Vec<PackageData> {
+        (0..3)
+            .map(|i| PackageData {
+                reference_colis: format!("CP{}", i + 1),
+                coord_y_destinataire: Some(48.86),
+                coord_x_destinataire: Some(2.36 + i as f64 * 0.01),
+                num_ordre_passage_prevu: Some(i + 1),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_starts_and_ends_at_the_depot() {
+        let departure = Utc.with_ymd_and_hms(2025, 1, 15, 7, 0, 0).unwrap();
+
+        let stops = depot_stops(&route(), Some(WAREHOUSE), departure);
+
+        assert_eq!(stops.len(), 2);
+        let (start, back) = (&stops[0], &stops[1]);
+        assert_eq!(start.kind, DepotStopKind::Start);
+        assert_eq!((start.latitude, start.longitude), WAREHOUSE);
+        assert_eq!(start.eta, departure);
+
+        assert_eq!(back.kind, DepotStopKind::Return);
+        assert_eq!((back.latitude, back.longitude), WAREHOUSE);
+        // 3 paradas de 3 minutos más los trayectos de ida, entre paradas y de vuelta
+        assert!(back.eta > departure + chrono::Duration::minutes(9));
+        assert!(back.eta < departure + chrono::Duration::minutes(30));
+
+        let json = serde_json::to_value(&stops).unwrap();
+        assert_eq!(json[0]["kind"], "start");
+        assert_eq!(json[1]["kind"], "return");
+    }
+
+    #[test]
+    fn test_return_leg_is_counted() {
+        let departure = Utc.with_ymd_and_hms(2025, 1, 15, 7, 0, 0).unwrap();
+        let near = depot_stops(&route(), Some(WAREHOUSE), departure);
+        // Mismo almacén desplazado ~11 km al sur: más trayecto de ida y de vuelta
+        let far = depot_stops(&route(), Some((WAREHOUSE.0 - 0.1, WAREHOUSE.1)), departure);
+
+        assert!(far[1].eta > near[1].eta);
+    }
+
+    #[test]
+    fn test_without_depot_no_entries_are_added() {
+        let departure = Utc.with_ymd_and_hms(2025, 1, 15, 7, 0, 0).unwrap();
+        assert!(depot_stops(&route(), None, departure).is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+//! División de tournées demasiado grandes
+//!
+//! Una ruta de 300+ paradas es lenta de optimizar con 2-opt y poco práctica de
+//! conducir. Cuando se supera el máximo de paradas por vehículo se agrupan las
+//! paradas con k-means (k = ⌈paradas / máximo⌉) y cada grupo se optimiza como
+//! una sub-ruta sugerida. El máximo es blando: k-means no garantiza que ningún
+//! grupo lo supere.
+
+use crate::services::local_optimizer_service::RouteStop;
+use crate::utils::geo::haversine_km;
+
+/// Iteraciones máximas de k-means si no converge antes
+const MAX_ITERATIONS: usize = 50;
+
+/// Agrupar las paradas en sub-rutas de como mucho ~`max_stops` paradas.
+///
+/// Devuelve los índices de `stops` de cada grupo, en orden de recorrido
+/// (vecino más cercano entre centroides empezando por el grupo de la primera
+/// parada). Un único grupo si no se supera el máximo.
+pub fn split_stops(stops: &[RouteStop], max_stops: usize) -> Vec<Vec<usize>> {
+    if max_stops == 0 || stops.len() <= max_stops {
+        return vec![(0..stops.len()).collect()];
+    }
+
+    let points: Vec<(f64, f64)> = stops.iter().map(|s| (s.latitude, s.longitude)).collect();
+    let k = stops.len().div_ceil(max_stops);
+    let mut centroids = initial_centroids(&points, k);
+    let mut assignment: Vec<usize> = points.iter().map(|point| nearest(point, &centroids)).collect();
+
+    for _ in 0..MAX_ITERATIONS {
+        // Recalcular centroides; un grupo vacío conserva el suyo
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let members: Vec<&(f64, f64)> = points
+                .iter()
+                .zip(&assignment)
+                .filter(|(_, &c)| c == cluster)
+                .map(|(point, _)| point)
+                .collect();
+            if !members.is_empty() {
+                let count = members.len() as f64;
+                *centroid = (
+                    members.iter().map(|p| p.0).sum::<f64>() / count,
+                    members.iter().map(|p| p.1).sum::<f64>() / count,
+                );
+            }
+        }
+
+        let next: Vec<usize> = points.iter().map(|point| nearest(point, &centroids)).collect();
+        if next == assignment {
+            break;
+        }
+        assignment = next;
+    }
+
+    let mut clusters: Vec<(usize, Vec<usize>)> = (0..k)
+        .map(|cluster| {
+            let members = (0..points.len()).filter(|&i| assignment[i] == cluster).collect();
+            (cluster, members)
+        })
+        .filter(|(_, members): &(usize, Vec<usize>)| !members.is_empty())
+        .collect();
+
+    // Encadenar los grupos por cercanía de centroides
+    let mut ordered = Vec::with_capacity(clusters.len());
+    let first = clusters.iter().position(|(_, members)| members.contains(&0)).unwrap_or(0);
+    let (mut current, members) = clusters.swap_remove(first);
+    ordered.push(members);
+    while !clusters.is_empty() {
+        let from = centroids[current];
+        let next = clusters
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(&from, &centroids[a.0]).total_cmp(&distance(&from, &centroids[b.0])))
+            .map(|(position, _)| position)
+            .unwrap_or(0);
+        let (cluster, members) = clusters.swap_remove(next);
+        current = cluster;
+        ordered.push(members);
+    }
+
+    ordered
+}
+
+/// Centroides iniciales deterministas: la primera parada y, después, la
+/// parada más alejada de los centroides ya elegidos
+fn initial_centroids(points: &[(f64, f64)], k: usize) -> Vec<(f64, f64)> {
+    let mut centroids = vec![points[0]];
+    while centroids.len() < k {
+        let farthest = points
+            .iter()
+            .max_by(|a, b| {
+                let da = distance(a, &centroids[nearest(a, &centroids)]);
+                let db = distance(b, &centroids[nearest(b, &centroids)]);
+                da.total_cmp(&db)
+            })
+            .copied()
+            .unwrap_or(points[0]);
+        centroids.push(farthest);
+    }
+    centroids
+}
+
+fn nearest(point: &(f64, f64), centroids: &[(f64, f64)]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| distance(point, a).total_cmp(&distance(point, b)))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+fn distance(a: &(f64, f64), b: &(f64, f64)) -> f64 {
+    haversine_km(a.0, a.1, b.0, b.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::colis_prive_dto::DeliverySlot;
+
+    /// `count` paradas en una rejilla de ~20 km alrededor de París
+    fn stops(count: usize) -> Vec<RouteStop> {
+        (0..count)
+            .map(|i| RouteStop {
+                latitude: 48.75 + (i % 20) as f64 * 0.01,
+                longitude: 2.25 + (i / 20) as f64 * 0.01,
+                high_priority: false,
+                delivery_slot: DeliverySlot::default(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_large_route_is_split_into_sub_routes() {
+        let clusters = split_stops(&stops(400), 150);
+
+        assert!(clusters.len() >= 3, "{} sub-rutas", clusters.len());
+
+        // Cada parada aparece exactamente una vez
+        let mut all: Vec<usize> = clusters.iter().flatten().copied().collect();
+        all.sort_unstable();
+        assert_eq!(all, (0..400).collect::<Vec<_>>());
+        // La primera sub-ruta empieza por el grupo de la primera parada
+        assert!(clusters[0].contains(&0));
+    }
+
+    #[test]
+    fn test_route_under_the_cap_is_not_split() {
+        let clusters = split_stops(&stops(150), 150);
+
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0], (0..150).collect::<Vec<_>>());
+    }
+}
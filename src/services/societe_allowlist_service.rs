@@ -0,0 +1,119 @@
+//! Lista de sociétés de Colis Privé soportadas
+//!
+//! Se inicializa con `COLIS_PRIVE_ALLOWED_SOCIETES` y puede refrescarse con los
+//! códigos del referencial de empresas. Una lista vacía desactiva el control.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+
+use validator::{ValidationError, ValidationErrors};
+
+use crate::utils::errors::AppError;
+
+#[derive(Clone, Debug, Default)]
+pub struct SocieteAllowlist {
+    configured: Vec<String>,
+    allowed: Arc<RwLock<BTreeSet<String>>>,
+}
+
+impl SocieteAllowlist {
+    pub fn new(configured: Vec<String>) -> Self {
+        let configured: Vec<String> = configured.iter().filter_map(|s| normalize(s)).collect();
+        let allowed = configured.iter().cloned().collect();
+
+        Self {
+            configured,
+            allowed: Arc::new(RwLock::new(allowed)),
+        }
+    }
+
+    /// Sin sociétés configuradas se acepta cualquiera
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed.read().unwrap().is_empty()
+    }
+
+    pub fn allowed(&self) -> Vec<String> {
+        self.allowed.read().unwrap().iter().cloned().collect()
+    }
+
+    pub fn is_allowed(&self, societe: &str) -> bool {
+        let allowed = self.allowed.read().unwrap();
+        allowed.is_empty() || normalize(societe).is_some_and(|s| allowed.contains(&s))
+    }
+
+    /// Reemplazar la lista por las sociétés configuradas más los códigos dados
+    pub fn refresh<I: IntoIterator<Item = String>>(&self, codes: I) -> usize {
+        let mut allowed: BTreeSet<String> = self.configured.iter().cloned().collect();
+        allowed.extend(codes.into_iter().filter_map(|code| normalize(&code)));
+
+        let count = allowed.len();
+        *self.allowed.write().unwrap() = allowed;
+        count
+    }
+
+    /// Error 422 con la lista de valores válidos si la société no está soportada
+    pub fn check(&self, societe: &str) -> Result<(), AppError> {
+        if self.is_allowed(societe) {
+            return Ok(());
+        }
+
+        let mut error = ValidationError::new("societe_not_allowed");
+        error.message = Some(format!("La société '{}' no está soportada", societe.trim()).into());
+        error.add_param("allowed".into(), &self.allowed());
+
+        let mut errors = ValidationErrors::new();
+        errors.add("societe", error);
+        Err(AppError::Validation(errors))
+    }
+}
+
+fn normalize(societe: &str) -> Option<String> {
+    let societe = societe.trim();
+    (!societe.is_empty()).then(|| societe.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_societe_passes() {
+        let allowlist = SocieteAllowlist::new(vec!["PCP0010699".to_string()]);
+
+        assert!(allowlist.check("PCP0010699").is_ok());
+        assert!(allowlist.check(" pcp0010699 ").is_ok());
+    }
+
+    #[test]
+    fn test_disallowed_societe_lists_valid_options() {
+        let allowlist = SocieteAllowlist::new(vec!["PCP0010699".to_string(), "PCP0020000".to_string()]);
+
+        let Err(AppError::Validation(errors)) = allowlist.check("PCP0099999") else {
+            panic!("se esperaba un error de validación");
+        };
+        let error = &errors.field_errors()["societe"][0];
+
+        assert_eq!(error.code, "societe_not_allowed");
+        assert_eq!(error.params["allowed"], serde_json::json!(["PCP0010699", "PCP0020000"]));
+    }
+
+    #[test]
+    fn test_refresh_keeps_configured_societes() {
+        let allowlist = SocieteAllowlist::new(vec!["PCP0010699".to_string()]);
+
+        allowlist.refresh(vec!["PCP0020000".to_string()]);
+        assert!(allowlist.is_allowed("PCP0010699"));
+        assert!(allowlist.is_allowed("PCP0020000"));
+
+        allowlist.refresh(Vec::new());
+        assert!(!allowlist.is_allowed("PCP0020000"));
+    }
+
+    #[test]
+    fn test_empty_allowlist_accepts_any_societe() {
+        let allowlist = SocieteAllowlist::new(Vec::new());
+
+        assert!(!allowlist.is_enabled());
+        assert!(allowlist.check("PCP0099999").is_ok());
+    }
+}
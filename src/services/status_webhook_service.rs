@@ -0,0 +1,176 @@
+//! Webhook de estados de paquetes
+//!
+//! Colis Privé (u otra integración) envía lotes firmados de cambios de
+//! estado. La firma es un HMAC-SHA256 del cuerpo en crudo con el secreto
+//! compartido, en la cabecera `X-Webhook-Signature: sha256=<hex>`.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::models::package_status::StatusUpdate;
+use crate::utils::errors::AppError;
+
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+/// Resultado de procesar un lote
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct WebhookOutcome {
+    pub received: usize,
+    pub updated: usize,
+    /// Referencias que no corresponden a ningún paquete conocido
+    pub ignored_unknown: usize,
+    /// Estados más antiguos que el ya guardado
+    pub ignored_stale: usize,
+}
+
+/// Verificar la firma HMAC-SHA256 del cuerpo (comparación en tiempo constante)
+pub fn verify_signature(secret: &str, body: &[u8], signature: Option<&str>) -> Result<(), AppError> {
+    let invalid = || AppError::Unauthorized("Firma del webhook inválida".to_string());
+
+    let signature = signature
+        .map(str::trim)
+        .and_then(|s| s.strip_prefix(SIGNATURE_PREFIX))
+        .and_then(decode_hex)
+        .ok_or_else(invalid)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|e| AppError::Internal(format!("Secreto de webhook inválido: {}", e)))?;
+    mac.update(body);
+    mac.verify_slice(&signature).map_err(|_| invalid())
+}
+
+/// Decidir qué actualizaciones aplicar dado el `status_at` actual de los paquetes conocidos.
+///
+/// Dentro del lote se conserva la actualización más reciente de cada paquete.
+pub fn plan_updates(
+    updates: Vec<StatusUpdate>,
+    known: &HashMap<String, Option<DateTime<Utc>>>,
+) -> (Vec<StatusUpdate>, WebhookOutcome) {
+    let mut outcome = WebhookOutcome {
+        received: updates.len(),
+        ..Default::default()
+    };
+    let mut latest: HashMap<String, StatusUpdate> = HashMap::new();
+
+    for update in updates {
+        let Some(current) = known.get(&update.tracking_number) else {
+            outcome.ignored_unknown += 1;
+            continue;
+        };
+
+        let newer_than_stored = current.is_none_or(|at| update.timestamp > at);
+        let newer_in_batch = latest
+            .get(&update.tracking_number)
+            .is_none_or(|previous| update.timestamp > previous.timestamp);
+
+        if newer_than_stored && newer_in_batch {
+            if latest.insert(update.tracking_number.clone(), update).is_some() {
+                outcome.ignored_stale += 1;
+            }
+        } else {
+            outcome.ignored_stale += 1;
+        }
+    }
+
+    let mut to_apply: Vec<StatusUpdate> = latest.into_values().collect();
+    to_apply.sort_by(|a, b| a.tracking_number.cmp(&b.tracking_number));
+    outcome.updated = to_apply.len();
+
+    (to_apply, outcome)
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+    use chrono::TimeZone;
+
+    const SECRET: &str = "webhook-secret";
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        let digest: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}{}", SIGNATURE_PREFIX, digest)
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 15, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_valid_signed_batch_updates_statuses() {
+        let body = br#"{"updates": [
+            {"tracking_number": "CP1", "status": "LIVRE", "timestamp": "2025-01-15T10:00:00Z"},
+            {"tracking_number": "CP2", "status": "ECHEC", "timestamp": "2025-01-15T11:00:00Z"},
+            {"tracking_number": "CP2", "status": "LIVRE", "timestamp": "2025-01-15T12:00:00Z"},
+            {"tracking_number": "DESCONOCIDO", "status": "LIVRE", "timestamp": "2025-01-15T10:00:00Z"}
+        ]}"#;
+
+        verify_signature(SECRET, body, Some(&sign(SECRET, body))).unwrap();
+
+        let batch: serde_json::Value = serde_json::from_slice(body).unwrap();
+        let updates: Vec<StatusUpdate> = serde_json::from_value(batch["updates"].clone()).unwrap();
+        let known = HashMap::from([
+            ("CP1".to_string(), Some(at(8))),
+            ("CP2".to_string(), None),
+        ]);
+
+        let (to_apply, outcome) = plan_updates(updates, &known);
+
+        assert_eq!(
+            outcome,
+            WebhookOutcome { received: 4, updated: 2, ignored_unknown: 1, ignored_stale: 1 }
+        );
+        assert_eq!(to_apply[0].tracking_number, "CP1");
+        assert_eq!(to_apply[0].status, "LIVRE");
+        assert_eq!((to_apply[1].status.as_str(), to_apply[1].timestamp), ("LIVRE", at(12)));
+    }
+
+    #[test]
+    fn test_invalid_signature_is_rejected_with_401() {
+        let body = br#"{"updates": []}"#;
+
+        for signature in [Some(sign("otro-secreto", body)), Some("sha256=zz".to_string()), None] {
+            let result = verify_signature(SECRET, body, signature.as_deref());
+            let error = result.expect_err("firma aceptada");
+            assert_eq!(error.into_response().status(), axum::http::StatusCode::UNAUTHORIZED);
+        }
+
+        // Un cuerpo modificado invalida una firma correcta
+        let tampered = br#"{"updates": [{}]}"#;
+        assert!(verify_signature(SECRET, tampered, Some(&sign(SECRET, body))).is_err());
+    }
+
+    #[test]
+    fn test_older_status_than_stored_is_ignored() {
+        let known = HashMap::from([("CP1".to_string(), Some(at(12)))]);
+        let updates = vec![StatusUpdate {
+            tracking_number: "CP1".to_string(),
+            status: "EN_COURS".to_string(),
+            timestamp: at(9),
+        }];
+
+        let (to_apply, outcome) = plan_updates(updates, &known);
+
+        assert!(to_apply.is_empty());
+        assert_eq!(outcome.ignored_stale, 1);
+    }
+}
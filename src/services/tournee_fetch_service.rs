@@ -0,0 +1,135 @@
+//! Descargas de tournée compartidas
+//!
+//! Cuando la app del chofer reintenta o varias pantallas piden la misma
+//! tournée a la vez, solo la primera petición llama a Colis Privé; las demás
+//! esperan y reciben el mismo resultado. La clave es (société, matricule, fecha).
+
+use std::future::Future;
+
+use crate::dto::colis_prive_dto::PackageData;
+use crate::utils::errors::AppError;
+use crate::utils::single_flight::SingleFlight;
+
+/// Fallo de la tournée repartido entre las peticiones en espera
+/// (`AppError` no es `Clone`); conserva el tipo para que un token
+/// rechazado siga respondiendo 401 a todas
+#[derive(Clone, Debug)]
+pub enum SharedTourneeError {
+    AuthExpired(String),
+    Parse(String),
+    Upstream(String),
+}
+
+impl From<AppError> for SharedTourneeError {
+    fn from(error: AppError) -> Self {
+        match error {
+            AppError::AuthExpired(msg) => Self::AuthExpired(msg),
+            AppError::ParseError(msg) => Self::Parse(msg),
+            AppError::ExternalApi(msg) => Self::Upstream(msg),
+            other => Self::Upstream(other.to_string()),
+        }
+    }
+}
+
+impl From<SharedTourneeError> for AppError {
+    fn from(error: SharedTourneeError) -> Self {
+        match error {
+            SharedTourneeError::AuthExpired(msg) => AppError::AuthExpired(msg),
+            SharedTourneeError::Parse(msg) => AppError::ParseError(msg),
+            SharedTourneeError::Upstream(msg) => AppError::ExternalApi(msg),
+        }
+    }
+}
+
+/// Descargas de tournée en curso, compartidas desde `AppState`
+pub type TourneeFetches = SingleFlight<Result<Vec<PackageData>, SharedTourneeError>>;
+
+/// Clave de la descarga: la fecha ya resuelta, para que "sin fecha" y la
+/// fecha de hoy compartan la misma llamada
+pub fn tournee_flight_key(societe: &str, matricule: &str, date: &str) -> String {
+    format!("{}_{}:{}", societe, matricule, date)
+}
+
+/// Ejecutar `fetch` para `key`, o esperar a la descarga idéntica en curso
+pub async fn fetch_shared<F>(
+    fetches: &TourneeFetches,
+    key: &str,
+    fetch: F,
+) -> Result<Vec<PackageData>, AppError>
+where
+    F: Future<Output = Result<Vec<PackageData>, AppError>>,
+{
+    let shared = fetches
+        .run(key, async { Ok(fetch.await.map_err(SharedTourneeError::from)) })
+        .await
+        .map_err(AppError::ExternalApi)?;
+    shared.map_err(AppError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn package(reference: &str) -> PackageData {
+        PackageData { reference_colis: reference.to_string(), ..Default::default() }
+    }
+
+    async fn upstream(calls: &AtomicUsize, result: Result<Vec<PackageData>, AppError>) -> Result<Vec<PackageData>, AppError> {
+        calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        result
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_identical_fetches_make_one_upstream_call() {
+        let fetches = TourneeFetches::new();
+        let calls = AtomicUsize::new(0);
+        let key = tournee_flight_key("PCP0010699", "A187518", "2025-01-15");
+
+        let results = futures::future::join_all((0..10).map(|_| {
+            fetch_shared(&fetches, &key, upstream(&calls, Ok(vec![package("CP001"), package("CP002")])))
+        }))
+        .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(results.len(), 10);
+        for result in results {
+            let packages = result.expect("todas las peticiones reciben la tournée");
+            assert_eq!(packages.len(), 2);
+            assert_eq!(packages[0].reference_colis, "CP001");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_auth_failure_keeps_its_type() {
+        let fetches = TourneeFetches::new();
+        let calls = AtomicUsize::new(0);
+        let key = tournee_flight_key("PCP0010699", "A187518", "2025-01-15");
+
+        let results = futures::future::join_all((0..5).map(|_| {
+            fetch_shared(&fetches, &key, upstream(&calls, Err(AppError::AuthExpired("token rechazado".to_string()))))
+        }))
+        .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|r| matches!(r, Err(AppError::AuthExpired(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_different_dates_are_fetched_separately() {
+        let fetches = TourneeFetches::new();
+        let calls = AtomicUsize::new(0);
+        let monday = tournee_flight_key("PCP0010699", "A187518", "2025-01-13");
+        let tuesday = tournee_flight_key("PCP0010699", "A187518", "2025-01-14");
+
+        let (a, b) = tokio::join!(
+            fetch_shared(&fetches, &monday, upstream(&calls, Ok(vec![]))),
+            fetch_shared(&fetches, &tuesday, upstream(&calls, Ok(vec![]))),
+        );
+
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
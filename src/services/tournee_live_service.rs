@@ -0,0 +1,150 @@
+//! Progreso de tournées en directo
+//!
+//! Cada tournée (société, matricule, fecha) con algún dispatcher conectado
+//! tiene un canal `broadcast`; al registrar una parada entregada o fallida se
+//! publica un evento que reciben todos sus suscriptores WebSocket. El canal se
+//! libera cuando se va el último suscriptor.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::models::delivery_progress::StopOutcome;
+use crate::utils::errors::AppError;
+
+/// Eventos que un suscriptor lento puede acumular antes de perder los más antiguos
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Parada registrada en una tournée seguida en directo
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct StopEvent {
+    pub matricule: String,
+    pub reference_colis: String,
+    pub outcome: StopOutcome,
+    pub completed_at: DateTime<Utc>,
+    pub completed_stops: usize,
+}
+
+/// Canales de las tournées seguidas en directo
+#[derive(Clone)]
+pub struct TourneeLiveHub {
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<StopEvent>>>>,
+    max_subscribers: usize,
+}
+
+impl TourneeLiveHub {
+    pub fn new(max_subscribers: usize) -> Self {
+        Self {
+            channels: Arc::new(Mutex::new(HashMap::new())),
+            max_subscribers,
+        }
+    }
+
+    pub fn tournee_key(societe: &str, matricule: &str, date: &str) -> String {
+        format!("{}:{}:{}", societe, matricule, date)
+    }
+
+    /// Suscribirse a una tournée; 503 si ya tiene `max_subscribers` suscriptores
+    pub fn subscribe(&self, tournee: &str) -> Result<broadcast::Receiver<StopEvent>, AppError> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        let sender = channels
+            .entry(tournee.to_string())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+        if sender.receiver_count() >= self.max_subscribers {
+            log::warn!("🚫 Tournée {} con {} suscriptores en directo", tournee, sender.receiver_count());
+            return Err(AppError::ServiceUnavailable(format!(
+                "La tournée ya tiene {} suscriptores en directo",
+                self.max_subscribers
+            )));
+        }
+        Ok(sender.subscribe())
+    }
+
+    /// Publicar una parada; no hace nada si nadie sigue la tournée
+    pub fn publish(&self, tournee: &str, event: StopEvent) {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = channels.get(tournee) {
+            if sender.send(event).is_err() {
+                // Sin suscriptores: se liberó el último
+                channels.remove(tournee);
+            }
+        }
+    }
+
+    /// Liberar el canal de una tournée cuando se desconecta su último suscriptor
+    pub fn release(&self, tournee: &str) {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if channels.get(tournee).is_some_and(|sender| sender.receiver_count() == 0) {
+            channels.remove(tournee);
+        }
+    }
+
+    #[cfg(test)]
+    fn channel_count(&self) -> usize {
+        self.channels.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOURNEE: &str = "PCP0010699:A187518:2025-01-15";
+
+    fn event(reference: &str) -> StopEvent {
+        StopEvent {
+            matricule: "A187518".to_string(),
+            reference_colis: reference.to_string(),
+            outcome: StopOutcome::Delivered,
+            completed_at: Utc::now(),
+            completed_stops: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_only_their_tournee() {
+        let hub = TourneeLiveHub::new(10);
+        let mut first = hub.subscribe(TOURNEE).unwrap();
+        let mut second = hub.subscribe(TOURNEE).unwrap();
+        let mut other = hub.subscribe("PCP0010699:B000001:2025-01-15").unwrap();
+
+        hub.publish(TOURNEE, event("CP001"));
+
+        assert_eq!(first.recv().await.unwrap().reference_colis, "CP001");
+        assert_eq!(second.recv().await.unwrap().reference_colis, "CP001");
+        assert!(other.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_subscribers_are_capped_per_tournee() {
+        let hub = TourneeLiveHub::new(2);
+        let _first = hub.subscribe(TOURNEE).unwrap();
+        let second = hub.subscribe(TOURNEE).unwrap();
+
+        assert!(matches!(hub.subscribe(TOURNEE), Err(AppError::ServiceUnavailable(_))));
+        // Otra tournée tiene su propio límite
+        assert!(hub.subscribe("PCP0010699:B000001:2025-01-15").is_ok());
+
+        drop(second);
+        assert!(hub.subscribe(TOURNEE).is_ok());
+    }
+
+    #[test]
+    fn test_channel_is_released_with_the_last_subscriber() {
+        let hub = TourneeLiveHub::new(10);
+        let subscriber = hub.subscribe(TOURNEE).unwrap();
+
+        drop(subscriber);
+        hub.release(TOURNEE);
+        assert_eq!(hub.channel_count(), 0);
+
+        // Publicar sin nadie suscrito no crea canales
+        hub.publish(TOURNEE, event("CP001"));
+        assert_eq!(hub.channel_count(), 0);
+    }
+}
@@ -0,0 +1,106 @@
+//! Fusión de tournées de varias sociétés
+//!
+//! Un chófer que cubre dos sociétés recibe dos tournées separadas. Se fusionan
+//! en una sola lista de paquetes, cada uno etiquetado con su société de origen,
+//! para optimizarla como una única ruta.
+
+use std::collections::HashSet;
+
+use crate::dto::colis_prive_dto::{MergedPackageDto, MergedSourceSummary, PackageData, TourneeSource};
+
+/// Máximo de tournées por fusión
+pub const MAX_MERGED_TOURNEES: usize = 5;
+
+/// Fusionar tournées en orden; un paquete repetido se conserva solo la primera vez
+pub fn merge_tournees(
+    tournees: Vec<(TourneeSource, Vec<PackageData>)>,
+) -> (Vec<MergedSourceSummary>, Vec<MergedPackageDto>) {
+    let mut seen = HashSet::new();
+    let mut sources = Vec::with_capacity(tournees.len());
+    let mut packages = Vec::new();
+
+    for (source, tournee) in tournees {
+        sources.push(MergedSourceSummary {
+            societe: source.societe.clone(),
+            matricule: source.matricule.clone(),
+            date: source.date.clone(),
+            packages: tournee.len(),
+        });
+
+        for package in tournee {
+            if !seen.insert(package.reference_colis.clone()) {
+                log::warn!("⚠️ Paquete {} repetido en varias tournées, se ignora", package.reference_colis);
+                continue;
+            }
+
+            packages.push(MergedPackageDto {
+                source_societe: source.societe.clone(),
+                source_matricule: source.matricule.clone(),
+                package: package.into(),
+            });
+        }
+    }
+
+    (sources, packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(societe: &str, matricule: &str) -> TourneeSource {
+        TourneeSource {
+            societe: societe.to_string(),
+            matricule: matricule.to_string(),
+            date: Some("2025-01-15".to_string()),
+        }
+    }
+
+    fn tournee(references: &[&str]) -> Vec<PackageData> {
+        references
+            .iter()
+            .map(|reference| PackageData {
+                reference_colis: reference.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merged_packages_are_tagged_with_their_societe() {
+        let (sources, packages) = merge_tournees(vec![
+            (source("PCP0010699", "A187518"), tournee(&["CP1", "CP2"])),
+            (source("PCP0010700", "B200001"), tournee(&["CP3", "CP4", "CP5"])),
+        ]);
+
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[1].packages, 3);
+        assert_eq!(packages.len(), 5);
+
+        let tagged: Vec<(&str, &str)> = packages
+            .iter()
+            .map(|p| (p.package.reference_colis.as_str(), p.source_societe.as_str()))
+            .collect();
+        assert_eq!(
+            tagged,
+            vec![
+                ("CP1", "PCP0010699"),
+                ("CP2", "PCP0010699"),
+                ("CP3", "PCP0010700"),
+                ("CP4", "PCP0010700"),
+                ("CP5", "PCP0010700"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicated_package_is_kept_once() {
+        let (_, packages) = merge_tournees(vec![
+            (source("PCP0010699", "A187518"), tournee(&["CP1", "CP2"])),
+            (source("PCP0010700", "B200001"), tournee(&["CP2", "CP3"])),
+        ]);
+
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[1].source_societe, "PCP0010699");
+    }
+}
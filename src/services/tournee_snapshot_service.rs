@@ -0,0 +1,126 @@
+//! Fotos del estado de una tournée a lo largo del día
+//!
+//! Cada foto guarda, por paquete, el estado de Colis Privé y el resultado
+//! registrado por el chofer. Comparar dos fotos indica qué paquetes cambiaron
+//! entre ambas, lo que permite reconstruir la jornada ante una disputa.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::dto::colis_prive_dto::{PackageData, PackageStateChange, SnapshotDiff};
+use crate::models::delivery_progress::DeliveryProgress;
+use crate::models::tournee_snapshot::PackageState;
+
+/// Estado de cada paquete de la tournée; `statuses` (webhook) prevalece sobre
+/// el estado que trae la tournée
+pub fn package_states(
+    packages: &[PackageData],
+    statuses: &HashMap<String, Option<String>>,
+    progress: Option<&DeliveryProgress>,
+) -> Vec<PackageState> {
+    packages
+        .iter()
+        .map(|package| {
+            let stop = progress.and_then(|progress| {
+                progress.completed.iter().find(|stop| stop.reference_colis == package.reference_colis)
+            });
+            PackageState {
+                reference_colis: package.reference_colis.clone(),
+                status: statuses
+                    .get(&package.reference_colis)
+                    .cloned()
+                    .flatten()
+                    .or_else(|| package.statut.clone()),
+                outcome: stop.map(|stop| stop.outcome),
+                attempts: stop.map(|stop| stop.attempts).unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+/// Paquetes cuyo estado cambió de `from` a `to`, más los que entraron o salieron de la tournée
+pub fn diff_snapshots(from: &[PackageState], to: &[PackageState]) -> SnapshotDiff {
+    let before: HashMap<&str, &PackageState> = from
+        .iter()
+        .map(|state| (state.reference_colis.as_str(), state))
+        .collect();
+    let current: HashSet<&str> = to.iter().map(|state| state.reference_colis.as_str()).collect();
+
+    let mut diff = SnapshotDiff::default();
+
+    for state in to {
+        match before.get(state.reference_colis.as_str()) {
+            Some(previous) if *previous == state => diff.unchanged += 1,
+            Some(previous) => diff.changed.push(PackageStateChange {
+                reference_colis: state.reference_colis.clone(),
+                before: (*previous).clone(),
+                after: state.clone(),
+            }),
+            None => diff.added.push(state.reference_colis.clone()),
+        }
+    }
+
+    diff.removed = from
+        .iter()
+        .filter(|state| !current.contains(state.reference_colis.as_str()))
+        .map(|state| state.reference_colis.clone())
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::delivery_progress::StopOutcome;
+    use chrono::{TimeZone, Utc};
+
+    fn tournee() -> Vec<PackageData> {
+        ["CP001", "CP002", "CP003"]
+            .iter()
+            .map(|reference| PackageData {
+                reference_colis: reference.to_string(),
+                statut: Some("EN_COURS".to_string()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_reports_exactly_the_status_change() {
+        let packages = tournee();
+        let mut progress = DeliveryProgress::new("PCP0010699", "A187518", "2025-01-15");
+
+        let morning = package_states(&packages, &HashMap::new(), Some(&progress));
+
+        let delivered_at = Utc.with_ymd_and_hms(2025, 1, 15, 10, 30, 0).unwrap();
+        progress.record("CP002", StopOutcome::Delivered, None, delivered_at);
+        let statuses = HashMap::from([("CP002".to_string(), Some("LIVRE".to_string()))]);
+        let noon = package_states(&packages, &statuses, Some(&progress));
+
+        let diff = diff_snapshots(&morning, &noon);
+
+        assert_eq!(diff.changed.len(), 1);
+        let change = &diff.changed[0];
+        assert_eq!(change.reference_colis, "CP002");
+        assert_eq!(change.before.status.as_deref(), Some("EN_COURS"));
+        assert_eq!(change.before.outcome, None);
+        assert_eq!(change.after.status.as_deref(), Some("LIVRE"));
+        assert_eq!(change.after.outcome, Some(StopOutcome::Delivered));
+        assert_eq!(diff.unchanged, 2);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+    }
+
+    #[test]
+    fn test_packages_entering_or_leaving_the_tournee() {
+        let packages = tournee();
+        let before = package_states(&packages[..2], &HashMap::new(), None);
+        let after = package_states(&packages[1..], &HashMap::new(), None);
+
+        let diff = diff_snapshots(&before, &after);
+
+        assert_eq!(diff.added, vec!["CP003"]);
+        assert_eq!(diff.removed, vec!["CP001"]);
+        assert!(diff.changed.is_empty());
+        assert_eq!(diff.unchanged, 1);
+    }
+}
@@ -0,0 +1,177 @@
+//! Evolución de la calidad de las direcciones
+//!
+//! Cada vez que se valida una tournée se guarda cuántos paquetes quedaron
+//! con cada método de validación (una fila por tournée y método, que se
+//! sustituye si la tournée se vuelve a pedir). Las tendencias suman esas
+//! filas por día para ver si la calidad de los datos mejora o empeora.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::dto::colis_prive_dto::PackageData;
+use crate::models::address_validation::DailyValidationOutcome;
+use crate::services::colis_prive_service::ValidationMethod;
+use crate::services::geocoding_quality_service::package_method;
+
+/// Paquetes de una tournée validados con un método
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodOutcome {
+    pub method: ValidationMethod,
+    pub outcomes: i64,
+    pub confidence_sum: f64,
+    pub scored: i64,
+}
+
+/// Resultados de validación de una tournée, por método
+pub fn tournee_outcomes(packages: &[PackageData]) -> Vec<MethodOutcome> {
+    let mut by_method: BTreeMap<&'static str, MethodOutcome> = BTreeMap::new();
+    for package in packages {
+        let method = package_method(package);
+        let outcome = by_method.entry(method.as_str()).or_insert(MethodOutcome {
+            method,
+            outcomes: 0,
+            confidence_sum: 0.0,
+            scored: 0,
+        });
+        outcome.outcomes += 1;
+        if let Some(confidence) = package.validation_confidence {
+            outcome.confidence_sum += confidence;
+            outcome.scored += 1;
+        }
+    }
+    by_method.into_values().collect()
+}
+
+/// Resultados de validación de un día
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DailyValidationTrend {
+    pub date: NaiveDate,
+    pub auto_validated: i64,
+    pub cleaned: i64,
+    pub completed: i64,
+    pub partial: i64,
+    /// Direcciones que necesitaron a un dispatcher: sin resolver, con error
+    /// de geocoding o con coordenadas fijadas a mano
+    pub manual: i64,
+    pub total: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_confidence: Option<f64>,
+}
+
+/// Una entrada por día de `from` a `to` (ambos incluidos); los días sin
+/// tournées quedan a cero
+pub fn build_trends(rows: &[DailyValidationOutcome], from: NaiveDate, to: NaiveDate) -> Vec<DailyValidationTrend> {
+    let mut days: BTreeMap<NaiveDate, (DailyValidationTrend, f64, i64)> = from
+        .iter_days()
+        .take_while(|day| *day <= to)
+        .map(|date| (date, (DailyValidationTrend { date, ..Default::default() }, 0.0, 0)))
+        .collect();
+
+    for row in rows {
+        let Some((trend, confidence_sum, scored)) = days.get_mut(&row.day) else { continue };
+        let Some(method) = ValidationMethod::parse(&row.validation_method) else {
+            log::warn!("⚠️ Método de validación desconocido en las tendencias: {}", row.validation_method);
+            continue;
+        };
+
+        let bucket = match method {
+            ValidationMethod::AutoValidated => &mut trend.auto_validated,
+            ValidationMethod::CleanedAuto => &mut trend.cleaned,
+            ValidationMethod::CompletedAuto => &mut trend.completed,
+            ValidationMethod::PartialFound => &mut trend.partial,
+            ValidationMethod::GeocodingError | ValidationMethod::RequiresManual | ValidationMethod::ManualConfirmed => {
+                &mut trend.manual
+            }
+        };
+        *bucket += row.outcomes;
+        trend.total += row.outcomes;
+        *confidence_sum += row.confidence_sum;
+        *scored += row.scored;
+    }
+
+    days.into_values()
+        .map(|(mut trend, confidence_sum, scored)| {
+            trend.average_confidence = (scored > 0).then(|| confidence_sum / scored as f64);
+            trend
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, d).unwrap()
+    }
+
+    fn package(method: &str, confidence: Option<f64>) -> PackageData {
+        PackageData {
+            validation_method: Some(method.to_string()),
+            validation_confidence: confidence,
+            ..Default::default()
+        }
+    }
+
+    /// Filas como las devuelve el repositorio: tournées sumadas por día y método
+    fn daily_rows(tournees: &[(NaiveDate, Vec<PackageData>)]) -> Vec<DailyValidationOutcome> {
+        let mut rows: BTreeMap<(NaiveDate, &'static str), DailyValidationOutcome> = BTreeMap::new();
+        for (date, packages) in tournees {
+            for outcome in tournee_outcomes(packages) {
+                let row = rows.entry((*date, outcome.method.as_str())).or_insert(DailyValidationOutcome {
+                    day: *date,
+                    validation_method: outcome.method.as_str().to_string(),
+                    outcomes: 0,
+                    confidence_sum: 0.0,
+                    scored: 0,
+                });
+                row.outcomes += outcome.outcomes;
+                row.confidence_sum += outcome.confidence_sum;
+                row.scored += outcome.scored;
+            }
+        }
+        rows.into_values().collect()
+    }
+
+    #[test]
+    fn test_tournee_outcomes_group_by_method() {
+        let outcomes = tournee_outcomes(&[
+            package("auto_validated", Some(1.0)),
+            package("geocoded", Some(0.9)),
+            package("completed_auto", None),
+            package("requires_manual", None),
+        ]);
+
+        let completed = outcomes.iter().find(|o| o.method == ValidationMethod::CompletedAuto).unwrap();
+        assert_eq!((completed.outcomes, completed.scored), (2, 1));
+        assert_eq!(outcomes.iter().map(|o| o.outcomes).sum::<i64>(), 4);
+    }
+
+    #[test]
+    fn test_trends_aggregate_outcomes_per_day() {
+        let rows = daily_rows(&[
+            (day(13), vec![package("auto_validated", Some(1.0)), package("requires_manual", None), package("geocoding_error", None)]),
+            (day(13), vec![package("cleaned_auto", Some(0.8)), package("partial_found", Some(0.6))]),
+            (day(15), vec![package("auto_validated", Some(1.0)), package("geocoded", Some(0.9)), package("manual_confirmed", Some(1.0))]),
+            (day(20), vec![package("auto_validated", Some(1.0))]),
+        ]);
+
+        let trends = build_trends(&rows, day(13), day(15));
+
+        assert_eq!(trends.iter().map(|t| t.date).collect::<Vec<_>>(), vec![day(13), day(14), day(15)]);
+        assert_eq!(
+            (trends[0].auto_validated, trends[0].cleaned, trends[0].partial, trends[0].manual, trends[0].total),
+            (1, 1, 1, 2, 5)
+        );
+        assert!((trends[0].average_confidence.unwrap() - 0.8).abs() < 1e-9);
+
+        assert_eq!(trends[1], DailyValidationTrend { date: day(14), ..Default::default() });
+
+        assert_eq!((trends[2].completed, trends[2].manual, trends[2].total), (1, 1, 3));
+        // El día 20 queda fuera del rango pedido
+        assert_eq!(trends.iter().map(|t| t.total).sum::<i64>(), 8);
+    }
+}
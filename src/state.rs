@@ -5,14 +5,24 @@
 
 use sqlx::PgPool;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use crate::config::environment::EnvironmentConfig;
+use crate::middleware::rate_limit::ColisPriveRateLimiter;
+use crate::services::tournee_live_service::TourneeLiveHub;
 use crate::cache::redis_client::RedisClient;
+use crate::cache::fallback_cache::{FallbackCache, DEFAULT_MEMORY_CAPACITY};
+use crate::cache::geocoding_cache::GeocodingCache;
+use crate::services::colis_prive_service::{AuthenticationResult, OPTIMIZE_TIMEOUT};
+use crate::services::societe_allowlist_service::SocieteAllowlist;
+use crate::services::tournee_fetch_service::TourneeFetches;
+use crate::utils::single_flight::SingleFlight;
+use crate::utils::tls::{HostClients, TlsPolicy};
 
 /// Estructura para almacenar tokens de autenticación
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AuthToken {
     pub token: String,
     pub expires_at: chrono::DateTime<chrono::Utc>,
@@ -33,6 +43,68 @@ impl AuthToken {
     pub fn is_expired(&self) -> bool {
         chrono::Utc::now() > self.expires_at
     }
+
+    /// Segundos de validez restantes (TTL de la copia en Redis, mínimo 1)
+    pub fn remaining_secs(&self, now: chrono::DateTime<chrono::Utc>) -> u64 {
+        (self.expires_at - now).num_seconds().max(1) as u64
+    }
+}
+
+/// Tokens SSO en memoria, compartidos entre peticiones (`{societe}:{matricule}` → token).
+///
+/// Cada operación toma el `RwLock` async solo durante el acceso al mapa y lo
+/// suelta antes de cualquier otra espera: lecturas concurrentes no se bloquean
+/// entre sí y la limpieza no retiene el lock de escritura.
+#[derive(Clone, Default)]
+pub struct AuthTokenStore {
+    tokens: Arc<RwLock<HashMap<String, AuthToken>>>,
+}
+
+impl AuthTokenStore {
+    fn key(societe: &str, matricule: &str) -> String {
+        format!("{}:{}", societe, matricule)
+    }
+
+    pub async fn get(&self, societe: &str, matricule: &str) -> Option<AuthToken> {
+        self.tokens.read().await.get(&Self::key(societe, matricule)).cloned()
+    }
+
+    /// Guardar el token bajo su société y usuario
+    pub async fn store(&self, token: AuthToken) {
+        let key = Self::key(&token.societe, &token.username);
+        self.tokens.write().await.insert(key, token);
+    }
+
+    pub async fn insert(&self, societe: &str, matricule: &str, token: AuthToken) {
+        self.tokens.write().await.insert(Self::key(societe, matricule), token);
+    }
+
+    /// Quitar el token; devuelve si había uno guardado
+    pub async fn remove(&self, societe: &str, matricule: &str) -> bool {
+        self.tokens.write().await.remove(&Self::key(societe, matricule)).is_some()
+    }
+
+    /// Quitar todos los tokens de una société; devuelve cuántos se quitaron
+    pub async fn remove_societe(&self, societe: &str) -> usize {
+        let prefix = Self::key(societe, "");
+        let mut tokens = self.tokens.write().await;
+        let before = tokens.len();
+        tokens.retain(|key, _| !key.starts_with(&prefix));
+        before - tokens.len()
+    }
+
+    pub async fn contains(&self, societe: &str, matricule: &str) -> bool {
+        self.tokens.read().await.contains_key(&Self::key(societe, matricule))
+    }
+
+    /// Eliminar los tokens expirados; devuelve cuántos se quitaron
+    pub async fn cleanup_expired(&self) -> usize {
+        let now = chrono::Utc::now();
+        let mut tokens = self.tokens.write().await;
+        let before = tokens.len();
+        tokens.retain(|_, token| token.expires_at >= now);
+        before - tokens.len()
+    }
 }
 
 #[derive(Clone)]
@@ -41,61 +113,183 @@ pub struct AppState {
     pub config: EnvironmentConfig,
     pub redis: RedisClient,
     pub http_client: Client,
-    pub auth_tokens: Arc<RwLock<HashMap<String, AuthToken>>>,
+    pub auth_tokens: AuthTokenStore,
+    pub societe_allowlist: SocieteAllowlist,
+    /// Clientes HTTP hacia Colis Privé (verificación SSL configurable por host)
+    pub colis_prive_clients: HostClients,
+    /// Cache de geocoding por dirección normalizada: Redis con LRU en memoria si Redis cae
+    pub geocode_cache: GeocodingCache,
+    /// Logins a Colis Privé en curso: peticiones idénticas simultáneas
+    /// comparten una sola llamada
+    pub colis_prive_logins: SingleFlight<AuthenticationResult>,
+    /// Descargas de tournée en curso: peticiones idénticas simultáneas
+    /// (misma société, matricule y fecha) comparten una sola llamada
+    pub tournee_fetches: TourneeFetches,
+    /// Límite de autenticaciones y descargas de tournée por cuenta de Colis Privé
+    pub colis_prive_rate_limits: ColisPriveRateLimiter,
+    /// Canales de las tournées seguidas en directo por WebSocket
+    pub tournee_live: TourneeLiveHub,
 }
 
 impl AppState {
     pub fn new(pool: PgPool, config: EnvironmentConfig, redis: RedisClient) -> Self {
+        let societe_allowlist = SocieteAllowlist::new(config.colis_prive_allowed_societes.clone());
+        if !societe_allowlist.is_enabled() {
+            log::warn!("⚠️ COLIS_PRIVE_ALLOWED_SOCIETES vacío: se acepta cualquier société");
+        }
+
+        let http_client = Client::new();
+        // Cliente con pool de conexiones para Colis Privé; el timeout máximo es
+        // el de la optimización (auth y tournée fijan 30 s por petición)
+        let colis_prive_client = Client::builder()
+            .timeout(OPTIMIZE_TIMEOUT)
+            .build()
+            .expect("Failed to create HTTP client");
+        let colis_prive_clients = HostClients::new(
+            colis_prive_client,
+            TlsPolicy::new(&config.colis_prive_ssl_bypass_hosts),
+        );
+
+        let geocode_cache = GeocodingCache::new(
+            FallbackCache::new(redis.clone(), DEFAULT_MEMORY_CAPACITY),
+            config.geocoding_cache_ttl_secs,
+        );
+
+        let colis_prive_rate_limits = ColisPriveRateLimiter::new(
+            config.colis_prive_auth_rate_per_min,
+            config.colis_prive_tournee_rate_per_min,
+        );
+
+        let tournee_live = TourneeLiveHub::new(config.tournee_live_max_subscribers);
+
         Self {
             pool,
             config,
             redis,
-            http_client: Client::new(),
-            auth_tokens: Arc::new(RwLock::new(HashMap::new())),
+            http_client,
+            auth_tokens: AuthTokenStore::default(),
+            societe_allowlist,
+            colis_prive_clients,
+            geocode_cache,
+            colis_prive_logins: SingleFlight::new(),
+            tournee_fetches: TourneeFetches::new(),
+            tournee_live,
+            colis_prive_rate_limits,
         }
     }
 
     /// Obtener token de autenticación para un usuario específico
     pub async fn get_auth_token(&self, username: &str, societe: &str) -> Option<AuthToken> {
-        let key = format!("{}:{}", societe, username);
-        log::info!("🔍 Buscando token con clave: '{}'", key);
-        
-        let tokens = self.auth_tokens.read().await;
-        let token_count = tokens.len();
-        log::info!("🔍 Total de tokens en estado: {}", token_count);
-        
-        // 🔍 DEBUG: Imprimir todas las claves disponibles
-        for (k, v) in tokens.iter() {
-            log::info!("🔍 Token disponible: '{}' -> username: '{}', societe: '{}'", k, v.username, v.societe);
-        }
-        
-        let result = tokens.get(&key).cloned();
+        let result = self.auth_tokens.get(societe, username).await;
         match &result {
-            Some(_token) => log::info!("✅ Token encontrado para clave '{}'", key),
-            None => log::warn!("❌ Token NO encontrado para clave '{}'", key),
+            Some(_) => log::info!("✅ Token encontrado para '{}:{}'", societe, username),
+            None => log::warn!("❌ Token NO encontrado para '{}:{}'", societe, username),
         }
         result
     }
 
     /// Almacenar token de autenticación
     pub async fn store_auth_token(&self, username: String, societe: String, token: String, expires_in_hours: i32) {
-        let key = format!("{}:{}", societe, username);
-        log::info!("💾 Almacenando token con clave: '{}' para username: '{}', societe: '{}'", key, username, societe);
-        
-        let auth_token = AuthToken::new(token, username, societe, expires_in_hours);
-        let mut tokens = self.auth_tokens.write().await;
-        
-        let old_count = tokens.len();
-        tokens.insert(key.clone(), auth_token);
-        let new_count = tokens.len();
-        
-        log::info!("💾 Token almacenado. Tokens antes: {}, después: {}", old_count, new_count);
-        log::info!("💾 Token almacenado exitosamente con clave: '{}'", key);
+        log::info!("💾 Almacenando token para '{}:{}'", societe, username);
+        self.auth_tokens.store(AuthToken::new(token, username, societe, expires_in_hours)).await;
     }
 
     /// Limpiar tokens expirados
     pub async fn cleanup_expired_tokens(&self) {
-        let mut tokens = self.auth_tokens.write().await;
-        tokens.retain(|_, token| !token.is_expired());
+        let removed = self.auth_tokens.cleanup_expired().await;
+        if removed > 0 {
+            log::info!("🧹 {} tokens expirados eliminados", removed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_ttl_matches_remaining_validity() {
+        let token = AuthToken::new("sso".to_string(), "A187518".to_string(), "PCP0010699".to_string(), 24);
+        let now = chrono::Utc::now();
+
+        let ttl = token.remaining_secs(now);
+        assert!((24 * 3600 - 5..=24 * 3600).contains(&ttl));
+        assert_eq!(token.remaining_secs(token.expires_at + chrono::Duration::hours(1)), 1);
     }
-}
\ No newline at end of file
+
+    fn token(username: &str, value: &str, expires_in_hours: i32) -> AuthToken {
+        AuthToken::new(value.to_string(), username.to_string(), "PCP0010699".to_string(), expires_in_hours)
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_store_and_get_are_consistent() {
+        let store = AuthTokenStore::default();
+
+        let mut tasks = Vec::new();
+        for i in 0..200 {
+            let store = store.clone();
+            tasks.push(tokio::spawn(async move {
+                let username = format!("A{}", i % 20);
+                store.store(token(&username, &format!("sso-{}", i), 24)).await;
+                let read = store.get("PCP0010699", &username).await.expect("token recién guardado");
+                assert_eq!(read.username, username);
+                if i % 7 == 0 {
+                    store.cleanup_expired().await;
+                }
+            }));
+        }
+
+        let all = futures::future::join_all(tasks);
+        let results = tokio::time::timeout(std::time::Duration::from_secs(5), all)
+            .await
+            .expect("deadlock en el almacén de tokens");
+        assert!(results.into_iter().all(|r| r.is_ok()));
+
+        for i in 0..20 {
+            let stored = store.get("PCP0010699", &format!("A{}", i)).await.unwrap();
+            // El último token escrito para cada usuario es uno de los suyos
+            let index: usize = stored.token.trim_start_matches("sso-").parse().unwrap();
+            assert_eq!(index % 20, i);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_only_removes_expired_tokens() {
+        let store = AuthTokenStore::default();
+        store.store(token("A1", "vigente", 24)).await;
+        store.store(token("A2", "caducado", -1)).await;
+
+        assert_eq!(store.cleanup_expired().await, 1);
+        assert!(store.contains("PCP0010699", "A1").await);
+        assert!(!store.contains("PCP0010699", "A2").await);
+    }
+
+    #[tokio::test]
+    async fn test_remove_reports_whether_token_was_present() {
+        let store = AuthTokenStore::default();
+        store.store(token("A1", "vigente", 24)).await;
+
+        assert!(store.remove("PCP0010699", "A1").await);
+        assert!(!store.remove("PCP0010699", "A1").await);
+        assert!(!store.contains("PCP0010699", "A1").await);
+    }
+
+    #[tokio::test]
+    async fn test_remove_societe_purges_only_that_societe() {
+        let store = AuthTokenStore::default();
+        store.store(token("A1", "viejo-1", 24)).await;
+        store.store(token("A2", "viejo-2", 24)).await;
+        store.store(AuthToken::new("otro".to_string(), "B1".to_string(), "PCP0010700".to_string(), 24)).await;
+
+        assert_eq!(store.remove_societe("PCP0010699").await, 2);
+        assert!(store.get("PCP0010699", "A1").await.is_none());
+        assert!(store.get("PCP0010699", "A2").await.is_none());
+        // "PCP001069" no es prefijo de otra société por la separación con ':'
+        assert_eq!(store.remove_societe("PCP001069").await, 0);
+        assert!(store.contains("PCP0010700", "B1").await);
+
+        // Tras la rotación el siguiente login guarda el token nuevo
+        store.store(token("A1", "nuevo", 24)).await;
+        assert_eq!(store.get("PCP0010699", "A1").await.unwrap().token, "nuevo");
+    }
+}
@@ -0,0 +1,63 @@
+//! Clave de administración
+//!
+//! Las operaciones de soporte que invalidan tokens guardados (el de un
+//! usuario o todos los de una société) exigen la cabecera `X-Admin-Key` con
+//! el secreto compartido `ADMIN_API_KEY`. Sin secreto configurado esas rutas
+//! quedan desactivadas.
+
+use axum::http::{HeaderMap, HeaderName};
+
+use crate::utils::errors::AppError;
+
+pub const ADMIN_KEY_HEADER: HeaderName = HeaderName::from_static("x-admin-key");
+
+/// Verificar la clave de administración de la petición (comparación en tiempo constante)
+pub fn require_admin_key(expected: Option<&str>, headers: &HeaderMap) -> Result<(), AppError> {
+    let expected = expected
+        .ok_or_else(|| AppError::ServiceUnavailable("ADMIN_API_KEY no configurada".to_string()))?;
+
+    let provided = headers
+        .get(ADMIN_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .ok_or_else(|| AppError::Unauthorized("Falta la cabecera X-Admin-Key".to_string()))?;
+
+    if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return Err(AppError::Forbidden("Clave de administración inválida".to_string()));
+    }
+
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ADMIN_KEY_HEADER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_matching_key_is_accepted() {
+        assert!(require_admin_key(Some("s3cret"), &headers("s3cret")).is_ok());
+    }
+
+    #[test]
+    fn test_missing_or_wrong_key_is_rejected() {
+        assert!(matches!(require_admin_key(Some("s3cret"), &HeaderMap::new()), Err(AppError::Unauthorized(_))));
+        assert!(matches!(require_admin_key(Some("s3cret"), &headers("s3cre")), Err(AppError::Forbidden(_))));
+        assert!(matches!(require_admin_key(Some("s3cret"), &headers("s3cres")), Err(AppError::Forbidden(_))));
+    }
+
+    #[test]
+    fn test_route_is_disabled_without_configured_key() {
+        assert!(matches!(require_admin_key(None, &headers("s3cret")), Err(AppError::ServiceUnavailable(_))));
+    }
+}
@@ -0,0 +1,124 @@
+//! Extractor de cuerpos JSON
+//!
+//! `axum::Json` rechaza un cuerpo mal formado con un 400/422 en texto plano.
+//! `AppJson` extrae igual, pero el rechazo es JSON como el resto de errores:
+//! `{"error": {"code": "invalid_json", "message": ...}}`.
+
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+
+/// Cuerpo JSON de la petición (sustituye a `Json<T>` como extractor)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AppJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for AppJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = InvalidJson;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(request, state).await.map_err(InvalidJson)?;
+        Ok(AppJson(value))
+    }
+}
+
+/// Cuerpo JSON rechazado; conserva el código de estado de axum (400 sintaxis,
+/// 422 campos, 415 sin `Content-Type: application/json`)
+#[derive(Debug)]
+pub struct InvalidJson(pub JsonRejection);
+
+impl IntoResponse for InvalidJson {
+    fn into_response(self) -> Response {
+        let message = self.0.body_text();
+        log::warn!("⚠️ Cuerpo JSON inválido: {}", message);
+        (
+            self.0.status(),
+            Json(json!({
+                "error": {
+                    "code": "invalid_json",
+                    "message": message,
+                }
+            })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header, Request as HttpRequest, StatusCode},
+        routing::post,
+        Router,
+    };
+    use serde::Deserialize;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct AuthBody {
+        username: String,
+    }
+
+    async fn post_auth(body: &'static str, content_type: Option<&str>) -> (StatusCode, Value) {
+        let app = Router::new().route(
+            "/colis-prive/auth",
+            post(|AppJson(body): AppJson<AuthBody>| async move { body.username }),
+        );
+        let mut request = HttpRequest::post("/colis-prive/auth");
+        if let Some(content_type) = content_type {
+            request = request.header(header::CONTENT_TYPE, content_type);
+        }
+
+        let response = app.oneshot(request.body(Body::from(body)).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_gets_the_json_envelope() {
+        let (status, body) = post_auth(r#"{"username": "A187518""#, Some("application/json")).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "invalid_json");
+        assert!(body["error"]["message"].as_str().is_some_and(|m| !m.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_missing_field_and_content_type_keep_their_status() {
+        let (status, body) = post_auth(r#"{"societe": "PCP0010699"}"#, Some("application/json")).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "invalid_json");
+
+        let (status, body) = post_auth(r#"{"username": "A187518"}"#, None).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(body["error"]["code"], "invalid_json");
+    }
+
+    #[tokio::test]
+    async fn test_valid_body_is_extracted() {
+        let app = Router::new().route(
+            "/colis-prive/auth",
+            post(|AppJson(body): AppJson<AuthBody>| async move { body.username }),
+        );
+        let request = HttpRequest::post("/colis-prive/auth")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"username": "A187518"}"#))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), "A187518");
+    }
+}
@@ -4,7 +4,7 @@
 //! y su conversión a respuestas HTTP apropiadas.
 
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -38,8 +38,9 @@ pub enum AppError {
     #[error("Internal server error: {0}")]
     Internal(String),
 
-    #[error("Rate limit exceeded")]
-    RateLimitExceeded,
+    /// Límite de peticiones agotado; `Retry-After` indica cuándo reintentar
+    #[error("Rate limit exceeded (retry after {retry_after_secs}s)")]
+    RateLimitExceeded { retry_after_secs: u64 },
 
     #[error("Service unavailable: {0}")]
     ServiceUnavailable(String),
@@ -53,6 +54,14 @@ pub enum AppError {
     #[error("External API error: {0}")]
     ExternalApi(String),
 
+    /// Token de Colis Privé caducado o rechazado: hay que volver a autenticarse
+    #[error("Auth expired: {0}")]
+    AuthExpired(String),
+
+    /// Respuesta del servicio externo con un formato inesperado
+    #[error("Parse error: {0}")]
+    ParseError(String),
+
     #[error("Not implemented: {0}")]
     NotImplemented(String),
     
@@ -61,6 +70,67 @@ pub enum AppError {
     
     #[error("Validation error: {0}")]
     ValidationError(String),
+
+    #[error("Optimization error: {0}")]
+    Optimization(#[from] OptimizationError),
+}
+
+/// Motivos de fallo de una optimización, con un `code` estable para el frontend
+#[derive(Error, Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "code", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OptimizationError {
+    #[error("{missing} paquetes sin coordenadas: valide las direcciones antes de optimizar")]
+    NoCoordinates { missing: usize, references: Vec<String> },
+
+    #[error("Demasiadas paradas: {stops} (máximo {max})")]
+    TooManyStops { stops: usize, max: usize },
+
+    #[error("{service} no respondió a tiempo")]
+    UpstreamTimeout { service: String },
+
+    #[error("{service} rechazó la optimización: {reason}")]
+    UpstreamRejected { service: String, reason: String },
+
+    #[error("Capacidad del vehículo superada: {reason}")]
+    CapacityExceeded { reason: String },
+}
+
+impl OptimizationError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            OptimizationError::NoCoordinates { .. } => "NO_COORDINATES",
+            OptimizationError::TooManyStops { .. } => "TOO_MANY_STOPS",
+            OptimizationError::UpstreamTimeout { .. } => "UPSTREAM_TIMEOUT",
+            OptimizationError::UpstreamRejected { .. } => "UPSTREAM_REJECTED",
+            OptimizationError::CapacityExceeded { .. } => "CAPACITY_EXCEEDED",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            OptimizationError::NoCoordinates { .. }
+            | OptimizationError::TooManyStops { .. }
+            | OptimizationError::CapacityExceeded { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            OptimizationError::UpstreamTimeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            OptimizationError::UpstreamRejected { .. } => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    /// Clasificar un mensaje de error del servicio de optimización
+    pub fn from_upstream_message(service: &str, message: &str) -> Self {
+        let lower = message.to_lowercase();
+
+        if lower.contains("capacit") {
+            OptimizationError::CapacityExceeded { reason: message.to_string() }
+        } else if lower.contains("timeout") || lower.contains("timed out") || lower.contains("délai") {
+            OptimizationError::UpstreamTimeout { service: service.to_string() }
+        } else {
+            OptimizationError::UpstreamRejected {
+                service: service.to_string(),
+                reason: message.to_string(),
+            }
+        }
+    }
 }
 
 /// Respuesta de error para la API
@@ -76,6 +146,11 @@ struct ErrorResponse {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let retry_after = match &self {
+            AppError::RateLimitExceeded { retry_after_secs } => Some(*retry_after_secs),
+            _ => None,
+        };
+
         let (status, error_response) = match self {
             AppError::Database(e) => {
                 eprintln!("Database error: {}", e);
@@ -93,7 +168,7 @@ impl IntoResponse for AppError {
             AppError::Validation(e) => {
                 eprintln!("Validation error: {}", e);
                 (
-                    StatusCode::BAD_REQUEST,
+                    StatusCode::UNPROCESSABLE_ENTITY,
                     ErrorResponse {
                         error: "Validation Error".to_string(),
                         message: "The provided data is invalid".to_string(),
@@ -181,14 +256,14 @@ impl IntoResponse for AppError {
                 )
             }
 
-            AppError::RateLimitExceeded => {
+            AppError::RateLimitExceeded { retry_after_secs } => {
                 eprintln!("Rate limit exceeded");
                 (
                     StatusCode::TOO_MANY_REQUESTS,
                     ErrorResponse {
                         error: "Rate Limit Exceeded".to_string(),
                         message: "Too many requests. Please try again later".to_string(),
-                        details: None,
+                        details: Some(json!({ "retry_after_secs": retry_after_secs })),
                         code: Some("RATE_LIMIT_EXCEEDED".to_string()),
                     },
                 )
@@ -241,7 +316,33 @@ impl IntoResponse for AppError {
                         error: "External API Error".to_string(),
                         message: "An error occurred while communicating with external service".to_string(),
                         details: Some(json!({ "external_api_error": msg })),
-                        code: Some("EXTERNAL_API_ERROR".to_string()),
+                        code: Some("UPSTREAM_ERROR".to_string()),
+                    },
+                )
+            }
+
+            AppError::AuthExpired(msg) => {
+                eprintln!("Auth expired: {}", msg);
+                (
+                    StatusCode::UNAUTHORIZED,
+                    ErrorResponse {
+                        error: "Auth Expired".to_string(),
+                        message: msg,
+                        details: None,
+                        code: Some("AUTH_EXPIRED".to_string()),
+                    },
+                )
+            }
+
+            AppError::ParseError(msg) => {
+                eprintln!("Parse error: {}", msg);
+                (
+                    StatusCode::BAD_GATEWAY,
+                    ErrorResponse {
+                        error: "Parse Error".to_string(),
+                        message: "The external service returned an unexpected response".to_string(),
+                        details: Some(json!({ "parse_error": msg })),
+                        code: Some("PARSE_ERROR".to_string()),
                     },
                 )
             }
@@ -284,9 +385,26 @@ impl IntoResponse for AppError {
                     },
                 )
             }
+
+            AppError::Optimization(e) => {
+                eprintln!("Optimization error: {}", e);
+                (
+                    e.status(),
+                    ErrorResponse {
+                        error: "Optimization Error".to_string(),
+                        message: e.to_string(),
+                        details: serde_json::to_value(&e).ok(),
+                        code: Some(e.code().to_string()),
+                    },
+                )
+            }
         };
 
-        (status, Json(error_response)).into_response()
+        let mut response = (status, Json(error_response)).into_response();
+        if let Some(seconds) = retry_after {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
     }
 }
 
@@ -331,3 +449,59 @@ pub fn bad_request_error(message: &str) -> AppError {
 pub fn internal_error(message: &str) -> AppError {
     AppError::Internal(message.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_messages_map_to_codes() {
+        let capacity = OptimizationError::from_upstream_message("Colis Privé", "Capacité du véhicule dépassée");
+        let timeout = OptimizationError::from_upstream_message("Mapbox", "operation timed out");
+        let rejected = OptimizationError::from_upstream_message("Colis Privé", "Tournée inconnue");
+
+        assert_eq!(capacity.code(), "CAPACITY_EXCEEDED");
+        assert_eq!(timeout.code(), "UPSTREAM_TIMEOUT");
+        assert_eq!(rejected.code(), "UPSTREAM_REJECTED");
+    }
+
+    #[test]
+    fn test_optimization_error_response_has_code_and_details() {
+        let error = OptimizationError::NoCoordinates {
+            missing: 3,
+            references: vec!["CP1".to_string(), "CP2".to_string(), "CP3".to_string()],
+        };
+        let details = serde_json::to_value(&error).unwrap();
+
+        assert_eq!(details["code"], "NO_COORDINATES");
+        assert_eq!(details["missing"], 3);
+        assert_eq!(AppError::from(error).into_response().status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let timeout = AppError::from(OptimizationError::UpstreamTimeout { service: "Mapbox".to_string() });
+        assert_eq!(timeout.into_response().status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    async fn error_code(error: AppError) -> (StatusCode, String) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        (status, json["code"].as_str().unwrap_or_default().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_colis_prive_failures_have_distinct_codes() {
+        assert_eq!(
+            error_code(AppError::AuthExpired("Token expirado".to_string())).await,
+            (StatusCode::UNAUTHORIZED, "AUTH_EXPIRED".to_string())
+        );
+        assert_eq!(
+            error_code(AppError::ExternalApi("Colis Privé respondió 503".to_string())).await,
+            (StatusCode::BAD_GATEWAY, "UPSTREAM_ERROR".to_string())
+        );
+        assert_eq!(
+            error_code(AppError::ParseError("expected value at line 1".to_string())).await,
+            (StatusCode::BAD_GATEWAY, "PARSE_ERROR".to_string())
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! ETag / If-None-Match
+//!
+//! Para endpoints que cambian poco (sociétés, tournée tipada): el ETag es el
+//! hash SHA-256 del cuerpo JSON. Si el cliente ya tiene esa versión recibe un
+//! `304 Not Modified` sin cuerpo en lugar de volver a descargarla.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::utils::errors::AppError;
+
+/// ETag fuerte (entre comillas) a partir de los bytes del cuerpo
+pub fn compute_etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest.iter().take(16).map(|byte| format!("{:02x}", byte)).collect();
+    format!("\"{}\"", hex)
+}
+
+/// ¿Alguno de los valores de `If-None-Match` coincide con el ETag actual?
+///
+/// Acepta listas separadas por comas, `*` y el prefijo débil `W/`.
+pub fn if_none_match_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|candidate| candidate.trim())
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+/// Serializar `body` como JSON con cabecera ETag, o 304 si el cliente ya lo tiene
+pub fn json_with_etag<T: Serialize>(headers: &HeaderMap, body: &T) -> Result<Response, AppError> {
+    let bytes = serde_json::to_vec(body)
+        .map_err(|e| AppError::Internal(format!("Error serializando respuesta: {}", e)))?;
+    let etag = compute_etag(&bytes);
+    let etag_value = HeaderValue::from_str(&etag)
+        .map_err(|e| AppError::Internal(format!("ETag inválido: {}", e)))?;
+
+    if if_none_match_matches(headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag_value)]).into_response());
+    }
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static("application/json")),
+            (header::ETAG, etag_value),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn headers_with(if_none_match: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(if_none_match).unwrap());
+        headers
+    }
+
+    fn etag_of(response: &Response) -> String {
+        response.headers().get(header::ETAG).unwrap().to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_matching_if_none_match_returns_304() {
+        let body = json!({"companies": ["PCP0010699"]});
+        let first = json_with_etag(&HeaderMap::new(), &body).unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = etag_of(&first);
+
+        let second = json_with_etag(&headers_with(&etag), &body).unwrap();
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(etag_of(&second), etag);
+
+        let weak = json_with_etag(&headers_with(&format!("\"otro\", W/{}", etag)), &body).unwrap();
+        assert_eq!(weak.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn test_stale_if_none_match_returns_200_with_new_etag() {
+        let old = json_with_etag(&HeaderMap::new(), &json!({"companies": ["A"]})).unwrap();
+        let stale_etag = etag_of(&old);
+
+        let fresh = json_with_etag(&headers_with(&stale_etag), &json!({"companies": ["A", "B"]})).unwrap();
+        assert_eq!(fresh.status(), StatusCode::OK);
+        assert_ne!(etag_of(&fresh), stale_etag);
+    }
+}
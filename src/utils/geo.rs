@@ -0,0 +1,184 @@
+//! Utilidades geográficas
+//!
+//! Cálculos de distancia sobre coordenadas WGS84 (latitud/longitud) y
+//! comprobación de que unas coordenadas caen en alguna de las regiones
+//! atendidas (Francia metropolitana, DOM...).
+
+/// Radio medio de la Tierra en kilómetros
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Distancia en línea recta (haversine) entre dos puntos, en kilómetros
+pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+
+    EARTH_RADIUS_KM * c
+}
+
+/// Región atendida: caja lat/lon con nombre
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub name: String,
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl Region {
+    pub fn new(name: &str, min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Self {
+        Self { name: name.to_string(), min_lat, min_lon, max_lat, max_lon }
+    }
+
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        (self.min_lat..=self.max_lat).contains(&latitude) && (self.min_lon..=self.max_lon).contains(&longitude)
+    }
+}
+
+/// Regiones predefinidas por nombre (metropole incluye Córcega)
+pub fn preset_region(name: &str) -> Option<Region> {
+    let region = match name {
+        "metropole" => Region::new("metropole", 41.3, -5.2, 51.1, 9.6),
+        "belgique" => Region::new("belgique", 49.5, 2.5, 51.5, 6.4),
+        "guadeloupe" => Region::new("guadeloupe", 15.8, -61.9, 16.6, -61.0),
+        "martinique" => Region::new("martinique", 14.3, -61.3, 14.9, -60.8),
+        "guyane" => Region::new("guyane", 2.1, -54.6, 5.8, -51.6),
+        "reunion" => Region::new("reunion", -21.4, 55.2, -20.8, 55.9),
+        "mayotte" => Region::new("mayotte", -13.1, 45.0, -12.6, 45.3),
+        _ => return None,
+    };
+    Some(region)
+}
+
+/// Resultado de comprobar unas coordenadas contra las regiones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateCheck {
+    Valid,
+    /// Latitud y longitud invertidas: al intercambiarlas caen en una región
+    Swapped,
+    OutOfRange,
+}
+
+/// Unión de las regiones en las que una coordenada se considera válida
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordinateBounds {
+    regions: Vec<Region>,
+}
+
+impl Default for CoordinateBounds {
+    fn default() -> Self {
+        Self { regions: vec![preset_region("metropole").unwrap()] }
+    }
+}
+
+impl CoordinateBounds {
+    pub fn new(regions: Vec<Region>) -> Self {
+        Self { regions }
+    }
+
+    /// Leer la lista de regiones separadas por `;`: nombres predefinidos
+    /// (`metropole;reunion`) o cajas `nombre:min_lat,min_lon,max_lat,max_lon`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let regions = spec
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once(':') {
+                None => preset_region(&entry.to_lowercase()).ok_or_else(|| format!("Región desconocida '{}'", entry)),
+                Some((name, bounds)) => {
+                    let values: Vec<f64> = bounds
+                        .split(',')
+                        .map(|v| v.trim().parse::<f64>())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| format!("Caja inválida para '{}': {}", name, bounds))?;
+                    match values[..] {
+                        [min_lat, min_lon, max_lat, max_lon] if min_lat < max_lat && min_lon < max_lon => {
+                            Ok(Region::new(name.trim(), min_lat, min_lon, max_lat, max_lon))
+                        }
+                        _ => Err(format!("Caja inválida para '{}': {}", name, bounds)),
+                    }
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if regions.is_empty() {
+            return Err("Ninguna región configurada".to_string());
+        }
+        Ok(Self { regions })
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Región que contiene el punto, si hay alguna
+    pub fn region_for(&self, latitude: f64, longitude: f64) -> Option<&Region> {
+        self.regions.iter().find(|region| region.contains(latitude, longitude))
+    }
+
+    pub fn check(&self, latitude: f64, longitude: f64) -> CoordinateCheck {
+        if self.region_for(latitude, longitude).is_some() {
+            CoordinateCheck::Valid
+        } else if self.region_for(longitude, latitude).is_some() {
+            CoordinateCheck::Swapped
+        } else {
+            CoordinateCheck::OutOfRange
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_haversine_same_point() {
+        assert_eq!(haversine_km(48.8566, 2.3522, 48.8566, 2.3522), 0.0);
+    }
+
+    #[test]
+    fn test_haversine_paris_lyon() {
+        // París - Lyon ≈ 392 km
+        let distance = haversine_km(48.8566, 2.3522, 45.7640, 4.8357);
+        assert!((distance - 392.0).abs() < 5.0, "distancia inesperada: {}", distance);
+    }
+
+    #[test]
+    fn test_metropolitan_coordinate_is_valid() {
+        let bounds = CoordinateBounds::default();
+        assert_eq!(bounds.check(48.8566, 2.3522), CoordinateCheck::Valid);
+        // Ajaccio (Córcega)
+        assert_eq!(bounds.check(41.9192, 8.7386), CoordinateCheck::Valid);
+    }
+
+    #[test]
+    fn test_overseas_coordinate_is_valid_only_when_configured() {
+        let saint_denis = (-20.8789, 55.4481);
+        let pointe_a_pitre = (16.2411, -61.5331);
+
+        assert_eq!(CoordinateBounds::default().check(saint_denis.0, saint_denis.1), CoordinateCheck::OutOfRange);
+
+        let bounds = CoordinateBounds::parse("metropole; reunion; guadeloupe").unwrap();
+        assert_eq!(bounds.check(saint_denis.0, saint_denis.1), CoordinateCheck::Valid);
+        assert_eq!(bounds.region_for(pointe_a_pitre.0, pointe_a_pitre.1).unwrap().name, "guadeloupe");
+    }
+
+    #[test]
+    fn test_out_of_range_and_swapped_coordinates_are_flagged() {
+        let bounds = CoordinateBounds::parse("metropole;zone-test:10,10,11,11").unwrap();
+
+        // Atlántico, lejos de cualquier región
+        assert_eq!(bounds.check(40.0, -30.0), CoordinateCheck::OutOfRange);
+        // París con lat/lon invertidas
+        assert_eq!(bounds.check(2.3522, 48.8566), CoordinateCheck::Swapped);
+        assert_eq!(bounds.check(10.5, 10.5), CoordinateCheck::Valid);
+
+        assert!(CoordinateBounds::parse("atlantide").is_err());
+        assert!(CoordinateBounds::parse("caja:1,2,3").is_err());
+        assert!(CoordinateBounds::parse(" ; ").is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! Idempotency-Key
+//!
+//! Con conexiones móviles inestables el cliente reintenta peticiones que ya se
+//! aplicaron pero cuya respuesta no llegó. Si la petición lleva
+//! `Idempotency-Key`, la respuesta se guarda con esa clave y un reintento con la
+//! misma clave recibe la respuesta original sin volver a ejecutarse.
+
+use axum::http::{HeaderMap, HeaderName};
+
+use crate::utils::errors::AppError;
+
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Tiempo durante el que se recuerda una clave ya procesada
+pub const IDEMPOTENCY_TTL: u64 = 24 * 3600;
+
+/// Tiempo durante el que una clave queda reservada mientras se procesa la
+/// petición; si el proceso muere a mitad la clave se libera sola
+pub const IDEMPOTENCY_RESERVATION_TTL: u64 = 60;
+
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Clave de idempotencia de la petición, si la trae (400 si no es válida)
+pub fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, AppError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+
+    let key = value
+        .to_str()
+        .map(str::trim)
+        .map_err(|_| AppError::ValidationError("Idempotency-Key debe ser ASCII".to_string()))?;
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(AppError::ValidationError(format!(
+            "Idempotency-Key debe tener entre 1 y {} caracteres",
+            MAX_KEY_LENGTH
+        )));
+    }
+
+    Ok(Some(key.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_key_is_optional_and_trimmed() {
+        assert_eq!(idempotency_key(&HeaderMap::new()).unwrap(), None);
+        assert_eq!(
+            idempotency_key(&headers(" 7f9c2ba4-e88f-4a2b ")).unwrap().as_deref(),
+            Some("7f9c2ba4-e88f-4a2b")
+        );
+    }
+
+    #[test]
+    fn test_blank_or_oversized_key_is_rejected() {
+        assert!(matches!(idempotency_key(&headers("  ")), Err(AppError::ValidationError(_))));
+        assert!(idempotency_key(&headers(&"k".repeat(256))).is_err());
+    }
+}
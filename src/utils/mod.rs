@@ -4,4 +4,14 @@
 
 pub mod errors;
 pub mod jwt;
-pub mod validation;
\ No newline at end of file
+pub mod validation;
+pub mod geo;
+pub mod tls;
+pub mod number;
+pub mod etag;
+pub mod single_flight;
+pub mod retry;
+pub mod idempotency;
+pub mod app_json;
+pub mod startup;
+pub mod admin_key;
@@ -0,0 +1,114 @@
+//! Lectura de números en formato francés
+//!
+//! Colis Privé envía a veces puntuaciones y coordenadas como texto con coma
+//! decimal ("0,85", "2,3522"). Estas funciones aceptan "." y "," indistintamente.
+//! Los enteros (numeroOrdre...) pueden llegar como `1`, `1.0` o `"1"`.
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Interpretar un número con separador decimal "." o ","
+pub fn parse_localized_f64(raw: &str) -> Option<f64> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    // Con ambos separadores ("1.234,5") el punto es de miles
+    let normalized = if trimmed.contains(',') {
+        trimmed.replace('.', "").replace(',', ".")
+    } else {
+        trimmed.to_string()
+    };
+
+    normalized.parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+/// Leer un campo JSON numérico que puede venir como número o como texto
+pub fn value_as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => parse_localized_f64(text),
+        _ => None,
+    }
+}
+
+/// Leer un entero que puede venir como entero, decimal sin fracción o texto
+pub fn value_as_i64(value: &Value) -> Option<i64> {
+    let number = match value {
+        Value::Number(number) => {
+            if let Some(integer) = number.as_i64() {
+                return Some(integer);
+            }
+            number.as_f64()?
+        }
+        Value::String(text) => {
+            if let Ok(integer) = text.trim().parse::<i64>() {
+                return Some(integer);
+            }
+            parse_localized_f64(text)?
+        }
+        _ => return None,
+    };
+
+    (number.fract() == 0.0 && number.abs() < i64::MAX as f64).then_some(number as i64)
+}
+
+/// `deserialize_with` para campos `Option<i32>` que pueden venir como `1.0` o `"1"`
+pub fn deserialize_lenient_i32<'de, D>(deserializer: D) -> Result<Option<i32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(value.as_ref().and_then(value_as_i64).and_then(|n| i32::try_from(n).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_comma_and_dot_decimals_parse_to_same_value() {
+        assert_eq!(parse_localized_f64("2,3522"), Some(2.3522));
+        assert_eq!(parse_localized_f64("2.3522"), Some(2.3522));
+        assert_eq!(parse_localized_f64(" 48,8566 "), Some(48.8566));
+        assert_eq!(parse_localized_f64("1.234,5"), Some(1234.5));
+        assert_eq!(parse_localized_f64(""), None);
+        assert_eq!(parse_localized_f64("abc"), None);
+    }
+
+    #[test]
+    fn test_integers_accept_int_float_and_string() {
+        for value in [json!(1), json!(1.0), json!("1"), json!(" 1 "), json!("1,0")] {
+            assert_eq!(value_as_i64(&value), Some(1), "{}", value);
+        }
+
+        assert_eq!(value_as_i64(&json!(1.5)), None);
+        assert_eq!(value_as_i64(&json!("1,5")), None);
+        assert_eq!(value_as_i64(&json!(true)), None);
+    }
+
+    #[test]
+    fn test_lenient_i32_field_deserializes() {
+        #[derive(Deserialize)]
+        struct Lieu {
+            #[serde(default, deserialize_with = "deserialize_lenient_i32")]
+            numero_ordre: Option<i32>,
+        }
+
+        let parse = |value: Value| serde_json::from_value::<Lieu>(value).unwrap().numero_ordre;
+
+        assert_eq!(parse(json!({ "numero_ordre": 3.0 })), Some(3));
+        assert_eq!(parse(json!({ "numero_ordre": "3" })), Some(3));
+        assert_eq!(parse(json!({ "numero_ordre": null })), None);
+        assert_eq!(parse(json!({})), None);
+    }
+
+    #[test]
+    fn test_json_values_accept_numbers_and_localized_strings() {
+        assert_eq!(value_as_f64(&json!(2.3522)), Some(2.3522));
+        assert_eq!(value_as_f64(&json!("0,85")), Some(0.85));
+        assert_eq!(value_as_f64(&json!(null)), None);
+    }
+}
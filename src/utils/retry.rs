@@ -0,0 +1,148 @@
+//! Reintentos con backoff exponencial
+//!
+//! Colis Privé devuelve 502/503 de forma intermitente en horas punta. Las
+//! llamadas se reintentan ante errores 5xx o de conexión, esperando
+//! `base_delay`, 2×, 4×... más un jitter aleatorio de hasta un cuarto del
+//! retardo. Un 401/403 (o cualquier 4xx) no se reintenta nunca.
+
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::StatusCode;
+
+/// Política de reintentos: número de reintentos tras el primer intento y retardo base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 3, base_delay: Duration::from_millis(250) }
+    }
+}
+
+impl RetryPolicy {
+    /// Espera antes del reintento `retry` (0 = primer reintento), sin jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(retry))
+    }
+
+    fn backoff_with_jitter(&self, retry: u32) -> Duration {
+        let delay = self.backoff(retry);
+        let max_jitter = delay.as_millis() as u64 / 4;
+        let jitter = if max_jitter == 0 { 0 } else { rand::thread_rng().gen_range(0..=max_jitter) };
+        delay + Duration::from_millis(jitter)
+    }
+}
+
+/// Si merece la pena reintentar: 5xx, fallo de conexión o timeout
+fn is_transient(result: &Result<(StatusCode, String), reqwest::Error>) -> bool {
+    match result {
+        Ok((status, _)) => status.is_server_error(),
+        Err(e) => e.is_connect() || e.is_timeout(),
+    }
+}
+
+/// Ejecutar `call` reintentando los fallos transitorios según `policy`;
+/// devuelve el último resultado obtenido
+pub async fn retry_transient<F, Fut>(
+    policy: RetryPolicy,
+    label: &str,
+    mut call: F,
+) -> Result<(StatusCode, String), reqwest::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(StatusCode, String), reqwest::Error>>,
+{
+    let mut retry = 0;
+    loop {
+        let result = call().await;
+        if retry >= policy.max_retries || !is_transient(&result) {
+            return result;
+        }
+
+        let delay = policy.backoff_with_jitter(retry);
+        match &result {
+            Ok((status, _)) => log::warn!("🔁 {} respondió {}, reintento {}/{} en {:?}", label, status, retry + 1, policy.max_retries, delay),
+            Err(e) => log::warn!("🔁 Error llamando a {} ({}), reintento {}/{} en {:?}", label, e, retry + 1, policy.max_retries, delay),
+        }
+        tokio::time::sleep(delay).await;
+        retry += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode as AxumStatus, routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Servidor que responde con `statuses` en orden y luego 200
+    async fn flaky_server(statuses: Vec<u16>) -> (String, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new().route(
+            "/tournee",
+            post(move || {
+                let call = counter.fetch_add(1, Ordering::SeqCst);
+                let status = statuses.get(call).copied().unwrap_or(200);
+                async move { (AxumStatus::from_u16(status).unwrap(), r#"{"LstLieuArticle":[]}"#) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}/tournee", addr), calls)
+    }
+
+    async fn call(client: &reqwest::Client, url: &str) -> Result<(StatusCode, String), reqwest::Error> {
+        let response = client.post(url).send().await?;
+        let status = response.status();
+        Ok((status, response.text().await?))
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy { max_retries: 3, base_delay: Duration::from_millis(10) }
+    }
+
+    #[test]
+    fn test_backoff_doubles_from_base_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(0), Duration::from_millis(250));
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_retries_503_until_success() {
+        let (url, calls) = flaky_server(vec![503, 503]).await;
+        let client = reqwest::Client::new();
+
+        let (status, body) = retry_transient(fast_policy(), "la tournée", || call(&client, &url)).await.unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("LstLieuArticle"));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_auth_rejection_and_exhausted_retries_are_returned() {
+        let client = reqwest::Client::new();
+
+        let (url, calls) = flaky_server(vec![401]).await;
+        let (status, _) = retry_transient(fast_policy(), "la tournée", || call(&client, &url)).await.unwrap();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let (url, calls) = flaky_server(vec![502; 10]).await;
+        let (status, _) = retry_transient(fast_policy(), "la tournée", || call(&client, &url)).await.unwrap();
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+}
@@ -0,0 +1,175 @@
+//! Single-flight por clave
+//!
+//! Cuando varias peticiones concurrentes necesitan el mismo resultado (p.ej.
+//! un login a Colis Privé para el mismo `{societe}_{username}`), solo la
+//! primera ejecuta el trabajo; las demás esperan y reciben su resultado, sea
+//! éxito o error. Si la primera se cancela, las que esperaban reciben un error
+//! en lugar de quedarse colgadas.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::broadcast;
+
+type Waiters<T> = Arc<Mutex<HashMap<String, broadcast::Sender<Result<T, String>>>>>;
+
+#[derive(Clone)]
+pub struct SingleFlight<T: Clone> {
+    in_flight: Waiters<T>,
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self { in_flight: Arc::new(Mutex::new(HashMap::new())) }
+    }
+}
+
+/// Quita la clave al terminar (o al cancelarse) el trabajo de la primera petición
+struct InFlightGuard<'a, T: Clone> {
+    in_flight: &'a Waiters<T>,
+    key: Option<String>,
+}
+
+impl<T: Clone> InFlightGuard<'_, T> {
+    fn finish(mut self) -> Option<broadcast::Sender<Result<T, String>>> {
+        let key = self.key.take()?;
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner()).remove(&key)
+    }
+}
+
+impl<T: Clone> Drop for InFlightGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.in_flight.lock().unwrap_or_else(|e| e.into_inner()).remove(&key);
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ejecutar `work` para `key`, o esperar al que ya está en curso
+    pub async fn run<F>(&self, key: &str, work: F) -> Result<T, String>
+    where
+        F: Future<Output = Result<T, String>>,
+    {
+        let waiter = {
+            let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+            match in_flight.get(key) {
+                Some(sender) => Some(sender.subscribe()),
+                None => {
+                    let (sender, _) = broadcast::channel(1);
+                    in_flight.insert(key.to_string(), sender);
+                    None
+                }
+            }
+        };
+
+        if let Some(mut receiver) = waiter {
+            log::info!("⏳ Esperando resultado en curso para '{}'", key);
+            return receiver
+                .recv()
+                .await
+                .unwrap_or_else(|_| Err(format!("La operación en curso para '{}' se canceló", key)));
+        }
+
+        let guard = InFlightGuard { in_flight: &self.in_flight, key: Some(key.to_string()) };
+        let result = work.await;
+        if let Some(sender) = guard.finish() {
+            let _ = sender.send(result.clone());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use crate::state::AuthToken;
+
+    fn expired_token() -> AuthToken {
+        AuthToken::new("viejo".to_string(), "A187518".to_string(), "PCP0010699".to_string(), -1)
+    }
+
+    async fn refresh_if_expired(
+        flight: &SingleFlight<AuthToken>,
+        cached: &AuthToken,
+        calls: &AtomicUsize,
+        fail: bool,
+    ) -> Result<AuthToken, String> {
+        if !cached.is_expired() {
+            return Ok(cached.clone());
+        }
+        flight
+            .run("PCP0010699_A187518", async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                if fail {
+                    return Err("Colis Privé rechazó el login".to_string());
+                }
+                Ok(AuthToken::new("nuevo".to_string(), "A187518".to_string(), "PCP0010699".to_string(), 24))
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refreshes_make_one_auth_call() {
+        let flight = SingleFlight::new();
+        let calls = AtomicUsize::new(0);
+        let cached = expired_token();
+
+        let results = futures::future::join_all(
+            (0..20).map(|_| refresh_if_expired(&flight, &cached, &calls, false)),
+        )
+        .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|r| r.as_ref().map(|t| t.token.as_str()) == Ok("nuevo")));
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_is_shared_and_not_cached() {
+        let flight = SingleFlight::new();
+        let calls = AtomicUsize::new(0);
+        let cached = expired_token();
+
+        let results = futures::future::join_all(
+            (0..20).map(|_| refresh_if_expired(&flight, &cached, &calls, true)),
+        )
+        .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|r| r.is_err()));
+
+        // El siguiente intento vuelve a llamar a Colis Privé
+        assert!(refresh_if_expired(&flight, &cached, &calls, false).await.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_refresh_releases_waiters() {
+        let flight: SingleFlight<String> = SingleFlight::new();
+
+        let leader_flight = flight.clone();
+        let leader = tokio::spawn(async move {
+            leader_flight.run("clave", std::future::pending()).await
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let waiter_flight = flight.clone();
+        let waiter = tokio::spawn(async move {
+            waiter_flight.run("clave", async { Ok("no debería ejecutarse".to_string()) }).await
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        leader.abort();
+
+        let result = tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        assert!(result.is_err());
+    }
+}
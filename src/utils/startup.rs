@@ -0,0 +1,95 @@
+//! Espera de dependencias al arrancar
+//!
+//! En entornos orquestados Postgres y Redis arrancan a la vez que la API; si
+//! la API falla al no encontrarlos entra en un bucle de reinicios. Al arrancar
+//! se reintenta la conexión hasta que responden o se agota el plazo
+//! (STARTUP_WAIT_TIMEOUT_SECS), y solo entonces se falla.
+
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Espera entre intentos de conexión
+pub const STARTUP_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Conectar a la dependencia `name` reintentando cada `interval` hasta `deadline`;
+/// devuelve el último error si no responde a tiempo
+pub async fn wait_for_dependency<T, E, F, Fut>(
+    name: &str,
+    deadline: Instant,
+    interval: Duration,
+    mut connect: F,
+) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        let error = match connect().await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        let now = Instant::now();
+        if now + interval > deadline {
+            log::error!("❌ {} no disponible tras {} intentos: {}", name, attempt, error);
+            return Err(error);
+        }
+
+        log::warn!(
+            "⏳ {} no disponible (intento {}): {}; reintentando en {:?} ({}s restantes)",
+            name,
+            attempt,
+            error,
+            interval,
+            (deadline - now).as_secs()
+        );
+        tokio::time::sleep(interval).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    #[tokio::test]
+    async fn test_retries_until_the_dependency_is_available() {
+        let calls = AtomicUsize::new(0);
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        let result = wait_for_dependency("Redis", deadline, INTERVAL, || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err("connection refused"),
+                _ => Ok("PONG"),
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok("PONG"));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fails_with_the_last_error_when_the_timeout_elapses() {
+        let calls = AtomicUsize::new(0);
+        let deadline = Instant::now() + Duration::from_millis(50);
+
+        let result: Result<(), String> = wait_for_dependency("PostgreSQL", deadline, INTERVAL, || async {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            Err(format!("connection refused ({})", call))
+        })
+        .await;
+
+        let attempts = calls.load(Ordering::SeqCst);
+        assert!(attempts > 1);
+        assert_eq!(result, Err(format!("connection refused ({})", attempts - 1)));
+    }
+}
@@ -0,0 +1,130 @@
+//! Verificación TLS por host
+//!
+//! La verificación de certificados está siempre activa salvo para los hosts
+//! de Colis Privé listados explícitamente en `COLIS_PRIVE_SSL_BYPASS_HOSTS`.
+//! Cualquier otro host (Mapbox, etc.) se verifica siempre.
+
+use reqwest::{Client, Url};
+
+/// Solo se admite desactivar la verificación para dominios de Colis Privé
+const COLIS_PRIVE_DOMAIN: &str = "colisprive.com";
+
+#[derive(Debug, Clone, Default)]
+pub struct TlsPolicy {
+    bypass_hosts: Vec<String>,
+}
+
+impl TlsPolicy {
+    pub fn new(configured_hosts: &[String]) -> Self {
+        let mut bypass_hosts = Vec::new();
+
+        for host in configured_hosts {
+            let host = host.trim().to_lowercase();
+            if host.is_empty() {
+                continue;
+            }
+            if is_colis_prive_host(&host) {
+                bypass_hosts.push(host);
+            } else {
+                log::warn!("⚠️ Host '{}' ignorado: el bypass SSL solo se permite para {}", host, COLIS_PRIVE_DOMAIN);
+            }
+        }
+
+        if !bypass_hosts.is_empty() {
+            log::warn!("⚠️ Verificación SSL DESACTIVADA para: {}", bypass_hosts.join(", "));
+        }
+
+        Self { bypass_hosts }
+    }
+
+    pub fn bypass_hosts(&self) -> &[String] {
+        &self.bypass_hosts
+    }
+
+    /// ¿Se omite la verificación de certificados para esta URL?
+    pub fn bypass_for(&self, url: &str) -> bool {
+        Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(|h| h.to_lowercase()))
+            .is_some_and(|host| self.bypass_hosts.contains(&host))
+    }
+}
+
+fn is_colis_prive_host(host: &str) -> bool {
+    host == COLIS_PRIVE_DOMAIN || host.ends_with(&format!(".{}", COLIS_PRIVE_DOMAIN))
+}
+
+/// Clientes HTTP por host: el inseguro solo se usa para los hosts de la política
+#[derive(Clone)]
+pub struct HostClients {
+    secure: Client,
+    insecure: Option<Client>,
+    policy: TlsPolicy,
+}
+
+impl HostClients {
+    pub fn new(secure: Client, policy: TlsPolicy) -> Self {
+        let insecure = (!policy.bypass_hosts().is_empty()).then(|| {
+            Client::builder()
+                .danger_accept_invalid_certs(true)
+                .build()
+                .expect("Failed to create HTTP client")
+        });
+
+        Self {
+            secure,
+            insecure,
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> &TlsPolicy {
+        &self.policy
+    }
+
+    pub fn client_for(&self, url: &str) -> &Client {
+        match &self.insecure {
+            Some(insecure) if self.policy.bypass_for(url) => insecure,
+            _ => &self.secure,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> TlsPolicy {
+        TlsPolicy::new(&[
+            "wstournee-v2.colisprive.com".to_string(),
+            "api.mapbox.com".to_string(),
+        ])
+    }
+
+    #[test]
+    fn test_bypass_only_applies_to_configured_host() {
+        let policy = policy();
+
+        assert!(policy.bypass_for("https://wstournee-v2.colisprive.com/WS-TourneeColis/api/getLettreVoitureEco_POST"));
+        assert!(!policy.bypass_for("https://wsauthentificationexterne.colisprive.com/api/auth/login/Membership"));
+        assert!(!policy.bypass_for("https://wstournee-v2.colisprive.com.evil.net/"));
+        assert!(!policy.bypass_for("https://gestiontournee.colisprive.com/x"));
+    }
+
+    #[test]
+    fn test_non_colis_prive_hosts_are_never_bypassed() {
+        let policy = policy();
+
+        assert_eq!(policy.bypass_hosts(), &["wstournee-v2.colisprive.com".to_string()]);
+        assert!(!policy.bypass_for("https://api.mapbox.com/geocoding/v5"));
+    }
+
+    #[test]
+    fn test_verification_on_by_default() {
+        let clients = HostClients::new(Client::new(), TlsPolicy::default());
+
+        assert!(clients.policy().bypass_hosts().is_empty());
+        assert!(!clients.policy().bypass_for("https://wstournee-v2.colisprive.com/"));
+        assert!(clients.insecure.is_none());
+    }
+}